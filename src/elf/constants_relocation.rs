@@ -905,6 +905,164 @@ pub const R_RISCV_SET16: u32 = 55;
 /// Local label subtraction
 pub const R_RISCV_SET32: u32 = 56;
 
+///////////////////
+// PowerPC 64
+///////////////////
+/// No relocation
+pub const R_PPC64_NONE: u32 = 0;
+/// 32 bit absolute address
+pub const R_PPC64_ADDR32: u32 = 1;
+/// 26 bit absolute address, word aligned
+pub const R_PPC64_ADDR24: u32 = 2;
+/// 16 bit absolute address
+pub const R_PPC64_ADDR16: u32 = 3;
+/// Lower 16 bits of an absolute address
+pub const R_PPC64_ADDR16_LO: u32 = 4;
+/// Upper 16 bits of an absolute address
+pub const R_PPC64_ADDR16_HI: u32 = 5;
+/// Adjusted upper 16 bits of an absolute address
+pub const R_PPC64_ADDR16_HA: u32 = 6;
+/// 16 bit absolute address, word aligned
+pub const R_PPC64_ADDR14: u32 = 7;
+/// 16 bit absolute address, word aligned, branch taken
+pub const R_PPC64_ADDR14_BRTAKEN: u32 = 8;
+/// 16 bit absolute address, word aligned, branch not taken
+pub const R_PPC64_ADDR14_BRNTAKEN: u32 = 9;
+/// PC relative 26 bit, word aligned
+pub const R_PPC64_REL24: u32 = 10;
+/// PC relative 16 bit, word aligned
+pub const R_PPC64_REL14: u32 = 11;
+/// PC relative 16 bit, word aligned, branch taken
+pub const R_PPC64_REL14_BRTAKEN: u32 = 12;
+/// PC relative 16 bit, word aligned, branch not taken
+pub const R_PPC64_REL14_BRNTAKEN: u32 = 13;
+/// 16 bit offset to the GOT entry
+pub const R_PPC64_GOT16: u32 = 14;
+/// Lower 16 bits of the offset to the GOT entry
+pub const R_PPC64_GOT16_LO: u32 = 15;
+/// Upper 16 bits of the offset to the GOT entry
+pub const R_PPC64_GOT16_HI: u32 = 16;
+/// Adjusted upper 16 bits of the offset to the GOT entry
+pub const R_PPC64_GOT16_HA: u32 = 17;
+/// Copy symbol at runtime
+pub const R_PPC64_COPY: u32 = 19;
+/// Create GOT entry
+pub const R_PPC64_GLOB_DAT: u32 = 20;
+/// Create PLT entry
+pub const R_PPC64_JMP_SLOT: u32 = 21;
+/// Adjust by program base
+pub const R_PPC64_RELATIVE: u32 = 22;
+/// Unaligned 32 bit absolute address
+pub const R_PPC64_UADDR32: u32 = 24;
+/// Unaligned 16 bit absolute address
+pub const R_PPC64_UADDR16: u32 = 25;
+/// PC relative 32 bit
+pub const R_PPC64_REL32: u32 = 26;
+/// 32 bit PLT address
+pub const R_PPC64_PLT32: u32 = 27;
+/// PC relative 32 bit PLT address
+pub const R_PPC64_PLTREL32: u32 = 28;
+/// Lower 16 bits of the PLT address
+pub const R_PPC64_PLT16_LO: u32 = 29;
+/// Upper 16 bits of the PLT address
+pub const R_PPC64_PLT16_HI: u32 = 30;
+/// Adjusted upper 16 bits of the PLT address
+pub const R_PPC64_PLT16_HA: u32 = 31;
+/// 16 bit section offset
+pub const R_PPC64_SECTOFF: u32 = 33;
+/// Lower 16 bits of a section offset
+pub const R_PPC64_SECTOFF_LO: u32 = 34;
+/// Upper 16 bits of a section offset
+pub const R_PPC64_SECTOFF_HI: u32 = 35;
+/// Adjusted upper 16 bits of a section offset
+pub const R_PPC64_SECTOFF_HA: u32 = 36;
+/// PC relative 32 bit, word aligned
+pub const R_PPC64_ADDR30: u32 = 37;
+/// 64 bit absolute address
+pub const R_PPC64_ADDR64: u32 = 38;
+/// Bits 32-47 of an absolute address
+pub const R_PPC64_ADDR16_HIGHER: u32 = 39;
+/// Adjusted bits 32-47 of an absolute address
+pub const R_PPC64_ADDR16_HIGHERA: u32 = 40;
+/// Bits 48-63 of an absolute address
+pub const R_PPC64_ADDR16_HIGHEST: u32 = 41;
+/// Adjusted bits 48-63 of an absolute address
+pub const R_PPC64_ADDR16_HIGHESTA: u32 = 42;
+/// Unaligned 64 bit absolute address
+pub const R_PPC64_UADDR64: u32 = 43;
+/// PC relative 64 bit
+pub const R_PPC64_REL64: u32 = 44;
+/// 64 bit PLT address
+pub const R_PPC64_PLT64: u32 = 45;
+/// PC relative 64 bit PLT address
+pub const R_PPC64_PLTREL64: u32 = 46;
+/// 16 bit offset to the TOC base
+pub const R_PPC64_TOC16: u32 = 47;
+/// Lower 16 bits of the offset to the TOC base
+pub const R_PPC64_TOC16_LO: u32 = 48;
+/// Upper 16 bits of the offset to the TOC base
+pub const R_PPC64_TOC16_HI: u32 = 49;
+/// Adjusted upper 16 bits of the offset to the TOC base
+pub const R_PPC64_TOC16_HA: u32 = 50;
+/// TOC base
+pub const R_PPC64_TOC: u32 = 51;
+/// 16 bit offset to the PLT GOT entry
+pub const R_PPC64_PLTGOT16: u32 = 52;
+/// Lower 16 bits of the offset to the PLT GOT entry
+pub const R_PPC64_PLTGOT16_LO: u32 = 53;
+/// Upper 16 bits of the offset to the PLT GOT entry
+pub const R_PPC64_PLTGOT16_HI: u32 = 54;
+/// Adjusted upper 16 bits of the offset to the PLT GOT entry
+pub const R_PPC64_PLTGOT16_HA: u32 = 55;
+/// 16 bit absolute address, word aligned
+pub const R_PPC64_ADDR16_DS: u32 = 56;
+/// Lower 16 bits of an absolute address, word aligned
+pub const R_PPC64_ADDR16_LO_DS: u32 = 57;
+/// 16 bit offset to the GOT entry, word aligned
+pub const R_PPC64_GOT16_DS: u32 = 58;
+/// Lower 16 bits of the offset to the GOT entry, word aligned
+pub const R_PPC64_GOT16_LO_DS: u32 = 59;
+/// Lower 16 bits of the PLT address, word aligned
+pub const R_PPC64_PLT16_LO_DS: u32 = 60;
+/// 16 bit section offset, word aligned
+pub const R_PPC64_SECTOFF_DS: u32 = 61;
+/// Lower 16 bits of a section offset, word aligned
+pub const R_PPC64_SECTOFF_LO_DS: u32 = 62;
+/// 16 bit offset to the TOC base, word aligned
+pub const R_PPC64_TOC16_DS: u32 = 63;
+/// Lower 16 bits of the offset to the TOC base, word aligned
+pub const R_PPC64_TOC16_LO_DS: u32 = 64;
+/// 16 bit offset to the PLT GOT entry, word aligned
+pub const R_PPC64_PLTGOT16_DS: u32 = 65;
+/// Lower 16 bits of the offset to the PLT GOT entry, word aligned
+pub const R_PPC64_PLTGOT16_LO_DS: u32 = 66;
+/// Marker of a TLS access
+pub const R_PPC64_TLS: u32 = 67;
+/// ID of module containing symbol
+pub const R_PPC64_DTPMOD64: u32 = 68;
+/// Offset in the static TLS block
+pub const R_PPC64_TPREL64: u32 = 73;
+/// Offset in the module TLS block
+pub const R_PPC64_DTPREL64: u32 = 78;
+/// Upper 16 bits of an absolute address, without overflow check
+pub const R_PPC64_ADDR16_HIGH: u32 = 110;
+/// Adjusted upper 16 bits of an absolute address, without overflow check
+pub const R_PPC64_ADDR16_HIGHA: u32 = 111;
+/// PC relative 26 bit, word aligned, without TOC restore
+pub const R_PPC64_REL24_NOTOC: u32 = 116;
+/// STT_GNU_IFUNC PLT entry
+pub const R_PPC64_JMP_IREL: u32 = 247;
+/// STT_GNU_IFUNC relocation
+pub const R_PPC64_IRELATIVE: u32 = 248;
+/// PC relative 16 bit
+pub const R_PPC64_REL16: u32 = 249;
+/// Lower 16 bits of a PC relative address
+pub const R_PPC64_REL16_LO: u32 = 250;
+/// Upper 16 bits of a PC relative address
+pub const R_PPC64_REL16_HI: u32 = 251;
+/// Adjusted upper 16 bits of a PC relative address
+pub const R_PPC64_REL16_HA: u32 = 252;
+
 #[inline]
 pub fn r_to_str(typ: u32, machine: u16) -> &'static str {
     use crate::elf::header::*;
@@ -1412,6 +1570,86 @@ pub fn r_to_str(typ: u32, machine: u16) -> &'static str {
         R_RISCV_SET32 => "R_RISCV_SET32",
         _ => "R_UNKNOWN_RISCV",
         }},
+        EM_PPC64 => { match typ {
+        R_PPC64_NONE => "PPC64_NONE",
+        R_PPC64_ADDR32 => "PPC64_ADDR32",
+        R_PPC64_ADDR24 => "PPC64_ADDR24",
+        R_PPC64_ADDR16 => "PPC64_ADDR16",
+        R_PPC64_ADDR16_LO => "PPC64_ADDR16_LO",
+        R_PPC64_ADDR16_HI => "PPC64_ADDR16_HI",
+        R_PPC64_ADDR16_HA => "PPC64_ADDR16_HA",
+        R_PPC64_ADDR14 => "PPC64_ADDR14",
+        R_PPC64_ADDR14_BRTAKEN => "PPC64_ADDR14_BRTAKEN",
+        R_PPC64_ADDR14_BRNTAKEN => "PPC64_ADDR14_BRNTAKEN",
+        R_PPC64_REL24 => "PPC64_REL24",
+        R_PPC64_REL14 => "PPC64_REL14",
+        R_PPC64_REL14_BRTAKEN => "PPC64_REL14_BRTAKEN",
+        R_PPC64_REL14_BRNTAKEN => "PPC64_REL14_BRNTAKEN",
+        R_PPC64_GOT16 => "PPC64_GOT16",
+        R_PPC64_GOT16_LO => "PPC64_GOT16_LO",
+        R_PPC64_GOT16_HI => "PPC64_GOT16_HI",
+        R_PPC64_GOT16_HA => "PPC64_GOT16_HA",
+        R_PPC64_COPY => "PPC64_COPY",
+        R_PPC64_GLOB_DAT => "PPC64_GLOB_DAT",
+        R_PPC64_JMP_SLOT => "PPC64_JMP_SLOT",
+        R_PPC64_RELATIVE => "PPC64_RELATIVE",
+        R_PPC64_UADDR32 => "PPC64_UADDR32",
+        R_PPC64_UADDR16 => "PPC64_UADDR16",
+        R_PPC64_REL32 => "PPC64_REL32",
+        R_PPC64_PLT32 => "PPC64_PLT32",
+        R_PPC64_PLTREL32 => "PPC64_PLTREL32",
+        R_PPC64_PLT16_LO => "PPC64_PLT16_LO",
+        R_PPC64_PLT16_HI => "PPC64_PLT16_HI",
+        R_PPC64_PLT16_HA => "PPC64_PLT16_HA",
+        R_PPC64_SECTOFF => "PPC64_SECTOFF",
+        R_PPC64_SECTOFF_LO => "PPC64_SECTOFF_LO",
+        R_PPC64_SECTOFF_HI => "PPC64_SECTOFF_HI",
+        R_PPC64_SECTOFF_HA => "PPC64_SECTOFF_HA",
+        R_PPC64_ADDR30 => "PPC64_ADDR30",
+        R_PPC64_ADDR64 => "PPC64_ADDR64",
+        R_PPC64_ADDR16_HIGHER => "PPC64_ADDR16_HIGHER",
+        R_PPC64_ADDR16_HIGHERA => "PPC64_ADDR16_HIGHERA",
+        R_PPC64_ADDR16_HIGHEST => "PPC64_ADDR16_HIGHEST",
+        R_PPC64_ADDR16_HIGHESTA => "PPC64_ADDR16_HIGHESTA",
+        R_PPC64_UADDR64 => "PPC64_UADDR64",
+        R_PPC64_REL64 => "PPC64_REL64",
+        R_PPC64_PLT64 => "PPC64_PLT64",
+        R_PPC64_PLTREL64 => "PPC64_PLTREL64",
+        R_PPC64_TOC16 => "PPC64_TOC16",
+        R_PPC64_TOC16_LO => "PPC64_TOC16_LO",
+        R_PPC64_TOC16_HI => "PPC64_TOC16_HI",
+        R_PPC64_TOC16_HA => "PPC64_TOC16_HA",
+        R_PPC64_TOC => "PPC64_TOC",
+        R_PPC64_PLTGOT16 => "PPC64_PLTGOT16",
+        R_PPC64_PLTGOT16_LO => "PPC64_PLTGOT16_LO",
+        R_PPC64_PLTGOT16_HI => "PPC64_PLTGOT16_HI",
+        R_PPC64_PLTGOT16_HA => "PPC64_PLTGOT16_HA",
+        R_PPC64_ADDR16_DS => "PPC64_ADDR16_DS",
+        R_PPC64_ADDR16_LO_DS => "PPC64_ADDR16_LO_DS",
+        R_PPC64_GOT16_DS => "PPC64_GOT16_DS",
+        R_PPC64_GOT16_LO_DS => "PPC64_GOT16_LO_DS",
+        R_PPC64_PLT16_LO_DS => "PPC64_PLT16_LO_DS",
+        R_PPC64_SECTOFF_DS => "PPC64_SECTOFF_DS",
+        R_PPC64_SECTOFF_LO_DS => "PPC64_SECTOFF_LO_DS",
+        R_PPC64_TOC16_DS => "PPC64_TOC16_DS",
+        R_PPC64_TOC16_LO_DS => "PPC64_TOC16_LO_DS",
+        R_PPC64_PLTGOT16_DS => "PPC64_PLTGOT16_DS",
+        R_PPC64_PLTGOT16_LO_DS => "PPC64_PLTGOT16_LO_DS",
+        R_PPC64_TLS => "PPC64_TLS",
+        R_PPC64_DTPMOD64 => "PPC64_DTPMOD64",
+        R_PPC64_TPREL64 => "PPC64_TPREL64",
+        R_PPC64_DTPREL64 => "PPC64_DTPREL64",
+        R_PPC64_ADDR16_HIGH => "PPC64_ADDR16_HIGH",
+        R_PPC64_ADDR16_HIGHA => "PPC64_ADDR16_HIGHA",
+        R_PPC64_REL24_NOTOC => "PPC64_REL24_NOTOC",
+        R_PPC64_JMP_IREL => "PPC64_JMP_IREL",
+        R_PPC64_IRELATIVE => "PPC64_IRELATIVE",
+        R_PPC64_REL16 => "PPC64_REL16",
+        R_PPC64_REL16_LO => "PPC64_REL16_LO",
+        R_PPC64_REL16_HI => "PPC64_REL16_HI",
+        R_PPC64_REL16_HA => "PPC64_REL16_HA",
+        _ => "R_UNKNOWN_PPC64",
+        }},
         _ => "R_UNKNOWN",
     }
 }
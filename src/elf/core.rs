@@ -0,0 +1,665 @@
+//! Linux core dumps.
+//!
+//! A core file (`ET_CORE`) has no sections: its `PT_NOTE` segment describes the process and its
+//! threads, and its `PT_LOAD` segments hold the memory of the process, unless it was filtered out
+//! of the dump. Each thread starts with an `NT_PRSTATUS` note, holding its general purpose
+//! registers, followed by its other register sets; the process wide `NT_PRPSINFO`, `NT_SIGINFO`,
+//! `NT_AUXV` and `NT_FILE` notes are written along with the first thread, the one that crashed.
+
+use alloc::vec::Vec;
+use core::cmp;
+use scroll::{ctx, Pread, SizeWith};
+
+use crate::container::{Container, Ctx};
+use crate::error;
+
+use super::header::{et_to_str, EM_386, EM_AARCH64, EM_ARM, EM_RISCV, EM_X86_64, ET_CORE};
+use super::note::{Note, NT_AUXV, NT_FILE, NT_PRPSINFO, NT_PRSTATUS, NT_SIGINFO};
+use super::program_header::PT_LOAD;
+use super::Elf;
+
+/// The name of the notes written by the kernel for core dumps
+pub const ELF_NOTE_CORE: &str = "CORE";
+
+/// End of the auxiliary vector
+pub const AT_NULL: u64 = 0;
+/// Address of the program headers of the executable
+pub const AT_PHDR: u64 = 3;
+/// Size of a program header
+pub const AT_PHENT: u64 = 4;
+/// Number of program headers
+pub const AT_PHNUM: u64 = 5;
+/// System page size
+pub const AT_PAGESZ: u64 = 6;
+/// Base address of the interpreter
+pub const AT_BASE: u64 = 7;
+/// Entry point of the executable
+pub const AT_ENTRY: u64 = 9;
+pub const AT_UID: u64 = 11;
+pub const AT_EUID: u64 = 12;
+pub const AT_GID: u64 = 13;
+pub const AT_EGID: u64 = 14;
+/// Address of the string identifying the platform
+pub const AT_PLATFORM: u64 = 15;
+/// Machine dependent hardware capabilities
+pub const AT_HWCAP: u64 = 16;
+/// Frequency of `times()`
+pub const AT_CLKTCK: u64 = 17;
+/// Whether the program runs with elevated privileges
+pub const AT_SECURE: u64 = 23;
+/// Address of 16 random bytes
+pub const AT_RANDOM: u64 = 25;
+/// More machine dependent hardware capabilities
+pub const AT_HWCAP2: u64 = 26;
+/// Address of the path the executable was run from
+pub const AT_EXECFN: u64 = 31;
+/// Address of the vDSO
+pub const AT_SYSINFO_EHDR: u64 = 33;
+
+const SIGILL: i32 = 4;
+const SIGTRAP: i32 = 5;
+const SIGBUS: i32 = 7;
+const SIGFPE: i32 = 8;
+const SIGSEGV: i32 = 11;
+
+/// Reads a `long` of the core's machine
+fn read_word(bytes: &[u8], offset: &mut usize, ctx: Ctx) -> error::Result<u64> {
+    Ok(if ctx.is_big() {
+        bytes.gread_with::<u64>(offset, ctx.le)?
+    } else {
+        u64::from(bytes.gread_with::<u32>(offset, ctx.le)?)
+    })
+}
+
+/// Reads a `char` array, up to its first NUL byte
+fn read_chars<'a>(bytes: &'a [u8], offset: &mut usize, len: usize) -> error::Result<&'a [u8]> {
+    let chars: &'a [u8] = bytes.gread_with(offset, len)?;
+    let end = chars.iter().position(|&c| c == 0).unwrap_or(len);
+    Ok(&chars[..end])
+}
+
+/// A `struct timeval`
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct TimeVal {
+    pub tv_sec: i64,
+    pub tv_usec: i64,
+}
+
+impl<'a> ctx::TryFromCtx<'a, Ctx> for TimeVal {
+    type Error = error::Error;
+    fn try_from_ctx(bytes: &'a [u8], ctx: Ctx) -> error::Result<(Self, usize)> {
+        let offset = &mut 0;
+        let tv_sec = read_word(bytes, offset, ctx)?;
+        let tv_usec = read_word(bytes, offset, ctx)?;
+        let (tv_sec, tv_usec) = if ctx.is_big() {
+            (tv_sec as i64, tv_usec as i64)
+        } else {
+            (i64::from(tv_sec as i32), i64::from(tv_usec as i32))
+        };
+        Ok((TimeVal { tv_sec, tv_usec }, *offset))
+    }
+}
+
+/// The status of a thread, from an `NT_PRSTATUS` note (`struct elf_prstatus`)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PrStatus<'a> {
+    /// The signal that stopped the thread
+    pub si_signo: i32,
+    pub si_code: i32,
+    pub si_errno: i32,
+    /// The current signal
+    pub pr_cursig: i16,
+    /// The set of pending signals
+    pub pr_sigpend: u64,
+    /// The set of held signals
+    pub pr_sighold: u64,
+    pub pr_pid: i32,
+    pub pr_ppid: i32,
+    pub pr_pgrp: i32,
+    pub pr_sid: i32,
+    /// User time
+    pub pr_utime: TimeVal,
+    /// System time
+    pub pr_stime: TimeVal,
+    /// Cumulative user time
+    pub pr_cutime: TimeVal,
+    /// Cumulative system time
+    pub pr_cstime: TimeVal,
+    /// The general purpose registers, laid out as the machine's `elf_gregset_t`
+    pub pr_reg: &'a [u8],
+    /// Whether the thread used the FPU, and its registers are in an `NT_FPREGSET` note
+    pub pr_fpvalid: i32,
+}
+
+impl<'a> PrStatus<'a> {
+    pub fn parse(desc: &'a [u8], ctx: Ctx) -> error::Result<Self> {
+        let offset = &mut 0;
+        let si_signo = desc.gread_with(offset, ctx.le)?;
+        let si_code = desc.gread_with(offset, ctx.le)?;
+        let si_errno = desc.gread_with(offset, ctx.le)?;
+        let pr_cursig = desc.gread_with(offset, ctx.le)?;
+        // `pr_sigpend` is aligned to a `long`, at offset 16 in both classes
+        *offset += 2;
+        let pr_sigpend = read_word(desc, offset, ctx)?;
+        let pr_sighold = read_word(desc, offset, ctx)?;
+        let pr_pid = desc.gread_with(offset, ctx.le)?;
+        let pr_ppid = desc.gread_with(offset, ctx.le)?;
+        let pr_pgrp = desc.gread_with(offset, ctx.le)?;
+        let pr_sid = desc.gread_with(offset, ctx.le)?;
+        let pr_utime = desc.gread_with(offset, ctx)?;
+        let pr_stime = desc.gread_with(offset, ctx)?;
+        let pr_cutime = desc.gread_with(offset, ctx)?;
+        let pr_cstime = desc.gread_with(offset, ctx)?;
+        // the registers are followed by `pr_fpvalid`, padded to the alignment of a `long`
+        let trailer = if ctx.is_big() { 8 } else { 4 };
+        let reg_size = desc.len().checked_sub(*offset + trailer).ok_or_else(|| {
+            error::Error::Malformed(format!("NT_PRSTATUS note is too small ({:#x})", desc.len()))
+        })?;
+        let pr_reg = desc.gread_with(offset, reg_size)?;
+        let pr_fpvalid = desc.gread_with(offset, ctx.le)?;
+        Ok(PrStatus {
+            si_signo,
+            si_code,
+            si_errno,
+            pr_cursig,
+            pr_sigpend,
+            pr_sighold,
+            pr_pid,
+            pr_ppid,
+            pr_pgrp,
+            pr_sid,
+            pr_utime,
+            pr_stime,
+            pr_cutime,
+            pr_cstime,
+            pr_reg,
+            pr_fpvalid,
+        })
+    }
+}
+
+/// The general purpose registers of an x86-64 thread (`struct user_regs_struct`)
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Pread, SizeWith)]
+pub struct X86_64Registers {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    /// The system call number, if the thread was in a system call
+    pub orig_rax: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub eflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+    pub fs_base: u64,
+    pub gs_base: u64,
+    pub ds: u64,
+    pub es: u64,
+    pub fs: u64,
+    pub gs: u64,
+}
+
+/// The general purpose registers of an AArch64 thread (`struct user_pt_regs`)
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Pread, SizeWith)]
+pub struct Aarch64Registers {
+    /// `x0` to `x30`, `x29` being the frame pointer and `x30` the link register
+    pub regs: [u64; 31],
+    pub sp: u64,
+    pub pc: u64,
+    pub pstate: u64,
+}
+
+/// The general purpose registers of a 64-bit RISC-V thread (`struct user_regs_struct`)
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Pread, SizeWith)]
+pub struct Riscv64Registers {
+    pub pc: u64,
+    /// `x1` (`ra`) to `x31` (`t6`); `x2` is the stack pointer
+    pub regs: [u64; 31],
+}
+
+/// The general purpose registers of a thread
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Registers {
+    X86_64(X86_64Registers),
+    Aarch64(Aarch64Registers),
+    Riscv64(Riscv64Registers),
+    /// The registers of another machine, only available as `PrStatus::pr_reg`
+    Unknown,
+}
+
+impl Registers {
+    /// Decodes the `elf_gregset_t` of `machine` in `pr_reg`
+    pub fn parse(pr_reg: &[u8], machine: u16, ctx: Ctx) -> error::Result<Self> {
+        Ok(match (machine, ctx.container) {
+            (EM_X86_64, Container::Big) => Registers::X86_64(pr_reg.pread_with(0, ctx.le)?),
+            (EM_AARCH64, Container::Big) => Registers::Aarch64(pr_reg.pread_with(0, ctx.le)?),
+            (EM_RISCV, Container::Big) => Registers::Riscv64(pr_reg.pread_with(0, ctx.le)?),
+            _ => Registers::Unknown,
+        })
+    }
+
+    /// The program counter
+    pub fn pc(&self) -> Option<u64> {
+        match self {
+            Registers::X86_64(regs) => Some(regs.rip),
+            Registers::Aarch64(regs) => Some(regs.pc),
+            Registers::Riscv64(regs) => Some(regs.pc),
+            Registers::Unknown => None,
+        }
+    }
+
+    /// The stack pointer
+    pub fn sp(&self) -> Option<u64> {
+        match self {
+            Registers::X86_64(regs) => Some(regs.rsp),
+            Registers::Aarch64(regs) => Some(regs.sp),
+            Registers::Riscv64(regs) => Some(regs.regs[1]),
+            Registers::Unknown => None,
+        }
+    }
+}
+
+/// A thread of the dumped process
+#[derive(Debug)]
+pub struct Thread<'a> {
+    pub prstatus: PrStatus<'a>,
+    pub registers: Registers,
+    /// The notes following the `NT_PRSTATUS` note of the thread, e.g., its `NT_FPREGSET` and
+    /// other register sets
+    pub notes: Vec<Note<'a>>,
+}
+
+/// The process information of an `NT_PRPSINFO` note (`struct elf_prpsinfo`)
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PrPsInfo<'a> {
+    /// Numeric process state
+    pub pr_state: u8,
+    /// Character of the process state, as `ps` shows it
+    pub pr_sname: u8,
+    /// Whether the process is a zombie
+    pub pr_zomb: u8,
+    pub pr_nice: i8,
+    /// The `PF_*` flags of the process
+    pub pr_flag: u64,
+    pub pr_uid: u32,
+    pub pr_gid: u32,
+    pub pr_pid: i32,
+    pub pr_ppid: i32,
+    pub pr_pgrp: i32,
+    pub pr_sid: i32,
+    /// The name of the executable, truncated to 15 bytes
+    pub pr_fname: &'a [u8],
+    /// The command line, truncated to 79 bytes, possibly within a character
+    pub pr_psargs: &'a [u8],
+}
+
+impl<'a> PrPsInfo<'a> {
+    pub fn parse(desc: &'a [u8], machine: u16, ctx: Ctx) -> error::Result<Self> {
+        let offset = &mut 0;
+        let pr_state = desc.gread(offset)?;
+        let pr_sname = desc.gread(offset)?;
+        let pr_zomb = desc.gread(offset)?;
+        let pr_nice = desc.gread(offset)?;
+        if ctx.is_big() {
+            *offset += 4;
+        }
+        let pr_flag = read_word(desc, offset, ctx)?;
+        // i386 and 32-bit ARM still use 16-bit ids
+        let (pr_uid, pr_gid) = match (machine, ctx.container) {
+            (EM_386, Container::Little) | (EM_ARM, Container::Little) => (
+                u32::from(desc.gread_with::<u16>(offset, ctx.le)?),
+                u32::from(desc.gread_with::<u16>(offset, ctx.le)?),
+            ),
+            _ => (
+                desc.gread_with(offset, ctx.le)?,
+                desc.gread_with(offset, ctx.le)?,
+            ),
+        };
+        Ok(PrPsInfo {
+            pr_state,
+            pr_sname,
+            pr_zomb,
+            pr_nice,
+            pr_flag,
+            pr_uid,
+            pr_gid,
+            pr_pid: desc.gread_with(offset, ctx.le)?,
+            pr_ppid: desc.gread_with(offset, ctx.le)?,
+            pr_pgrp: desc.gread_with(offset, ctx.le)?,
+            pr_sid: desc.gread_with(offset, ctx.le)?,
+            pr_fname: read_chars(desc, offset, 16)?,
+            pr_psargs: read_chars(desc, offset, 80)?,
+        })
+    }
+}
+
+/// The signal that killed the process, from an `NT_SIGINFO` note (`siginfo_t`)
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct SigInfo {
+    pub si_signo: i32,
+    pub si_errno: i32,
+    /// Why the signal was sent; positive values are set by the kernel, others by a process
+    pub si_code: i32,
+    /// The faulting address of a `SIGILL`, `SIGTRAP`, `SIGBUS`, `SIGFPE` or `SIGSEGV` raised by
+    /// the kernel
+    pub si_addr: Option<u64>,
+    /// The process which sent the signal, if it was not the kernel
+    pub si_pid: Option<i32>,
+    /// The real user ID of `si_pid`
+    pub si_uid: Option<u32>,
+}
+
+impl SigInfo {
+    pub fn parse(desc: &[u8], ctx: Ctx) -> error::Result<Self> {
+        let offset = &mut 0;
+        let si_signo = desc.gread_with(offset, ctx.le)?;
+        let si_errno = desc.gread_with(offset, ctx.le)?;
+        let si_code = desc.gread_with(offset, ctx.le)?;
+        // the union of the signal specific fields is aligned to a `long`
+        if ctx.is_big() {
+            *offset += 4;
+        }
+        let mut siginfo = SigInfo {
+            si_signo,
+            si_errno,
+            si_code,
+            si_addr: None,
+            si_pid: None,
+            si_uid: None,
+        };
+        if si_code <= 0 {
+            siginfo.si_pid = Some(desc.gread_with(offset, ctx.le)?);
+            siginfo.si_uid = Some(desc.gread_with(offset, ctx.le)?);
+        } else if let SIGILL | SIGTRAP | SIGBUS | SIGFPE | SIGSEGV = si_signo {
+            siginfo.si_addr = Some(read_word(desc, offset, ctx)?);
+        }
+        Ok(siginfo)
+    }
+}
+
+/// A file mapped in the address space of the process, from an `NT_FILE` note
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct MappedFile<'a> {
+    /// The start address of the mapping
+    pub start: u64,
+    /// The end address of the mapping, exclusive
+    pub end: u64,
+    /// The offset of the mapping in the file, in bytes
+    pub offset: u64,
+    pub path: &'a str,
+}
+
+impl<'a> MappedFile<'a> {
+    /// Parses the mappings of an `NT_FILE` note
+    pub fn parse_all(desc: &'a [u8], ctx: Ctx) -> error::Result<Vec<Self>> {
+        let offset = &mut 0;
+        let count = read_word(desc, offset, ctx)?;
+        let page_size = read_word(desc, offset, ctx)?;
+        let word_size = if ctx.is_big() { 8 } else { 4 };
+        // every mapping needs at least 3 words and a NUL terminator
+        if count > (desc.len() / (3 * word_size + 1)) as u64 {
+            return Err(error::Error::Malformed(format!(
+                "NT_FILE note has too many mappings ({})",
+                count
+            )));
+        }
+        let mut files = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let start = read_word(desc, offset, ctx)?;
+            let end = read_word(desc, offset, ctx)?;
+            let page_offset = read_word(desc, offset, ctx)?;
+            files.push(MappedFile {
+                start,
+                end,
+                offset: page_offset.wrapping_mul(page_size),
+                path: "",
+            });
+        }
+        for file in &mut files {
+            file.path = desc.gread(offset)?;
+        }
+        Ok(files)
+    }
+}
+
+/// An entry of the auxiliary vector, from an `NT_AUXV` note
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct AuxvEntry {
+    /// One of the `AT_*` types
+    pub a_type: u64,
+    pub a_val: u64,
+}
+
+impl AuxvEntry {
+    /// Parses the entries of an `NT_AUXV` note, up to its `AT_NULL` entry
+    pub fn parse_all(desc: &[u8], ctx: Ctx) -> error::Result<Vec<Self>> {
+        let mut auxv = Vec::new();
+        let offset = &mut 0;
+        while *offset < desc.len() {
+            let a_type = read_word(desc, offset, ctx)?;
+            let a_val = read_word(desc, offset, ctx)?;
+            if a_type == AT_NULL {
+                break;
+            }
+            auxv.push(AuxvEntry { a_type, a_val });
+        }
+        Ok(auxv)
+    }
+}
+
+/// A Linux core dump
+#[derive(Debug)]
+pub struct Core<'a> {
+    pub elf: Elf<'a>,
+    /// The threads of the process, starting with the one that crashed
+    pub threads: Vec<Thread<'a>>,
+    pub prpsinfo: Option<PrPsInfo<'a>>,
+    pub siginfo: Option<SigInfo>,
+    /// The files mapped by the process
+    pub mapped_files: Vec<MappedFile<'a>>,
+    /// The auxiliary vector of the process
+    pub auxv: Vec<AuxvEntry>,
+    bytes: &'a [u8],
+}
+
+impl<'a> Core<'a> {
+    /// Parses the core dump in `bytes`
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        Self::from_elf(Elf::parse(bytes)?, bytes)
+    }
+
+    /// Decodes the notes of `elf`, parsed from `bytes`, which must be a core dump
+    pub fn from_elf(elf: Elf<'a>, bytes: &'a [u8]) -> error::Result<Self> {
+        if elf.header.e_type != ET_CORE {
+            return Err(error::Error::Malformed(format!(
+                "ELF file is not a core dump ({})",
+                et_to_str(elf.header.e_type)
+            )));
+        }
+        let machine = elf.header.e_machine;
+        let ctx = elf.ctx;
+        let mut core = Core {
+            elf,
+            threads: Vec::new(),
+            prpsinfo: None,
+            siginfo: None,
+            mapped_files: Vec::new(),
+            auxv: Vec::new(),
+            bytes,
+        };
+        let notes = match core.elf.iter_note_headers(bytes) {
+            Some(notes) => notes,
+            None => return Ok(core),
+        };
+        for note in notes {
+            let note = note?;
+            if note.name != ELF_NOTE_CORE {
+                if let Some(thread) = core.threads.last_mut() {
+                    thread.notes.push(note);
+                }
+                continue;
+            }
+            match note.n_type {
+                NT_PRSTATUS => {
+                    let prstatus = PrStatus::parse(note.desc, ctx)?;
+                    let registers = Registers::parse(prstatus.pr_reg, machine, ctx)?;
+                    core.threads.push(Thread {
+                        prstatus,
+                        registers,
+                        notes: Vec::new(),
+                    });
+                }
+                NT_PRPSINFO => core.prpsinfo = Some(PrPsInfo::parse(note.desc, machine, ctx)?),
+                NT_SIGINFO => core.siginfo = Some(SigInfo::parse(note.desc, ctx)?),
+                NT_FILE => core.mapped_files = MappedFile::parse_all(note.desc, ctx)?,
+                NT_AUXV => core.auxv = AuxvEntry::parse_all(note.desc, ctx)?,
+                _ => {
+                    if let Some(thread) = core.threads.last_mut() {
+                        thread.notes.push(note);
+                    }
+                }
+            }
+        }
+        Ok(core)
+    }
+
+    /// The value of the auxiliary vector entry of type `a_type`
+    pub fn auxv_value(&self, a_type: u64) -> Option<u64> {
+        self.auxv
+            .iter()
+            .find(|entry| entry.a_type == a_type)
+            .map(|entry| entry.a_val)
+    }
+
+    /// The file mapped at `address`, if any
+    pub fn mapped_file(&self, address: u64) -> Option<&MappedFile<'a>> {
+        self.mapped_files
+            .iter()
+            .find(|file| file.start <= address && address < file.end)
+    }
+
+    /// Reads `len` bytes of the memory of the process at `address`.
+    ///
+    /// Returns `None` if any of them is not mapped, or was not dumped into the file.
+    pub fn read_memory(&self, address: u64, len: usize) -> Option<&'a [u8]> {
+        let available = self.memory_at(address)?;
+        available.get(..len)
+    }
+
+    /// Reads a `long` of the memory of the process at `address`
+    pub fn read_word(&self, address: u64) -> Option<u64> {
+        let word_size = if self.elf.is_64 { 8 } else { 4 };
+        let bytes = self.read_memory(address, word_size)?;
+        read_word(bytes, &mut 0, self.elf.ctx).ok()
+    }
+
+    /// Reads the NUL terminated string of the memory of the process at `address`, e.g., the
+    /// value of `AT_EXECFN`
+    pub fn read_str(&self, address: u64) -> Option<&'a str> {
+        let available = self.memory_at(address)?;
+        let end = available.iter().position(|&byte| byte == 0)?;
+        core::str::from_utf8(&available[..end]).ok()
+    }
+
+    /// Returns the memory dumped from `address` up to the end of its `PT_LOAD` segment
+    fn memory_at(&self, address: u64) -> Option<&'a [u8]> {
+        let phdr = self.elf.program_headers.iter().find(|phdr| {
+            phdr.p_type == PT_LOAD
+                && phdr.p_vaddr <= address
+                && address - phdr.p_vaddr < cmp::min(phdr.p_filesz, phdr.p_memsz)
+        })?;
+        let start = phdr.p_offset.checked_add(address - phdr.p_vaddr)?;
+        let end = phdr
+            .p_offset
+            .checked_add(cmp::min(phdr.p_filesz, phdr.p_memsz))?;
+        self.bytes.get(start as usize..end as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LE64: Ctx = Ctx {
+        container: Container::Big,
+        le: scroll::Endian::Little,
+    };
+    const LE32: Ctx = Ctx {
+        container: Container::Little,
+        le: scroll::Endian::Little,
+    };
+
+    fn words(words: &[u64]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn parse_registers() {
+        let pr_reg = words(&(0..34).collect::<Vec<_>>());
+        match Registers::parse(&pr_reg, EM_AARCH64, LE64).unwrap() {
+            Registers::Aarch64(regs) => {
+                assert_eq!(regs.regs[30], 30);
+                assert_eq!((regs.sp, regs.pc, regs.pstate), (31, 32, 33));
+            }
+            regs => panic!("unexpected registers {:?}", regs),
+        }
+        let registers = Registers::parse(&pr_reg[..256], EM_RISCV, LE64).unwrap();
+        assert_eq!(registers.pc(), Some(0));
+        assert_eq!(registers.sp(), Some(2));
+        assert!(Registers::parse(&pr_reg[..248], EM_RISCV, LE64).is_err());
+        assert_eq!(
+            Registers::parse(&pr_reg, EM_RISCV, LE32).unwrap(),
+            Registers::Unknown
+        );
+    }
+
+    #[test]
+    fn parse_siginfo() {
+        // SIGTERM sent by kill(2) from pid 42, uid 1000
+        let desc = [
+            15, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 42, 0, 0, 0, 0xe8, 3, 0, 0,
+        ];
+        let siginfo = SigInfo::parse(&desc, LE64).unwrap();
+        assert_eq!(siginfo.si_signo, 15);
+        assert_eq!((siginfo.si_pid, siginfo.si_uid), (Some(42), Some(1000)));
+        assert_eq!(siginfo.si_addr, None);
+
+        // SIGSEGV on a 32-bit machine, the address follows the first three fields
+        let desc = [11, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0x10, 0, 0, 0];
+        let siginfo = SigInfo::parse(&desc, LE32).unwrap();
+        assert_eq!(siginfo.si_addr, Some(0x10));
+        assert_eq!(siginfo.si_pid, None);
+    }
+
+    #[test]
+    fn parse_mapped_files() {
+        let mut desc = Vec::new();
+        for word in &[2u32, 0x1000, 0x1000, 0x3000, 0, 0x8000, 0x9000, 2] {
+            desc.extend_from_slice(&word.to_le_bytes());
+        }
+        desc.extend_from_slice(b"/bin/true\0/lib/libc.so\0");
+        let files = MappedFile::parse_all(&desc, LE32).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].offset, 0x2000);
+        assert_eq!(files[1].path, "/lib/libc.so");
+        assert!(MappedFile::parse_all(&desc[..20], LE32).is_err());
+
+        desc[0] = 0xff;
+        assert!(MappedFile::parse_all(&desc, LE32).is_err());
+    }
+}
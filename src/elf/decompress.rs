@@ -0,0 +1,105 @@
+//! Decompression of compressed ELF sections.
+//!
+//! Sections flagged `SHF_COMPRESSED` start with an `Elf_Chdr` naming the algorithm (zlib or
+//! zstd) and the uncompressed size. The legacy GNU format instead renames `.debug_*` sections to
+//! `.zdebug_*` and prefixes their zlib stream with `ZLIB` and the big-endian uncompressed size.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use scroll::Pread;
+
+use crate::error;
+
+use super::compression_header::{ELFCOMPRESS_ZLIB, ELFCOMPRESS_ZSTD};
+
+/// The magic of legacy GNU compressed sections
+pub const ZDEBUG_MAGIC: &[u8; 4] = b"ZLIB";
+/// The size of the legacy GNU compression header: the magic and the uncompressed size
+pub const SIZEOF_ZDEBUG_HEADER: usize = 12;
+
+/// Decompresses `data`, compressed with the `ELFCOMPRESS_*` algorithm `ch_type`, checking that
+/// it holds exactly `size` bytes
+pub fn decompress(ch_type: u32, data: &[u8], size: u64) -> error::Result<Vec<u8>> {
+    let size = usize::try_from(size).map_err(|_| {
+        error::Error::Malformed(format!(
+            "Compressed section size ({:#x}) is too large",
+            size
+        ))
+    })?;
+    let decompressed = match ch_type {
+        ELFCOMPRESS_ZLIB => zlib(data, size)?,
+        ELFCOMPRESS_ZSTD => zstd(data, size)?,
+        _ => {
+            return Err(error::Error::Malformed(format!(
+                "Unsupported section compression type ({:#x})",
+                ch_type
+            )))
+        }
+    };
+    if decompressed.len() != size {
+        return Err(error::Error::Malformed(format!(
+            "Decompressed section size ({:#x}) does not match the expected size ({:#x})",
+            decompressed.len(),
+            size
+        )));
+    }
+    Ok(decompressed)
+}
+
+/// Decompresses a legacy GNU `.zdebug_*` section, returning `None` if it has no `ZLIB` header
+pub fn decompress_zdebug(data: &[u8]) -> Option<error::Result<Vec<u8>>> {
+    if !data.starts_with(ZDEBUG_MAGIC) {
+        return None;
+    }
+    let decompressed = data
+        .pread_with::<u64>(ZDEBUG_MAGIC.len(), scroll::BE)
+        .map_err(Into::into)
+        .and_then(|size| decompress(ELFCOMPRESS_ZLIB, &data[SIZEOF_ZDEBUG_HEADER..], size));
+    Some(decompressed)
+}
+
+fn zlib(data: &[u8], size: usize) -> error::Result<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, size).map_err(|err| {
+        error::Error::Malformed(format!("Invalid zlib compressed section ({})", err))
+    })
+}
+
+fn zstd(data: &[u8], size: usize) -> error::Result<Vec<u8>> {
+    use ruzstd::io::Read;
+
+    let invalid = |err: &dyn core::fmt::Display| {
+        error::Error::Malformed(format!("Invalid zstd compressed section ({})", err))
+    };
+    let decoder = ruzstd::decoding::StreamingDecoder::new(data).map_err(|err| invalid(&err))?;
+    // the buffer grows with the decompressed data rather than trusting the size, reading a byte
+    // past it catches sections larger than announced
+    let mut decompressed = Vec::new();
+    decoder
+        .take((size as u64).saturating_add(1))
+        .read_to_end(&mut decompressed)
+        .map_err(|err| invalid(&err))?;
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A zstd frame holding "hello" in a raw block
+    const ZSTD_HELLO: &[u8] = b"\x28\xb5\x2f\xfd\x20\x05\x29\x00\x00hello";
+
+    #[test]
+    fn zstd_sizes() {
+        assert_eq!(
+            decompress(ELFCOMPRESS_ZSTD, ZSTD_HELLO, 5).unwrap(),
+            b"hello"
+        );
+        assert!(decompress(ELFCOMPRESS_ZSTD, ZSTD_HELLO, 4).is_err());
+        assert!(decompress(ELFCOMPRESS_ZSTD, &ZSTD_HELLO[..12], 5).is_err());
+        // a huge size is only checked once the data is decompressed, nothing is allocated for it
+        match decompress(ELFCOMPRESS_ZSTD, ZSTD_HELLO, 3 << 30) {
+            Err(error::Error::Malformed(message)) => assert!(message.contains("does not match")),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -98,10 +98,30 @@ pub const DT_PREINIT_ARRAY: u64 = 32;
 pub const DT_PREINIT_ARRAYSZ: u64 = 33;
 /// Number used
 pub const DT_NUM: u64 = 34;
+/// Total size of RELR relative relocations
+pub const DT_RELRSZ: u64 = 35;
+/// Address of RELR relative relocations
+pub const DT_RELR: u64 = 36;
+/// Size of one RELR relative relocation
+pub const DT_RELRENT: u64 = 37;
 /// Start of OS-specific
 pub const DT_LOOS: u64 = 0x6000_000d;
 /// End of OS-specific
 pub const DT_HIOS: u64 = 0x6fff_f000;
+/// Address of Android packed relocations without addends
+pub const DT_ANDROID_REL: u64 = 0x6000_000f;
+/// Total size of Android packed relocations without addends
+pub const DT_ANDROID_RELSZ: u64 = 0x6000_0010;
+/// Address of Android packed relocations with addends
+pub const DT_ANDROID_RELA: u64 = 0x6000_0011;
+/// Total size of Android packed relocations with addends
+pub const DT_ANDROID_RELASZ: u64 = 0x6000_0012;
+/// Address of RELR relative relocations, as tagged by Android before `DT_RELR`
+pub const DT_ANDROID_RELR: u64 = 0x6fff_e000;
+/// Total size of RELR relative relocations, as tagged by Android before `DT_RELRSZ`
+pub const DT_ANDROID_RELRSZ: u64 = 0x6fff_e001;
+/// Size of one RELR relative relocation, as tagged by Android before `DT_RELRENT`
+pub const DT_ANDROID_RELRENT: u64 = 0x6fff_e003;
 /// Start of processor-specific
 pub const DT_LOPROC: u64 = 0x7000_0000;
 /// End of processor-specific
@@ -196,8 +216,18 @@ pub fn tag_to_str(tag: u64) -> &'static str {
         DT_PREINIT_ARRAY => "DT_PREINIT_ARRAY",
         DT_PREINIT_ARRAYSZ => "DT_PREINIT_ARRAYSZ",
         DT_NUM => "DT_NUM",
+        DT_RELRSZ => "DT_RELRSZ",
+        DT_RELR => "DT_RELR",
+        DT_RELRENT => "DT_RELRENT",
         DT_LOOS => "DT_LOOS",
         DT_HIOS => "DT_HIOS",
+        DT_ANDROID_REL => "DT_ANDROID_REL",
+        DT_ANDROID_RELSZ => "DT_ANDROID_RELSZ",
+        DT_ANDROID_RELA => "DT_ANDROID_RELA",
+        DT_ANDROID_RELASZ => "DT_ANDROID_RELASZ",
+        DT_ANDROID_RELR => "DT_ANDROID_RELR",
+        DT_ANDROID_RELRSZ => "DT_ANDROID_RELRSZ",
+        DT_ANDROID_RELRENT => "DT_ANDROID_RELRENT",
         DT_LOPROC => "DT_LOPROC",
         DT_HIPROC => "DT_HIPROC",
         DT_VERSYM => "DT_VERSYM",
@@ -605,6 +635,13 @@ macro_rules! elf_dynamic_info_std_impl {
             pub relsz: usize,
             pub relent: $size,
             pub relcount: usize,
+            pub relr: usize,
+            pub relrsz: usize,
+            pub relrent: $size,
+            pub android_rel: usize,
+            pub android_relsz: usize,
+            pub android_rela: usize,
+            pub android_relasz: usize,
             pub gnu_hash: Option<$size>,
             pub hash: Option<$size>,
             pub strtab: usize,
@@ -645,6 +682,19 @@ macro_rules! elf_dynamic_info_std_impl {
                     DT_RELSZ => self.relsz = dynamic.d_val as usize,
                     DT_RELENT => self.relent = dynamic.d_val as _,
                     DT_RELCOUNT => self.relcount = dynamic.d_val as usize,
+                    DT_RELR | DT_ANDROID_RELR => {
+                        self.relr = vm_to_offset(phdrs, dynamic.d_val).unwrap_or(0) as usize
+                    }
+                    DT_RELRSZ | DT_ANDROID_RELRSZ => self.relrsz = dynamic.d_val as usize,
+                    DT_RELRENT | DT_ANDROID_RELRENT => self.relrent = dynamic.d_val as _,
+                    DT_ANDROID_REL => {
+                        self.android_rel = vm_to_offset(phdrs, dynamic.d_val).unwrap_or(0) as usize
+                    }
+                    DT_ANDROID_RELSZ => self.android_relsz = dynamic.d_val as usize,
+                    DT_ANDROID_RELA => {
+                        self.android_rela = vm_to_offset(phdrs, dynamic.d_val).unwrap_or(0) as usize
+                    }
+                    DT_ANDROID_RELASZ => self.android_relasz = dynamic.d_val as usize,
                     DT_GNU_HASH => self.gnu_hash = vm_to_offset(phdrs, dynamic.d_val),
                     DT_HASH => self.hash = vm_to_offset(phdrs, dynamic.d_val),
                     DT_STRTAB => {
@@ -745,6 +795,12 @@ macro_rules! elf_dynamic_info_std_impl {
                         .field("relasz", &self.relasz)
                         .field("relaent", &self.relaent)
                         .field("relacount", &self.relacount)
+                        .field("relr", &format_args!("0x{:x}", self.relr))
+                        .field("relrsz", &self.relrsz)
+                        .field("android_rel", &format_args!("0x{:x}", self.android_rel))
+                        .field("android_relsz", &self.android_relsz)
+                        .field("android_rela", &format_args!("0x{:x}", self.android_rela))
+                        .field("android_relasz", &self.android_relasz)
                         .field("gnu_hash", &format_args!("0x{:x}", gnu_hash))
                         .field("hash", &format_args!("0x{:x}", hash))
                         .field("strtab", &format_args!("0x{:x}", self.strtab))
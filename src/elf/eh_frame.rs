@@ -0,0 +1,870 @@
+//! Call frame information: the `.eh_frame` and `.eh_frame_hdr` sections.
+//!
+//! `.eh_frame` holds the call frame information used to unwind the stack, in a variant of the
+//! DWARF `.debug_frame` format. It is a list of records, each a Common Information Entry (CIE) or
+//! a Frame Description Entry (FDE), ended by a zero length. An FDE covers the code range of a
+//! function, usually exactly, and refers to the CIE holding what its functions have in common.
+//! Both carry call frame instructions, which are exposed here as raw bytes.
+//!
+//! The CIE augmentation string tells what the records carry on top of DWARF: with `z`, the CIE
+//! and its FDEs have augmentation data, holding the encoding of the FDE addresses (`R`), the
+//! personality routine (`P`) and the encoding of the FDE language-specific data areas (`L`).
+//! Addresses are encoded with the `DW_EH_PE_*` pointer encodings: a value format, relative to an
+//! optional base, such as the address of the value itself.
+//!
+//! `.eh_frame_hdr`, loaded by the `PT_GNU_EH_FRAME` segment, points to `.eh_frame` and usually
+//! holds a table of the FDEs sorted by the start of their code range, for binary search.
+//!
+//! See the [LSB] for the details.
+//!
+//! [LSB]: https://refspecs.linuxfoundation.org/LSB_5.0.0/LSB-Core-generic/LSB-Core-generic/ehframechpt.html
+
+use core::convert::TryFrom;
+use core::fmt;
+use core::iter::FusedIterator;
+use scroll::{Pread, Sleb128, Uleb128};
+
+use crate::container::Ctx;
+use crate::error;
+
+/// The value is a native word
+pub const DW_EH_PE_ABSPTR: u8 = 0x00;
+/// The value is an unsigned LEB128
+pub const DW_EH_PE_ULEB128: u8 = 0x01;
+/// The value is a 2 bytes unsigned integer
+pub const DW_EH_PE_UDATA2: u8 = 0x02;
+/// The value is a 4 bytes unsigned integer
+pub const DW_EH_PE_UDATA4: u8 = 0x03;
+/// The value is an 8 bytes unsigned integer
+pub const DW_EH_PE_UDATA8: u8 = 0x04;
+/// The value is a signed LEB128
+pub const DW_EH_PE_SLEB128: u8 = 0x09;
+/// The value is a 2 bytes signed integer
+pub const DW_EH_PE_SDATA2: u8 = 0x0a;
+/// The value is a 4 bytes signed integer
+pub const DW_EH_PE_SDATA4: u8 = 0x0b;
+/// The value is an 8 bytes signed integer
+pub const DW_EH_PE_SDATA8: u8 = 0x0c;
+/// The value is relative to its own address
+pub const DW_EH_PE_PCREL: u8 = 0x10;
+/// The value is relative to the start of the text segment
+pub const DW_EH_PE_TEXTREL: u8 = 0x20;
+/// The value is relative to the start of the data: `.eh_frame_hdr` in `.eh_frame_hdr`, the GOT
+/// elsewhere
+pub const DW_EH_PE_DATAREL: u8 = 0x30;
+/// The value is relative to the start of the function
+pub const DW_EH_PE_FUNCREL: u8 = 0x40;
+/// The value is a native word, aligned on its size
+pub const DW_EH_PE_ALIGNED: u8 = 0x50;
+/// The value is the address of the pointer, not the pointer
+pub const DW_EH_PE_INDIRECT: u8 = 0x80;
+/// There is no value
+pub const DW_EH_PE_OMIT: u8 = 0xff;
+
+/// The mask of the value format of a pointer encoding
+const DW_EH_PE_FORMAT_MASK: u8 = 0x0f;
+/// The mask of the base of a pointer encoding
+const DW_EH_PE_APPLICATION_MASK: u8 = 0x70;
+
+/// The extended length marking a 64-bit DWARF record
+const DWARF64_LENGTH: u32 = 0xffff_ffff;
+
+/// A pointer decoded from a `DW_EH_PE_*` pointer encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pointer {
+    /// The address itself
+    Direct(u64),
+    /// The address where the address is stored (`DW_EH_PE_indirect`), e.g., in the GOT
+    Indirect(u64),
+}
+
+impl Pointer {
+    /// The decoded address, which is the address of the pointer if it is indirect
+    pub fn address(self) -> u64 {
+        match self {
+            Pointer::Direct(address) | Pointer::Indirect(address) => address,
+        }
+    }
+}
+
+/// Reads a value of the `DW_EH_PE_*` value `format`
+fn read_value(bytes: &[u8], offset: &mut usize, format: u8, ctx: Ctx) -> error::Result<u64> {
+    Ok(match format {
+        DW_EH_PE_ABSPTR if ctx.container.is_big() => bytes.gread_with::<u64>(offset, ctx.le)?,
+        DW_EH_PE_ABSPTR => u64::from(bytes.gread_with::<u32>(offset, ctx.le)?),
+        DW_EH_PE_ULEB128 => u64::from(bytes.gread::<Uleb128>(offset)?),
+        DW_EH_PE_UDATA2 => u64::from(bytes.gread_with::<u16>(offset, ctx.le)?),
+        DW_EH_PE_UDATA4 => u64::from(bytes.gread_with::<u32>(offset, ctx.le)?),
+        DW_EH_PE_UDATA8 => bytes.gread_with::<u64>(offset, ctx.le)?,
+        DW_EH_PE_SLEB128 => i64::from(bytes.gread::<Sleb128>(offset)?) as u64,
+        DW_EH_PE_SDATA2 => i64::from(bytes.gread_with::<i16>(offset, ctx.le)?) as u64,
+        DW_EH_PE_SDATA4 => i64::from(bytes.gread_with::<i32>(offset, ctx.le)?) as u64,
+        DW_EH_PE_SDATA8 => bytes.gread_with::<i64>(offset, ctx.le)? as u64,
+        _ => {
+            return Err(error::Error::Malformed(format!(
+                "Unknown pointer encoding format ({:#x})",
+                format
+            )))
+        }
+    })
+}
+
+/// Reads a pointer of the `DW_EH_PE_*` `encoding` at `offset` in `bytes`, which are loaded at
+/// `address`; `data_base` is the base of `DW_EH_PE_datarel` pointers, if they have one
+fn read_pointer(
+    bytes: &[u8],
+    offset: &mut usize,
+    encoding: u8,
+    address: u64,
+    data_base: Option<u64>,
+    ctx: Ctx,
+) -> error::Result<Pointer> {
+    let pointer_address = address.wrapping_add(*offset as u64);
+    let (value, base) = match encoding & DW_EH_PE_APPLICATION_MASK {
+        DW_EH_PE_ALIGNED => {
+            let size = ctx.size();
+            *offset = offset.checked_add(size - 1).ok_or(error::Error::Malformed(
+                "Aligned pointer is out of bounds".into(),
+            ))? / size
+                * size;
+            (read_value(bytes, offset, DW_EH_PE_ABSPTR, ctx)?, 0)
+        }
+        application => {
+            let value = read_value(bytes, offset, encoding & DW_EH_PE_FORMAT_MASK, ctx)?;
+            let base = match application {
+                DW_EH_PE_ABSPTR => Some(0),
+                DW_EH_PE_PCREL => Some(pointer_address),
+                DW_EH_PE_DATAREL => data_base,
+                _ => None,
+            };
+            let base = base.ok_or_else(|| {
+                error::Error::Malformed(format!("Unsupported pointer encoding ({:#x})", encoding))
+            })?;
+            (value, base)
+        }
+    };
+    let mut pointer = base.wrapping_add(value);
+    if !ctx.container.is_big() {
+        pointer &= 0xffff_ffff;
+    }
+    Ok(if encoding & DW_EH_PE_INDIRECT != 0 {
+        Pointer::Indirect(pointer)
+    } else {
+        Pointer::Direct(pointer)
+    })
+}
+
+/// A Common Information Entry, holding what the functions of its FDEs have in common
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cie<'a> {
+    /// The offset of the CIE in `.eh_frame`
+    pub offset: usize,
+    /// The version of the call frame information: 1, 3 or 4
+    pub version: u8,
+    /// The augmentation string, e.g., `zR` or `zPLR`
+    pub augmentation: &'a str,
+    /// The factor of the advance location instructions
+    pub code_alignment_factor: u64,
+    /// The factor of the offset instructions
+    pub data_alignment_factor: i64,
+    /// The column of the return address in the rule table
+    pub return_address_register: u64,
+    /// The augmentation data, present if the augmentation string starts with `z`
+    pub augmentation_data: &'a [u8],
+    /// The `DW_EH_PE_*` encoding of the FDE addresses
+    pub fde_encoding: u8,
+    /// The `DW_EH_PE_*` encoding of the FDE language-specific data areas, `DW_EH_PE_OMIT` if they
+    /// have none
+    pub lsda_encoding: u8,
+    /// The personality routine, if there is one
+    pub personality: Option<Pointer>,
+    /// Whether the FDEs describe signal handler frames (`S`)
+    pub is_signal_frame: bool,
+    /// The initial call frame instructions, shared by the FDEs
+    pub initial_instructions: &'a [u8],
+}
+
+/// A Frame Description Entry, describing how to unwind the frames of a code range
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fde<'a> {
+    /// The offset of the FDE in `.eh_frame`
+    pub offset: usize,
+    /// The CIE of the FDE
+    pub cie: Cie<'a>,
+    /// The start of the code range
+    pub pc_begin: u64,
+    /// The size of the code range
+    pub pc_range: u64,
+    /// The augmentation data, present if the augmentation string of the CIE starts with `z`
+    pub augmentation_data: &'a [u8],
+    /// The language-specific data area, e.g., the C++ exception tables, if there is one
+    pub lsda: Option<Pointer>,
+    /// The call frame instructions
+    pub instructions: &'a [u8],
+}
+
+impl Fde<'_> {
+    /// The end of the code range, exclusive
+    pub fn pc_end(&self) -> u64 {
+        self.pc_begin.saturating_add(self.pc_range)
+    }
+
+    /// Whether `pc` is in the code range
+    pub fn contains(&self, pc: u64) -> bool {
+        self.pc_begin <= pc && pc < self.pc_end()
+    }
+}
+
+/// A record of `.eh_frame`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CfiEntry<'a> {
+    /// A Common Information Entry
+    Cie(Cie<'a>),
+    /// A Frame Description Entry
+    Fde(Fde<'a>),
+}
+
+/// The header of a record: its length, then its CIE id or CIE pointer
+struct Record {
+    /// The offset of the CIE id or CIE pointer
+    id_offset: usize,
+    id: u64,
+    /// The offset following the CIE id or CIE pointer
+    content: usize,
+    /// The offset of the next record
+    end: usize,
+}
+
+/// The `.eh_frame` call frame information
+#[derive(Clone, Copy)]
+pub struct EhFrame<'a> {
+    data: &'a [u8],
+    address: u64,
+    ctx: Ctx,
+}
+
+impl fmt::Debug for EhFrame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EhFrame")
+            .field("address", &format_args!("{:#x}", self.address))
+            .field("size", &self.data.len())
+            .finish()
+    }
+}
+
+impl<'a> EhFrame<'a> {
+    /// Creates the call frame information of the `.eh_frame` contents `data`, loaded at `address`.
+    ///
+    /// `data` may run past the end of `.eh_frame`, e.g., to the end of its segment, as long as
+    /// it is terminated by a zero length.
+    pub fn new(data: &'a [u8], address: u64, ctx: Ctx) -> Self {
+        EhFrame { data, address, ctx }
+    }
+
+    /// The address `.eh_frame` is loaded at
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// The `.eh_frame` contents
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Iterates over the CIEs and FDEs
+    pub fn entries(&self) -> CfiEntryIterator<'a> {
+        CfiEntryIterator {
+            eh_frame: *self,
+            offset: 0,
+        }
+    }
+
+    /// Iterates over the FDEs
+    pub fn fdes(&self) -> impl Iterator<Item = error::Result<Fde<'a>>> {
+        self.entries().filter_map(|entry| match entry {
+            Ok(CfiEntry::Fde(fde)) => Some(Ok(fde)),
+            Ok(CfiEntry::Cie(_)) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+
+    /// Finds the FDE covering `pc` with a linear scan; prefer [`EhFrameHdr::find_fde`] if there
+    /// is a `.eh_frame_hdr` table
+    pub fn find_fde(&self, pc: u64) -> error::Result<Option<Fde<'a>>> {
+        for fde in self.fdes() {
+            let fde = fde?;
+            if fde.contains(pc) {
+                return Ok(Some(fde));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parses the CIE or FDE at `offset`
+    pub fn entry_at(&self, offset: usize) -> error::Result<CfiEntry<'a>> {
+        let record = self
+            .record(offset)?
+            .ok_or_else(|| error::Error::Malformed(format!("No CFI record at {:#x}", offset)))?;
+        if record.id == 0 {
+            self.parse_cie(offset, &record).map(CfiEntry::Cie)
+        } else {
+            self.parse_fde(offset, &record).map(CfiEntry::Fde)
+        }
+    }
+
+    /// Parses the CIE at `offset`
+    pub fn cie_at(&self, offset: usize) -> error::Result<Cie<'a>> {
+        // only the header is read, an FDE pointing to an FDE would otherwise recurse
+        let record = self
+            .record(offset)?
+            .ok_or_else(|| error::Error::Malformed(format!("No CFI record at {:#x}", offset)))?;
+        if record.id != 0 {
+            return Err(error::Error::Malformed(format!(
+                "CFI record at {:#x} is not a CIE",
+                offset
+            )));
+        }
+        self.parse_cie(offset, &record)
+    }
+
+    /// Parses the FDE at `offset`
+    pub fn fde_at(&self, offset: usize) -> error::Result<Fde<'a>> {
+        match self.entry_at(offset)? {
+            CfiEntry::Fde(fde) => Ok(fde),
+            CfiEntry::Cie(_) => Err(error::Error::Malformed(format!(
+                "CFI record at {:#x} is not an FDE",
+                offset
+            ))),
+        }
+    }
+
+    /// Reads the header of the record at `offset`, or `None` for the terminator
+    fn record(&self, offset: usize) -> error::Result<Option<Record>> {
+        let mut cursor = offset;
+        let length = self.data.gread_with::<u32>(&mut cursor, self.ctx.le)?;
+        if length == 0 {
+            return Ok(None);
+        }
+        let is_dwarf64 = length == DWARF64_LENGTH;
+        let length = if is_dwarf64 {
+            self.data.gread_with::<u64>(&mut cursor, self.ctx.le)?
+        } else {
+            u64::from(length)
+        };
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|length| cursor.checked_add(length))
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "CFI record at {:#x} has an invalid length ({:#x})",
+                    offset, length
+                ))
+            })?;
+        let id_offset = cursor;
+        let id = if is_dwarf64 {
+            self.data[..end].gread_with::<u64>(&mut cursor, self.ctx.le)?
+        } else {
+            u64::from(self.data[..end].gread_with::<u32>(&mut cursor, self.ctx.le)?)
+        };
+        Ok(Some(Record {
+            id_offset,
+            id,
+            content: cursor,
+            end,
+        }))
+    }
+
+    fn parse_cie(&self, offset: usize, record: &Record) -> error::Result<Cie<'a>> {
+        let bytes = &self.data[..record.end];
+        let mut cursor = record.content;
+        let version = bytes.gread::<u8>(&mut cursor)?;
+        if version != 1 && version != 3 && version != 4 {
+            return Err(error::Error::Malformed(format!(
+                "CIE at {:#x} has an unknown version ({})",
+                offset, version
+            )));
+        }
+        let augmentation = bytes.gread::<&str>(&mut cursor)?;
+        if augmentation.contains("eh") {
+            // the address of the exception table of old GCCs
+            cursor += self.ctx.size();
+        }
+        if version >= 4 {
+            // the address and segment selector sizes
+            cursor += 2;
+        }
+        let code_alignment_factor = u64::from(bytes.gread::<Uleb128>(&mut cursor)?);
+        let data_alignment_factor = i64::from(bytes.gread::<Sleb128>(&mut cursor)?);
+        let return_address_register = if version == 1 {
+            u64::from(bytes.gread::<u8>(&mut cursor)?)
+        } else {
+            u64::from(bytes.gread::<Uleb128>(&mut cursor)?)
+        };
+
+        let mut cie = Cie {
+            offset,
+            version,
+            augmentation,
+            code_alignment_factor,
+            data_alignment_factor,
+            return_address_register,
+            augmentation_data: &[],
+            fde_encoding: DW_EH_PE_ABSPTR,
+            lsda_encoding: DW_EH_PE_OMIT,
+            personality: None,
+            is_signal_frame: false,
+            initial_instructions: &[],
+        };
+        if let Some(augmentation) = augmentation.strip_prefix('z') {
+            let (data, end) = self.augmentation_data(bytes, &mut cursor)?;
+            cie.augmentation_data = data;
+            let mut data_cursor = end - data.len();
+            for c in augmentation.chars() {
+                match c {
+                    'L' => cie.lsda_encoding = bytes[..end].gread::<u8>(&mut data_cursor)?,
+                    'P' => {
+                        let encoding = bytes[..end].gread::<u8>(&mut data_cursor)?;
+                        cie.personality =
+                            Some(self.pointer(&bytes[..end], &mut data_cursor, encoding)?);
+                    }
+                    'R' => cie.fde_encoding = bytes[..end].gread::<u8>(&mut data_cursor)?,
+                    'S' => cie.is_signal_frame = true,
+                    // the AArch64 BTI and MTE frames, which need no data
+                    'B' | 'G' => (),
+                    // the length of the augmentation data lets us skip what we don't know
+                    _ => break,
+                }
+            }
+        } else if !augmentation.is_empty() && augmentation != "eh" {
+            return Err(error::Error::Malformed(format!(
+                "CIE at {:#x} has an unknown augmentation ({:?})",
+                offset, augmentation
+            )));
+        }
+        cie.initial_instructions = &bytes[cursor..];
+        Ok(cie)
+    }
+
+    fn parse_fde(&self, offset: usize, record: &Record) -> error::Result<Fde<'a>> {
+        let cie_offset = usize::try_from(record.id)
+            .ok()
+            .and_then(|id| record.id_offset.checked_sub(id))
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "FDE at {:#x} has an invalid CIE pointer ({:#x})",
+                    offset, record.id
+                ))
+            })?;
+        let cie = self.cie_at(cie_offset)?;
+        let bytes = &self.data[..record.end];
+        let mut cursor = record.content;
+        let pc_begin = self
+            .pointer(bytes, &mut cursor, cie.fde_encoding)?
+            .address();
+        let mut pc_range = read_value(
+            bytes,
+            &mut cursor,
+            cie.fde_encoding & DW_EH_PE_FORMAT_MASK,
+            self.ctx,
+        )?;
+        if !self.ctx.container.is_big() {
+            pc_range &= 0xffff_ffff;
+        }
+        let mut fde = Fde {
+            offset,
+            cie,
+            pc_begin,
+            pc_range,
+            augmentation_data: &[],
+            lsda: None,
+            instructions: &[],
+        };
+        if cie.augmentation.starts_with('z') {
+            let (data, end) = self.augmentation_data(bytes, &mut cursor)?;
+            fde.augmentation_data = data;
+            if cie.lsda_encoding != DW_EH_PE_OMIT && !data.is_empty() {
+                let mut data_cursor = end - data.len();
+                fde.lsda =
+                    Some(self.pointer(&bytes[..end], &mut data_cursor, cie.lsda_encoding)?);
+            }
+        }
+        fde.instructions = &bytes[cursor..];
+        Ok(fde)
+    }
+
+    /// Reads the augmentation data at `cursor`, returning it with the offset following it
+    fn augmentation_data(
+        &self,
+        bytes: &'a [u8],
+        cursor: &mut usize,
+    ) -> error::Result<(&'a [u8], usize)> {
+        let length = u64::from(bytes.gread::<Uleb128>(cursor)?);
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|length| cursor.checked_add(length))
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "CFI augmentation data at {:#x} has an invalid length ({:#x})",
+                    cursor, length
+                ))
+            })?;
+        let data = &bytes[*cursor..end];
+        *cursor = end;
+        Ok((data, end))
+    }
+
+    fn pointer(&self, bytes: &[u8], cursor: &mut usize, encoding: u8) -> error::Result<Pointer> {
+        read_pointer(bytes, cursor, encoding, self.address, None, self.ctx)
+    }
+}
+
+/// Iterator over the records of `.eh_frame`, ending at the terminator or after an error
+pub struct CfiEntryIterator<'a> {
+    eh_frame: EhFrame<'a>,
+    offset: usize,
+}
+
+impl<'a> Iterator for CfiEntryIterator<'a> {
+    type Item = error::Result<CfiEntry<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.eh_frame.data.len() {
+            return None;
+        }
+        let offset = self.offset;
+        let record = match self.eh_frame.record(offset) {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                self.offset = self.eh_frame.data.len();
+                return None;
+            }
+            Err(e) => {
+                self.offset = self.eh_frame.data.len();
+                return Some(Err(e));
+            }
+        };
+        self.offset = record.end;
+        let entry = if record.id == 0 {
+            self.eh_frame.parse_cie(offset, &record).map(CfiEntry::Cie)
+        } else {
+            self.eh_frame.parse_fde(offset, &record).map(CfiEntry::Fde)
+        };
+        Some(entry)
+    }
+}
+
+impl FusedIterator for CfiEntryIterator<'_> {}
+
+/// The `.eh_frame_hdr` header, pointing to `.eh_frame`, with its optional table of FDEs
+#[derive(Clone, Copy)]
+pub struct EhFrameHdr<'a> {
+    data: &'a [u8],
+    address: u64,
+    /// The version of the header, 1
+    pub version: u8,
+    eh_frame_ptr: u64,
+    fde_count: usize,
+    table_encoding: u8,
+    table_offset: usize,
+    ctx: Ctx,
+}
+
+impl fmt::Debug for EhFrameHdr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EhFrameHdr")
+            .field("address", &format_args!("{:#x}", self.address))
+            .field("version", &self.version)
+            .field("eh_frame_ptr", &format_args!("{:#x}", self.eh_frame_ptr))
+            .field("fde_count", &self.fde_count)
+            .field(
+                "table_encoding",
+                &format_args!("{:#x}", self.table_encoding),
+            )
+            .finish()
+    }
+}
+
+impl<'a> EhFrameHdr<'a> {
+    /// Parses the `.eh_frame_hdr` contents `data`, loaded at `address`
+    pub fn parse(data: &'a [u8], address: u64, ctx: Ctx) -> error::Result<Self> {
+        let mut cursor = 0;
+        let version = data.gread::<u8>(&mut cursor)?;
+        if version != 1 {
+            return Err(error::Error::Malformed(format!(
+                "Unknown .eh_frame_hdr version ({})",
+                version
+            )));
+        }
+        let eh_frame_ptr_encoding = data.gread::<u8>(&mut cursor)?;
+        let fde_count_encoding = data.gread::<u8>(&mut cursor)?;
+        let table_encoding = data.gread::<u8>(&mut cursor)?;
+        let data_base = Some(address);
+        let eh_frame_ptr = read_pointer(
+            data,
+            &mut cursor,
+            eh_frame_ptr_encoding,
+            address,
+            data_base,
+            ctx,
+        )?
+        .address();
+        let mut hdr = EhFrameHdr {
+            data,
+            address,
+            version,
+            eh_frame_ptr,
+            fde_count: 0,
+            table_encoding,
+            table_offset: 0,
+            ctx,
+        };
+        if fde_count_encoding == DW_EH_PE_OMIT || table_encoding == DW_EH_PE_OMIT {
+            return Ok(hdr);
+        }
+        let fde_count = read_pointer(
+            data,
+            &mut cursor,
+            fde_count_encoding,
+            address,
+            data_base,
+            ctx,
+        )?
+        .address();
+        let size = hdr.entry_size()?;
+        hdr.fde_count = usize::try_from(fde_count)
+            .ok()
+            .filter(|&count| {
+                matches!(count.checked_mul(2 * size), Some(table) if table <= data.len() - cursor)
+            })
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "Invalid .eh_frame_hdr FDE count ({:#x})",
+                    fde_count
+                ))
+            })?;
+        hdr.table_offset = cursor;
+        Ok(hdr)
+    }
+
+    /// The address of `.eh_frame`
+    pub fn eh_frame_ptr(&self) -> u64 {
+        self.eh_frame_ptr
+    }
+
+    /// The number of FDEs in the table
+    pub fn len(&self) -> usize {
+        self.fde_count
+    }
+
+    /// Whether there is no table of FDEs, or an empty one
+    pub fn is_empty(&self) -> bool {
+        self.fde_count == 0
+    }
+
+    /// Returns the start of the code range and the address of the FDE at `idx` in the table
+    pub fn entry(&self, idx: usize) -> error::Result<(u64, u64)> {
+        if idx >= self.fde_count {
+            return Err(error::Error::Malformed(format!(
+                ".eh_frame_hdr table index {} is out of bounds",
+                idx
+            )));
+        }
+        let mut cursor = self.table_offset + idx * 2 * self.entry_size()?;
+        let data_base = Some(self.address);
+        let mut read = || {
+            read_pointer(
+                self.data,
+                &mut cursor,
+                self.table_encoding,
+                self.address,
+                data_base,
+                self.ctx,
+            )
+            .map(Pointer::address)
+        };
+        Ok((read()?, read()?))
+    }
+
+    /// Binary searches the table for the address of the FDE whose code range may cover `pc`,
+    /// the last one starting at or before `pc`; its range still has to be checked
+    pub fn find_fde_address(&self, pc: u64) -> error::Result<Option<u64>> {
+        let (mut low, mut high) = (0, self.fde_count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.entry(mid)?.0 <= pc {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if low == 0 {
+            return Ok(None);
+        }
+        self.entry(low - 1).map(|(_, fde)| Some(fde))
+    }
+
+    /// Finds the FDE covering `pc` in `eh_frame` with a binary search of the table
+    pub fn find_fde(&self, eh_frame: &EhFrame<'a>, pc: u64) -> error::Result<Option<Fde<'a>>> {
+        let address = match self.find_fde_address(pc)? {
+            Some(address) => address,
+            None => return Ok(None),
+        };
+        let offset = address
+            .checked_sub(eh_frame.address)
+            .and_then(|offset| usize::try_from(offset).ok())
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    ".eh_frame_hdr FDE address ({:#x}) is not in .eh_frame",
+                    address
+                ))
+            })?;
+        let fde = eh_frame.fde_at(offset)?;
+        Ok(if fde.contains(pc) { Some(fde) } else { None })
+    }
+
+    /// The size of the values in the table, which must have a fixed size
+    fn entry_size(&self) -> error::Result<usize> {
+        match self.table_encoding & DW_EH_PE_FORMAT_MASK {
+            DW_EH_PE_ABSPTR => Ok(self.ctx.size()),
+            DW_EH_PE_UDATA2 | DW_EH_PE_SDATA2 => Ok(2),
+            DW_EH_PE_UDATA4 | DW_EH_PE_SDATA4 => Ok(4),
+            DW_EH_PE_UDATA8 | DW_EH_PE_SDATA8 => Ok(8),
+            _ => Err(error::Error::Malformed(format!(
+                "Unsupported .eh_frame_hdr table encoding ({:#x})",
+                self.table_encoding
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::Container;
+    use scroll::Pwrite;
+
+    const BE32: Ctx = Ctx {
+        container: Container::Little,
+        le: scroll::Endian::Big,
+    };
+
+    /// A CIE for signal frames with 4 bytes absolute FDE addresses, one FDE and the terminator
+    fn eh_frame() -> [u8; 48] {
+        let mut bytes = [0; 48];
+        bytes.pwrite_with::<u32>(20, 0, BE32.le).unwrap();
+        bytes[8..20].copy_from_slice(b"\x03zRS\x00\x01\x7c\x08\x01\x03\x0c\x04");
+        bytes[20] = 0x04;
+        bytes.pwrite_with::<u32>(16, 24, BE32.le).unwrap();
+        bytes.pwrite_with::<u32>(28, 28, BE32.le).unwrap();
+        bytes.pwrite_with::<u32>(0x1000, 32, BE32.le).unwrap();
+        bytes.pwrite_with::<u32>(0x20, 36, BE32.le).unwrap();
+        bytes[41..44].copy_from_slice(b"\x0e\x08\x00");
+        bytes
+    }
+
+    #[test]
+    fn parse_eh_frame() {
+        let bytes = eh_frame();
+        let eh_frame = EhFrame::new(&bytes, 0x8000, BE32);
+        let entries = eh_frame.entries().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(entries.len(), 2);
+        let cie = eh_frame.cie_at(0).unwrap();
+        assert_eq!(entries[0], CfiEntry::Cie(cie));
+        assert_eq!(cie.version, 3);
+        assert_eq!(cie.augmentation, "zRS");
+        assert_eq!(cie.code_alignment_factor, 1);
+        assert_eq!(cie.data_alignment_factor, -4);
+        assert_eq!(cie.return_address_register, 8);
+        assert_eq!(cie.augmentation_data, [DW_EH_PE_UDATA4]);
+        assert_eq!(cie.fde_encoding, DW_EH_PE_UDATA4);
+        assert_eq!(cie.lsda_encoding, DW_EH_PE_OMIT);
+        assert!(cie.is_signal_frame);
+        assert_eq!(cie.initial_instructions, [0x0c, 0x04, 0x04, 0, 0, 0]);
+
+        let fde = eh_frame.fde_at(24).unwrap();
+        assert_eq!(entries[1], CfiEntry::Fde(fde));
+        assert_eq!(fde.cie, cie);
+        assert_eq!((fde.pc_begin, fde.pc_end()), (0x1000, 0x1020));
+        assert!(fde.augmentation_data.is_empty());
+        assert_eq!(fde.lsda, None);
+        assert_eq!(fde.instructions, [0x0e, 0x08, 0x00]);
+        assert_eq!(eh_frame.find_fde(0x101f).unwrap(), Some(fde));
+        assert_eq!(eh_frame.find_fde(0x1020).unwrap(), None);
+
+        assert!(eh_frame.fde_at(0).is_err());
+        assert!(eh_frame.cie_at(24).is_err());
+        assert!(eh_frame.entry_at(44).is_err());
+        // the FDE runs past the end
+        let eh_frame = EhFrame::new(&bytes[..40], 0x8000, BE32);
+        let entries: Vec<_> = eh_frame.entries().collect();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[1].is_err());
+
+        // an FDE whose CIE pointer points to itself
+        let bytes = [8, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let ctx = Ctx::new(Container::Little, scroll::Endian::Little);
+        let eh_frame = EhFrame::new(&bytes, 0, ctx);
+        assert!(eh_frame.entry_at(0).is_err());
+        assert!(eh_frame.cie_at(0).is_err());
+        assert!(eh_frame.find_fde(0).is_err());
+    }
+
+    #[test]
+    fn parse_eh_frame_hdr() {
+        let bytes = eh_frame();
+        let eh_frame = EhFrame::new(&bytes, 0x8000, BE32);
+        let mut hdr = [0; 20];
+        hdr[..4].copy_from_slice(&[
+            1,
+            DW_EH_PE_PCREL | DW_EH_PE_SDATA4,
+            DW_EH_PE_UDATA4,
+            DW_EH_PE_DATAREL | DW_EH_PE_SDATA4,
+        ]);
+        hdr.pwrite_with::<u32>(0xffc, 4, BE32.le).unwrap();
+        hdr.pwrite_with::<u32>(1, 8, BE32.le).unwrap();
+        hdr.pwrite_with::<i32>(-0x6000, 12, BE32.le).unwrap();
+        hdr.pwrite_with::<i32>(0x1018, 16, BE32.le).unwrap();
+        let hdr = EhFrameHdr::parse(&hdr, 0x7000, BE32).unwrap();
+        assert_eq!(hdr.eh_frame_ptr(), 0x8000);
+        assert_eq!(hdr.len(), 1);
+        assert_eq!(hdr.entry(0).unwrap(), (0x1000, 0x8018));
+        assert!(hdr.entry(1).is_err());
+        assert_eq!(hdr.find_fde_address(0xfff).unwrap(), None);
+        assert_eq!(hdr.find_fde_address(0x2000).unwrap(), Some(0x8018));
+        assert_eq!(
+            hdr.find_fde(&eh_frame, 0x1000).unwrap(),
+            Some(eh_frame.fde_at(24).unwrap())
+        );
+        assert_eq!(hdr.find_fde(&eh_frame, 0x2000).unwrap(), None);
+    }
+
+    #[test]
+    fn pointer_encodings() {
+        let bytes = [0xff, 0xff, 0xff, 0xf0, 0, 0, 0, 0x10, 0x7f];
+        let read = |offset: usize, encoding| {
+            let mut offset = offset;
+            read_pointer(&bytes, &mut offset, encoding, 0x100, Some(0x200), BE32)
+                .map(|pointer| (pointer, offset))
+        };
+        assert_eq!(
+            read(0, DW_EH_PE_SDATA4 | DW_EH_PE_PCREL).unwrap(),
+            (Pointer::Direct(0xf0), 4)
+        );
+        assert_eq!(
+            read(4, DW_EH_PE_UDATA4 | DW_EH_PE_DATAREL | DW_EH_PE_INDIRECT).unwrap(),
+            (Pointer::Indirect(0x210), 8)
+        );
+        assert_eq!(
+            read(1, DW_EH_PE_ALIGNED).unwrap(),
+            (Pointer::Direct(0x10), 8)
+        );
+        assert_eq!(
+            read(8, DW_EH_PE_SLEB128).unwrap(),
+            (Pointer::Direct(0xffff_ffff), 9)
+        );
+        assert!(read(0, DW_EH_PE_UDATA4 | DW_EH_PE_TEXTREL).is_err());
+        assert!(read(0, 0x07).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+//! The symbol hash tables of the dynamic symbols: the SysV `DT_HASH` and the GNU `DT_GNU_HASH`.
+//!
+//! Both map the hash of a symbol name to a chain of candidate indices into the dynamic symbol
+//! table, the candidates still have to be compared by name.
+//!
+//! The SysV table is an array of words: `nbucket`, `nchain`, the buckets and the chains. A
+//! bucket holds the first index of its chain, and `chains[idx]` the next one, until `STN_UNDEF`.
+//!
+//! The GNU table starts with `nbuckets`, `symoffset`, `bloom_size` and `bloom_shift`, followed by
+//! a bloom filter of native words, the buckets and the chains. A bucket holds the first index of
+//! its chain, which runs through the consecutive symbols; `chains[idx - symoffset]` holds the
+//! hash of the symbol `idx`, with the lowest bit set on the last one of the chain. See
+//! [`super::gnu_hash`] for more details.
+
+use core::fmt;
+use scroll::{Endian, Pread};
+
+use crate::container::Ctx;
+use crate::error;
+
+use super::header;
+
+pub use super::gnu_hash::hash as gnu_hash;
+
+/// SysV ELF hash function, used by `DT_HASH` tables and to hash version names
+pub fn hash(name: &str) -> u32 {
+    name.bytes().fold(0, |hash, b| {
+        let hash = (hash << 4).wrapping_add(u32::from(b));
+        let high = hash & 0xf000_0000;
+        (hash ^ (high >> 24)) & !high
+    })
+}
+
+/// A SysV `DT_HASH` symbol hash table
+#[derive(Clone)]
+pub struct SysvHashTable<'a> {
+    bytes: &'a [u8],
+    nbucket: usize,
+    nchain: usize,
+    /// The size of the table words; 8 on 64-bit Alpha and s390, 4 everywhere else
+    word_size: usize,
+    le: Endian,
+}
+
+impl fmt::Debug for SysvHashTable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SysvHashTable")
+            .field("nbucket", &self.nbucket)
+            .field("nchain", &self.nchain)
+            .finish()
+    }
+}
+
+impl<'a> SysvHashTable<'a> {
+    /// Parses the hash table at `offset` in `bytes`, for an ELF of the given `machine`
+    pub fn parse(bytes: &'a [u8], offset: usize, machine: u16, ctx: Ctx) -> error::Result<Self> {
+        // Based on readelf code.
+        let word_size = if (machine == header::EM_FAKE_ALPHA || machine == header::EM_S390)
+            && ctx.container.is_big()
+        {
+            8
+        } else {
+            4
+        };
+        let bytes = bytes.get(offset..).unwrap_or_default();
+        let mut table = SysvHashTable {
+            bytes,
+            nbucket: 0,
+            nchain: 0,
+            word_size,
+            le: ctx.le,
+        };
+        table.nbucket = table.word(0)?;
+        table.nchain = table.word(1)?;
+        let size = table
+            .nbucket
+            .checked_add(table.nchain)
+            .and_then(|words| words.checked_add(2))
+            .and_then(|words| words.checked_mul(word_size));
+        match size {
+            Some(size) if size <= bytes.len() => Ok(table),
+            _ => Err(error::Error::Malformed(format!(
+                "Invalid DT_HASH: nbucket={} nchain={}",
+                table.nbucket, table.nchain
+            ))),
+        }
+    }
+
+    /// The number of buckets
+    pub fn nbucket(&self) -> usize {
+        self.nbucket
+    }
+
+    /// The number of chain entries, which is also the number of dynamic symbols
+    pub fn nchain(&self) -> usize {
+        self.nchain
+    }
+
+    /// Walks the chain of `name`, returning the first symbol index accepted by `matches`
+    pub fn find<F: FnMut(usize) -> bool>(&self, name: &str, mut matches: F) -> Option<usize> {
+        if self.nbucket == 0 {
+            return None;
+        }
+        let bucket = hash(name) as usize % self.nbucket;
+        let mut idx = self.word(2 + bucket).ok()?;
+        // a chain can't be longer than the table, this stops on malformed loops
+        for _ in 0..self.nchain {
+            if idx == 0 || idx >= self.nchain {
+                return None;
+            }
+            if matches(idx) {
+                return Some(idx);
+            }
+            idx = self.word(2 + self.nbucket + idx).ok()?;
+        }
+        None
+    }
+
+    fn word(&self, idx: usize) -> error::Result<usize> {
+        let offset = idx * self.word_size;
+        Ok(if self.word_size == 8 {
+            self.bytes.pread_with::<u64>(offset, self.le)? as usize
+        } else {
+            self.bytes.pread_with::<u32>(offset, self.le)? as usize
+        })
+    }
+}
+
+/// A GNU `DT_GNU_HASH` symbol hash table
+#[derive(Clone)]
+pub struct GnuHashTable<'a> {
+    bytes: &'a [u8],
+    nbuckets: u32,
+    symoffset: u32,
+    bloom_size: u32,
+    bloom_shift: u32,
+    ctx: Ctx,
+}
+
+impl fmt::Debug for GnuHashTable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GnuHashTable")
+            .field("nbuckets", &self.nbuckets)
+            .field("symoffset", &self.symoffset)
+            .field("bloom_size", &self.bloom_size)
+            .field("bloom_shift", &self.bloom_shift)
+            .finish()
+    }
+}
+
+impl<'a> GnuHashTable<'a> {
+    /// Parses the hash table at `offset` in `bytes`
+    pub fn parse(bytes: &'a [u8], offset: usize, ctx: Ctx) -> error::Result<Self> {
+        let bytes = bytes.get(offset..).unwrap_or_default();
+        let nbuckets = bytes.pread_with::<u32>(0, ctx.le)?;
+        let symoffset = bytes.pread_with::<u32>(4, ctx.le)?;
+        let bloom_size = bytes.pread_with::<u32>(8, ctx.le)?;
+        let bloom_shift = bytes.pread_with::<u32>(12, ctx.le)?;
+        let table = GnuHashTable {
+            bytes,
+            nbuckets,
+            symoffset,
+            bloom_size,
+            bloom_shift,
+            ctx,
+        };
+        if nbuckets == 0 || bloom_size == 0 || table.chains_offset() > bytes.len() {
+            return Err(error::Error::Malformed(format!(
+                "Invalid DT_GNU_HASH: nbuckets={} symoffset={} bloom_size={}",
+                nbuckets, symoffset, bloom_size
+            )));
+        }
+        Ok(table)
+    }
+
+    /// The number of buckets
+    pub fn nbuckets(&self) -> u32 {
+        self.nbuckets
+    }
+
+    /// The index of the first dynamic symbol in the table; the symbols before it, usually the
+    /// imports, can't be looked up
+    pub fn symoffset(&self) -> u32 {
+        self.symoffset
+    }
+
+    /// Walks the chain of `name`, returning the first symbol index accepted by `matches`
+    pub fn find<F: FnMut(usize) -> bool>(&self, name: &str, mut matches: F) -> Option<usize> {
+        let hash = gnu_hash(name);
+        if !self.bloom_contains(hash).ok()? {
+            return None;
+        }
+        let bucket = self.buckets_offset() + (hash % self.nbuckets) as usize * 4;
+        let mut idx = self.bytes.pread_with::<u32>(bucket, self.ctx.le).ok()?;
+        if idx < self.symoffset {
+            return None;
+        }
+        loop {
+            let chain = self.chains_offset() + (idx - self.symoffset) as usize * 4;
+            let chain_hash = self.bytes.pread_with::<u32>(chain, self.ctx.le).ok()?;
+            if hash | 1 == chain_hash | 1 && matches(idx as usize) {
+                return Some(idx as usize);
+            }
+            if chain_hash & 1 != 0 {
+                return None;
+            }
+            idx = idx.checked_add(1)?;
+        }
+    }
+
+    fn bloom_contains(&self, hash: u32) -> error::Result<bool> {
+        let (word, bits) = if self.ctx.container.is_big() {
+            (8, 64)
+        } else {
+            (4, 32)
+        };
+        let offset = 16 + ((hash / bits) % self.bloom_size) as usize * word;
+        let filter = if self.ctx.container.is_big() {
+            self.bytes.pread_with::<u64>(offset, self.ctx.le)?
+        } else {
+            u64::from(self.bytes.pread_with::<u32>(offset, self.ctx.le)?)
+        };
+        let mask = (1 << (hash % bits)) | (1 << (hash.wrapping_shr(self.bloom_shift) % bits));
+        Ok(filter & mask == mask)
+    }
+
+    fn buckets_offset(&self) -> usize {
+        let word = if self.ctx.container.is_big() { 8 } else { 4 };
+        16 + self.bloom_size as usize * word
+    }
+
+    fn chains_offset(&self) -> usize {
+        self.buckets_offset() + self.nbuckets as usize * 4
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::Container;
+
+    #[test]
+    fn sysv_hash() {
+        assert_eq!(hash(""), 0);
+        assert_eq!(hash("printf"), 0x0779_05a6);
+        assert_eq!(hash("exit"), 0x0006_cf04);
+        assert_eq!(hash("syscall"), 0x0b09_985c);
+        assert_eq!(hash("flapenguin.me"), 0x0398_7915);
+    }
+
+    fn words(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn sysv_find() {
+        let names = ["", "foo", "bar", "baz"];
+        // a single bucket chaining 3 -> 1 -> 2
+        let bytes = words(&[1, 4, 3, 0, 2, 0, 1]);
+        let ctx = Ctx::new(Container::Little, Endian::Little);
+        let table = SysvHashTable::parse(&bytes, 0, header::EM_386, ctx).unwrap();
+        assert_eq!(table.nchain(), 4);
+        for (idx, name) in names.iter().enumerate().skip(1) {
+            assert_eq!(table.find(name, |idx| names[idx] == *name), Some(idx));
+        }
+        assert_eq!(table.find("qux", |idx| names[idx] == "qux"), None);
+        assert!(SysvHashTable::parse(&bytes[..24], 0, header::EM_386, ctx).is_err());
+    }
+
+    #[test]
+    fn gnu_find() {
+        let names = ["", "foo", "bar"];
+        let hashes = [gnu_hash("foo"), gnu_hash("bar")];
+        // a single bucket, with every bloom filter bit set
+        let bytes = words(&[1, 1, 1, 0, !0, 1, hashes[0] & !1, hashes[1] | 1]);
+        let ctx = Ctx::new(Container::Little, Endian::Little);
+        let table = GnuHashTable::parse(&bytes, 0, ctx).unwrap();
+        assert_eq!(table.find("foo", |idx| names[idx] == "foo"), Some(1));
+        assert_eq!(table.find("bar", |idx| names[idx] == "bar"), Some(2));
+        assert_eq!(table.find("baz", |idx| names[idx] == "baz"), None);
+        // an empty bloom filter rejects everything
+        let bytes = words(&[1, 1, 1, 0, 0, 1, hashes[0] & !1, hashes[1] | 1]);
+        let table = GnuHashTable::parse(&bytes, 0, ctx).unwrap();
+        assert_eq!(table.find("foo", |_| true), None);
+    }
+}
@@ -37,7 +37,7 @@
 //! To use the automagic ELF datatype union parser, you _must_ enable/opt-in to the  `elf64`, `elf32`, and
 //! `endian_fd` features if you disable `default`.
 
-use core::ffi::CStr;
+use ::core::ffi::CStr;
 use std::borrow::Cow;
 
 use crate::elf::dynamic::DF_1_PIE;
@@ -49,6 +49,8 @@ pub(crate) mod gnu_hash;
 //
 // They are publicly re-exported by the pub-using module
 pub mod compression_header;
+#[cfg(feature = "elf_decompress")]
+pub mod decompress;
 pub mod header;
 pub mod program_header;
 pub mod section_header;
@@ -58,8 +60,22 @@ pub mod dynamic;
 #[macro_use]
 pub mod reloc;
 pub mod note;
+#[cfg(all(feature = "elf32", feature = "elf64", feature = "endian_fd"))]
+pub mod core;
+#[cfg(all(feature = "elf32", feature = "elf64", feature = "endian_fd"))]
+pub mod writer;
+#[cfg(all(feature = "elf32", feature = "elf64", feature = "endian_fd"))]
+pub mod plt;
+#[cfg(all(feature = "elf32", feature = "elf64", feature = "endian_fd"))]
+pub mod relocate;
+#[cfg(all(any(feature = "elf32", feature = "elf64"), feature = "alloc"))]
+pub mod packed_reloc;
 #[cfg(all(any(feature = "elf32", feature = "elf64"), feature = "alloc"))]
 pub mod symver;
+#[cfg(all(any(feature = "elf32", feature = "elf64"), feature = "alloc"))]
+pub mod hash;
+#[cfg(all(any(feature = "elf32", feature = "elf64"), feature = "alloc"))]
+pub mod eh_frame;
 
 macro_rules! if_sylvan {
     ($($i:item)*) => ($(
@@ -73,8 +89,10 @@ if_sylvan! {
     use crate::strtab::Strtab;
     use crate::error;
     use crate::container::{Container, Ctx};
+    use crate::overlay::Overlay;
     use alloc::vec::Vec;
-    use core::cmp;
+    use ::core::cmp;
+    use ::core::convert::TryFrom;
 
     pub use header::Header;
     pub use program_header::ProgramHeader;
@@ -86,6 +104,9 @@ if_sylvan! {
     pub use reloc::Reloc;
     pub use reloc::RelocSection;
     pub use symver::{VersymSection, VerdefSection, VerneedSection};
+    pub use hash::{GnuHashTable, SysvHashTable};
+    pub use eh_frame::{EhFrame, EhFrameHdr, Fde};
+    pub use packed_reloc::{RelrSection, AndroidRelocSection};
 
     pub type ProgramHeaders = Vec<ProgramHeader>;
     pub type SectionHeaders = Vec<SectionHeader>;
@@ -122,6 +143,10 @@ if_sylvan! {
         pub dynrels: RelocSection<'a>,
         /// The plt relocation entries (procedure linkage table). For 32-bit binaries these are usually Rel (no addend)
         pub pltrelocs: RelocSection<'a>,
+        /// The relative relocations in the compact RELR format (`DT_RELR`)
+        pub relr: RelrSection<'a>,
+        /// The dynamic relocation entries in Android's packed format (`DT_ANDROID_REL(A)`)
+        pub android_relocs: AndroidRelocSection<'a>,
         /// Section relocations by section index (only present if this is a relocatable object file)
         pub shdr_relocs: Vec<(ShdrIdx, RelocSection<'a>)>,
         /// The binary's soname, if it has one
@@ -153,6 +178,10 @@ if_sylvan! {
         /// Contains the version needed information from the optional section
         /// [`SHT_GNU_VERNEED`][section_header::SHT_GNU_VERNEED] (GNU extenstion).
         pub verneed : Option<VerneedSection<'a>>,
+        /// The GNU symbol hash table of the dynamic symbols (`DT_GNU_HASH`), if it has one
+        pub gnu_hash: Option<GnuHashTable<'a>>,
+        /// The SysV symbol hash table of the dynamic symbols (`DT_HASH`), if it has one
+        pub sysv_hash: Option<SysvHashTable<'a>>,
         ctx: Ctx,
     }
 
@@ -220,6 +249,221 @@ if_sylvan! {
                 })
             }
         }
+        /// Returns the first note named `name` of type `n_type`, looking in the `PT_NOTE`
+        /// segments, then in the `SHT_NOTE` sections
+        ///
+        /// A malformed note ends the search of its segment or section only; its error is returned
+        /// if the note is found nowhere else.
+        fn find_note(&self, data: &'a [u8], name: &str, n_type: u32) -> Option<error::Result<note::Note<'a>>> {
+            let segments = self.iter_note_headers(data).into_iter().flat_map(|notes| notes.iters);
+            let sections = self.iter_note_sections(data, None).into_iter().flat_map(|notes| notes.iters);
+            let mut error = None;
+            for notes in segments.chain(sections) {
+                for note in notes {
+                    match note {
+                        Ok(note) if note.name == name && note.n_type == n_type => return Some(Ok(note)),
+                        Ok(_) => (),
+                        // the iterator does not move past a malformed note
+                        Err(e) => {
+                            error.get_or_insert(e);
+                            break;
+                        }
+                    }
+                }
+            }
+            error.map(Err)
+        }
+        /// Returns the build ID bits of the `NT_GNU_BUILD_ID` note, if the binary has one
+        pub fn build_id(&self, data: &'a [u8]) -> Option<error::Result<&'a [u8]>> {
+            self.find_note(data, note::ELF_NOTE_GNU, note::NT_GNU_BUILD_ID)
+                .map(|note| note.map(|note| note.desc))
+        }
+        /// Returns the OS and minimum kernel version of the `NT_GNU_ABI_TAG` note, if the binary has one
+        pub fn abi_tag(&self, data: &'a [u8]) -> Option<error::Result<note::AbiTag>> {
+            self.find_note(data, note::ELF_NOTE_GNU, note::NT_GNU_ABI_TAG)
+                .map(|note| note.and_then(|note| note::AbiTag::parse(note.desc, self.ctx)))
+        }
+        /// Returns the program properties of the `NT_GNU_PROPERTY_TYPE_0` note, if the binary has one,
+        /// e.g., to check whether it supports CET (IBT and SHSTK) on x86, or BTI and PAC on AArch64
+        pub fn gnu_properties(&self, data: &'a [u8]) -> Option<error::Result<note::GnuProperties<'a>>> {
+            self.find_note(data, note::ELF_NOTE_GNU, note::NT_GNU_PROPERTY_TYPE_0)
+                .map(|note| note.and_then(|note| note::GnuProperties::parse(note.desc, self.ctx)))
+        }
+        /// Returns the packaging metadata of the `.note.package` note, if the binary has one
+        pub fn package_metadata(&self, data: &'a [u8]) -> Option<error::Result<note::PackageMetadata<'a>>> {
+            self.find_note(data, note::ELF_NOTE_FDO, note::NT_FDO_PACKAGING_METADATA)
+                .map(|note| note.and_then(|note| note::PackageMetadata::parse(note.desc)))
+        }
+        /// Looks up the defined dynamic symbol `name`, like the dynamic linker does: with the GNU
+        /// hash table if there is one, then with the SysV hash table, and with a linear scan of
+        /// the dynamic symbols otherwise.
+        ///
+        /// `name` may carry a version: `foo@@VER` matches the default version `VER` of `foo`,
+        /// `foo@VER` matches any version `VER` of it, including a hidden one. Like `dlsym`, a plain
+        /// `foo` matches its unversioned definition first, then its default version.
+        pub fn find_dynsym(&self, name: &str) -> Option<Sym> {
+            let idx = match name.find('@') {
+                Some(at) => {
+                    let version = &name[at + 1..];
+                    let (version, default) = match version.strip_prefix('@') {
+                        Some(version) => (version, true),
+                        None => (version, false),
+                    };
+                    self.lookup_dynsym(&name[..at], |versym| {
+                        matches!(versym, Some(versym) if (!default || !versym.is_hidden())
+                            && self.version_definition_name(versym.version()) == Some(version))
+                    })
+                }
+                None => self
+                    .lookup_dynsym(name, |versym| {
+                        !matches!(versym, Some(versym) if versym.version() > symver::VER_NDX_GLOBAL)
+                    })
+                    .or_else(|| self.lookup_dynsym(name, |versym| !matches!(versym, Some(versym) if versym.is_hidden()))),
+            };
+            idx.and_then(|idx| self.dynsyms.get(idx))
+        }
+        /// Returns the index of the defined dynamic symbol `name` whose version is accepted by
+        /// `version_matches`
+        fn lookup_dynsym<F: Fn(Option<symver::Versym>) -> bool>(&self, name: &str, version_matches: F) -> Option<usize> {
+            let matches = |idx: usize| {
+                matches!(self.dynsyms.get(idx), Some(sym) if sym.st_shndx != section_header::SHN_UNDEF as usize
+                    && self.dynstrtab.get_at(sym.st_name) == Some(name)
+                    && version_matches(self.versym.as_ref().and_then(|versym| versym.get_at(idx))))
+            };
+            if let Some(gnu_hash) = &self.gnu_hash {
+                gnu_hash.find(name, matches)
+            } else if let Some(sysv_hash) = &self.sysv_hash {
+                sysv_hash.find(name, matches)
+            } else {
+                (0..self.dynsyms.len()).find(|&idx| matches(idx))
+            }
+        }
+        /// Returns the `.eh_frame` call frame information: the `.eh_frame` section, or the one the
+        /// `PT_GNU_EH_FRAME` segment points to if there are no section headers
+        pub fn eh_frame(&self, bytes: &'a [u8]) -> Option<error::Result<EhFrame<'a>>> {
+            let shdr = self.section_headers.iter()
+                .find(|shdr| self.shdr_strtab.get_at(shdr.sh_name) == Some(".eh_frame"));
+            if let Some(shdr) = shdr {
+                let data = shdr.file_range().and_then(|range| bytes.get(range)).unwrap_or_default();
+                return Some(Ok(EhFrame::new(data, shdr.sh_addr, self.ctx)));
+            }
+            let hdr = match self.eh_frame_hdr(bytes)? {
+                Ok(hdr) => hdr,
+                Err(e) => return Some(Err(e)),
+            };
+            let data = self.segment_data_at(bytes, hdr.eh_frame_ptr()).ok_or_else(|| {
+                error::Error::Malformed(format!(".eh_frame address ({:#x}) is not in a segment", hdr.eh_frame_ptr()))
+            });
+            Some(data.map(|data| EhFrame::new(data, hdr.eh_frame_ptr(), self.ctx)))
+        }
+        /// Returns the `.eh_frame_hdr` header of the `PT_GNU_EH_FRAME` segment, or of the
+        /// `.eh_frame_hdr` section if there are no program headers
+        pub fn eh_frame_hdr(&self, bytes: &'a [u8]) -> Option<error::Result<EhFrameHdr<'a>>> {
+            let (range, address) = if let Some(phdr) = self.program_headers.iter()
+                .find(|phdr| phdr.p_type == program_header::PT_GNU_EH_FRAME) {
+                (phdr.file_range(), phdr.p_vaddr)
+            } else {
+                let shdr = self.section_headers.iter()
+                    .find(|shdr| self.shdr_strtab.get_at(shdr.sh_name) == Some(".eh_frame_hdr"))?;
+                (shdr.file_range()?, shdr.sh_addr)
+            };
+            let data = bytes.get(range).unwrap_or_default();
+            Some(EhFrameHdr::parse(data, address, self.ctx))
+        }
+        /// Finds the FDE covering `pc`, with a binary search of the `.eh_frame_hdr` table if there
+        /// is one, with a linear scan of `.eh_frame` otherwise
+        pub fn find_fde(&self, bytes: &'a [u8], pc: u64) -> error::Result<Option<Fde<'a>>> {
+            let eh_frame = match self.eh_frame(bytes) {
+                Some(eh_frame) => eh_frame?,
+                None => return Ok(None),
+            };
+            match self.eh_frame_hdr(bytes).transpose()? {
+                Some(hdr) if !hdr.is_empty() => hdr.find_fde(&eh_frame, pc),
+                _ => eh_frame.find_fde(pc),
+            }
+        }
+        /// Returns the file contents from the virtual address `address` to the end of its
+        /// `PT_LOAD` segment
+        fn segment_data_at(&self, bytes: &'a [u8], address: u64) -> Option<&'a [u8]> {
+            let phdr = self.program_headers.iter().find(|phdr| {
+                phdr.p_type == program_header::PT_LOAD
+                    && phdr.p_vaddr <= address
+                    && address - phdr.p_vaddr < phdr.p_filesz
+            })?;
+            let start = phdr.p_offset.checked_add(address - phdr.p_vaddr)?;
+            let end = phdr.p_offset.checked_add(phdr.p_filesz)?;
+            bytes.get(usize::try_from(start).ok()?..usize::try_from(end).ok()?)
+        }
+        /// Iterates over the dynamic symbols with their names and resolved versions, e.g., to list
+        /// them like `nm -D` or to find the newest `GLIBC_*` version the binary requires
+        pub fn versioned_dynsyms(&self) -> symver::VersionedSymIter<'_, 'a> {
+            symver::VersionedSymIter::new(
+                &self.dynsyms,
+                &self.dynstrtab,
+                self.versym.as_ref(),
+                self.verdef.as_ref(),
+                self.verneed.as_ref(),
+            )
+        }
+        /// Returns the name of the version definition with the index `ndx`, if there is one
+        fn version_definition_name(&self, ndx: u16) -> Option<&'a str> {
+            let verdef = self.verdef.as_ref()?;
+            let verdef = verdef.into_iter().find(|verdef| verdef.vd_ndx == ndx)?;
+            let verdaux = (&verdef).into_iter().next()?;
+            self.dynstrtab.get_at(verdaux.vda_name)
+        }
+        /// Returns the data appended after the end of the ELF image in `bytes`, if there is any.
+        ///
+        /// The end of the image is the furthest byte covered by the ELF header, the program and
+        /// section header tables, the file contents of the segments, or a section occupying
+        /// space in the file.
+        pub fn overlay(&self, bytes: &'a [u8]) -> Option<Overlay<'a>> {
+            let header_end = u64::from(self.header.e_ehsize);
+            let phdrs_end = self.header.e_phoff
+                .saturating_add(u64::from(self.header.e_phnum) * u64::from(self.header.e_phentsize));
+            let shdrs_end = self.header.e_shoff
+                .saturating_add(u64::from(self.header.e_shnum) * u64::from(self.header.e_shentsize));
+            let segments_end = self.program_headers.iter()
+                .map(|phdr| phdr.p_offset.saturating_add(phdr.p_filesz));
+            let sections_end = self.section_headers.iter()
+                .filter(|shdr| shdr.sh_type != section_header::SHT_NOBITS)
+                .map(|shdr| shdr.sh_offset.saturating_add(shdr.sh_size));
+            let image_end = segments_end
+                .chain(sections_end)
+                .fold(cmp::max(header_end, cmp::max(phdrs_end, shdrs_end)), cmp::max);
+            let image_end = usize::try_from(image_end).ok()?;
+            Overlay::after(bytes, image_end)
+        }
+
+        /// Returns the contents of the section at `idx` in `bytes`, decompressed if it is a
+        /// `SHF_COMPRESSED` section or a legacy GNU `.zdebug_*` section.
+        ///
+        /// Decompression requires the `elf_decompress` feature; without it, compressed sections
+        /// are an error.
+        pub fn section_data(&self, bytes: &'a [u8], idx: usize) -> error::Result<Cow<'a, [u8]>> {
+            let shdr = self.section_headers.get(idx).ok_or_else(|| {
+                error::Error::Malformed(format!("Section index {} is out of bounds", idx))
+            })?;
+            let range = match shdr.file_range() {
+                Some(range) => range,
+                None => return Ok(Cow::Borrowed(&[])),
+            };
+            shdr.check_size(bytes.len())?;
+            let data = &bytes[range];
+
+            if shdr.sh_flags & u64::from(section_header::SHF_COMPRESSED) != 0 {
+                let chdr = compression_header::CompressionHeader::parse(data, 0, self.ctx)?;
+                let compressed = &data[compression_header::CompressionHeader::size(self.ctx)..];
+                return decompress_section(chdr.ch_type, compressed, chdr.ch_size).map(Cow::Owned);
+            }
+            if matches!(self.shdr_strtab.get_at(shdr.sh_name), Some(name) if name.starts_with(".zdebug")) {
+                if let Some(decompressed) = decompress_zdebug(data) {
+                    return decompressed.map(Cow::Owned);
+                }
+            }
+            Ok(Cow::Borrowed(data))
+        }
+
         pub fn is_object_file(&self) -> bool {
             self.header.e_type == header::ET_REL
         }
@@ -261,6 +505,8 @@ if_sylvan! {
                 dynrelas: Default::default(),
                 dynrels: Default::default(),
                 pltrelocs: Default::default(),
+                relr: Default::default(),
+                android_relocs: Default::default(),
                 shdr_relocs: Default::default(),
                 soname: None,
                 interpreter: None,
@@ -275,6 +521,8 @@ if_sylvan! {
                 versym: None,
                 verdef: None,
                 verneed: None,
+                gnu_hash: None,
+                sysv_hash: None,
             })
         }
 
@@ -334,7 +582,11 @@ if_sylvan! {
             let mut dynrelas = RelocSection::default();
             let mut dynrels = RelocSection::default();
             let mut pltrelocs = RelocSection::default();
+            let mut relr = RelrSection::default();
+            let mut android_relocs = AndroidRelocSection::default();
             let mut dynstrtab = Strtab::default();
+            let mut gnu_hash = None;
+            let mut sysv_hash = None;
             let dynamic = Dynamic::parse(bytes, &program_headers, ctx)?;
             if let Some(ref dynamic) = dynamic {
                 let dyn_info = &dynamic.info;
@@ -366,6 +618,15 @@ if_sylvan! {
                 dynrels = RelocSection::parse(bytes, dyn_info.rel, dyn_info.relsz, false, ctx)?;
                 let is_rela = dyn_info.pltrel as u64 == dynamic::DT_RELA;
                 pltrelocs = RelocSection::parse(bytes, dyn_info.jmprel, dyn_info.pltrelsz, is_rela, ctx)?;
+                if dyn_info.relrsz != 0 {
+                    let r_type = packed_reloc::relative_reloc_type(header.e_machine);
+                    relr = RelrSection::parse(bytes, dyn_info.relr, dyn_info.relrsz, r_type, ctx)?;
+                }
+                if dyn_info.android_relasz != 0 {
+                    android_relocs = AndroidRelocSection::parse(bytes, dyn_info.android_rela, dyn_info.android_relasz, true, ctx)?;
+                } else if dyn_info.android_relsz != 0 {
+                    android_relocs = AndroidRelocSection::parse(bytes, dyn_info.android_rel, dyn_info.android_relsz, false, ctx)?;
+                }
 
                 let mut num_syms = if let Some(gnu_hash) = dyn_info.gnu_hash {
                     gnu_hash_len(bytes, gnu_hash as usize, ctx)?
@@ -374,6 +635,9 @@ if_sylvan! {
                 } else {
                     0
                 };
+                // a malformed table only disables the lookups using it
+                gnu_hash = dyn_info.gnu_hash.and_then(|offset| GnuHashTable::parse(bytes, offset as usize, ctx).ok());
+                sysv_hash = dyn_info.hash.and_then(|offset| SysvHashTable::parse(bytes, offset as usize, header.e_machine, ctx).ok());
                 let max_reloc_sym = dynrelas.iter()
                     .chain(dynrels.iter())
                     .chain(pltrelocs.iter())
@@ -411,6 +675,8 @@ if_sylvan! {
                 dynrelas,
                 dynrels,
                 pltrelocs,
+                relr,
+                android_relocs,
                 shdr_relocs,
                 soname,
                 interpreter,
@@ -425,6 +691,8 @@ if_sylvan! {
                 versym,
                 verdef,
                 verneed,
+                gnu_hash,
+                sysv_hash,
             })
         }
     }
@@ -437,6 +705,30 @@ if_sylvan! {
         }
     }
 
+    #[cfg(feature = "elf_decompress")]
+    fn decompress_section(ch_type: u32, data: &[u8], size: u64) -> error::Result<Vec<u8>> {
+        decompress::decompress(ch_type, data, size)
+    }
+
+    #[cfg(feature = "elf_decompress")]
+    fn decompress_zdebug(data: &[u8]) -> Option<error::Result<Vec<u8>>> {
+        decompress::decompress_zdebug(data)
+    }
+
+    #[cfg(not(feature = "elf_decompress"))]
+    fn decompress_section(_ch_type: u32, _data: &[u8], _size: u64) -> error::Result<Vec<u8>> {
+        Err(error::Error::Malformed("Decompressing sections requires the `elf_decompress` feature".into()))
+    }
+
+    #[cfg(not(feature = "elf_decompress"))]
+    fn decompress_zdebug(data: &[u8]) -> Option<error::Result<Vec<u8>>> {
+        if data.starts_with(b"ZLIB") {
+            Some(decompress_section(compression_header::ELFCOMPRESS_ZLIB, data, 0))
+        } else {
+            None
+        }
+    }
+
     fn gnu_hash_len(bytes: &[u8], offset: usize, ctx: Ctx) -> error::Result<usize> {
         let buckets_num = bytes.pread_with::<u32>(offset, ctx.le)? as usize;
         let min_chain = bytes.pread_with::<u32>(offset + 4, ctx.le)? as usize;
@@ -42,12 +42,66 @@ pub const NT_GNU_BUILD_ID: u32 = 3;
 /// Version note generated by GNU gold containing a version string.
 pub const NT_GNU_GOLD_VERSION: u32 = 4;
 
+/// Program property.
+///
+/// The descriptor is an array of properties, each made of a word giving its type, a word giving
+/// the size of its data, and the data itself, padded to 8 bytes on 64-bit and 4 bytes on 32-bit.
+pub const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+
+/// Packaging metadata, as described by the `.note.package` specification of systemd.
+///
+/// The descriptor is a NUL terminated JSON object.
+pub const NT_FDO_PACKAGING_METADATA: u32 = 0xcafe_1a7e;
+
+/// The name of GNU notes
+pub const ELF_NOTE_GNU: &str = "GNU";
+/// The name of FreeDesktop.org notes
+pub const ELF_NOTE_FDO: &str = "FDO";
+
+/// The stack size needed by the program, in a word
+pub const GNU_PROPERTY_STACK_SIZE: u32 = 1;
+/// No copy relocation on protected data symbol
+pub const GNU_PROPERTY_NO_COPY_ON_PROTECTED: u32 = 2;
+
+/// The AArch64 features all input objects support, in a 4-byte bitmask
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_AND: u32 = 0xc000_0000;
+/// Branch Target Identification
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_BTI: u32 = 1 << 0;
+/// Pointer Authentication of return addresses
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_PAC: u32 = 1 << 1;
+
+/// The x86 features used by any input object, in a 4-byte bitmask
+pub const GNU_PROPERTY_X86_FEATURE_2_USED: u32 = 0xc001_0001;
+/// The x86 features needed by any input object, in a 4-byte bitmask
+pub const GNU_PROPERTY_X86_FEATURE_2_NEEDED: u32 = 0xc000_8001;
+/// The x86 features all input objects support, in a 4-byte bitmask
+pub const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc000_0002;
+/// Indirect Branch Tracking
+pub const GNU_PROPERTY_X86_FEATURE_1_IBT: u32 = 1 << 0;
+/// Shadow Stack
+pub const GNU_PROPERTY_X86_FEATURE_1_SHSTK: u32 = 1 << 1;
+
+/// The x86 ISA levels needed by any input object, in a 4-byte bitmask
+pub const GNU_PROPERTY_X86_ISA_1_NEEDED: u32 = 0xc000_8002;
+/// The x86 ISA levels used by any input object, in a 4-byte bitmask
+pub const GNU_PROPERTY_X86_ISA_1_USED: u32 = 0xc001_0002;
+pub const GNU_PROPERTY_X86_ISA_1_BASELINE: u32 = 1 << 0;
+pub const GNU_PROPERTY_X86_ISA_1_V2: u32 = 1 << 1;
+pub const GNU_PROPERTY_X86_ISA_1_V3: u32 = 1 << 2;
+pub const GNU_PROPERTY_X86_ISA_1_V4: u32 = 1 << 3;
+
 ///Contains copy of prstatus struct.
 pub const NT_PRSTATUS: u32 = 1;
 
+///Contains copy of fpregset struct.
+pub const NT_FPREGSET: u32 = 2;
+
 ///Contains copy of prpsinfo struct.
 pub const NT_PRPSINFO: u32 = 3;
 
+///Contains copy of auxv array.
+pub const NT_AUXV: u32 = 6;
+
 ///Fields of siginfo_t.
 pub const NT_SIGINFO: u32 = 0x5349_4749;
 
@@ -184,9 +238,217 @@ if_alloc! {
                 NT_GNU_HWCAP => "NT_GNU_HWCAP",
                 NT_GNU_BUILD_ID => "NT_GNU_BUILD_ID",
                 NT_GNU_GOLD_VERSION => "NT_GNU_GOLD_VERSION",
+                NT_GNU_PROPERTY_TYPE_0 => "NT_GNU_PROPERTY_TYPE_0",
+                NT_FDO_PACKAGING_METADATA => "NT_FDO_PACKAGING_METADATA",
                 _ => "NT_UNKNOWN"
             }
         }
+
+        /// Decodes the descriptor of this note, if it is a GNU build ID, ABI tag or property
+        /// note, or an FDO packaging metadata note
+        pub fn data(&self, ctx: container::Ctx) -> error::Result<NoteData<'a>> {
+            Ok(match (self.name, self.n_type) {
+                (ELF_NOTE_GNU, NT_GNU_BUILD_ID) => NoteData::BuildId(self.desc),
+                (ELF_NOTE_GNU, NT_GNU_ABI_TAG) => NoteData::AbiTag(AbiTag::parse(self.desc, ctx)?),
+                (ELF_NOTE_GNU, NT_GNU_PROPERTY_TYPE_0) => {
+                    NoteData::GnuProperties(GnuProperties::parse(self.desc, ctx)?)
+                }
+                (ELF_NOTE_FDO, NT_FDO_PACKAGING_METADATA) => {
+                    NoteData::PackageMetadata(PackageMetadata::parse(self.desc)?)
+                }
+                _ => NoteData::Unknown(self.desc),
+            })
+        }
+    }
+
+    /// The decoded descriptor of a note
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    pub enum NoteData<'a> {
+        /// The build ID bits of an `NT_GNU_BUILD_ID` note
+        BuildId(&'a [u8]),
+        AbiTag(AbiTag),
+        GnuProperties(GnuProperties<'a>),
+        PackageMetadata(PackageMetadata<'a>),
+        /// The raw descriptor of any other note
+        Unknown(&'a [u8]),
+    }
+
+    /// The OS and minimum kernel version of an `NT_GNU_ABI_TAG` note
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    pub struct AbiTag {
+        /// One of the `ELF_NOTE_OS_*` values
+        pub os: u32,
+        pub major: u32,
+        pub minor: u32,
+        pub subminor: u32,
+    }
+
+    impl AbiTag {
+        pub fn parse(desc: &[u8], ctx: container::Ctx) -> error::Result<Self> {
+            let offset = &mut 0;
+            let mut word = || -> error::Result<u32> { Ok(desc.gread_with(offset, ctx.le)?) };
+            Ok(AbiTag {
+                os: word()?,
+                major: word()?,
+                minor: word()?,
+                subminor: word()?,
+            })
+        }
+
+        pub fn os_to_str(&self) -> &'static str {
+            match self.os {
+                ELF_NOTE_OS_LINUX => "Linux",
+                ELF_NOTE_OS_GNU => "GNU",
+                ELF_NOTE_OS_SOLARIS2 => "Solaris2",
+                ELF_NOTE_OS_FREEBSD => "FreeBSD",
+                _ => "Unknown",
+            }
+        }
+    }
+
+    /// A program property of an `NT_GNU_PROPERTY_TYPE_0` note
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    pub struct GnuProperty<'a> {
+        /// One of the `GNU_PROPERTY_*` types
+        pub pr_type: u32,
+        pub pr_data: &'a [u8],
+        /// The data read as a word, if it is 4 or 8 bytes long
+        pub value: Option<u64>,
+    }
+
+    /// The program properties of an `NT_GNU_PROPERTY_TYPE_0` note.
+    ///
+    /// Processor specific properties share the same range of types, so the `x86_*` and
+    /// `aarch64_*` accessors are only meaningful for binaries of the matching machine.
+    #[derive(Debug, PartialEq, Eq, Clone, Default)]
+    pub struct GnuProperties<'a> {
+        pub properties: Vec<GnuProperty<'a>>,
+    }
+
+    impl<'a> GnuProperties<'a> {
+        pub fn parse(desc: &'a [u8], ctx: container::Ctx) -> error::Result<Self> {
+            let alignment = if ctx.is_big() { 8 } else { 4 };
+            let mut properties = Vec::new();
+            let offset = &mut 0;
+            while *offset < desc.len() {
+                let pr_type: u32 = desc.gread_with(offset, ctx.le)?;
+                let pr_datasz: u32 = desc.gread_with(offset, ctx.le)?;
+                let pr_data: &'a [u8] = desc.gread_with(offset, pr_datasz as usize)?;
+                let value = match pr_data.len() {
+                    4 => Some(u64::from(pr_data.pread_with::<u32>(0, ctx.le)?)),
+                    8 => Some(pr_data.pread_with::<u64>(0, ctx.le)?),
+                    _ => None,
+                };
+                properties.push(GnuProperty { pr_type, pr_data, value });
+                align(alignment, offset);
+            }
+            Ok(GnuProperties { properties })
+        }
+
+        /// Returns the property of type `pr_type`, if there is one
+        pub fn get(&self, pr_type: u32) -> Option<&GnuProperty<'a>> {
+            self.properties.iter().find(|property| property.pr_type == pr_type)
+        }
+
+        fn bitmask(&self, pr_type: u32) -> u32 {
+            self.get(pr_type)
+                .and_then(|property| property.value)
+                .map_or(0, |value| value as u32)
+        }
+
+        /// The `GNU_PROPERTY_X86_FEATURE_1_*` bits all input objects support
+        pub fn x86_feature_1_and(&self) -> u32 {
+            self.bitmask(GNU_PROPERTY_X86_FEATURE_1_AND)
+        }
+
+        /// Whether the binary is compatible with Indirect Branch Tracking
+        pub fn x86_ibt(&self) -> bool {
+            self.x86_feature_1_and() & GNU_PROPERTY_X86_FEATURE_1_IBT != 0
+        }
+
+        /// Whether the binary is compatible with Shadow Stacks
+        pub fn x86_shstk(&self) -> bool {
+            self.x86_feature_1_and() & GNU_PROPERTY_X86_FEATURE_1_SHSTK != 0
+        }
+
+        /// The `GNU_PROPERTY_X86_ISA_1_*` levels needed by the binary
+        pub fn x86_isa_1_needed(&self) -> u32 {
+            self.bitmask(GNU_PROPERTY_X86_ISA_1_NEEDED)
+        }
+
+        /// The `GNU_PROPERTY_X86_ISA_1_*` levels used by the binary
+        pub fn x86_isa_1_used(&self) -> u32 {
+            self.bitmask(GNU_PROPERTY_X86_ISA_1_USED)
+        }
+
+        /// The `GNU_PROPERTY_AARCH64_FEATURE_1_*` bits all input objects support
+        pub fn aarch64_feature_1_and(&self) -> u32 {
+            self.bitmask(GNU_PROPERTY_AARCH64_FEATURE_1_AND)
+        }
+
+        /// Whether the binary is compatible with Branch Target Identification
+        pub fn aarch64_bti(&self) -> bool {
+            self.aarch64_feature_1_and() & GNU_PROPERTY_AARCH64_FEATURE_1_BTI != 0
+        }
+
+        /// Whether the binary signs its return addresses with Pointer Authentication
+        pub fn aarch64_pac(&self) -> bool {
+            self.aarch64_feature_1_and() & GNU_PROPERTY_AARCH64_FEATURE_1_PAC != 0
+        }
+    }
+
+    /// The JSON packaging metadata of an `NT_FDO_PACKAGING_METADATA` note
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    pub struct PackageMetadata<'a> {
+        pub json: &'a str,
+    }
+
+    impl<'a> PackageMetadata<'a> {
+        /// Reads the JSON object in `desc`, up to the first NUL byte
+        pub fn parse(desc: &'a [u8]) -> error::Result<Self> {
+            let end = desc.iter().position(|&byte| byte == 0).unwrap_or(desc.len());
+            let json = core::str::from_utf8(&desc[..end]).map_err(|_| {
+                error::Error::Malformed("Package metadata note is not valid UTF-8".into())
+            })?;
+            Ok(PackageMetadata { json })
+        }
+
+        /// The string value of `key`, e.g., `"name"`, `"version"`, `"os"` or `"type"`.
+        ///
+        /// This is not a full JSON parser: the metadata is expected to be a flat object, and
+        /// escape sequences within the value are left as-is.
+        pub fn get(&self, key: &str) -> Option<&'a str> {
+            let mut rest = self.json;
+            while let Some(quote) = rest.find('"') {
+                let string = &rest[quote + 1..];
+                let len = string_len(string)?;
+                let name = &string[..len];
+                rest = string[len + 1..].trim_start();
+                if let Some(value) = rest.strip_prefix(':') {
+                    if let Some(value) = value.trim_start().strip_prefix('"') {
+                        let len = string_len(value)?;
+                        if name == key {
+                            return Some(&value[..len]);
+                        }
+                        rest = &value[len + 1..];
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    /// The length of the JSON string at the start of `s`, up to its closing quote
+    fn string_len(s: &str) -> Option<usize> {
+        let mut escaped = false;
+        for (i, c) in s.char_indices() {
+            match c {
+                '"' if !escaped => return Some(i),
+                '\\' => escaped = !escaped,
+                _ => escaped = false,
+            }
+        }
+        None
     }
 
     impl<'a> ctx::TryFromCtx<'a, (usize, container::Ctx)> for Note<'a> {
@@ -295,5 +557,67 @@ if_alloc! {
             let mut notes = NoteIterator { iters: vec![], index: 0 };
             assert!(notes.next().is_none());
         }
+
+        #[test]
+        fn decode_notes() {
+            let mut notes = make_note_iter(0, 68);
+            let abi_tag = notes.next().unwrap().unwrap().data(CONTEXT.1).unwrap();
+            assert_eq!(abi_tag, NoteData::AbiTag(AbiTag { os: ELF_NOTE_OS_LINUX, major: 2, minor: 6, subminor: 32 }));
+            match notes.next().unwrap().unwrap().data(CONTEXT.1).unwrap() {
+                NoteData::BuildId(build_id) => assert_eq!(build_id, &NOTE_DATA[48..]),
+                data => panic!("unexpected note data {:?}", data),
+            }
+
+            let core = Note { n_type: NT_PRSTATUS, name: "CORE", desc: &[1, 2, 3, 4] };
+            assert_eq!(core.data(CONTEXT.1).unwrap(), NoteData::Unknown(&[1, 2, 3, 4]));
+            let abi_tag = Note { n_type: NT_GNU_ABI_TAG, name: ELF_NOTE_GNU, desc: &[0; 12] };
+            assert!(abi_tag.data(CONTEXT.1).is_err());
+        }
+
+        #[test]
+        fn decode_gnu_properties() {
+            #[rustfmt::skip]
+            let desc = [
+                // GNU_PROPERTY_X86_FEATURE_1_AND: IBT | SHSTK
+                0x02, 0x00, 0x00, 0xc0, 0x04, 0x00, 0x00, 0x00,
+                0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                // GNU_PROPERTY_X86_ISA_1_NEEDED: baseline | v2
+                0x02, 0x80, 0x00, 0xc0, 0x04, 0x00, 0x00, 0x00,
+                0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                // GNU_PROPERTY_STACK_SIZE: 0x800000
+                0x01, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ];
+            let properties = GnuProperties::parse(&desc, CONTEXT.1).unwrap();
+            assert_eq!(properties.properties.len(), 3);
+            assert!(properties.x86_ibt());
+            assert!(properties.x86_shstk());
+            assert_eq!(properties.x86_isa_1_needed(), GNU_PROPERTY_X86_ISA_1_BASELINE | GNU_PROPERTY_X86_ISA_1_V2);
+            assert_eq!(properties.x86_isa_1_used(), 0);
+            assert_eq!(properties.get(GNU_PROPERTY_STACK_SIZE).unwrap().value, Some(0x80_0000));
+            assert!(!properties.aarch64_bti());
+
+            // 32-bit properties are only padded to 4 bytes
+            let ctx = container::Ctx::new(container::Container::Little, ::scroll::Endian::Little);
+            let desc = [0x00, 0x00, 0x00, 0xc0, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00];
+            let properties = GnuProperties::parse(&desc, ctx).unwrap();
+            assert!(properties.aarch64_bti());
+            assert!(properties.aarch64_pac());
+
+            assert!(GnuProperties::parse(&desc[..10], ctx).is_err());
+        }
+
+        #[test]
+        fn decode_package_metadata() {
+            let desc = b"{ \"type\": \"rpm\", \"name\":\"a\\\"b\", \"epoch\": 1, \"version\" : \"1.0\" }\0\0";
+            let metadata = PackageMetadata::parse(desc).unwrap();
+            assert!(metadata.json.ends_with('}'));
+            assert_eq!(metadata.get("type"), Some("rpm"));
+            assert_eq!(metadata.get("name"), Some("a\\\"b"));
+            assert_eq!(metadata.get("version"), Some("1.0"));
+            assert_eq!(metadata.get("rpm"), None);
+            assert_eq!(metadata.get("epoch"), None);
+            assert!(PackageMetadata::parse(b"{\xff}").is_err());
+        }
     }
 }
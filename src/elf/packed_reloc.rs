@@ -0,0 +1,595 @@
+//! Compact relocation encodings: RELR and Android's packed relocations (`APS2`).
+//!
+//! Both only appear in dynamically linked images and decode to ordinary [`Reloc`] entries.
+//!
+//! RELR (`DT_RELR`, `SHT_RELR`) stores relative relocations as a list of words: an even word is
+//! the address of a relocation, and an odd word is a bitmap of the relocations among the next
+//! 31 or 63 words following the previous address. The relocations have an implicit addend,
+//! stored at their address.
+//!
+//! Android's packed format (`DT_ANDROID_REL`, `DT_ANDROID_RELA`) is the `APS2` magic followed
+//! by SLEB128 values: the relocation count, the initial offset, then groups of relocations that
+//! may share their offset delta, their `r_info` or their addend.
+
+use alloc::vec::Vec;
+use core::fmt;
+use scroll::{Pread, Sleb128};
+
+use crate::container::Ctx;
+use crate::error;
+
+use super::header;
+use super::reloc::{reloc32, reloc64, Reloc};
+use super::reloc::{
+    R_386_RELATIVE, R_AARCH64_RELATIVE, R_ARM_RELATIVE, R_RISCV_RELATIVE, R_X86_64_RELATIVE,
+};
+
+/// The magic of Android packed relocations
+pub const ANDROID_RELOC_MAGIC: &[u8; 4] = b"APS2";
+
+/// The relocations of the group share their `r_info`
+pub const RELOCATION_GROUPED_BY_INFO_FLAG: u64 = 1;
+/// The relocations of the group share the delta between their offsets
+pub const RELOCATION_GROUPED_BY_OFFSET_DELTA_FLAG: u64 = 2;
+/// The relocations of the group share their addend
+pub const RELOCATION_GROUPED_BY_ADDEND_FLAG: u64 = 4;
+/// The relocations of the group have addends; they are zero otherwise
+pub const RELOCATION_GROUP_HAS_ADDEND_FLAG: u64 = 8;
+
+/// Returns the relative relocation type of `machine`, the type given to RELR relocations, or
+/// `0` (`R_*_NONE`) if it is unknown
+pub fn relative_reloc_type(machine: u16) -> u32 {
+    match machine {
+        header::EM_X86_64 => R_X86_64_RELATIVE,
+        header::EM_386 => R_386_RELATIVE,
+        header::EM_AARCH64 => R_AARCH64_RELATIVE,
+        header::EM_ARM => R_ARM_RELATIVE,
+        header::EM_RISCV => R_RISCV_RELATIVE,
+        _ => 0,
+    }
+}
+
+/// Truncates `value` to the word size of `ctx`
+fn to_word(value: u64, ctx: Ctx) -> u64 {
+    if ctx.is_big() {
+        value
+    } else {
+        u64::from(value as u32)
+    }
+}
+
+#[derive(Clone, Default)]
+/// A RELR table, allowing lazy iteration over its relocations
+pub struct RelrSection<'a> {
+    bytes: &'a [u8],
+    r_type: u32,
+    ctx: Ctx,
+}
+
+impl<'a> fmt::Debug for RelrSection<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("RelrSection")
+            .field("bytes", &self.bytes.len())
+            .field("r_type", &self.r_type)
+            .field("Relocations", &self.to_vec())
+            .finish()
+    }
+}
+
+impl<'a> RelrSection<'a> {
+    /// Parse a RELR table of size `filesz` from `offset`; its relocations have type `r_type`
+    pub fn parse(
+        bytes: &'a [u8],
+        offset: usize,
+        filesz: usize,
+        r_type: u32,
+        ctx: Ctx,
+    ) -> error::Result<Self> {
+        let bytes: &[u8] = bytes.pread_with(offset, filesz)?;
+        if !bytes.chunks_exact(ctx.size()).remainder().is_empty() {
+            return Err(error::Error::Malformed(format!(
+                "RELR table size ({:#x}) is not a multiple of the word size",
+                filesz
+            )));
+        }
+        Ok(RelrSection { bytes, r_type, ctx })
+    }
+
+    /// Returns true if the table has no relocations
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Iterate over all relocations
+    pub fn iter(&self) -> RelrIterator<'a> {
+        RelrIterator {
+            bytes: self.bytes,
+            offset: 0,
+            next_address: 0,
+            bitmap_address: 0,
+            bitmap: 0,
+            r_type: self.r_type,
+            ctx: self.ctx,
+        }
+    }
+
+    /// Parse all relocations into a vector
+    pub fn to_vec(&self) -> Vec<Reloc> {
+        self.iter().collect()
+    }
+}
+
+impl<'a> IntoIterator for &RelrSection<'a> {
+    type Item = Reloc;
+    type IntoIter = RelrIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Debug)]
+pub struct RelrIterator<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    /// The address following the last address entry or bitmap
+    next_address: u64,
+    /// The address of the first word covered by `bitmap`
+    bitmap_address: u64,
+    /// The relocations of the current bitmap that are yet to be returned
+    bitmap: u64,
+    r_type: u32,
+    ctx: Ctx,
+}
+
+impl<'a> RelrIterator<'a> {
+    fn reloc(&self, r_offset: u64) -> Reloc {
+        Reloc {
+            r_offset: to_word(r_offset, self.ctx),
+            r_addend: None,
+            r_sym: 0,
+            r_type: self.r_type,
+        }
+    }
+}
+
+impl<'a> Iterator for RelrIterator<'a> {
+    type Item = Reloc;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let word_size = self.ctx.size() as u64;
+        loop {
+            if self.bitmap != 0 {
+                let index = u64::from(self.bitmap.trailing_zeros());
+                self.bitmap &= self.bitmap - 1;
+                let r_offset = self.bitmap_address.wrapping_add(index * word_size);
+                return Some(self.reloc(r_offset));
+            }
+
+            let entry = if self.ctx.is_big() {
+                self.bytes.gread_with::<u64>(&mut self.offset, self.ctx.le)
+            } else {
+                self.bytes
+                    .gread_with::<u32>(&mut self.offset, self.ctx.le)
+                    .map(u64::from)
+            }
+            .ok()?;
+            if entry & 1 == 0 {
+                self.next_address = entry.wrapping_add(word_size);
+                return Some(self.reloc(entry));
+            }
+            // Each bitmap covers one word less than it has bits, the lowest bit being the tag
+            let bits = word_size * 8 - 1;
+            self.bitmap = entry >> 1;
+            self.bitmap_address = self.next_address;
+            self.next_address = self.next_address.wrapping_add(bits * word_size);
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+/// Android packed relocations, allowing lazy iteration over them
+pub struct AndroidRelocSection<'a> {
+    /// The groups of relocations, after the header
+    bytes: &'a [u8],
+    count: usize,
+    initial_offset: u64,
+    is_rela: bool,
+    ctx: Ctx,
+}
+
+impl<'a> fmt::Debug for AndroidRelocSection<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("AndroidRelocSection")
+            .field("bytes", &self.bytes.len())
+            .field("count", &self.count)
+            .field(
+                "initial_offset",
+                &format_args!("{:#x}", self.initial_offset),
+            )
+            .field("Relocations", &self.to_vec())
+            .finish()
+    }
+}
+
+impl<'a> AndroidRelocSection<'a> {
+    /// Parse packed relocations of size `filesz` from `offset`
+    ///
+    /// The relocation count of the header is checked against the size of `bytes`, as each
+    /// relocation patches a distinct word of the file's contents.
+    pub fn parse(
+        bytes: &'a [u8],
+        offset: usize,
+        filesz: usize,
+        is_rela: bool,
+        ctx: Ctx,
+    ) -> error::Result<Self> {
+        let max_count = bytes.len() / ctx.size();
+        let bytes: &[u8] = bytes.pread_with(offset, filesz)?;
+        if !bytes.starts_with(ANDROID_RELOC_MAGIC) {
+            return Err(error::Error::BadMagic(
+                bytes.pread_with::<u32>(0, scroll::LE).map(u64::from)?,
+            ));
+        }
+        let offset = &mut ANDROID_RELOC_MAGIC.len();
+        let count = i64::from(bytes.gread::<Sleb128>(offset)?);
+        let initial_offset = i64::from(bytes.gread::<Sleb128>(offset)?);
+        if count < 0 || count as u64 > max_count as u64 {
+            return Err(error::Error::Malformed(format!(
+                "Android packed relocation count ({}) exceeds the size of the file",
+                count
+            )));
+        }
+        Ok(AndroidRelocSection {
+            bytes: &bytes[*offset..],
+            count: count as usize,
+            initial_offset: to_word(initial_offset as u64, ctx),
+            is_rela,
+            ctx,
+        })
+    }
+
+    /// The number of relocations, as stated by the header
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns true if there are no relocations
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Iterate over all relocations; iteration stops early if the data is malformed
+    pub fn iter(&self) -> AndroidRelocIterator<'a> {
+        AndroidRelocIterator {
+            bytes: self.bytes,
+            offset: 0,
+            remaining: self.count,
+            group_remaining: 0,
+            group_flags: 0,
+            group_offset_delta: 0,
+            r_offset: self.initial_offset,
+            r_info: 0,
+            r_addend: 0,
+            is_rela: self.is_rela,
+            ctx: self.ctx,
+        }
+    }
+
+    /// Parse all relocations into a vector
+    pub fn to_vec(&self) -> Vec<Reloc> {
+        self.iter().collect()
+    }
+}
+
+impl<'a> IntoIterator for &AndroidRelocSection<'a> {
+    type Item = Reloc;
+    type IntoIter = AndroidRelocIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Debug)]
+pub struct AndroidRelocIterator<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    remaining: usize,
+    group_remaining: u64,
+    group_flags: u64,
+    group_offset_delta: u64,
+    r_offset: u64,
+    r_info: u64,
+    r_addend: i64,
+    is_rela: bool,
+    ctx: Ctx,
+}
+
+impl<'a> AndroidRelocIterator<'a> {
+    fn sleb(&mut self) -> Option<i64> {
+        self.bytes
+            .gread::<Sleb128>(&mut self.offset)
+            .ok()
+            .map(i64::from)
+    }
+
+    fn has_flag(&self, flag: u64) -> bool {
+        self.group_flags & flag != 0
+    }
+
+    fn next_group(&mut self) -> Option<()> {
+        let group_size = self.sleb()? as u64;
+        if group_size == 0 || group_size > self.remaining as u64 {
+            return None;
+        }
+        self.group_remaining = group_size;
+        self.group_flags = self.sleb()? as u64;
+        if self.has_flag(RELOCATION_GROUPED_BY_OFFSET_DELTA_FLAG) {
+            self.group_offset_delta = self.sleb()? as u64;
+        }
+        if self.has_flag(RELOCATION_GROUPED_BY_INFO_FLAG) {
+            self.r_info = self.sleb()? as u64;
+        }
+        if !self.has_flag(RELOCATION_GROUP_HAS_ADDEND_FLAG) {
+            self.r_addend = 0;
+        } else if self.has_flag(RELOCATION_GROUPED_BY_ADDEND_FLAG) {
+            self.r_addend = self.r_addend.wrapping_add(self.sleb()?);
+        }
+        // each relocation takes at least a byte per field it doesn't share with the group
+        let fields = [
+            !self.has_flag(RELOCATION_GROUPED_BY_OFFSET_DELTA_FLAG),
+            !self.has_flag(RELOCATION_GROUPED_BY_INFO_FLAG),
+            self.has_flag(RELOCATION_GROUP_HAS_ADDEND_FLAG)
+                && !self.has_flag(RELOCATION_GROUPED_BY_ADDEND_FLAG),
+        ];
+        let min_size = fields.iter().filter(|&&field| field).count() as u64;
+        let available = self.bytes.len().saturating_sub(self.offset) as u64;
+        if min_size != 0 && group_size > available / min_size {
+            return None;
+        }
+        Some(())
+    }
+
+    fn next_reloc(&mut self) -> Option<Reloc> {
+        if self.group_remaining == 0 {
+            self.next_group()?;
+        }
+        let delta = if self.has_flag(RELOCATION_GROUPED_BY_OFFSET_DELTA_FLAG) {
+            self.group_offset_delta
+        } else {
+            self.sleb()? as u64
+        };
+        self.r_offset = to_word(self.r_offset.wrapping_add(delta), self.ctx);
+        if !self.has_flag(RELOCATION_GROUPED_BY_INFO_FLAG) {
+            self.r_info = self.sleb()? as u64;
+        }
+        if self.has_flag(RELOCATION_GROUP_HAS_ADDEND_FLAG)
+            && !self.has_flag(RELOCATION_GROUPED_BY_ADDEND_FLAG)
+        {
+            self.r_addend = self.r_addend.wrapping_add(self.sleb()?);
+        }
+        self.group_remaining -= 1;
+
+        let (r_sym, r_type) = if self.ctx.is_big() {
+            (reloc64::r_sym(self.r_info), reloc64::r_type(self.r_info))
+        } else {
+            let r_info = self.r_info as u32;
+            (reloc32::r_sym(r_info), reloc32::r_type(r_info))
+        };
+        let r_addend = if self.ctx.is_big() {
+            self.r_addend
+        } else {
+            i64::from(self.r_addend as i32)
+        };
+        Some(Reloc {
+            r_offset: self.r_offset,
+            r_addend: if self.is_rela { Some(r_addend) } else { None },
+            r_sym: r_sym as usize,
+            r_type,
+        })
+    }
+}
+
+impl<'a> Iterator for AndroidRelocIterator<'a> {
+    type Item = Reloc;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        match self.next_reloc() {
+            Some(reloc) => {
+                self.remaining -= 1;
+                Some(reloc)
+            }
+            None => {
+                self.remaining = 0;
+                None
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::Container;
+    use scroll::Pwrite;
+
+    fn ctx(container: Container) -> Ctx {
+        Ctx::new(container, scroll::LE)
+    }
+
+    fn relr_bytes(entries: &[u64], ctx: Ctx) -> Vec<u8> {
+        let mut bytes = vec![0; entries.len() * ctx.size()];
+        let offset = &mut 0;
+        for &entry in entries {
+            if ctx.is_big() {
+                bytes.gwrite_with(entry, offset, ctx.le).unwrap();
+            } else {
+                bytes.gwrite_with(entry as u32, offset, ctx.le).unwrap();
+            }
+        }
+        bytes
+    }
+
+    fn sleb(mut value: i64, bytes: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+            bytes.push(if done { byte } else { byte | 0x80 });
+            if done {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn relr64() {
+        let ctx = ctx(Container::Big);
+        // 0x10000, then a bitmap for 0x10008, 0x10010 and 0x10008 + 62 * 8, then 0x20000 and
+        // a bitmap for the second word after it
+        let bitmap = (0b11 << 1) | (1 << 63) | 1;
+        let bytes = relr_bytes(&[0x10000, bitmap, 0x20000, 0b101], ctx);
+        let relr = RelrSection::parse(&bytes, 0, bytes.len(), R_X86_64_RELATIVE, ctx).unwrap();
+        let offsets = relr.iter().map(|reloc| reloc.r_offset).collect::<Vec<_>>();
+        assert_eq!(
+            offsets,
+            [
+                0x10000,
+                0x10008,
+                0x10010,
+                0x10008 + 62 * 8,
+                0x20000,
+                0x20010
+            ]
+        );
+        let reloc = relr.iter().next().unwrap();
+        assert_eq!(reloc.r_type, R_X86_64_RELATIVE);
+        assert_eq!(reloc.r_addend, None);
+        assert_eq!(reloc.r_sym, 0);
+
+        assert!(RelrSection::parse(&bytes, 0, 12, R_X86_64_RELATIVE, ctx).is_err());
+    }
+
+    #[test]
+    fn relr32() {
+        let ctx = ctx(Container::Little);
+        let bytes = relr_bytes(&[0x1000, 0x8000_0003, 0x1000 + 32 * 4], ctx);
+        let relr = RelrSection::parse(&bytes, 0, bytes.len(), R_386_RELATIVE, ctx).unwrap();
+        let offsets = relr.iter().map(|reloc| reloc.r_offset).collect::<Vec<_>>();
+        assert_eq!(offsets, [0x1000, 0x1004, 0x1000 + 31 * 4, 0x1080]);
+    }
+
+    #[test]
+    fn android_relocs() {
+        let mut bytes = ANDROID_RELOC_MAGIC.to_vec();
+        for value in [5, 0x1000] {
+            sleb(value, &mut bytes);
+        }
+        // Three relative relocations, 8 bytes apart, with addends 0x100, 0x108 and 0x110
+        let flags = RELOCATION_GROUPED_BY_INFO_FLAG
+            | RELOCATION_GROUPED_BY_OFFSET_DELTA_FLAG
+            | RELOCATION_GROUP_HAS_ADDEND_FLAG;
+        for value in [
+            3,
+            flags as i64,
+            8,
+            i64::from(R_AARCH64_RELATIVE),
+            0x100,
+            8,
+            8,
+        ] {
+            sleb(value, &mut bytes);
+        }
+        // Two symbol relocations without addends, going backwards
+        for value in [2, 0, -0x10, (2 << 32) | 1025, 0x20, (3 << 32) | 1026] {
+            sleb(value, &mut bytes);
+        }
+
+        let ctx = ctx(Container::Big);
+        let size = bytes.len();
+        // the relocated words are part of the file
+        bytes.resize(0x100, 0);
+        let section = AndroidRelocSection::parse(&bytes, 0, size, true, ctx).unwrap();
+        assert_eq!(section.len(), 5);
+        let relocs = section.to_vec();
+        let offsets = relocs
+            .iter()
+            .map(|reloc| reloc.r_offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [0x1008, 0x1010, 0x1018, 0x1008, 0x1028]);
+        let addends = relocs
+            .iter()
+            .map(|reloc| reloc.r_addend)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            addends,
+            [Some(0x100), Some(0x108), Some(0x110), Some(0), Some(0)]
+        );
+        assert_eq!(relocs[0].r_type, R_AARCH64_RELATIVE);
+        assert_eq!((relocs[3].r_sym, relocs[3].r_type), (2, 1025));
+        assert_eq!((relocs[4].r_sym, relocs[4].r_type), (3, 1026));
+
+        // Truncated data ends the iteration
+        let section = AndroidRelocSection::parse(&bytes, 0, size - 2, true, ctx).unwrap();
+        assert_eq!(section.iter().count(), 4);
+
+        assert!(AndroidRelocSection::parse(b"APS1\x00\x00", 0, 6, true, ctx).is_err());
+    }
+
+    #[test]
+    fn android_relocs_sizes() {
+        let ctx = ctx(Container::Big);
+        let header = |count: i64| {
+            let mut bytes = ANDROID_RELOC_MAGIC.to_vec();
+            sleb(count, &mut bytes);
+            sleb(0x1000, &mut bytes);
+            bytes
+        };
+
+        // the count can't exceed the words of the file, nor be negative
+        let mut bytes = header(i64::MAX);
+        let size = bytes.len();
+        bytes.resize(0x1000, 0);
+        assert!(AndroidRelocSection::parse(&bytes, 0, size, false, ctx).is_err());
+        let bytes = header(-1);
+        assert!(AndroidRelocSection::parse(&bytes, 0, bytes.len(), false, ctx).is_err());
+
+        let group = |size: i64, flags: u64| {
+            let mut bytes = header(0x100);
+            for value in [size, flags as i64, 8, i64::from(R_AARCH64_RELATIVE)] {
+                sleb(value, &mut bytes);
+            }
+            let size = bytes.len();
+            bytes.resize(0x1000, 0);
+            (bytes, size)
+        };
+        let shared = RELOCATION_GROUPED_BY_INFO_FLAG | RELOCATION_GROUPED_BY_OFFSET_DELTA_FLAG;
+
+        // a group can't have more relocations than the header announces
+        let (bytes, size) = group(i64::MAX, shared);
+        let section = AndroidRelocSection::parse(&bytes, 0, size, false, ctx).unwrap();
+        assert_eq!(section.iter().count(), 0);
+
+        // relocations sharing everything with their group take no space
+        let (bytes, size) = group(0x100, shared);
+        let section = AndroidRelocSection::parse(&bytes, 0, size, false, ctx).unwrap();
+        assert_eq!(section.iter().count(), 0x100);
+
+        // but an addend per relocation needs as many bytes
+        let flags = shared | RELOCATION_GROUP_HAS_ADDEND_FLAG;
+        let (mut bytes, size) = group(0x10, flags);
+        bytes[size..size + 0x10].fill(1);
+        let section = AndroidRelocSection::parse(&bytes, 0, size + 0xf, true, ctx).unwrap();
+        assert_eq!(section.iter().count(), 0);
+        let section = AndroidRelocSection::parse(&bytes, 0, size + 0x10, true, ctx).unwrap();
+        assert_eq!(section.iter().count(), 0x10);
+    }
+}
@@ -0,0 +1,260 @@
+//! Procedure linkage table stubs, mapped to the symbols they call.
+//!
+//! Calls to imported functions go to a PLT stub, which jumps to the address the dynamic linker
+//! stores in the GOT slot of the function. The stubs live in:
+//!
+//!  * `.plt`: the lazy binding stubs, after the header which calls the resolver
+//!  * `.plt.sec`: the stubs called with x86 IBT, whose `.plt` entries only jump to the resolver
+//!  * `.plt.got`: the x86 stubs of functions without a lazy GOT slot, e.g., those whose address
+//!    is also taken
+//!
+//! The stubs are decoded to find their GOT slot, which is named by the symbol of its dynamic
+//! relocation (`R_*_JUMP_SLOT` or `R_*_GLOB_DAT`). This covers the lazy, IBT and BIND_NOW
+//! layouts on x86_64, i386 and AArch64, with or without BTI.
+
+use alloc::vec::Vec;
+use scroll::Pread;
+
+use super::header::{EM_386, EM_AARCH64, EM_X86_64};
+use super::Elf;
+
+/// The size of a stub in `.plt` and `.plt.sec` on x86
+const X86_STUB_SIZE: u64 = 16;
+/// The size of a stub in `.plt.got` on x86 without IBT
+const X86_PLT_GOT_STUB_SIZE: u64 = 8;
+
+/// The `endbr64` instruction
+const ENDBR64: [u8; 4] = [0xf3, 0x0f, 0x1e, 0xfa];
+/// The `endbr32` instruction
+const ENDBR32: [u8; 4] = [0xf3, 0x0f, 0x1e, 0xfb];
+/// The MPX `bnd` prefix
+const BND_PREFIX: u8 = 0xf2;
+/// The AArch64 `bti c` instruction
+const BTI_C: u32 = 0xd503_245f;
+
+/// A PLT stub and the symbol it calls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PltEntry<'a> {
+    /// The address of the stub, which calls go to
+    pub address: u64,
+    /// The address of the GOT slot the stub jumps through
+    pub got_address: u64,
+    /// The index of the symbol in the dynamic symbols
+    pub sym: usize,
+    /// The name of the symbol
+    pub name: &'a str,
+}
+
+/// Decodes the address of the GOT slot the PLT stub at `address` jumps through.
+///
+/// `got` is the address of the GOT, which i386 position independent stubs are relative to.
+/// Returns `None` if the stub isn't one of the known `machine` stubs.
+pub fn stub_got_address(machine: u16, stub: &[u8], address: u64, got: u64) -> Option<u64> {
+    match machine {
+        EM_X86_64 | EM_386 => {
+            let endbr = if machine == EM_X86_64 {
+                ENDBR64
+            } else {
+                ENDBR32
+            };
+            let mut offset = if stub.starts_with(&endbr) {
+                endbr.len()
+            } else {
+                0
+            };
+            if stub.get(offset) == Some(&BND_PREFIX) {
+                offset += 1;
+            }
+            let opcode = stub.get(offset..offset + 2)?;
+            let disp = u64::from(stub.pread_with::<u32>(offset + 2, scroll::LE).ok()?);
+            match (machine, opcode) {
+                // jmp *disp(%rip)
+                (EM_X86_64, [0xff, 0x25]) => {
+                    let next = address.wrapping_add(offset as u64 + 6);
+                    Some(next.wrapping_add(disp as i32 as u64))
+                }
+                // jmp *addr
+                (EM_386, [0xff, 0x25]) => Some(disp),
+                // jmp *disp(%ebx)
+                (EM_386, [0xff, 0xa3]) => Some(got.wrapping_add(disp) & 0xffff_ffff),
+                _ => None,
+            }
+        }
+        EM_AARCH64 => {
+            let word = |idx: usize| stub.pread_with::<u32>(idx * 4, scroll::LE).ok();
+            let first = if word(0)? == BTI_C { 1 } else { 0 };
+            // adrp x16, page
+            let adrp = word(first)?;
+            if adrp & 0x9f00_001f != 0x9000_0010 {
+                return None;
+            }
+            // ldr x17, [x16, #offset]
+            let ldr = word(first + 1)?;
+            if ldr & 0xffc0_03ff != 0xf940_0211 {
+                return None;
+            }
+            let imm = (((adrp >> 5) & 0x7_ffff) << 2) | ((adrp >> 29) & 0x3);
+            // sign extend the 21 bits page count
+            let pages = ((imm << 11) as i32 >> 11) as i64 as u64;
+            let pc = address.wrapping_add(first as u64 * 4);
+            let page = (pc & !0xfff).wrapping_add(pages << 12);
+            Some(page.wrapping_add(u64::from((ldr >> 10) & 0xfff) * 8))
+        }
+        _ => None,
+    }
+}
+
+impl<'a> Elf<'a> {
+    /// Returns the PLT stubs with the symbols they call, sorted by address, e.g., to annotate
+    /// `call 0x1040` as `call printf@plt`.
+    ///
+    /// The stubs are found in the `.plt`, `.plt.sec` and `.plt.got` sections, so this requires
+    /// the section headers. Stubs whose GOT slot has no symbol, such as the `.plt` header or
+    /// `IRELATIVE` stubs, are left out.
+    pub fn plt_entries(&self, bytes: &'a [u8]) -> Vec<PltEntry<'a>> {
+        let machine = self.header.e_machine;
+        let mut slots: Vec<(u64, usize)> = self
+            .pltrelocs
+            .iter()
+            .chain(self.dynrelas.iter())
+            .chain(self.dynrels.iter())
+            .filter(|reloc| reloc.r_sym != 0)
+            .map(|reloc| (reloc.r_offset, reloc.r_sym))
+            .collect();
+        slots.sort_unstable();
+        let got = self.got_address();
+
+        let mut entries = Vec::new();
+        for shdr in &self.section_headers {
+            let name = self.shdr_strtab.get_at(shdr.sh_name);
+            let stub_size = match (machine, name) {
+                (EM_AARCH64, Some(".plt")) => 4,
+                (EM_X86_64, Some(".plt")) | (EM_386, Some(".plt")) => X86_STUB_SIZE,
+                (EM_X86_64, Some(".plt.sec")) | (EM_386, Some(".plt.sec")) => X86_STUB_SIZE,
+                (EM_X86_64, Some(".plt.got")) | (EM_386, Some(".plt.got")) => {
+                    if shdr.sh_entsize == X86_STUB_SIZE {
+                        X86_STUB_SIZE
+                    } else {
+                        X86_PLT_GOT_STUB_SIZE
+                    }
+                }
+                _ => continue,
+            };
+            let data = match shdr.file_range().and_then(|range| bytes.get(range)) {
+                Some(data) => data,
+                None => continue,
+            };
+            // AArch64 stubs are 16 or 24 bytes long depending on BTI and PAC, they are found by
+            // trying every instruction, then skipping the `bti`, `adrp` and `ldr` of a match
+            let mut offset = 0;
+            while offset < data.len() {
+                let address = shdr.sh_addr.wrapping_add(offset as u64);
+                let slot = stub_got_address(machine, &data[offset..], address, got)
+                    .and_then(|slot| slots.binary_search_by_key(&slot, |&(slot, _)| slot).ok());
+                let matched = slot.is_some();
+                if let Some(slot) = slot {
+                    let (got_address, sym) = slots[slot];
+                    let name = self
+                        .dynsyms
+                        .get(sym)
+                        .and_then(|sym| self.dynstrtab.get_at(sym.st_name));
+                    if let Some(name) = name {
+                        entries.push(PltEntry {
+                            address,
+                            got_address,
+                            sym,
+                            name,
+                        });
+                    }
+                }
+                offset += if matched && machine == EM_AARCH64 {
+                    12
+                } else {
+                    stub_size as usize
+                };
+            }
+        }
+        entries.sort_unstable_by_key(|entry| entry.address);
+        entries
+    }
+
+    /// The address of the GOT which i386 position independent stubs are relative to: the start
+    /// of `.got.plt`, or `DT_PLTGOT`
+    fn got_address(&self) -> u64 {
+        let got_plt = self
+            .section_headers
+            .iter()
+            .find(|shdr| self.shdr_strtab.get_at(shdr.sh_name) == Some(".got.plt"));
+        if let Some(shdr) = got_plt {
+            return shdr.sh_addr;
+        }
+        self.dynamic
+            .as_ref()
+            .and_then(|dynamic| {
+                dynamic
+                    .dyns
+                    .iter()
+                    .find(|dyn_| dyn_.d_tag == super::dynamic::DT_PLTGOT)
+            })
+            .map_or(0, |dyn_| dyn_.d_val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_x86_stubs() {
+        let stub = [0xff, 0x25, 0xca, 0x2f, 0x00, 0x00, 0x68, 0, 0, 0, 0];
+        assert_eq!(stub_got_address(EM_X86_64, &stub, 0x1030, 0), Some(0x4000));
+        let stub = [
+            0xf3, 0x0f, 0x1e, 0xfa, 0xf2, 0xff, 0x25, 0x75, 0x2f, 0x00, 0x00,
+        ];
+        assert_eq!(stub_got_address(EM_X86_64, &stub, 0x1060, 0), Some(0x3fe0));
+        let stub = [0xf3, 0x0f, 0x1e, 0xfb, 0xff, 0xa3, 0x0c, 0x00, 0x00, 0x00];
+        assert_eq!(
+            stub_got_address(EM_386, &stub, 0x1030, 0x3ff4),
+            Some(0x4000)
+        );
+        let stub = [0xff, 0x25, 0x00, 0xc0, 0x04, 0x08];
+        assert_eq!(
+            stub_got_address(EM_386, &stub, 0x0804_9010, 0),
+            Some(0x0804_c000)
+        );
+        // the lazy IBT stubs push the relocation index and jump to the header
+        let stub = [
+            0xf3, 0x0f, 0x1e, 0xfa, 0x68, 0, 0, 0, 0, 0xf2, 0xe9, 0xe2, 0xff, 0xff, 0xff,
+        ];
+        assert_eq!(stub_got_address(EM_X86_64, &stub, 0x1030, 0), None);
+        assert_eq!(stub_got_address(EM_X86_64, &[0xff, 0x25, 0], 0, 0), None);
+    }
+
+    #[test]
+    fn decode_aarch64_stubs() {
+        let words = |words: &[u32]| -> Vec<u8> {
+            words.iter().flat_map(|word| word.to_le_bytes()).collect()
+        };
+        // adrp x16, 0x11000; ldr x17, [x16, #0xf80]; add x16, x16, #0xf80; br x17
+        let stub = words(&[0xb000_0010, 0xf947_c211, 0x913e_0210, 0xd61f_0220]);
+        assert_eq!(
+            stub_got_address(EM_AARCH64, &stub, 0x10560, 0),
+            Some(0x11f80)
+        );
+        // with BTI: bti c; adrp x16, 0x11000; ldr x17, [x16, #0x18]
+        let stub = words(&[BTI_C, 0x9000_0010, 0xf940_0e11, 0x9100_6210, 0xd61f_0220]);
+        assert_eq!(
+            stub_got_address(EM_AARCH64, &stub, 0x10ffc, 0),
+            Some(0x11018)
+        );
+        // adrp x16 backwards by a page
+        let stub = words(&[0xf0ff_fff0, 0xf940_0211]);
+        assert_eq!(
+            stub_got_address(EM_AARCH64, &stub, 0x20000, 0),
+            Some(0x1f000)
+        );
+        // the header: stp x16, x30, [sp, #-16]!
+        let stub = words(&[0xa9bf_7bf0, 0x9000_0090, 0xf947_fe11]);
+        assert_eq!(stub_got_address(EM_AARCH64, &stub, 0x10540, 0), None);
+    }
+}
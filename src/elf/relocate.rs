@@ -0,0 +1,1236 @@
+//! Applying relocations, to link a relocatable object at given section addresses or to load a
+//! mapped image at a given base.
+//!
+//! A relocation computes a value from:
+//!
+//!  * `S`: the value of the symbol
+//!  * `A`: the addend, stored in the relocation for `RELA` or at the place for `REL`
+//!  * `P`: the address of the place being relocated
+//!  * `B`: the base address the image is loaded at
+//!  * `Z`: the size of the symbol
+//!
+//! and writes it at the place, as data or into the fields of an instruction. The common types of
+//! x86_64, i386, AArch64, ARM, RISC-V and PPC64 are supported; relocations needing a GOT, a PLT,
+//! a TOC or thread local storage are an error when linking, as the linker creates those, and are
+//! left to the caller when loading an image.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use scroll::Endian;
+
+use crate::container::Ctx;
+use crate::error;
+use crate::strtab::Strtab;
+
+use super::header::{EM_386, EM_AARCH64, EM_ARM, EM_PPC64, EM_RISCV, EM_X86_64, ET_REL};
+use super::program_header::PT_LOAD;
+use super::reloc::*;
+use super::section_header::{SHN_ABS, SHN_UNDEF};
+use super::sym::{Sym, STB_WEAK};
+use super::Elf;
+
+/// The values a relocation is computed from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RelocValues {
+    /// `S`: the value of the symbol. For the RISC-V `PCREL_LO12` relocations, which refer to the
+    /// label of their `PCREL_HI20`, this is the `S + A - P` of that `PCREL_HI20`
+    pub symbol: u64,
+    /// `Z`: the size of the symbol
+    pub symbol_size: u64,
+    /// `A`: the addend, or `None` for `REL` relocations, whose addend is read from the place
+    pub addend: Option<i64>,
+    /// `P`: the address of the place
+    pub place: u64,
+    /// `B`: the base address of the image
+    pub base: u64,
+}
+
+/// Applies the relocation `r_type` of `machine` to the place at `offset` in `data`
+pub fn apply(
+    machine: u16,
+    r_type: u32,
+    data: &mut [u8],
+    offset: usize,
+    values: &RelocValues,
+    ctx: Ctx,
+) -> error::Result<()> {
+    let mut place = Place {
+        data,
+        offset,
+        le: ctx.le,
+        machine,
+        r_type,
+        unsupported: false,
+    };
+    relocate(&mut place, values, ctx)
+}
+
+/// Applies a relocation like [`apply`], but returns `Ok(false)` rather than an error when its
+/// type is unsupported
+fn apply_supported(
+    machine: u16,
+    r_type: u32,
+    data: &mut [u8],
+    offset: usize,
+    values: &RelocValues,
+    ctx: Ctx,
+) -> error::Result<bool> {
+    let mut place = Place {
+        data,
+        offset,
+        le: ctx.le,
+        machine,
+        r_type,
+        unsupported: false,
+    };
+    match relocate(&mut place, values, ctx) {
+        Err(_) if place.unsupported => Ok(false),
+        result => result.map(|()| true),
+    }
+}
+
+fn relocate(place: &mut Place, values: &RelocValues, ctx: Ctx) -> error::Result<()> {
+    let machine = place.machine;
+    match machine {
+        EM_X86_64 => x86_64(place, values),
+        EM_386 => i386(place, values),
+        EM_AARCH64 => aarch64(place, values),
+        EM_ARM => arm(place, values),
+        EM_RISCV => riscv(place, values, ctx.container.is_big()),
+        EM_PPC64 => ppc64(place, values),
+        _ => Err(error::Error::Malformed(format!(
+            "Relocations of machine {} are unsupported",
+            machine
+        ))),
+    }
+}
+
+/// The place being relocated
+struct Place<'b> {
+    data: &'b mut [u8],
+    offset: usize,
+    le: Endian,
+    machine: u16,
+    r_type: u32,
+    /// Whether the type of the relocation turned out to be unsupported
+    unsupported: bool,
+}
+
+impl Place<'_> {
+    fn bytes(&self, size: usize) -> error::Result<core::ops::Range<usize>> {
+        self.offset
+            .checked_add(size)
+            .filter(|&end| end <= self.data.len())
+            .map(|end| self.offset..end)
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "Relocation {} at {:#x} is out of bounds",
+                    self.name(),
+                    self.offset
+                ))
+            })
+    }
+
+    /// Reads the `size` bytes at the place, in `endian`
+    fn read_endian(&self, size: usize, endian: Endian) -> error::Result<u64> {
+        let range = self.bytes(size)?;
+        let bytes = &self.data[range];
+        let value = if endian.is_little() {
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |value, &b| value << 8 | u64::from(b))
+        } else {
+            bytes.iter().fold(0, |value, &b| value << 8 | u64::from(b))
+        };
+        Ok(value)
+    }
+
+    /// Writes the low `size` bytes of `value` at the place, in `endian`
+    fn write_endian(&mut self, size: usize, value: u64, endian: Endian) -> error::Result<()> {
+        let range = self.bytes(size)?;
+        for (idx, b) in self.data[range].iter_mut().enumerate() {
+            let shift = if endian.is_little() {
+                idx
+            } else {
+                size - 1 - idx
+            };
+            *b = (value >> (shift * 8)) as u8;
+        }
+        Ok(())
+    }
+
+    fn read(&self, size: usize) -> error::Result<u64> {
+        self.read_endian(size, self.le)
+    }
+
+    fn write(&mut self, size: usize, value: u64) -> error::Result<()> {
+        self.write_endian(size, value, self.le)
+    }
+
+    /// Reads the instruction at the place; instructions are little endian on AArch64, ARM and
+    /// RISC-V whatever the data is
+    fn insn(&self, size: usize) -> error::Result<u64> {
+        self.read_endian(size, self.insn_endian())
+    }
+
+    fn write_insn(&mut self, size: usize, value: u64) -> error::Result<()> {
+        self.write_endian(size, value, self.insn_endian())
+    }
+
+    /// Replaces the `mask` bits of the 32-bit instruction at the place with `bits`
+    fn patch_insn(&mut self, mask: u32, bits: u32) -> error::Result<()> {
+        let insn = self.insn(4)? as u32;
+        self.write_insn(4, u64::from((insn & !mask) | (bits & mask)))
+    }
+
+    fn insn_endian(&self) -> Endian {
+        match self.machine {
+            EM_AARCH64 | EM_ARM | EM_RISCV => Endian::Little,
+            _ => self.le,
+        }
+    }
+
+    /// Reads the implicit addend of `REL` relocations from the `size` bytes at the place
+    fn addend(&self, values: &RelocValues, size: usize) -> error::Result<i64> {
+        match values.addend {
+            Some(addend) => Ok(addend),
+            None => Ok(sign_extend(self.read(size)?, size as u32 * 8)),
+        }
+    }
+
+    /// The addend of a relocation into an instruction, which `REL` relocations can't have
+    fn insn_addend(&self, values: &RelocValues) -> error::Result<i64> {
+        values.addend.ok_or_else(|| {
+            error::Error::Malformed(format!(
+                "Relocation {} needs an explicit addend",
+                self.name()
+            ))
+        })
+    }
+
+    /// Checks that `value` fits in `bits` signed bits
+    fn check_signed(&self, value: u64, bits: u32) -> error::Result<u64> {
+        if sign_extend(value, bits) == value as i64 {
+            Ok(value)
+        } else {
+            Err(self.overflow(value))
+        }
+    }
+
+    /// Checks that `value` fits in `bits` bits, either signed or unsigned
+    fn check_bits(&self, value: u64, bits: u32) -> error::Result<u64> {
+        if value >> bits == 0 {
+            Ok(value)
+        } else {
+            self.check_signed(value, bits)
+        }
+    }
+
+    /// Checks that the branch displacement `value` is aligned to `align`
+    fn check_aligned(&self, value: u64, align: u64) -> error::Result<u64> {
+        if value & (align - 1) == 0 {
+            Ok(value)
+        } else {
+            Err(error::Error::Malformed(format!(
+                "Relocation {} at {:#x} is misaligned: {:#x}",
+                self.name(),
+                self.offset,
+                value
+            )))
+        }
+    }
+
+    fn overflow(&self, value: u64) -> error::Error {
+        error::Error::Malformed(format!(
+            "Relocation {} at {:#x} overflows: {:#x}",
+            self.name(),
+            self.offset,
+            value
+        ))
+    }
+
+    fn unsupported(&mut self) -> error::Error {
+        self.unsupported = true;
+        error::Error::Malformed(format!("Unsupported relocation {}", self.name()))
+    }
+
+    fn name(&self) -> &'static str {
+        r_to_str(self.r_type, self.machine)
+    }
+}
+
+/// Sign extends the low `bits` bits of `value`
+fn sign_extend(value: u64, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    ((value << shift) as i64) >> shift
+}
+
+/// `S + A`
+fn sa(values: &RelocValues, addend: i64) -> u64 {
+    values.symbol.wrapping_add(addend as u64)
+}
+
+/// `S + A - P`
+fn sap(values: &RelocValues, addend: i64) -> u64 {
+    sa(values, addend).wrapping_sub(values.place)
+}
+
+/// `B + A`
+fn ba(values: &RelocValues, addend: i64) -> u64 {
+    values.base.wrapping_add(addend as u64)
+}
+
+fn x86_64(place: &mut Place, values: &RelocValues) -> error::Result<()> {
+    match place.r_type {
+        R_X86_64_NONE => Ok(()),
+        R_X86_64_64 | R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT => {
+            let value = sa(values, place.addend(values, 8)?);
+            place.write(8, value)
+        }
+        R_X86_64_PC64 => {
+            let value = sap(values, place.addend(values, 8)?);
+            place.write(8, value)
+        }
+        R_X86_64_RELATIVE | R_X86_64_RELATIVE64 | R_X86_64_IRELATIVE => {
+            let value = ba(values, place.addend(values, 8)?);
+            place.write(8, value)
+        }
+        R_X86_64_SIZE64 => {
+            let value = values
+                .symbol_size
+                .wrapping_add(place.addend(values, 8)? as u64);
+            place.write(8, value)
+        }
+        R_X86_64_32 => {
+            let value = sa(values, place.addend(values, 4)?);
+            if value >> 32 != 0 {
+                return Err(place.overflow(value));
+            }
+            place.write(4, value)
+        }
+        R_X86_64_32S => {
+            let value = place.check_signed(sa(values, place.addend(values, 4)?), 32)?;
+            place.write(4, value)
+        }
+        R_X86_64_SIZE32 => {
+            let value = values
+                .symbol_size
+                .wrapping_add(place.addend(values, 4)? as u64);
+            place.write(4, place.check_bits(value, 32)?)
+        }
+        R_X86_64_PC32 | R_X86_64_PLT32 => {
+            let value = place.check_signed(sap(values, place.addend(values, 4)?), 32)?;
+            place.write(4, value)
+        }
+        R_X86_64_16 => {
+            let value = place.check_bits(sa(values, place.addend(values, 2)?), 16)?;
+            place.write(2, value)
+        }
+        R_X86_64_PC16 => {
+            let value = place.check_signed(sap(values, place.addend(values, 2)?), 16)?;
+            place.write(2, value)
+        }
+        R_X86_64_8 => {
+            let value = place.check_bits(sa(values, place.addend(values, 1)?), 8)?;
+            place.write(1, value)
+        }
+        R_X86_64_PC8 => {
+            let value = place.check_signed(sap(values, place.addend(values, 1)?), 8)?;
+            place.write(1, value)
+        }
+        _ => Err(place.unsupported()),
+    }
+}
+
+fn i386(place: &mut Place, values: &RelocValues) -> error::Result<()> {
+    let value = match place.r_type {
+        R_386_NONE => return Ok(()),
+        R_386_32 => sa(values, place.addend(values, 4)?),
+        R_386_PC32 | R_386_PLT32 => sap(values, place.addend(values, 4)?),
+        // the implicit addend of these is the lazy binding address, it isn't added
+        R_386_GLOB_DAT | R_386_JMP_SLOT => values.symbol,
+        R_386_RELATIVE | R_386_IRELATIVE => ba(values, place.addend(values, 4)?),
+        R_386_SIZE32 => values
+            .symbol_size
+            .wrapping_add(place.addend(values, 4)? as u64),
+        R_386_16 => {
+            let value = place.check_bits(sa(values, place.addend(values, 2)?), 16)?;
+            return place.write(2, value);
+        }
+        R_386_PC16 => {
+            let value = place.check_signed(sap(values, place.addend(values, 2)?), 16)?;
+            return place.write(2, value);
+        }
+        R_386_8 => {
+            let value = place.check_bits(sa(values, place.addend(values, 1)?), 8)?;
+            return place.write(1, value);
+        }
+        R_386_PC8 => {
+            let value = place.check_signed(sap(values, place.addend(values, 1)?), 8)?;
+            return place.write(1, value);
+        }
+        _ => return Err(place.unsupported()),
+    };
+    place.write(4, value)
+}
+
+fn aarch64(place: &mut Place, values: &RelocValues) -> error::Result<()> {
+    match place.r_type {
+        R_AARCH64_NONE => Ok(()),
+        R_AARCH64_ABS64 | R_AARCH64_GLOB_DAT | R_AARCH64_JUMP_SLOT => {
+            let value = sa(values, place.addend(values, 8)?);
+            place.write(8, value)
+        }
+        R_AARCH64_RELATIVE | R_AARCH64_IRELATIVE => {
+            let value = ba(values, place.addend(values, 8)?);
+            place.write(8, value)
+        }
+        R_AARCH64_PREL64 => {
+            let value = sap(values, place.addend(values, 8)?);
+            place.write(8, value)
+        }
+        R_AARCH64_ABS32 => {
+            let value = place.check_bits(sa(values, place.addend(values, 4)?), 32)?;
+            place.write(4, value)
+        }
+        R_AARCH64_PREL32 => {
+            let value = place.check_bits(sap(values, place.addend(values, 4)?), 32)?;
+            place.write(4, value)
+        }
+        R_AARCH64_ABS16 => {
+            let value = place.check_bits(sa(values, place.addend(values, 2)?), 16)?;
+            place.write(2, value)
+        }
+        R_AARCH64_PREL16 => {
+            let value = place.check_bits(sap(values, place.addend(values, 2)?), 16)?;
+            place.write(2, value)
+        }
+        R_AARCH64_CALL26 | R_AARCH64_JUMP26 => {
+            let value = sap(values, place.insn_addend(values)?);
+            let value = place.check_signed(place.check_aligned(value, 4)?, 28)?;
+            place.patch_insn(0x03ff_ffff, (value >> 2) as u32)
+        }
+        R_AARCH64_CONDBR19 | R_AARCH64_LD_PREL_LO19 => {
+            let value = sap(values, place.insn_addend(values)?);
+            let value = place.check_signed(place.check_aligned(value, 4)?, 21)?;
+            place.patch_insn(0x7_ffff << 5, ((value >> 2) as u32) << 5)
+        }
+        R_AARCH64_TSTBR14 => {
+            let value = sap(values, place.insn_addend(values)?);
+            let value = place.check_signed(place.check_aligned(value, 4)?, 16)?;
+            place.patch_insn(0x3fff << 5, ((value >> 2) as u32) << 5)
+        }
+        R_AARCH64_ADR_PREL_LO21 => {
+            let value = place.check_signed(sap(values, place.insn_addend(values)?), 21)?;
+            place.patch_insn(ADR_IMM_MASK, adr_imm(value))
+        }
+        R_AARCH64_ADR_PREL_PG_HI21 | R_AARCH64_ADR_PREL_PG_HI21_NC => {
+            let page = |address: u64| address & !0xfff;
+            let pages =
+                page(sa(values, place.insn_addend(values)?)).wrapping_sub(page(values.place));
+            // the page count is signed, targets can be on lower pages
+            let value = ((pages as i64) >> 12) as u64;
+            let value = if place.r_type == R_AARCH64_ADR_PREL_PG_HI21 {
+                place.check_signed(value, 21)?
+            } else {
+                value
+            };
+            place.patch_insn(ADR_IMM_MASK, adr_imm(value))
+        }
+        R_AARCH64_ADD_ABS_LO12_NC | R_AARCH64_LDST8_ABS_LO12_NC => {
+            let value = sa(values, place.insn_addend(values)?) & 0xfff;
+            place.patch_insn(0xfff << 10, (value as u32) << 10)
+        }
+        R_AARCH64_LDST16_ABS_LO12_NC
+        | R_AARCH64_LDST32_ABS_LO12_NC
+        | R_AARCH64_LDST64_ABS_LO12_NC
+        | R_AARCH64_LDST128_ABS_LO12_NC => {
+            let shift = match place.r_type {
+                R_AARCH64_LDST16_ABS_LO12_NC => 1,
+                R_AARCH64_LDST32_ABS_LO12_NC => 2,
+                R_AARCH64_LDST64_ABS_LO12_NC => 3,
+                _ => 4,
+            };
+            let value = sa(values, place.insn_addend(values)?) & 0xfff;
+            let value = place.check_aligned(value, 1 << shift)? >> shift;
+            place.patch_insn(0xfff << 10, (value as u32) << 10)
+        }
+        R_AARCH64_MOVW_UABS_G0
+        | R_AARCH64_MOVW_UABS_G0_NC
+        | R_AARCH64_MOVW_UABS_G1
+        | R_AARCH64_MOVW_UABS_G1_NC
+        | R_AARCH64_MOVW_UABS_G2
+        | R_AARCH64_MOVW_UABS_G2_NC
+        | R_AARCH64_MOVW_UABS_G3 => {
+            let (group, checked) = match place.r_type {
+                R_AARCH64_MOVW_UABS_G0 => (0, true),
+                R_AARCH64_MOVW_UABS_G0_NC => (0, false),
+                R_AARCH64_MOVW_UABS_G1 => (1, true),
+                R_AARCH64_MOVW_UABS_G1_NC => (1, false),
+                R_AARCH64_MOVW_UABS_G2 => (2, true),
+                R_AARCH64_MOVW_UABS_G2_NC => (2, false),
+                _ => (3, false),
+            };
+            let value = sa(values, place.insn_addend(values)?);
+            if checked && group < 3 && value >> (16 * (group + 1)) != 0 {
+                return Err(place.overflow(value));
+            }
+            let imm = (value >> (16 * group)) & 0xffff;
+            place.patch_insn(0xffff << 5, (imm as u32) << 5)
+        }
+        _ => Err(place.unsupported()),
+    }
+}
+
+/// The `immlo` and `immhi` fields of `adr` and `adrp`
+const ADR_IMM_MASK: u32 = (0x3 << 29) | (0x7_ffff << 5);
+
+fn adr_imm(value: u64) -> u32 {
+    let value = value as u32;
+    ((value & 0x3) << 29) | (((value >> 2) & 0x7_ffff) << 5)
+}
+
+fn arm(place: &mut Place, values: &RelocValues) -> error::Result<()> {
+    match place.r_type {
+        R_ARM_NONE | R_ARM_V4BX => Ok(()),
+        R_ARM_ABS32 | R_ARM_TARGET1 => {
+            let value = sa(values, place.addend(values, 4)?);
+            place.write(4, value)
+        }
+        R_ARM_REL32 => {
+            let value = sap(values, place.addend(values, 4)?);
+            place.write(4, value)
+        }
+        // the implicit addend of these is the lazy binding address, it isn't added
+        R_ARM_GLOB_DAT | R_ARM_JUMP_SLOT => {
+            let value = sa(values, values.addend.unwrap_or(0));
+            place.write(4, value)
+        }
+        R_ARM_RELATIVE | R_ARM_IRELATIVE => {
+            let value = ba(values, place.addend(values, 4)?);
+            place.write(4, value)
+        }
+        R_ARM_ABS16 => {
+            let value = place.check_bits(sa(values, place.addend(values, 2)?), 16)?;
+            place.write(2, value)
+        }
+        R_ARM_ABS8 => {
+            let value = place.check_bits(sa(values, place.addend(values, 1)?), 8)?;
+            place.write(1, value)
+        }
+        R_ARM_PREL31 => {
+            let insn = place.insn(4)?;
+            let addend = match values.addend {
+                Some(addend) => addend,
+                None => sign_extend(insn, 31),
+            };
+            let value = place.check_signed(sap(values, addend), 31)?;
+            place.write_insn(4, (insn & 0x8000_0000) | (value & 0x7fff_ffff))
+        }
+        R_ARM_PC24 | R_ARM_PLT32 | R_ARM_CALL | R_ARM_JUMP24 => {
+            let insn = place.insn(4)?;
+            let addend = match values.addend {
+                Some(addend) => addend,
+                None => sign_extend((insn & 0xff_ffff) << 2, 26),
+            };
+            let value = sap(values, addend);
+            let value = place.check_signed(place.check_aligned(value, 4)?, 26)?;
+            place.patch_insn(0xff_ffff, (value >> 2) as u32)
+        }
+        R_ARM_MOVW_ABS_NC | R_ARM_MOVT_ABS => {
+            let insn = place.insn(4)? as u32;
+            let addend = match values.addend {
+                Some(addend) => addend,
+                None => sign_extend(u64::from(((insn >> 4) & 0xf000) | (insn & 0xfff)), 16),
+            };
+            let value = sa(values, addend);
+            let imm = if place.r_type == R_ARM_MOVT_ABS {
+                (value >> 16) as u32 & 0xffff
+            } else {
+                value as u32 & 0xffff
+            };
+            place.patch_insn(0xf_0fff, ((imm & 0xf000) << 4) | (imm & 0xfff))
+        }
+        R_ARM_THM_PC22 | R_ARM_THM_JUMP24 => {
+            // two halfwords: S:imm10, and J1:J2:imm11
+            let high = place.insn(2)? as u32;
+            place.offset += 2;
+            let low = place.insn(2)? as u32;
+            let addend = match values.addend {
+                Some(addend) => addend,
+                None => {
+                    let s = (high >> 10) & 1;
+                    let i1 = !((low >> 13) & 1 ^ s) & 1;
+                    let i2 = !((low >> 11) & 1 ^ s) & 1;
+                    let imm = (s << 24)
+                        | (i1 << 23)
+                        | (i2 << 22)
+                        | ((high & 0x3ff) << 12)
+                        | ((low & 0x7ff) << 1);
+                    sign_extend(u64::from(imm), 25)
+                }
+            };
+            let value = place.check_signed(sap(values, addend), 25)? as u32;
+            let s = (value >> 24) & 1;
+            let j1 = (!(value >> 23) & 1) ^ s;
+            let j2 = (!(value >> 22) & 1) ^ s;
+            let low = (low & 0xd000) | (j1 << 13) | (j2 << 11) | ((value >> 1) & 0x7ff);
+            let high = (high & 0xf800) | (s << 10) | ((value >> 12) & 0x3ff);
+            place.write_insn(2, u64::from(low))?;
+            place.offset -= 2;
+            place.write_insn(2, u64::from(high))
+        }
+        _ => Err(place.unsupported()),
+    }
+}
+
+fn riscv(place: &mut Place, values: &RelocValues, is_64: bool) -> error::Result<()> {
+    let word = if is_64 { 8 } else { 4 };
+    match place.r_type {
+        R_RISCV_NONE | R_RISCV_RELAX | R_RISCV_ALIGN => Ok(()),
+        R_RISCV_32 => {
+            let value = sa(values, place.addend(values, 4)?);
+            place.write(4, value)
+        }
+        R_RISCV_64 => {
+            let value = sa(values, place.addend(values, 8)?);
+            place.write(8, value)
+        }
+        R_RISCV_JUMP_SLOT => {
+            let value = sa(values, place.addend(values, word)?);
+            place.write(word, value)
+        }
+        R_RISCV_RELATIVE => {
+            let value = ba(values, place.addend(values, word)?);
+            place.write(word, value)
+        }
+        R_RISCV_ADD8 | R_RISCV_ADD16 | R_RISCV_ADD32 | R_RISCV_ADD64 | R_RISCV_SUB6
+        | R_RISCV_SUB8 | R_RISCV_SUB16 | R_RISCV_SUB32 | R_RISCV_SUB64 => {
+            let size = match place.r_type {
+                R_RISCV_ADD8 | R_RISCV_SUB6 | R_RISCV_SUB8 => 1,
+                R_RISCV_ADD16 | R_RISCV_SUB16 => 2,
+                R_RISCV_ADD32 | R_RISCV_SUB32 => 4,
+                _ => 8,
+            };
+            let old = place.read(size)?;
+            let value = sa(values, place.insn_addend(values)?);
+            let new = match place.r_type {
+                R_RISCV_ADD8 | R_RISCV_ADD16 | R_RISCV_ADD32 | R_RISCV_ADD64 => {
+                    old.wrapping_add(value)
+                }
+                _ => old.wrapping_sub(value),
+            };
+            let new = if place.r_type == R_RISCV_SUB6 {
+                (old & !0x3f) | (new & 0x3f)
+            } else {
+                new
+            };
+            place.write(size, new)
+        }
+        R_RISCV_SET6 | R_RISCV_SET8 | R_RISCV_SET16 | R_RISCV_SET32 => {
+            let value = sa(values, place.insn_addend(values)?);
+            match place.r_type {
+                R_RISCV_SET6 => {
+                    let old = place.read(1)?;
+                    place.write(1, (old & !0x3f) | (value & 0x3f))
+                }
+                R_RISCV_SET8 => place.write(1, value),
+                R_RISCV_SET16 => place.write(2, value),
+                _ => place.write(4, value),
+            }
+        }
+        R_RISCV_BRANCH => {
+            let value = sap(values, place.insn_addend(values)?);
+            let value = place.check_signed(place.check_aligned(value, 2)?, 13)? as u32;
+            let imm = ((value >> 12) & 1) << 31
+                | ((value >> 5) & 0x3f) << 25
+                | ((value >> 1) & 0xf) << 8
+                | ((value >> 11) & 1) << 7;
+            place.patch_insn(0xfe00_0f80, imm)
+        }
+        R_RISCV_JAL => {
+            let value = sap(values, place.insn_addend(values)?);
+            let value = place.check_signed(place.check_aligned(value, 2)?, 21)? as u32;
+            let imm = ((value >> 20) & 1) << 31
+                | ((value >> 1) & 0x3ff) << 21
+                | ((value >> 11) & 1) << 20
+                | ((value >> 12) & 0xff) << 12;
+            place.patch_insn(0xffff_f000, imm)
+        }
+        R_RISCV_RVC_BRANCH => {
+            let value = sap(values, place.insn_addend(values)?);
+            let value = place.check_signed(place.check_aligned(value, 2)?, 9)?;
+            let imm = ((value >> 8) & 1) << 12
+                | ((value >> 3) & 0x3) << 10
+                | ((value >> 6) & 0x3) << 5
+                | ((value >> 1) & 0x3) << 3
+                | ((value >> 5) & 1) << 2;
+            let insn = place.insn(2)?;
+            place.write_insn(2, (insn & !0x1c7c) | imm)
+        }
+        R_RISCV_RVC_JUMP => {
+            let value = sap(values, place.insn_addend(values)?);
+            let value = place.check_signed(place.check_aligned(value, 2)?, 12)?;
+            let imm = ((value >> 11) & 1) << 12
+                | ((value >> 4) & 1) << 11
+                | ((value >> 8) & 0x3) << 9
+                | ((value >> 10) & 1) << 8
+                | ((value >> 6) & 1) << 7
+                | ((value >> 7) & 1) << 6
+                | ((value >> 1) & 0x7) << 3
+                | ((value >> 5) & 1) << 2;
+            let insn = place.insn(2)?;
+            place.write_insn(2, (insn & !0x1ffc) | imm)
+        }
+        R_RISCV_CALL | R_RISCV_CALL_PLT => {
+            // auipc and jalr
+            let value = place.check_signed(sap(values, place.insn_addend(values)?), 32)?;
+            place.patch_insn(0xffff_f000, riscv_hi20(value))?;
+            place.offset += 4;
+            let result = place.patch_insn(0xfff0_0000, riscv_lo12_i(value));
+            place.offset -= 4;
+            result
+        }
+        R_RISCV_PCREL_HI20 => {
+            let value = place.check_signed(sap(values, place.insn_addend(values)?), 32)?;
+            place.patch_insn(0xffff_f000, riscv_hi20(value))
+        }
+        R_RISCV_HI20 => {
+            let value = place.check_signed(sa(values, place.insn_addend(values)?), 32)?;
+            place.patch_insn(0xffff_f000, riscv_hi20(value))
+        }
+        // `S` is already the value of the `PCREL_HI20`
+        R_RISCV_PCREL_LO12_I => place.patch_insn(0xfff0_0000, riscv_lo12_i(values.symbol)),
+        R_RISCV_PCREL_LO12_S => place.patch_insn(0xfe00_0f80, riscv_lo12_s(values.symbol)),
+        R_RISCV_LO12_I => {
+            let value = sa(values, place.insn_addend(values)?);
+            place.patch_insn(0xfff0_0000, riscv_lo12_i(value))
+        }
+        R_RISCV_LO12_S => {
+            let value = sa(values, place.insn_addend(values)?);
+            place.patch_insn(0xfe00_0f80, riscv_lo12_s(value))
+        }
+        _ => Err(place.unsupported()),
+    }
+}
+
+/// The U-type immediate of `value`, rounded for the sign extended low 12 bits
+fn riscv_hi20(value: u64) -> u32 {
+    (value.wrapping_add(0x800) as u32) & 0xffff_f000
+}
+
+/// The I-type immediate of the low 12 bits of `value`
+fn riscv_lo12_i(value: u64) -> u32 {
+    (value as u32 & 0xfff) << 20
+}
+
+/// The S-type immediate of the low 12 bits of `value`
+fn riscv_lo12_s(value: u64) -> u32 {
+    let value = value as u32;
+    ((value >> 5) & 0x7f) << 25 | (value & 0x1f) << 7
+}
+
+fn ppc64(place: &mut Place, values: &RelocValues) -> error::Result<()> {
+    // 16-bit relocations point at the halfword of the instruction, not at the instruction
+    let half = |value: u64| value & 0xffff;
+    let ha = |value: u64| value.wrapping_add(0x8000);
+    match place.r_type {
+        R_PPC64_NONE => Ok(()),
+        R_PPC64_ADDR64 | R_PPC64_UADDR64 | R_PPC64_GLOB_DAT | R_PPC64_JMP_SLOT => {
+            let value = sa(values, place.addend(values, 8)?);
+            place.write(8, value)
+        }
+        R_PPC64_RELATIVE | R_PPC64_IRELATIVE => {
+            let value = ba(values, place.addend(values, 8)?);
+            place.write(8, value)
+        }
+        R_PPC64_REL64 => {
+            let value = sap(values, place.addend(values, 8)?);
+            place.write(8, value)
+        }
+        R_PPC64_ADDR32 | R_PPC64_UADDR32 => {
+            let value = place.check_bits(sa(values, place.addend(values, 4)?), 32)?;
+            place.write(4, value)
+        }
+        R_PPC64_REL32 => {
+            let value = place.check_signed(sap(values, place.addend(values, 4)?), 32)?;
+            place.write(4, value)
+        }
+        R_PPC64_REL24 | R_PPC64_REL24_NOTOC => {
+            let value = sap(values, place.insn_addend(values)?);
+            let value = place.check_signed(place.check_aligned(value, 4)?, 26)?;
+            place.patch_insn(0x03ff_fffc, value as u32)
+        }
+        R_PPC64_REL14 | R_PPC64_REL14_BRTAKEN | R_PPC64_REL14_BRNTAKEN => {
+            let value = sap(values, place.insn_addend(values)?);
+            let value = place.check_signed(place.check_aligned(value, 4)?, 16)?;
+            place.patch_insn(0xfffc, value as u32)
+        }
+        R_PPC64_ADDR16
+        | R_PPC64_UADDR16
+        | R_PPC64_ADDR16_LO
+        | R_PPC64_ADDR16_HI
+        | R_PPC64_ADDR16_HA
+        | R_PPC64_ADDR16_HIGH
+        | R_PPC64_ADDR16_HIGHA
+        | R_PPC64_ADDR16_HIGHER
+        | R_PPC64_ADDR16_HIGHERA
+        | R_PPC64_ADDR16_HIGHEST
+        | R_PPC64_ADDR16_HIGHESTA
+        | R_PPC64_REL16
+        | R_PPC64_REL16_LO
+        | R_PPC64_REL16_HI
+        | R_PPC64_REL16_HA => {
+            let addend = place.insn_addend(values)?;
+            let value = match place.r_type {
+                R_PPC64_REL16 | R_PPC64_REL16_LO | R_PPC64_REL16_HI | R_PPC64_REL16_HA => {
+                    sap(values, addend)
+                }
+                _ => sa(values, addend),
+            };
+            let value = match place.r_type {
+                R_PPC64_ADDR16 | R_PPC64_UADDR16 | R_PPC64_REL16 => {
+                    place.check_signed(value, 16)?
+                }
+                R_PPC64_ADDR16_LO | R_PPC64_REL16_LO => value,
+                R_PPC64_ADDR16_HI | R_PPC64_REL16_HI => place.check_signed(value, 32)? >> 16,
+                R_PPC64_ADDR16_HA | R_PPC64_REL16_HA => place.check_signed(ha(value), 32)? >> 16,
+                R_PPC64_ADDR16_HIGH => value >> 16,
+                R_PPC64_ADDR16_HIGHA => ha(value) >> 16,
+                R_PPC64_ADDR16_HIGHER => value >> 32,
+                R_PPC64_ADDR16_HIGHERA => ha(value) >> 32,
+                R_PPC64_ADDR16_HIGHEST => value >> 48,
+                _ => ha(value) >> 48,
+            };
+            place.write(2, half(value))
+        }
+        R_PPC64_ADDR16_DS | R_PPC64_ADDR16_LO_DS => {
+            let value = sa(values, place.insn_addend(values)?);
+            let value = if place.r_type == R_PPC64_ADDR16_DS {
+                place.check_signed(value, 16)?
+            } else {
+                value
+            };
+            let value = place.check_aligned(half(value), 4)?;
+            let old = place.read(2)?;
+            place.write(2, (old & 0x3) | value)
+        }
+        _ => Err(place.unsupported()),
+    }
+}
+
+impl<'a> Elf<'a> {
+    /// Returns the contents of the section at `idx` of this `ET_REL` object, with the
+    /// relocations of its `SHT_REL` and `SHT_RELA` sections applied.
+    ///
+    /// `section_addresses` holds the address each section is loaded at, by section index; the
+    /// sections past its end are at `0`. Defined symbols are at the address of their section
+    /// plus their value, and `resolve` returns the address of the undefined ones by name; an
+    /// undefined symbol it doesn't resolve is an error, unless it is weak, in which case it is at
+    /// `0`.
+    pub fn relocate_section<F>(
+        &self,
+        bytes: &'a [u8],
+        idx: usize,
+        section_addresses: &[u64],
+        mut resolve: F,
+    ) -> error::Result<Vec<u8>>
+    where
+        F: FnMut(&str) -> Option<u64>,
+    {
+        if self.header.e_type != ET_REL {
+            return Err(error::Error::Malformed(format!(
+                "Can't relocate the sections of an ELF of type {}",
+                self.header.e_type
+            )));
+        }
+        let machine = self.header.e_machine;
+        let mut data = self.section_data(bytes, idx)?.into_owned();
+        let address = section_addresses.get(idx).copied().unwrap_or(0);
+        for (_, relocs) in self
+            .shdr_relocs
+            .iter()
+            .filter(|(reloc_idx, _)| self.section_headers[*reloc_idx].sh_info as usize == idx)
+        {
+            // the `PCREL_LO12` relocations refer to the label of their `PCREL_HI20`
+            let mut hi20 = BTreeMap::new();
+            let mut symbols = Vec::with_capacity(relocs.len());
+            for reloc in relocs.iter() {
+                let sym = self.syms.get(reloc.r_sym).unwrap_or_default();
+                let symbol = if reloc.r_sym == 0 {
+                    0
+                } else {
+                    self.symbol_address(&sym, &self.strtab, section_addresses, &mut resolve)?
+                };
+                let values = RelocValues {
+                    symbol,
+                    symbol_size: sym.st_size,
+                    addend: reloc.r_addend,
+                    place: address.wrapping_add(reloc.r_offset),
+                    base: 0,
+                };
+                if machine == EM_RISCV && reloc.r_type == R_RISCV_PCREL_HI20 {
+                    hi20.insert(values.place, sap(&values, values.addend.unwrap_or(0)));
+                }
+                symbols.push(values);
+            }
+            for (reloc, mut values) in relocs.iter().zip(symbols) {
+                if machine == EM_RISCV
+                    && matches!(reloc.r_type, R_RISCV_PCREL_LO12_I | R_RISCV_PCREL_LO12_S)
+                {
+                    values.symbol = *hi20.get(&values.symbol).ok_or_else(|| {
+                        error::Error::Malformed(format!(
+                            "No R_RISCV_PCREL_HI20 at {:#x} for the relocation at {:#x}",
+                            values.symbol, reloc.r_offset
+                        ))
+                    })?;
+                }
+                apply(
+                    machine,
+                    reloc.r_type,
+                    &mut data,
+                    reloc.r_offset as usize,
+                    &values,
+                    self.ctx,
+                )?;
+            }
+        }
+        Ok(data)
+    }
+
+    /// Returns the link-time address and the contents of the memory image of the `PT_LOAD`
+    /// segments, with the `.bss` parts zeroed, ready to be relocated by
+    /// [`relocate_image`](Elf::relocate_image).
+    ///
+    /// The image spans from the lowest to the highest segment address, which the program
+    /// headers can make arbitrarily large; images larger than `max_size` bytes are an error.
+    pub fn load_image(&self, bytes: &'a [u8], max_size: usize) -> error::Result<(u64, Vec<u8>)> {
+        let loads = || {
+            self.program_headers
+                .iter()
+                .filter(|phdr| phdr.p_type == PT_LOAD)
+        };
+        let start = loads().map(|phdr| phdr.p_vaddr).min().unwrap_or(0);
+        let end = loads()
+            .map(|phdr| phdr.p_vaddr.saturating_add(phdr.p_memsz))
+            .max()
+            .unwrap_or(0);
+        let size = usize::try_from(end - start)
+            .ok()
+            .filter(|&size| size <= max_size)
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "Image size {:#x} exceeds the maximum size {:#x}",
+                    end - start,
+                    max_size
+                ))
+            })?;
+        let mut image = Vec::new();
+        image
+            .try_reserve_exact(size)
+            .map_err(|_| error::Error::Malformed(format!("Image size {:#x} is too big", size)))?;
+        image.resize(size, 0);
+        for phdr in loads() {
+            let data = bytes.get(phdr.file_range()).ok_or_else(|| {
+                error::Error::Malformed(format!("Segment at {:#x} is out of bounds", phdr.p_vaddr))
+            })?;
+            let offset = (phdr.p_vaddr - start) as usize;
+            let len = data.len().min(phdr.p_memsz as usize);
+            image[offset..offset + len].copy_from_slice(&data[..len]);
+        }
+        Ok((start, image))
+    }
+
+    /// Applies the dynamic relocations to `image`, the memory image of the binary from the
+    /// link-time address `image_address`, loaded `base` bytes higher; `base` is `0` for
+    /// executables which aren't position independent.
+    ///
+    /// Defined symbols are at `base` plus their value, and `resolve` returns the address of the
+    /// undefined ones by name; an undefined symbol it doesn't resolve is an error, unless it is
+    /// weak, in which case it is at `0`. `IRELATIVE` relocations are set to the address of their
+    /// resolver function, which isn't called.
+    ///
+    /// Relocations of unsupported types, like those of thread local storage or `COPY`, are left
+    /// untouched and returned, for the caller to apply once every other relocation is.
+    pub fn relocate_image<F>(
+        &self,
+        image: &mut [u8],
+        image_address: u64,
+        base: u64,
+        mut resolve: F,
+    ) -> error::Result<Vec<Reloc>>
+    where
+        F: FnMut(&str) -> Option<u64>,
+    {
+        let machine = self.header.e_machine;
+        let relocs = self
+            .dynrelas
+            .iter()
+            .chain(self.dynrels.iter())
+            .chain(self.pltrelocs.iter())
+            .chain(self.relr.iter())
+            .chain(self.android_relocs.iter());
+        let mut unsupported = Vec::new();
+        for reloc in relocs {
+            let sym = self.dynsyms.get(reloc.r_sym).unwrap_or_default();
+            let symbol = if reloc.r_sym == 0 {
+                0
+            } else if sym.st_shndx as u32 == SHN_UNDEF {
+                self.symbol_address(&sym, &self.dynstrtab, &[], &mut resolve)?
+            } else if sym.st_shndx as u32 == SHN_ABS {
+                sym.st_value
+            } else {
+                base.wrapping_add(sym.st_value)
+            };
+            let offset = reloc.r_offset.checked_sub(image_address).ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "Relocation at {:#x} is before the image at {:#x}",
+                    reloc.r_offset, image_address
+                ))
+            })?;
+            let values = RelocValues {
+                symbol,
+                symbol_size: sym.st_size,
+                addend: reloc.r_addend,
+                place: base.wrapping_add(reloc.r_offset),
+                base,
+            };
+            if !apply_supported(
+                machine,
+                reloc.r_type,
+                image,
+                offset as usize,
+                &values,
+                self.ctx,
+            )? {
+                unsupported.push(reloc);
+            }
+        }
+        Ok(unsupported)
+    }
+
+    /// The address of `sym` in an object whose sections are at `section_addresses`
+    fn symbol_address<F>(
+        &self,
+        sym: &Sym,
+        strtab: &Strtab<'a>,
+        section_addresses: &[u64],
+        resolve: &mut F,
+    ) -> error::Result<u64>
+    where
+        F: FnMut(&str) -> Option<u64>,
+    {
+        match sym.st_shndx as u32 {
+            SHN_UNDEF => {
+                let name = strtab.get_at(sym.st_name).unwrap_or("");
+                match resolve(name) {
+                    Some(address) => Ok(address),
+                    None if sym.st_bind() == STB_WEAK => Ok(0),
+                    None => Err(error::Error::Malformed(format!(
+                        "Undefined symbol {}",
+                        name
+                    ))),
+                }
+            }
+            SHN_ABS => Ok(sym.st_value),
+            shndx => match section_addresses.get(shndx as usize) {
+                Some(address) => Ok(address.wrapping_add(sym.st_value)),
+                None if (shndx as usize) < self.section_headers.len() => Ok(sym.st_value),
+                None => Err(error::Error::Malformed(format!(
+                    "Symbol section index {} is out of bounds",
+                    shndx
+                ))),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::Container;
+
+    const LE: Ctx = Ctx {
+        container: Container::Big,
+        le: Endian::Little,
+    };
+    const BE: Ctx = Ctx {
+        container: Container::Big,
+        le: Endian::Big,
+    };
+
+    /// Relocates the little endian instruction words `insns` from `place` to `symbol`
+    fn relocate(machine: u16, r_type: u32, insns: &[u32], symbol: u64, place: u64) -> Vec<u32> {
+        let mut data: Vec<u8> = insns.iter().flat_map(|insn| insn.to_le_bytes()).collect();
+        let values = RelocValues {
+            symbol,
+            addend: Some(0),
+            place,
+            ..Default::default()
+        };
+        apply(machine, r_type, &mut data, 0, &values, LE).unwrap();
+        data.chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn aarch64() {
+        let reloc =
+            |r_type, insn, symbol| relocate(EM_AARCH64, r_type, &[insn], symbol, 0x40_0010)[0];
+        // bl
+        assert_eq!(reloc(R_AARCH64_CALL26, 0x9400_0000, 0x40_1010), 0x9400_0400);
+        assert_eq!(reloc(R_AARCH64_CALL26, 0x9400_0000, 0x3f_f010), 0x97ff_fc00);
+        // adrp x0; add x0, x0; ldr x1, [x0]
+        assert_eq!(
+            reloc(R_AARCH64_ADR_PREL_PG_HI21, 0x9000_0000, 0x41_2345),
+            0xd000_0080
+        );
+        assert_eq!(
+            reloc(R_AARCH64_ADR_PREL_PG_HI21, 0x9000_0000, 0x3f_f123),
+            0xf0ff_ffe0
+        );
+        assert_eq!(
+            reloc(R_AARCH64_ADD_ABS_LO12_NC, 0x9100_0000, 0x41_2345),
+            0x910d_1400
+        );
+        assert_eq!(
+            reloc(R_AARCH64_LDST64_ABS_LO12_NC, 0xf940_0001, 0x41_2348),
+            0xf941_a401
+        );
+        // movz x0, #0x1234, lsl #16
+        assert_eq!(
+            reloc(R_AARCH64_MOVW_UABS_G1, 0xd2a0_0000, 0x1234_5678),
+            0xd2a2_4680
+        );
+
+        let mut data = 0xf940_0001u32.to_le_bytes();
+        let values = RelocValues {
+            symbol: 0x41_2345,
+            addend: Some(0),
+            ..Default::default()
+        };
+        let r_type = R_AARCH64_LDST64_ABS_LO12_NC;
+        assert!(apply(EM_AARCH64, r_type, &mut data, 0, &values, LE).is_err());
+        let values = RelocValues {
+            symbol: 0x1000_0000,
+            addend: Some(0),
+            ..Default::default()
+        };
+        assert!(apply(EM_AARCH64, R_AARCH64_CALL26, &mut data, 0, &values, LE).is_err());
+    }
+
+    #[test]
+    fn arm() {
+        // REL relocations: the addend is in the instruction
+        let reloc = |r_type, data: &mut [u8], symbol| {
+            let values = RelocValues {
+                symbol,
+                place: 0x1000,
+                ..Default::default()
+            };
+            apply(EM_ARM, r_type, data, 0, &values, LE).unwrap();
+        };
+        // bl, whose implicit addend is -8
+        let mut data = 0xebff_fffeu32.to_le_bytes();
+        reloc(R_ARM_CALL, &mut data, 0x2000);
+        assert_eq!(u32::from_le_bytes(data), 0xeb00_03fe);
+        // movw r0; movt r0
+        let mut data = 0xe300_0000u32.to_le_bytes();
+        reloc(R_ARM_MOVW_ABS_NC, &mut data, 0x1234_5678);
+        assert_eq!(u32::from_le_bytes(data), 0xe305_0678);
+        let mut data = 0xe340_0000u32.to_le_bytes();
+        reloc(R_ARM_MOVT_ABS, &mut data, 0x1234_5678);
+        assert_eq!(u32::from_le_bytes(data), 0xe341_0234);
+        // Thumb bl, whose implicit addend is -4
+        let mut data = [0xff, 0xf7, 0xfe, 0xff];
+        reloc(R_ARM_THM_PC22, &mut data, 0x2000);
+        assert_eq!(data, [0x00, 0xf0, 0xfe, 0xff]);
+        let mut data = [0xff, 0xf7, 0xfe, 0xff];
+        reloc(R_ARM_THM_PC22, &mut data, 0x800);
+        assert_eq!(data, [0xff, 0xf7, 0xfe, 0xfb]);
+        let mut data = 0x10u32.to_le_bytes();
+        reloc(R_ARM_ABS32, &mut data, 0x2000);
+        assert_eq!(u32::from_le_bytes(data), 0x2010);
+    }
+
+    #[test]
+    fn riscv() {
+        // auipc ra; jalr ra
+        let call = [0x0000_0097, 0x0000_80e7];
+        let reloc =
+            |r_type, insns: &[u32], symbol| relocate(EM_RISCV, r_type, insns, symbol, 0x1000);
+        assert_eq!(
+            reloc(R_RISCV_CALL_PLT, &call, 0x1_2345),
+            [0x0001_1097, 0x3450_80e7]
+        );
+        // the low 12 bits are sign extended by jalr
+        assert_eq!(
+            reloc(R_RISCV_CALL, &call, 0x1_2900),
+            [0x0001_2097, 0x9000_80e7]
+        );
+        // beq a0, a1; jal ra
+        assert_eq!(reloc(R_RISCV_BRANCH, &[0x00b5_0063], 0x1010), [0x00b5_0863]);
+        assert_eq!(reloc(R_RISCV_JAL, &[0x0000_00ef], 0x1800), [0x0010_00ef]);
+        // lui a0; addi a0, a0
+        assert_eq!(reloc(R_RISCV_HI20, &[0x0000_0537], 0x1_2900), [0x0001_3537]);
+        assert_eq!(
+            reloc(R_RISCV_LO12_I, &[0x0005_0513], 0x1_2900),
+            [0x9005_0513]
+        );
+        // c.j, a 16-bit instruction
+        let mut data = [0x01, 0xa0];
+        let values = RelocValues {
+            symbol: 0x1010,
+            addend: Some(0),
+            place: 0x1000,
+            ..Default::default()
+        };
+        apply(EM_RISCV, R_RISCV_RVC_JUMP, &mut data, 0, &values, LE).unwrap();
+        assert_eq!(u16::from_le_bytes(data), 0xa801);
+        // label differences
+        let mut data = 100u32.to_le_bytes();
+        apply(EM_RISCV, R_RISCV_SUB32, &mut data, 0, &values, LE).unwrap();
+        assert_eq!(u32::from_le_bytes(data), 100u32.wrapping_sub(0x1010));
+    }
+
+    #[test]
+    fn ppc64() {
+        let reloc = |r_type, data: &mut [u8], symbol| {
+            let values = RelocValues {
+                symbol,
+                addend: Some(0),
+                place: 0x1000,
+                ..Default::default()
+            };
+            apply(EM_PPC64, r_type, data, 0, &values, BE).unwrap();
+        };
+        // bl
+        let mut data = 0x4800_0001u32.to_be_bytes();
+        reloc(R_PPC64_REL24, &mut data, 0x2000);
+        assert_eq!(u32::from_be_bytes(data), 0x4800_1001);
+        let mut data = [0; 2];
+        reloc(R_PPC64_ADDR16_HA, &mut data, 0x1234_8000);
+        assert_eq!(data, [0x12, 0x35]);
+        reloc(R_PPC64_ADDR16_LO, &mut data, 0x1234_8000);
+        assert_eq!(data, [0x80, 0x00]);
+        // the DS form keeps the low 2 bits of the instruction
+        let mut data = [0x00, 0x01];
+        reloc(R_PPC64_ADDR16_LO_DS, &mut data, 0x1_0008);
+        assert_eq!(data, [0x00, 0x09]);
+        let mut data = [0; 8];
+        reloc(R_PPC64_ADDR64, &mut data, 0x1234_5678_9abc);
+        assert_eq!(u64::from_be_bytes(data), 0x1234_5678_9abc);
+        assert!(apply(
+            EM_PPC64,
+            R_PPC64_TOC16,
+            &mut data,
+            0,
+            &RelocValues::default(),
+            BE
+        )
+        .is_err());
+    }
+}
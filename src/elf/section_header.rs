@@ -118,10 +118,18 @@ pub const SHT_PREINIT_ARRAY: u32 = 16;
 pub const SHT_GROUP: u32 = 17;
 /// Extended section indeces.
 pub const SHT_SYMTAB_SHNDX: u32 = 18;
-/// Number of defined types.
-pub const SHT_NUM: u32 = 19;
+/// RELR relative relocations.
+pub const SHT_RELR: u32 = 19;
+/// Number of defined types, one past `SHT_RELR` (it used to be 19, before `SHT_RELR` was assigned).
+pub const SHT_NUM: u32 = 20;
 /// Start OS-specific.
 pub const SHT_LOOS: u32 = 0x6000_0000;
+/// Android packed relocations without addends.
+pub const SHT_ANDROID_REL: u32 = 0x6000_0001;
+/// Android packed relocations with addends.
+pub const SHT_ANDROID_RELA: u32 = 0x6000_0002;
+/// RELR relative relocations, as typed by Android before `SHT_RELR`.
+pub const SHT_ANDROID_RELR: u32 = 0x6fff_ff00;
 /// Object attributes.
 pub const SHT_GNU_ATTRIBUTES: u32 = 0x6fff_fff5;
 /// GNU-style hash table.
@@ -226,8 +234,12 @@ pub fn sht_to_str(sht: u32) -> &'static str {
         SHT_PREINIT_ARRAY => "SHT_PREINIT_ARRAY",
         SHT_GROUP => "SHT_GROUP",
         SHT_SYMTAB_SHNDX => "SHT_SYMTAB_SHNDX",
+        SHT_RELR => "SHT_RELR",
         SHT_NUM => "SHT_NUM",
         SHT_LOOS => "SHT_LOOS",
+        SHT_ANDROID_REL => "SHT_ANDROID_REL",
+        SHT_ANDROID_RELA => "SHT_ANDROID_RELA",
+        SHT_ANDROID_RELR => "SHT_ANDROID_RELR",
         SHT_GNU_ATTRIBUTES => "SHT_GNU_ATTRIBUTES",
         SHT_GNU_HASH => "SHT_GNU_HASH",
         SHT_GNU_LIBLIST => "SHT_GNU_LIBLIST",
@@ -61,7 +61,11 @@
 
 use crate::container;
 use crate::elf::section_header::{SectionHeader, SHT_GNU_VERDEF, SHT_GNU_VERNEED, SHT_GNU_VERSYM};
+use crate::elf::sym::{Sym, SymIterator, Symtab};
 use crate::error::Result;
+use crate::strtab::Strtab;
+use alloc::vec::Vec;
+use core::fmt;
 use core::iter::FusedIterator;
 use scroll::Pread;
 
@@ -828,6 +832,154 @@ pub struct Vernaux {
     pub vna_next: u32,
 }
 
+/***********************
+ *  Resolved Versions  *
+ ***********************/
+
+/// The version of a symbol, resolved from its [`Versym`] entry through the version definitions
+/// or the version requirements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolVersion<'a> {
+    /// Version index, without the hidden bit.
+    pub index: u16,
+    /// Version name, e.g. `GLIBC_2.2.5`.
+    pub name: &'a str,
+    /// File name of the library the version is needed from, e.g. `libc.so.6`, or `None` if the
+    /// version is defined by this object.
+    pub library: Option<&'a str>,
+    /// Whether the version is hidden, i.e. not the default version of the symbol.
+    pub hidden: bool,
+}
+
+impl SymbolVersion<'_> {
+    /// Returns true if this is the default version of a symbol defined by this object, which is
+    /// written `foo@@VER` rather than `foo@VER`.
+    #[inline]
+    pub fn is_default(&self) -> bool {
+        !self.hidden && self.library.is_none()
+    }
+}
+
+/// A dynamic symbol with its name and resolved version, displayed like `nm -D` does: `foo`,
+/// `foo@VER` or `foo@@VER`.
+#[derive(Debug, Clone, Copy)]
+pub struct VersionedSym<'a> {
+    /// The symbol.
+    pub sym: Sym,
+    /// The symbol name, if it is valid.
+    pub name: Option<&'a str>,
+    /// The symbol version, or `None` if the symbol is local or unversioned.
+    pub version: Option<SymbolVersion<'a>>,
+}
+
+impl fmt::Display for VersionedSym<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name.unwrap_or_default())?;
+        match self.version {
+            Some(version) if version.is_default() => write!(f, "@@{}", version.name),
+            Some(version) => write!(f, "@{}", version.name),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Iterator over the dynamic symbols with their resolved versions.
+pub struct VersionedSymIter<'s, 'a> {
+    syms: SymIterator<'a>,
+    idx: usize,
+    strtab: &'s Strtab<'a>,
+    versym: Option<&'s VersymSection<'a>>,
+    /// The version names and libraries, by version index
+    versions: Vec<Option<(&'a str, Option<&'a str>)>>,
+}
+
+impl<'s, 'a> VersionedSymIter<'s, 'a> {
+    /// Create an iterator over the dynamic symbols `dynsyms`, whose names and version names are
+    /// in `dynstrtab`.
+    pub fn new(
+        dynsyms: &Symtab<'a>,
+        dynstrtab: &'s Strtab<'a>,
+        versym: Option<&'s VersymSection<'a>>,
+        verdef: Option<&VerdefSection<'a>>,
+        verneed: Option<&VerneedSection<'a>>,
+    ) -> Self {
+        let mut versions = Vec::new();
+        let mut insert = |index: u16, name: Option<&'a str>, library: Option<&'a str>| {
+            let index = usize::from(index & VERSYM_VERSION);
+            if let Some(name) = name {
+                if versions.len() <= index {
+                    versions.resize(index + 1, None);
+                }
+                versions[index] = Some((name, library));
+            }
+        };
+        for verdef in verdef.into_iter().flatten() {
+            // The first verdaux entry holds the version name, the others its parents.
+            let name = (&verdef).into_iter().next();
+            insert(
+                verdef.vd_ndx,
+                name.and_then(|verdaux| dynstrtab.get_at(verdaux.vda_name)),
+                None,
+            );
+        }
+        for verneed in verneed.into_iter().flatten() {
+            let library = dynstrtab.get_at(verneed.vn_file);
+            for vernaux in &verneed {
+                insert(
+                    vernaux.vna_other,
+                    dynstrtab.get_at(vernaux.vna_name),
+                    library,
+                );
+            }
+        }
+        VersionedSymIter {
+            syms: dynsyms.iter(),
+            idx: 0,
+            strtab: dynstrtab,
+            versym,
+            versions,
+        }
+    }
+
+    fn version(&self, versym: &Versym) -> Option<SymbolVersion<'a>> {
+        let index = versym.version();
+        if index == VER_NDX_LOCAL || index == VER_NDX_GLOBAL {
+            return None;
+        }
+        let (name, library) = (*self.versions.get(usize::from(index))?)?;
+        Some(SymbolVersion {
+            index,
+            name,
+            library,
+            hidden: versym.is_hidden(),
+        })
+    }
+}
+
+impl<'a> Iterator for VersionedSymIter<'_, 'a> {
+    type Item = VersionedSym<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sym = self.syms.next()?;
+        let versym = self.versym.and_then(|versym| versym.get_at(self.idx));
+        self.idx += 1;
+        Some(VersionedSym {
+            sym,
+            name: self.strtab.get_at(sym.st_name),
+            version: versym.and_then(|versym| self.version(&versym)),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.syms.len();
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for VersionedSymIter<'_, '_> {}
+
+impl FusedIterator for VersionedSymIter<'_, '_> {}
+
 #[cfg(test)]
 mod test {
     use super::{ElfVerdaux, ElfVerdef, ElfVernaux, ElfVerneed, ElfVersym};
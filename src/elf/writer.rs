@@ -0,0 +1,562 @@
+//! Rewriting the dynamic linking information of ELF executables and shared objects, in the way
+//! of `patchelf`.
+//!
+//! The [`Writer`] changes the program interpreter (`PT_INTERP`), the `DT_NEEDED` libraries, the
+//! `DT_RUNPATH` and the `DT_SONAME` of a binary. Changes are made in place when they fit: a
+//! shorter interpreter, removed entries, or strings already present in `.dynstr`. Otherwise, the
+//! grown `.interp`, `.dynstr` and `.dynamic` are moved to a new `PT_LOAD` segment appended to the
+//! file, along with a copy of the program header table which makes room for that segment.
+//! `PT_PHDR`, `PT_INTERP`, `PT_DYNAMIC`, `DT_STRTAB`, `DT_STRSZ` and the section headers of the
+//! moved sections are updated to match.
+//!
+//! The new segment is mapped after the end of the others, `.bss` included, without storing that
+//! gap in the file, so the moved program header table isn't at the address of the first
+//! `PT_LOAD` plus `e_phoff`. Linux finds it through `PT_PHDR` since 5.18; older kernels pass that
+//! wrong address as `AT_PHDR`, and executables rewritten with a new segment don't start there.
+//! Shared objects, whose program headers the dynamic linker reads from the file, aren't affected.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp;
+use scroll::ctx::SizeWith;
+use scroll::{Pread, Pwrite};
+
+use crate::error;
+
+use super::dynamic::{
+    Dyn, DT_NEEDED, DT_NULL, DT_RPATH, DT_RUNPATH, DT_SONAME, DT_STRSZ, DT_STRTAB,
+};
+use super::program_header::{ProgramHeader, PF_R, PF_W, PT_DYNAMIC, PT_INTERP, PT_LOAD, PT_PHDR};
+use super::{Elf, SectionHeader};
+
+/// The alignment of the contents of the new segment
+const ALIGNMENT: usize = 8;
+/// The smallest page size assumed for the new segment
+const MIN_PAGE_SIZE: u64 = 0x1000;
+
+fn align_up(value: u64, alignment: u64) -> Option<u64> {
+    Some(value.checked_add(alignment - 1)? & !(alignment - 1))
+}
+
+/// A set of changes to the dynamic linking information of an ELF binary
+#[derive(Debug)]
+pub struct Writer<'a> {
+    bytes: &'a [u8],
+    elf: Elf<'a>,
+    interpreter: Option<String>,
+    add_needed: Vec<String>,
+    remove_needed: Vec<String>,
+    runpath: Option<Option<String>>,
+    soname: Option<String>,
+}
+
+/// Where the new segment holds a relocated part of the file, relative to its start
+#[derive(Debug, Default)]
+struct Layout {
+    phdrs: usize,
+    interp: Option<usize>,
+    dynstr: Option<usize>,
+    dynamic: Option<usize>,
+    size: usize,
+}
+
+impl Layout {
+    fn push(&mut self, len: usize) -> usize {
+        let offset = self.size;
+        self.size = (offset + len + ALIGNMENT - 1) & !(ALIGNMENT - 1);
+        offset
+    }
+}
+
+impl<'a> Writer<'a> {
+    /// Parses the binary in `bytes`, to be rewritten
+    pub fn new(bytes: &'a [u8]) -> error::Result<Self> {
+        Ok(Writer {
+            bytes,
+            elf: Elf::parse(bytes)?,
+            interpreter: None,
+            add_needed: Vec::new(),
+            remove_needed: Vec::new(),
+            runpath: None,
+            soname: None,
+        })
+    }
+
+    /// Sets the program interpreter, i.e., the dynamic linker
+    pub fn set_interpreter(&mut self, interpreter: &str) -> &mut Self {
+        self.interpreter = Some(interpreter.to_string());
+        self
+    }
+
+    /// Adds a `DT_NEEDED` library after the existing ones, unless it is already needed
+    pub fn add_needed(&mut self, library: &str) -> &mut Self {
+        self.remove_needed.retain(|name| name != library);
+        self.add_needed.push(library.to_string());
+        self
+    }
+
+    /// Removes the `DT_NEEDED` entries of `library`
+    pub fn remove_needed(&mut self, library: &str) -> &mut Self {
+        self.add_needed.retain(|name| name != library);
+        self.remove_needed.push(library.to_string());
+        self
+    }
+
+    /// Sets the `DT_RUNPATH`, replacing any `DT_RPATH` as well
+    pub fn set_runpath(&mut self, runpath: &str) -> &mut Self {
+        self.runpath = Some(Some(runpath.to_string()));
+        self
+    }
+
+    /// Removes the `DT_RUNPATH` and `DT_RPATH` entries
+    pub fn remove_runpath(&mut self) -> &mut Self {
+        self.runpath = Some(None);
+        self
+    }
+
+    /// Sets the `DT_SONAME`
+    pub fn set_soname(&mut self, soname: &str) -> &mut Self {
+        self.soname = Some(soname.to_string());
+        self
+    }
+
+    /// Returns the rewritten binary
+    pub fn write(&self) -> error::Result<Vec<u8>> {
+        let elf = &self.elf;
+        let ctx = elf.ctx;
+        let mut phdrs = elf.program_headers.clone();
+        let mut shdrs = elf.section_headers.clone();
+        let mut out = self.bytes.to_vec();
+        let mut layout = Layout::default();
+        layout.phdrs = layout.push((phdrs.len() + 1) * usize::from(elf.header.e_phentsize));
+
+        // the interpreter is rewritten in place if it fits
+        let mut interp = None;
+        if let Some(interpreter) = &self.interpreter {
+            let idx = phdrs
+                .iter()
+                .position(|phdr| phdr.p_type == PT_INTERP)
+                .ok_or_else(|| error::Error::Malformed("ELF file has no PT_INTERP".into()))?;
+            let mut contents = interpreter.as_bytes().to_vec();
+            contents.push(0);
+            if contents.len() as u64 > phdrs[idx].p_filesz {
+                layout.interp = Some(layout.push(contents.len()));
+            }
+            interp = Some((idx, contents));
+        }
+
+        let mut dynamic = None;
+        if !self.add_needed.is_empty()
+            || !self.remove_needed.is_empty()
+            || self.runpath.is_some()
+            || self.soname.is_some()
+        {
+            dynamic = Some(self.edit_dynamic(&mut layout)?);
+        }
+
+        // the new segment is appended at the end of the file, and mapped after everything else
+        // at an address congruent to its offset modulo the page size, as `mmap` requires. Its
+        // program headers are found through `PT_PHDR`
+        let mut segment = None;
+        if layout.interp.is_some() || layout.dynstr.is_some() || layout.dynamic.is_some() {
+            let loads = phdrs.iter().filter(|phdr| phdr.p_type == PT_LOAD);
+            if loads.clone().next().is_none() {
+                return Err(error::Error::Malformed("ELF file has no PT_LOAD".into()));
+            }
+            let page_size = loads
+                .clone()
+                .map(|phdr| phdr.p_align)
+                .fold(MIN_PAGE_SIZE, cmp::max);
+            if !page_size.is_power_of_two() {
+                return Err(error::Error::Malformed(format!(
+                    "PT_LOAD alignment ({:#x}) is not a power of two",
+                    page_size
+                )));
+            }
+            let mem_end = loads
+                .map(|phdr| phdr.p_vaddr.saturating_add(phdr.p_memsz))
+                .fold(0, cmp::max);
+            let no_room =
+                || error::Error::Malformed("ELF file has no room for a new segment".into());
+            let offset = align_up(out.len() as u64, MIN_PAGE_SIZE).ok_or_else(no_room)?;
+            let vaddr = align_up(mem_end, page_size)
+                .and_then(|vaddr| vaddr.checked_add(offset & (page_size - 1)))
+                .ok_or_else(no_room)?;
+            out.resize(offset as usize + layout.size, 0);
+            segment = Some(ProgramHeader {
+                p_type: PT_LOAD,
+                p_flags: PF_R,
+                p_offset: offset,
+                p_vaddr: vaddr,
+                p_paddr: vaddr,
+                p_filesz: layout.size as u64,
+                p_memsz: layout.size as u64,
+                p_align: page_size,
+            });
+        }
+        // the location of `len` bytes at `offset` in the new segment, if there is one
+        let base = segment
+            .as_ref()
+            .map(|segment| (segment.p_offset, segment.p_vaddr));
+        let relocate = |offset: usize, len: usize| {
+            let (base_offset, base_vaddr) = base.unwrap_or_default();
+            ProgramHeader {
+                p_offset: base_offset + offset as u64,
+                p_vaddr: base_vaddr + offset as u64,
+                p_paddr: base_vaddr + offset as u64,
+                p_filesz: len as u64,
+                p_memsz: len as u64,
+                ..Default::default()
+            }
+        };
+
+        if let Some((idx, contents)) = &interp {
+            let phdr = &mut phdrs[*idx];
+            let end = match layout.interp {
+                Some(interp_offset) => {
+                    let location = relocate(interp_offset, contents.len());
+                    phdr.p_offset = location.p_offset;
+                    phdr.p_vaddr = location.p_vaddr;
+                    phdr.p_paddr = location.p_paddr;
+                    location.p_offset + location.p_filesz
+                }
+                None => phdr.p_offset + phdr.p_filesz,
+            };
+            let old = out
+                .get_mut(phdr.p_offset as usize..end as usize)
+                .ok_or_else(|| error::Error::Malformed("PT_INTERP is out of bounds".into()))?;
+            old.fill(0);
+            old[..contents.len()].copy_from_slice(contents);
+            phdr.p_filesz = contents.len() as u64;
+            phdr.p_memsz = contents.len() as u64;
+            let phdr = phdr.clone();
+            self.update_section(&mut shdrs, ".interp", &phdr);
+        }
+
+        if let Some(mut edit) = dynamic {
+            if let Some(dynstr_offset) = layout.dynstr {
+                let location = relocate(dynstr_offset, edit.dynstr.len());
+                let start = location.p_offset as usize;
+                out[start..start + edit.dynstr.len()].copy_from_slice(&edit.dynstr);
+                for dyn_ in &mut edit.dyns {
+                    match dyn_.d_tag {
+                        DT_STRTAB => dyn_.d_val = location.p_vaddr,
+                        DT_STRSZ => dyn_.d_val = location.p_filesz,
+                        _ => (),
+                    }
+                }
+                self.update_section(&mut shdrs, ".dynstr", &location);
+            }
+            let size = Dyn::size_with(&ctx);
+            if let Some(dynamic_offset) = layout.dynamic {
+                let location = relocate(dynamic_offset, (edit.dyns.len() + 1) * size);
+                let phdr = &mut phdrs[edit.phdr];
+                phdr.p_offset = location.p_offset;
+                phdr.p_vaddr = location.p_vaddr;
+                phdr.p_paddr = location.p_paddr;
+                phdr.p_filesz = location.p_filesz;
+                phdr.p_memsz = location.p_memsz;
+                // the dynamic linker writes `DT_DEBUG`
+                if let Some(segment) = segment.as_mut() {
+                    segment.p_flags |= PF_W;
+                }
+                self.update_section(&mut shdrs, ".dynamic", &location);
+            }
+            // unused entries are filled with `DT_NULL`
+            let phdr = &phdrs[edit.phdr];
+            let count = phdr.p_filesz as usize / size;
+            let null = Dyn {
+                d_tag: DT_NULL,
+                d_val: 0,
+            };
+            let mut offset = phdr.p_offset as usize;
+            for dyn_ in edit
+                .dyns
+                .into_iter()
+                .chain(core::iter::repeat(null))
+                .take(count)
+            {
+                out.gwrite_with(dyn_, &mut offset, ctx)?;
+            }
+        }
+
+        let mut header = elf.header;
+        if let Some(segment) = segment {
+            // the new segment goes after the last `PT_LOAD`, to keep them sorted by address
+            let idx = phdrs
+                .iter()
+                .rposition(|phdr| phdr.p_type == PT_LOAD)
+                .map_or(phdrs.len(), |idx| idx + 1);
+            phdrs.insert(idx, segment);
+            let table = relocate(layout.phdrs, phdrs.len() * usize::from(header.e_phentsize));
+            for phdr in phdrs.iter_mut().filter(|phdr| phdr.p_type == PT_PHDR) {
+                phdr.p_offset = table.p_offset;
+                phdr.p_vaddr = table.p_vaddr;
+                phdr.p_paddr = table.p_paddr;
+                phdr.p_filesz = table.p_filesz;
+                phdr.p_memsz = table.p_memsz;
+            }
+            header.e_phoff = table.p_offset;
+            header.e_phnum = phdrs.len() as u16;
+        }
+        out.pwrite_with(header, 0, ctx.le)?;
+        let mut offset = header.e_phoff as usize;
+        for phdr in phdrs {
+            out.gwrite_with(phdr, &mut offset, ctx)?;
+        }
+        let mut offset = header.e_shoff as usize;
+        for shdr in shdrs {
+            out.gwrite_with(shdr, &mut offset, ctx)?;
+        }
+        Ok(out)
+    }
+
+    /// Applies the changes to the dynamic entries, reserving room in the new segment for
+    /// `.dynstr` and `.dynamic` if they have to grow
+    fn edit_dynamic(&self, layout: &mut Layout) -> error::Result<DynamicEdit> {
+        let elf = &self.elf;
+        let ctx = elf.ctx;
+        let dynamic = elf
+            .dynamic
+            .as_ref()
+            .ok_or_else(|| error::Error::Malformed("ELF file has no PT_DYNAMIC".into()))?;
+        let phdr = elf
+            .program_headers
+            .iter()
+            .position(|phdr| phdr.p_type == PT_DYNAMIC)
+            .ok_or_else(|| error::Error::Malformed("ELF file has no PT_DYNAMIC".into()))?;
+        let mut dyns: Vec<Dyn> = dynamic
+            .dyns
+            .iter()
+            .filter(|dyn_| dyn_.d_tag != DT_NULL)
+            .cloned()
+            .collect();
+        let strsz = dyns
+            .iter()
+            .find(|dyn_| dyn_.d_tag == DT_STRSZ)
+            .map_or(0, |dyn_| dyn_.d_val as usize);
+        let dynstr = self
+            .bytes
+            .pread_with::<&[u8]>(dynamic.info.strtab, strsz)
+            .map_err(|_| {
+                error::Error::Malformed(format!(
+                    "Invalid .dynstr (offset {:#x}, size {:#x})",
+                    dynamic.info.strtab, strsz
+                ))
+            })?;
+        let mut edit = DynamicEdit {
+            phdr,
+            dyns: Vec::new(),
+            dynstr: dynstr.to_vec(),
+            relocated_dynstr: false,
+        };
+
+        let name = |dyn_: &Dyn| elf.dynstrtab.get_at(dyn_.d_val as usize);
+        let removed = |library: &str| self.remove_needed.iter().any(|name| name == library);
+        dyns.retain(|dyn_| {
+            dyn_.d_tag != DT_NEEDED || !matches!(name(dyn_), Some(library) if removed(library))
+        });
+        for library in &self.add_needed {
+            let needed = dyns
+                .iter()
+                .any(|dyn_| dyn_.d_tag == DT_NEEDED && name(dyn_) == Some(library.as_str()));
+            if !needed {
+                let entry = Dyn {
+                    d_tag: DT_NEEDED,
+                    d_val: edit.string(library),
+                };
+                let idx = Self::insertion_index(&dyns);
+                dyns.insert(idx, entry);
+            }
+        }
+        if let Some(runpath) = &self.runpath {
+            let idx = dyns
+                .iter()
+                .position(|dyn_| dyn_.d_tag == DT_RUNPATH || dyn_.d_tag == DT_RPATH);
+            dyns.retain(|dyn_| dyn_.d_tag != DT_RUNPATH && dyn_.d_tag != DT_RPATH);
+            if let Some(runpath) = runpath {
+                let entry = Dyn {
+                    d_tag: DT_RUNPATH,
+                    d_val: edit.string(runpath),
+                };
+                let idx = idx.unwrap_or_else(|| Self::insertion_index(&dyns));
+                dyns.insert(cmp::min(idx, dyns.len()), entry);
+            }
+        }
+        if let Some(soname) = &self.soname {
+            let d_val = edit.string(soname);
+            match dyns.iter_mut().find(|dyn_| dyn_.d_tag == DT_SONAME) {
+                Some(dyn_) => dyn_.d_val = d_val,
+                None => {
+                    let idx = Self::insertion_index(&dyns);
+                    dyns.insert(
+                        idx,
+                        Dyn {
+                            d_tag: DT_SONAME,
+                            d_val,
+                        },
+                    );
+                }
+            }
+        }
+
+        if edit.relocated_dynstr {
+            layout.dynstr = Some(layout.push(edit.dynstr.len()));
+        }
+        let capacity = (elf.program_headers[phdr].p_filesz as usize) / Dyn::size_with(&ctx);
+        // one `DT_NULL` terminates the entries
+        if dyns.len() >= capacity {
+            layout.dynamic = Some(layout.push((dyns.len() + 1) * Dyn::size_with(&ctx)));
+        }
+        edit.dyns = dyns;
+        Ok(edit)
+    }
+
+    /// The index of new entries: after the `DT_NEEDED` ones
+    fn insertion_index(dyns: &[Dyn]) -> usize {
+        dyns.iter()
+            .rposition(|dyn_| dyn_.d_tag == DT_NEEDED)
+            .map_or(0, |idx| idx + 1)
+    }
+
+    /// Points the section header named `name` to `location`
+    fn update_section(&self, shdrs: &mut [SectionHeader], name: &str, location: &ProgramHeader) {
+        for shdr in shdrs.iter_mut() {
+            if self.elf.shdr_strtab.get_at(shdr.sh_name) == Some(name) {
+                shdr.sh_offset = location.p_offset;
+                shdr.sh_addr = location.p_vaddr;
+                shdr.sh_size = location.p_filesz;
+            }
+        }
+    }
+}
+
+/// The rewritten dynamic entries
+struct DynamicEdit {
+    /// The index of `PT_DYNAMIC`
+    phdr: usize,
+    dyns: Vec<Dyn>,
+    dynstr: Vec<u8>,
+    relocated_dynstr: bool,
+}
+
+impl DynamicEdit {
+    /// The offset of `string` in `.dynstr`, which is appended to it if missing
+    fn string(&mut self, string: &str) -> u64 {
+        let mut needle = string.as_bytes().to_vec();
+        needle.push(0);
+        if let Some(offset) = self
+            .dynstr
+            .windows(needle.len())
+            .position(|window| window == &needle[..])
+        {
+            return offset as u64;
+        }
+        self.relocated_dynstr = true;
+        let offset = self.dynstr.len();
+        self.dynstr.extend_from_slice(&needle);
+        offset as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static BSS: &[u8] = include_bytes!("../../tests/bins/elf/writer/bss");
+    const INTERPRETER: &str = "/opt/glibc/lib64/ld-linux-x86-64.so.2";
+
+    fn loads<'b>(elf: &'b Elf) -> impl Iterator<Item = &'b ProgramHeader> {
+        elf.program_headers
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD)
+    }
+
+    #[test]
+    fn new_segment_skips_bss() {
+        let out = Writer::new(BSS)
+            .unwrap()
+            .set_interpreter(INTERPRETER)
+            .write()
+            .unwrap();
+        // the 256 MiB of .bss aren't stored in the file
+        assert!(out.len() < BSS.len() + 2 * MIN_PAGE_SIZE as usize);
+        let elf = Elf::parse(&out).unwrap();
+        assert_eq!(elf.interpreter.as_deref(), Some(INTERPRETER));
+
+        let input = Elf::parse(BSS).unwrap();
+        let mem_end = loads(&input)
+            .map(|phdr| phdr.p_vaddr + phdr.p_memsz)
+            .max()
+            .unwrap();
+        let segment = loads(&elf).last().unwrap();
+        assert_eq!(loads(&elf).count(), loads(&input).count() + 1);
+        assert!(segment.p_vaddr >= mem_end);
+        assert_eq!(segment.p_offset % MIN_PAGE_SIZE, 0);
+        assert_eq!(
+            segment.p_vaddr % segment.p_align,
+            segment.p_offset % segment.p_align
+        );
+        assert_eq!(segment.p_offset + segment.p_filesz, out.len() as u64);
+        // PT_PHDR and PT_INTERP point into the new segment
+        let phdr = &elf.program_headers[0];
+        assert_eq!(phdr.p_type, PT_PHDR);
+        assert_eq!(
+            (phdr.p_offset, phdr.p_vaddr),
+            (segment.p_offset, segment.p_vaddr)
+        );
+        assert_eq!(elf.header.e_phoff, segment.p_offset);
+        let interp = &elf.program_headers[1];
+        assert_eq!(interp.p_type, PT_INTERP);
+        assert!(segment.vm_range().contains(&(interp.p_vaddr as usize)));
+        assert_eq!(
+            interp.p_vaddr - segment.p_vaddr,
+            interp.p_offset - segment.p_offset
+        );
+    }
+
+    #[test]
+    fn huge_alignment() {
+        // a PT_LOAD aligned to 1 TiB must not pad the file to that alignment
+        let mut bytes = BSS.to_vec();
+        let input = Elf::parse(BSS).unwrap();
+        let size = ProgramHeader::size_with(&input.ctx);
+        let mut set_alignment = |alignment: u64| {
+            for (idx, phdr) in input.program_headers.iter().enumerate() {
+                if phdr.p_type == PT_LOAD {
+                    let offset = input.header.e_phoff as usize + idx * size + size - 8;
+                    bytes.pwrite_with(alignment, offset, scroll::LE).unwrap();
+                }
+            }
+            bytes.clone()
+        };
+        let bytes = set_alignment(1 << 40);
+        let out = Writer::new(&bytes)
+            .unwrap()
+            .set_interpreter(INTERPRETER)
+            .write()
+            .unwrap();
+        assert!(out.len() < bytes.len() + 2 * MIN_PAGE_SIZE as usize);
+        let elf = Elf::parse(&out).unwrap();
+        let segment = loads(&elf).last().unwrap();
+        assert_eq!(segment.p_vaddr % (1 << 40), segment.p_offset);
+
+        // not a power of two
+        let bytes = set_alignment(0x3000);
+        let mut writer = Writer::new(&bytes).unwrap();
+        assert!(writer.set_interpreter(INTERPRETER).write().is_err());
+    }
+
+    #[test]
+    fn in_place() {
+        let out = Writer::new(BSS)
+            .unwrap()
+            .set_interpreter("/lib/ld.so")
+            .write()
+            .unwrap();
+        assert_eq!(out.len(), BSS.len());
+        let elf = Elf::parse(&out).unwrap();
+        assert_eq!(elf.interpreter.as_deref(), Some("/lib/ld.so"));
+        assert_eq!(loads(&elf).count(), 4);
+    }
+}
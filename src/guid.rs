@@ -0,0 +1,88 @@
+//! Globally unique identifiers, as used throughout UEFI and Authenticode structures.
+
+use core::fmt;
+use scroll::{Pread, Pwrite, SizeWith};
+
+/// The size of a [`Guid`] in bytes
+pub const SIZEOF_GUID: usize = 16;
+
+/// A GUID in its mixed-endian in-memory layout (`EFI_GUID`)
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Pread, Pwrite, SizeWith)]
+pub struct Guid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+
+impl Guid {
+    /// Builds a GUID from its four fields, as spelled in C headers
+    pub const fn new(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Self {
+        Guid {
+            data1,
+            data2,
+            data3,
+            data4,
+        }
+    }
+
+    /// Reads a GUID from its 16-byte on-disk representation
+    pub fn from_bytes(bytes: &[u8; SIZEOF_GUID]) -> Self {
+        // a 16-byte buffer always holds a GUID
+        bytes.pread_with(0, scroll::LE).unwrap()
+    }
+
+    /// Returns the 16-byte on-disk representation of this GUID
+    pub fn to_bytes(&self) -> [u8; SIZEOF_GUID] {
+        let mut bytes = [0u8; SIZEOF_GUID];
+        // a 16-byte buffer always fits a GUID
+        bytes.pwrite_with(*self, 0, scroll::LE).unwrap();
+        bytes
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-",
+            self.data1, self.data2, self.data3, self.data4[0], self.data4[1]
+        )?;
+        for byte in &self.data4[2..] {
+            write!(f, "{:02X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_and_bytes() {
+        let bytes = [
+            0x78, 0xe5, 0x8c, 0x8c, 0x3d, 0x8a, 0x1c, 0x4f, 0x99, 0x35, 0x89, 0x61, 0x85, 0xc3,
+            0x2d, 0xd3,
+        ];
+        let guid = Guid::from_bytes(&bytes);
+        assert_eq!(
+            guid,
+            Guid::new(
+                0x8c8c_e578,
+                0x8a3d,
+                0x4f1c,
+                [0x99, 0x35, 0x89, 0x61, 0x85, 0xc3, 0x2d, 0xd3]
+            )
+        );
+        assert_eq!(guid.to_string(), "8C8CE578-8A3D-4F1C-9935-896185C32DD3");
+        assert_eq!(guid.to_bytes(), bytes);
+    }
+}
@@ -77,6 +77,9 @@
 //!
 //! If you want endian aware reading, and you don't use `default`, then you need to opt in as normal
 //! via `endian_fd`
+//!
+//! UEFI firmware volumes, capsules and option ROMs are parsed with the `uefi` feature, which isn't
+//! part of `default` as it adds the `Object::FirmwareVolume` variant
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
@@ -110,6 +113,10 @@ macro_rules! if_alloc {
 #[cfg(feature = "alloc")]
 pub mod error;
 
+#[cfg(feature = "alloc")]
+pub mod guid;
+#[cfg(any(all(feature = "elf32", feature = "elf64", feature = "endian_fd"), feature = "mach32", feature = "mach64", feature = "pe32", feature = "pe64"))]
+pub mod overlay;
 pub mod strtab;
 
 /// Binary container size information and byte-order context
@@ -309,6 +316,9 @@ if_everything! {
         Mach(mach::Mach<'a>),
         /// A Unix archive
         Archive(archive::Archive<'a>),
+        /// A UEFI firmware volume, only with the `uefi` feature, which isn't enabled by default
+        #[cfg(feature = "uefi")]
+        FirmwareVolume(uefi::fv::FirmwareVolume<'a>),
         /// None of the above, with the given magic value
         Unknown(u64),
     }
@@ -323,6 +333,11 @@ if_everything! {
                     Hint::Archive => Ok(Object::Archive(archive::Archive::parse(bytes)?)),
                     Hint::PE => Ok(Object::PE(pe::PE::parse(bytes)?)),
                     Hint::TE => Ok(Object::TE(te::TE::parse(bytes)?)),
+                    // firmware volumes start with a zero (or reset) vector, their signature comes later
+                    #[cfg(feature = "uefi")]
+                    Hint::Unknown(_) if uefi::fv::is_firmware_volume(bytes) => {
+                        Ok(Object::FirmwareVolume(uefi::fv::FirmwareVolume::parse(bytes)?))
+                    }
                     Hint::Unknown(magic) => Ok(Object::Unknown(magic))
                 }
             } else {
@@ -383,6 +398,9 @@ pub mod pe;
 #[cfg(feature = "te")]
 pub mod te;
 
+#[cfg(feature = "uefi")]
+pub mod uefi;
+
 #[cfg(feature = "archive")]
 pub mod archive;
 
@@ -1,5 +1,7 @@
 //! The Mach-o, mostly zero-copy, binary format parser and raw struct definitions
 use alloc::vec::Vec;
+use core::cmp;
+use core::convert::TryFrom;
 use core::fmt;
 
 use log::debug;
@@ -9,6 +11,7 @@ use scroll::{Pread, BE};
 
 use crate::container;
 use crate::error;
+use crate::overlay::Overlay;
 
 pub mod bind_opcodes;
 pub mod constants;
@@ -147,6 +150,89 @@ impl<'a> MachO<'a> {
             Ok(vec![])
         }
     }
+    /// Returns the data appended after the end of this Mach-o image, if there is any.
+    ///
+    /// The end of the image is the furthest byte covered by the header and load commands, the
+    /// file contents of the segments, the section relocations, or the `__LINKEDIT` data referenced
+    /// by the load commands (symbol tables, dyld info, code signature, etc.)
+    pub fn overlay(&self) -> Option<Overlay<'a>> {
+        fn end(offset: u32, size: u64) -> u64 {
+            u64::from(offset).saturating_add(size)
+        }
+
+        let mut image_end = (header::Header::size_with(&self.ctx.container) as u64)
+            .saturating_add(u64::from(self.header.sizeofcmds));
+        for segment in self.segments.iter() {
+            image_end = cmp::max(image_end, segment.fileoff.saturating_add(segment.filesize));
+            for (section, _) in segment.sections().unwrap_or_default() {
+                if section.nreloc != 0 {
+                    let relocs_size =
+                        u64::from(section.nreloc) * relocation::SIZEOF_RELOCATION_INFO as u64;
+                    image_end = cmp::max(image_end, end(section.reloff, relocs_size));
+                }
+            }
+        }
+        for cmd in &self.load_commands {
+            use self::load_command::CommandVariant;
+            let cmd_end = match cmd.command {
+                CommandVariant::Symtab(symtab) => {
+                    let nlist_size = if self.is_64 {
+                        symbols::SIZEOF_NLIST_64
+                    } else {
+                        symbols::SIZEOF_NLIST_32
+                    };
+                    cmp::max(
+                        end(symtab.symoff, u64::from(symtab.nsyms) * nlist_size as u64),
+                        end(symtab.stroff, u64::from(symtab.strsize)),
+                    )
+                }
+                CommandVariant::Dysymtab(dysymtab) => {
+                    let relocs_size = relocation::SIZEOF_RELOCATION_INFO as u64;
+                    [
+                        end(
+                            dysymtab.indirectsymoff,
+                            u64::from(dysymtab.nindirectsyms) * 4,
+                        ),
+                        end(
+                            dysymtab.extreloff,
+                            u64::from(dysymtab.nextrel) * relocs_size,
+                        ),
+                        end(
+                            dysymtab.locreloff,
+                            u64::from(dysymtab.nlocrel) * relocs_size,
+                        ),
+                    ]
+                    .iter()
+                    .copied()
+                    .fold(0, cmp::max)
+                }
+                CommandVariant::DyldInfo(info) | CommandVariant::DyldInfoOnly(info) => [
+                    end(info.rebase_off, u64::from(info.rebase_size)),
+                    end(info.bind_off, u64::from(info.bind_size)),
+                    end(info.weak_bind_off, u64::from(info.weak_bind_size)),
+                    end(info.lazy_bind_off, u64::from(info.lazy_bind_size)),
+                    end(info.export_off, u64::from(info.export_size)),
+                ]
+                .iter()
+                .copied()
+                .fold(0, cmp::max),
+                CommandVariant::CodeSignature(linkedit)
+                | CommandVariant::SegmentSplitInfo(linkedit)
+                | CommandVariant::FunctionStarts(linkedit)
+                | CommandVariant::DataInCode(linkedit)
+                | CommandVariant::DylibCodeSignDrs(linkedit)
+                | CommandVariant::LinkerOptimizationHint(linkedit)
+                | CommandVariant::DyldExportsTrie(linkedit)
+                | CommandVariant::DyldChainedFixups(linkedit) => {
+                    end(linkedit.dataoff, u64::from(linkedit.datasize))
+                }
+                _ => 0,
+            };
+            image_end = cmp::max(image_end, cmd_end);
+        }
+
+        Overlay::after(self.data, usize::try_from(image_end).ok()?)
+    }
     /// Parses the Mach-o binary from `bytes` at `offset`
     pub fn parse(bytes: &'a [u8], mut offset: usize) -> error::Result<MachO<'a>> {
         let (magic, maybe_ctx) = parse_magic_and_ctx(bytes, offset)?;
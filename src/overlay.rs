@@ -0,0 +1,37 @@
+//! Data appended past the end of a binary image.
+//!
+//! Installers, self-extracting archives and signed-then-appended payloads commonly store extra
+//! data after the last byte that the loader (or the format itself) accounts for. That trailing
+//! data is usually called the _overlay_.
+
+/// The bytes trailing the end of an image, as returned by the various `overlay()` methods
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Overlay<'a> {
+    /// The file offset at which the overlay begins
+    pub offset: usize,
+    /// The length of the overlay, in bytes
+    pub size: usize,
+    /// The overlay contents
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Overlay<'a> {
+    /// Returns the overlay covering `bytes[start..end]`, or `None` if that range is empty or out of bounds
+    pub(crate) fn from_range(bytes: &'a [u8], start: usize, end: usize) -> Option<Self> {
+        if start >= end {
+            return None;
+        }
+        let data = bytes.get(start..end)?;
+        Some(Overlay {
+            offset: start,
+            size: data.len(),
+            bytes: data,
+        })
+    }
+
+    /// Returns the overlay starting at `image_end` and spanning to the end of `bytes`, if any
+    #[cfg(any(all(feature = "elf32", feature = "elf64", feature = "endian_fd"), feature = "mach32", feature = "mach64"))]
+    pub(crate) fn after(bytes: &'a [u8], image_end: usize) -> Option<Self> {
+        Self::from_range(bytes, image_end, bytes.len())
+    }
+}
@@ -0,0 +1,332 @@
+//! Structural anomaly detection for PE images.
+//!
+//! [`ParseMode::Permissive`](super::options::ParseMode::Permissive) lets the parser carry on past
+//! malformed data. This module reports the structural oddities that packers, protectors and
+//! hand-crafted samples tend to leave behind, each one anchored at a file offset.
+
+use alloc::vec::Vec;
+use core::cmp;
+
+use super::data_directories::SIZEOF_DATA_DIRECTORY;
+use super::section_table::{
+    SectionTable, IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_WRITE, SIZEOF_SECTION_TABLE,
+};
+use super::{header, optional_header, options, utils, PE};
+
+/// Offset of the `AddressOfEntryPoint` field from the start of the optional header
+const OFFSET_ADDRESS_OF_ENTRY_POINT: usize = 16;
+/// Offset of the `SizeOfImage` field from the start of the optional header (PE32 and PE32+)
+const OFFSET_SIZE_OF_IMAGE: usize = 56;
+/// Offset of the `CheckSum` field from the start of the optional header (PE32 and PE32+)
+const OFFSET_CHECKSUM: usize = 64;
+/// The maximum number of data directories in an optional header
+const NUM_DATA_DIRECTORIES: usize = 16;
+
+/// The kind of a structural anomaly
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// The raw data of the sections at indices `first` and `second` overlap in the file
+    OverlappingSections { first: usize, second: usize },
+    /// The sections at indices `first` and `second` overlap once mapped in memory
+    OverlappingVirtualSections { first: usize, second: usize },
+    /// The raw data of the section at index `section` extends past the end of the file
+    SectionOutOfFile { section: usize },
+    /// The entry point is not within any executable section
+    EntryPointNotExecutable { entry: u32 },
+    /// The `CheckSum` field in the optional header does not match the computed checksum
+    ChecksumMismatch { expected: u32, computed: u32 },
+    /// `SizeOfImage` is not a multiple of `SectionAlignment`
+    UnalignedSizeOfImage {
+        size_of_image: u32,
+        section_alignment: u32,
+    },
+    /// The data directory at `index` is populated, but lies beyond `NumberOfRvaAndSizes`
+    /// and is therefore ignored by the loader
+    IgnoredDataDirectory { index: usize },
+    /// The section at index `section` is both writable and executable
+    WritableExecutableSection { section: usize },
+    /// The import table lives in the writable section at index `section`
+    ImportTableInWritableSection { section: usize },
+}
+
+/// A structural anomaly found in a PE image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anomaly {
+    /// The file offset of the offending structure or field
+    pub offset: usize,
+    /// What is wrong at `offset`
+    pub kind: AnomalyKind,
+}
+
+/// Computes the PE image checksum of `bytes`, as `CheckSumMappedFile` does.
+///
+/// The 4 bytes at `checksum_offset` (the `CheckSum` field itself) are skipped.
+pub fn checksum(bytes: &[u8], checksum_offset: usize) -> u32 {
+    let mut sum: u32 = 0;
+    for (i, word) in bytes.chunks(2).enumerate() {
+        let offset = i * 2;
+        if offset == checksum_offset || offset == checksum_offset + 2 {
+            continue;
+        }
+        let word = u32::from(word[0]) | u32::from(*word.get(1).unwrap_or(&0)) << 8;
+        sum += word;
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum = (sum & 0xffff) + (sum >> 16);
+    sum.wrapping_add(bytes.len() as u32)
+}
+
+/// The size a section occupies in memory, falling back to its raw size when `VirtualSize` is zero
+fn virtual_extent(section: &SectionTable) -> u32 {
+    if section.virtual_size == 0 {
+        section.size_of_raw_data
+    } else {
+        section.virtual_size
+    }
+}
+
+fn contains_rva(section: &SectionTable, rva: u32) -> bool {
+    let start = section.virtual_address;
+    let end = start.saturating_add(virtual_extent(section));
+    start <= rva && rva < end
+}
+
+impl PE<'_> {
+    /// Returns the structural anomalies found in this image, in file order of discovery.
+    ///
+    /// This is a pure analysis pass over the already parsed headers and the underlying bytes; it
+    /// never fails, and an empty result means nothing suspicious was found.
+    pub fn anomalies(&self) -> Vec<Anomaly> {
+        let mut anomalies = Vec::new();
+        let optional_header_offset = self.header.dos_header.pe_pointer as usize
+            + header::SIZEOF_PE_MAGIC
+            + header::SIZEOF_COFF_HEADER;
+        let section_table_offset =
+            optional_header_offset + self.header.coff_header.size_of_optional_header as usize;
+        let section_offset = |index: usize| section_table_offset + index * SIZEOF_SECTION_TABLE;
+
+        // sections whose raw data is (partly) outside of the file
+        for (index, section) in self.sections.iter().enumerate() {
+            let end = u64::from(section.pointer_to_raw_data) + u64::from(section.size_of_raw_data);
+            if section.size_of_raw_data != 0 && end > self.bytes.len() as u64 {
+                anomalies.push(Anomaly {
+                    offset: section_offset(index),
+                    kind: AnomalyKind::SectionOutOfFile { section: index },
+                });
+            }
+        }
+
+        // sections whose raw data overlap
+        let mut by_offset: Vec<(usize, &SectionTable)> = self
+            .sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| section.size_of_raw_data != 0)
+            .collect();
+        by_offset.sort_by_key(|(_, section)| section.pointer_to_raw_data);
+        let mut furthest: Option<(usize, u64)> = None;
+        for &(index, section) in &by_offset {
+            let start = u64::from(section.pointer_to_raw_data);
+            let end = start + u64::from(section.size_of_raw_data);
+            match furthest {
+                Some((first, first_end)) if start < first_end => {
+                    anomalies.push(Anomaly {
+                        offset: section.pointer_to_raw_data as usize,
+                        kind: AnomalyKind::OverlappingSections {
+                            first,
+                            second: index,
+                        },
+                    });
+                    if end > first_end {
+                        furthest = Some((index, end));
+                    }
+                }
+                _ => furthest = Some((index, end)),
+            }
+        }
+
+        // sections overlapping in memory
+        let mut by_rva: Vec<(usize, &SectionTable)> = self
+            .sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| virtual_extent(section) != 0)
+            .collect();
+        by_rva.sort_by_key(|(_, section)| section.virtual_address);
+        let mut furthest: Option<(usize, u64)> = None;
+        for &(index, section) in &by_rva {
+            let start = u64::from(section.virtual_address);
+            let end = start + u64::from(virtual_extent(section));
+            match furthest {
+                Some((first, first_end)) if start < first_end => {
+                    anomalies.push(Anomaly {
+                        offset: section_offset(index),
+                        kind: AnomalyKind::OverlappingVirtualSections {
+                            first,
+                            second: index,
+                        },
+                    });
+                    if end > first_end {
+                        furthest = Some((index, end));
+                    }
+                }
+                _ => furthest = Some((index, end)),
+            }
+        }
+
+        // writable and executable sections
+        for (index, section) in self.sections.iter().enumerate() {
+            let wx = IMAGE_SCN_MEM_WRITE | IMAGE_SCN_MEM_EXECUTE;
+            if section.characteristics & wx == wx {
+                anomalies.push(Anomaly {
+                    offset: section_offset(index),
+                    kind: AnomalyKind::WritableExecutableSection { section: index },
+                });
+            }
+        }
+
+        let optional_header = match self.header.optional_header {
+            Some(optional_header) => optional_header,
+            None => return anomalies,
+        };
+        let windows_fields = &optional_header.windows_fields;
+
+        // entry point outside of any executable section; DLLs may have no entry point at all
+        // (the field is always 32-bit wide, whatever the image bitness)
+        let entry = optional_header.standard_fields.address_of_entry_point as u32;
+        let has_entry = entry != 0 || !self.is_lib;
+        if has_entry
+            && !self.sections.iter().any(|section| {
+                section.characteristics & IMAGE_SCN_MEM_EXECUTE != 0 && contains_rva(section, entry)
+            })
+        {
+            anomalies.push(Anomaly {
+                offset: optional_header_offset + OFFSET_ADDRESS_OF_ENTRY_POINT,
+                kind: AnomalyKind::EntryPointNotExecutable { entry },
+            });
+        }
+
+        // checksum mismatch, when the image has one at all
+        let checksum_offset = optional_header_offset + OFFSET_CHECKSUM;
+        if windows_fields.check_sum != 0 {
+            let computed = checksum(self.bytes, checksum_offset);
+            if computed != windows_fields.check_sum {
+                anomalies.push(Anomaly {
+                    offset: checksum_offset,
+                    kind: AnomalyKind::ChecksumMismatch {
+                        expected: windows_fields.check_sum,
+                        computed,
+                    },
+                });
+            }
+        }
+
+        // SizeOfImage not aligned on SectionAlignment
+        let section_alignment = windows_fields.section_alignment;
+        if section_alignment != 0 && windows_fields.size_of_image % section_alignment != 0 {
+            anomalies.push(Anomaly {
+                offset: optional_header_offset + OFFSET_SIZE_OF_IMAGE,
+                kind: AnomalyKind::UnalignedSizeOfImage {
+                    size_of_image: windows_fields.size_of_image,
+                    section_alignment,
+                },
+            });
+        }
+
+        // populated data directories beyond NumberOfRvaAndSizes
+        let data_directories_offset = optional_header_offset
+            + match optional_header.standard_fields.magic {
+                optional_header::MAGIC_64 => {
+                    optional_header::SIZEOF_STANDARD_FIELDS_64
+                        + optional_header::SIZEOF_WINDOWS_FIELDS_64
+                }
+                _ => {
+                    optional_header::SIZEOF_STANDARD_FIELDS_32
+                        + optional_header::SIZEOF_WINDOWS_FIELDS_32
+                }
+            };
+        let room =
+            section_table_offset.saturating_sub(data_directories_offset) / SIZEOF_DATA_DIRECTORY;
+        let count = windows_fields.number_of_rva_and_sizes as usize;
+        for index in count..cmp::min(room, NUM_DATA_DIRECTORIES) {
+            let offset = data_directories_offset + index * SIZEOF_DATA_DIRECTORY;
+            let populated = self
+                .bytes
+                .get(offset..offset + SIZEOF_DATA_DIRECTORY)
+                .is_some_and(|entry| entry.iter().any(|&byte| byte != 0));
+            if populated {
+                anomalies.push(Anomaly {
+                    offset,
+                    kind: AnomalyKind::IgnoredDataDirectory { index },
+                });
+            }
+        }
+
+        // import table in a writable section
+        if let Some(import_table) = *optional_header.data_directories.get_import_table() {
+            let rva = import_table.virtual_address;
+            if let Some(index) = self.sections.iter().position(|section| {
+                section.characteristics & IMAGE_SCN_MEM_WRITE != 0 && contains_rva(section, rva)
+            }) {
+                let offset = utils::find_offset(
+                    rva as usize,
+                    &self.sections,
+                    windows_fields.file_alignment,
+                    &options::ParseOptions::default(),
+                )
+                .unwrap_or_else(|| section_offset(index));
+                anomalies.push(Anomaly {
+                    offset,
+                    kind: AnomalyKind::ImportTableInWritableSection { section: index },
+                });
+            }
+        }
+
+        anomalies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum, AnomalyKind};
+    use crate::pe::section_table::{IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_WRITE};
+    use crate::pe::PE;
+
+    #[test]
+    fn well_formed_image() {
+        let file = include_bytes!("../../tests/bins/efi/MultiCerts.efi");
+        let pe = PE::parse(&file[..]).unwrap();
+        let anomalies = pe.anomalies();
+        assert!(anomalies.is_empty(), "{:#?}", anomalies);
+    }
+
+    #[test]
+    fn writable_executable_section() {
+        let mut file = include_bytes!("../../tests/bins/efi/MultiCerts.efi").to_vec();
+        let (section_offset, characteristics) = {
+            let pe = PE::parse(&file).unwrap();
+            let optional_header_offset = pe.header.dos_header.pe_pointer as usize + 24;
+            let section_table_offset =
+                optional_header_offset + pe.header.coff_header.size_of_optional_header as usize;
+            (section_table_offset, pe.sections[0].characteristics)
+        };
+        let characteristics = characteristics | IMAGE_SCN_MEM_WRITE | IMAGE_SCN_MEM_EXECUTE;
+        file[section_offset + 36..section_offset + 40]
+            .copy_from_slice(&characteristics.to_le_bytes());
+
+        let pe = PE::parse(&file).unwrap();
+        let anomalies = pe.anomalies();
+        let anomaly = anomalies
+            .iter()
+            .find(|anomaly| anomaly.kind == AnomalyKind::WritableExecutableSection { section: 0 })
+            .expect("W+X section");
+        assert_eq!(anomaly.offset, section_offset);
+    }
+
+    #[test]
+    fn checksum_skips_checksum_field() {
+        let bytes = [0x01, 0x00, 0xff, 0xff, 0xff, 0xff, 0x02];
+        assert_eq!(checksum(&bytes, 2), 0x01 + 0x02 + bytes.len() as u32);
+    }
+}
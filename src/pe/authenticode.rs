@@ -23,6 +23,16 @@ impl PE<'_> {
             pe: self,
             state: IterState::default(),
             sections: VecDeque::default(),
+            pad: true,
+        }
+    }
+
+    /// Returns the ranges of the binary hashed by the TCG PE/COFF image measurement, i.e., the
+    /// authenticode ranges without the final padding to a multiple of 8 bytes.
+    pub fn measured_ranges(&self) -> ExcludedSectionsIter<'_> {
+        ExcludedSectionsIter {
+            pad: false,
+            ..self.authenticode_ranges()
         }
     }
 }
@@ -57,6 +67,7 @@ pub struct ExcludedSectionsIter<'s> {
     pe: &'s PE<'s>,
     state: IterState,
     sections: VecDeque<SectionTable>,
+    pad: bool,
 }
 
 #[derive(Debug, PartialEq)]
@@ -228,7 +239,7 @@ impl<'s> Iterator for ExcludedSectionsIter<'s> {
 
                         // If FILE_SIZE is not a multiple of 8 bytes, the data added to the hash must
                         // be appended with zero padding of length (8 – (FILE_SIZE % 8)) bytes
-                        let pad_size = (8 - file_size % 8) % 8;
+                        let pad_size = if self.pad { (8 - file_size % 8) % 8 } else { 0 };
                         self.state = IterState::Padding(pad_size);
 
                         if file_size > sum_of_bytes_hashed {
@@ -5,6 +5,7 @@
 use core::convert::{TryFrom, TryInto};
 
 use crate::error;
+use crate::guid::{Guid, SIZEOF_GUID};
 use scroll::{ctx, Pread, Pwrite};
 
 use alloc::string::ToString;
@@ -98,6 +99,153 @@ pub struct AttributeCertificateHeader {
 
 const CERTIFICATE_DATA_OFFSET: u32 = 8;
 
+/// EFI_CERT_TYPE_PKCS7_GUID: the data is a DER-encoded PKCS#7 `SignedData`
+pub const EFI_CERT_TYPE_PKCS7_GUID: Guid = Guid::new(
+    0x4aaf_d29d,
+    0x68df,
+    0x49ee,
+    [0x8a, 0xa9, 0x34, 0x7d, 0x37, 0x56, 0x65, 0xa7],
+);
+/// EFI_CERT_TYPE_RSA2048_SHA256_GUID: the data is an `EFI_CERT_BLOCK_RSA_2048_SHA256`
+pub const EFI_CERT_TYPE_RSA2048_SHA256_GUID: Guid = Guid::new(
+    0xa771_7414,
+    0xc616,
+    0x4977,
+    [0x94, 0x20, 0x84, 0x47, 0x12, 0xa7, 0x35, 0xbf],
+);
+
+/// EFI_HASH_ALGORITHM_SHA1_GUID
+pub const EFI_HASH_ALGORITHM_SHA1_GUID: Guid = Guid::new(
+    0x2ae9_d80f,
+    0x3fb2,
+    0x4095,
+    [0xb7, 0xb1, 0xe9, 0x31, 0x57, 0xb9, 0x46, 0xb6],
+);
+/// EFI_HASH_ALGORITHM_SHA224_GUID
+pub const EFI_HASH_ALGORITHM_SHA224_GUID: Guid = Guid::new(
+    0x8df0_1a06,
+    0x9bd5,
+    0x4bf7,
+    [0xb0, 0x21, 0xdb, 0x4f, 0xd9, 0xcc, 0xf4, 0x5b],
+);
+/// EFI_HASH_ALGORITHM_SHA256_GUID
+pub const EFI_HASH_ALGORITHM_SHA256_GUID: Guid = Guid::new(
+    0x51aa_59de,
+    0xfdf2,
+    0x4ea3,
+    [0xbc, 0x63, 0x87, 0x5f, 0xb7, 0x84, 0x2e, 0xe9],
+);
+/// EFI_HASH_ALGORITHM_SHA384_GUID
+pub const EFI_HASH_ALGORITHM_SHA384_GUID: Guid = Guid::new(
+    0xefa9_6432,
+    0xde33,
+    0x4dd2,
+    [0xae, 0xe6, 0x32, 0x8c, 0x33, 0xdf, 0x77, 0x7a],
+);
+/// EFI_HASH_ALGORITHM_SHA512_GUID
+pub const EFI_HASH_ALGORITHM_SHA512_GUID: Guid = Guid::new(
+    0xcaa4_381e,
+    0x750c,
+    0x4770,
+    [0xb8, 0x70, 0x7a, 0x23, 0xb4, 0xe4, 0x21, 0x30],
+);
+
+/// Size of the RSA 2048 public key modulus and of the signature in an
+/// `EFI_CERT_BLOCK_RSA_2048_SHA256`
+pub const SIZEOF_RSA2048: usize = 256;
+
+/// Returns the name of a well-known `WIN_CERTIFICATE_UEFI_GUID` certificate type or
+/// `WIN_CERTIFICATE_EFI_PKCS1_15` hash algorithm GUID
+pub fn guid_to_str(guid: &Guid) -> Option<&'static str> {
+    Some(match *guid {
+        EFI_CERT_TYPE_PKCS7_GUID => "PKCS7",
+        EFI_CERT_TYPE_RSA2048_SHA256_GUID => "RSA2048_SHA256",
+        EFI_HASH_ALGORITHM_SHA1_GUID => "SHA1",
+        EFI_HASH_ALGORITHM_SHA224_GUID => "SHA224",
+        EFI_HASH_ALGORITHM_SHA256_GUID => "SHA256",
+        EFI_HASH_ALGORITHM_SHA384_GUID => "SHA384",
+        EFI_HASH_ALGORITHM_SHA512_GUID => "SHA512",
+        _ => return None,
+    })
+}
+
+/// `WIN_CERTIFICATE_UEFI_GUID`: a certificate whose format is given by a GUID
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct UefiGuidCertificate<'a> {
+    /// `CertType`, e.g., [`EFI_CERT_TYPE_PKCS7_GUID`]
+    pub cert_type: Guid,
+    /// `CertData`
+    pub data: &'a [u8],
+}
+
+impl<'a> UefiGuidCertificate<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, error::Error> {
+        let cert_type = bytes.pread_with(0, scroll::LE)?;
+        Ok(UefiGuidCertificate {
+            cert_type,
+            data: &bytes[SIZEOF_GUID..],
+        })
+    }
+
+    /// Decodes the data as an `EFI_CERT_BLOCK_RSA_2048_SHA256`, if `cert_type` says so
+    pub fn rsa2048_sha256(&self) -> Option<Result<CertBlockRsa2048Sha256<'a>, error::Error>> {
+        if self.cert_type != EFI_CERT_TYPE_RSA2048_SHA256_GUID {
+            return None;
+        }
+        Some(CertBlockRsa2048Sha256::parse(self.data))
+    }
+}
+
+/// `EFI_CERT_BLOCK_RSA_2048_SHA256`
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct CertBlockRsa2048Sha256<'a> {
+    pub hash_type: Guid,
+    /// The public key modulus, big endian
+    pub public_key: &'a [u8],
+    pub signature: &'a [u8],
+}
+
+impl<'a> CertBlockRsa2048Sha256<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, error::Error> {
+        let offset = &mut 0;
+        let hash_type = bytes.gread_with(offset, scroll::LE)?;
+        let public_key = bytes.gread_with(offset, SIZEOF_RSA2048)?;
+        let signature = bytes.gread_with(offset, SIZEOF_RSA2048)?;
+        Ok(CertBlockRsa2048Sha256 {
+            hash_type,
+            public_key,
+            signature,
+        })
+    }
+}
+
+/// `WIN_CERTIFICATE_EFI_PKCS1_15`: a PKCS#1 v1.5 RSA signature
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Pkcs1v15Certificate<'a> {
+    /// `HashAlgorithm`, e.g., [`EFI_HASH_ALGORITHM_SHA256_GUID`]
+    pub hash_algorithm: Guid,
+    pub signature: &'a [u8],
+}
+
+impl<'a> Pkcs1v15Certificate<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, error::Error> {
+        let hash_algorithm = bytes.pread_with(0, scroll::LE)?;
+        Ok(Pkcs1v15Certificate {
+            hash_algorithm,
+            signature: &bytes[SIZEOF_GUID..],
+        })
+    }
+}
+
+/// The typed contents of an attribute certificate
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CertificateData<'a> {
+    UefiGuid(UefiGuidCertificate<'a>),
+    Pkcs1v15(Pkcs1v15Certificate<'a>),
+    /// Certificates of other types, whose contents are not decoded further
+    Raw(&'a [u8]),
+}
+
 #[derive(Debug, Clone)]
 pub struct AttributeCertificate<'a> {
     pub length: u32,
@@ -142,6 +290,21 @@ impl<'a> AttributeCertificate<'a> {
     }
 }
 
+impl<'a> AttributeCertificate<'a> {
+    /// Decodes the certificate contents according to `certificate_type`
+    pub fn data(&self) -> Result<CertificateData<'a>, error::Error> {
+        Ok(match self.certificate_type {
+            AttributeCertificateType::EfiGuid => {
+                CertificateData::UefiGuid(UefiGuidCertificate::parse(self.certificate)?)
+            }
+            AttributeCertificateType::EfiPkcs115 => {
+                CertificateData::Pkcs1v15(Pkcs1v15Certificate::parse(self.certificate)?)
+            }
+            _ => CertificateData::Raw(self.certificate),
+        })
+    }
+}
+
 impl<'a> ctx::TryIntoCtx<scroll::Endian> for &AttributeCertificate<'a> {
     type Error = error::Error;
 
@@ -201,9 +364,62 @@ pub(crate) fn enumerate_certificates(
 
 #[cfg(test)]
 mod tests {
-    use crate::pe::certificate_table::{AttributeCertificateRevision, AttributeCertificateType};
+    use crate::pe::certificate_table::*;
     use crate::pe::PE;
 
+    fn certificate(certificate_type: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = ((8 + data.len()) as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&0x0200u16.to_le_bytes());
+        bytes.extend_from_slice(&certificate_type.to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn decode_uefi_guid_certificate() {
+        let mut data = EFI_CERT_TYPE_RSA2048_SHA256_GUID.to_bytes().to_vec();
+        data.extend_from_slice(&EFI_HASH_ALGORITHM_SHA256_GUID.to_bytes());
+        data.extend_from_slice(&[0x11; SIZEOF_RSA2048]);
+        data.extend_from_slice(&[0x22; SIZEOF_RSA2048]);
+        let bytes = certificate(AttributeCertificateType::EfiGuid as u16, &data);
+        let cert = AttributeCertificate::parse(&bytes, &mut 0).unwrap();
+
+        let uefi = match cert.data().unwrap() {
+            CertificateData::UefiGuid(uefi) => uefi,
+            data => panic!("unexpected certificate data: {:?}", data),
+        };
+        assert_eq!(uefi.cert_type, EFI_CERT_TYPE_RSA2048_SHA256_GUID);
+        assert_eq!(guid_to_str(&uefi.cert_type), Some("RSA2048_SHA256"));
+        assert_eq!(uefi.data, &data[16..]);
+        let block = uefi.rsa2048_sha256().unwrap().unwrap();
+        assert_eq!(block.hash_type, EFI_HASH_ALGORITHM_SHA256_GUID);
+        assert_eq!(block.public_key, &[0x11; SIZEOF_RSA2048][..]);
+        assert_eq!(block.signature, &[0x22; SIZEOF_RSA2048][..]);
+
+        // a truncated RSA block
+        let uefi = UefiGuidCertificate::parse(&data[..16 + 16 + 100]).unwrap();
+        assert!(uefi.rsa2048_sha256().unwrap().is_err());
+    }
+
+    #[test]
+    fn decode_pkcs1v15_certificate() {
+        let mut data = EFI_HASH_ALGORITHM_SHA1_GUID.to_bytes().to_vec();
+        data.extend_from_slice(&[0x33; 128]);
+        let bytes = certificate(AttributeCertificateType::EfiPkcs115 as u16, &data);
+        let cert = AttributeCertificate::parse(&bytes, &mut 0).unwrap();
+        assert_eq!(
+            cert.data().unwrap(),
+            CertificateData::Pkcs1v15(Pkcs1v15Certificate {
+                hash_algorithm: EFI_HASH_ALGORITHM_SHA1_GUID,
+                signature: &[0x33; 128],
+            })
+        );
+
+        let bytes = certificate(AttributeCertificateType::EfiPkcs115 as u16, &data[..8]);
+        let cert = AttributeCertificate::parse(&bytes, &mut 0).unwrap();
+        assert!(cert.data().is_err());
+    }
+
     #[test]
     fn parse_certs_table() -> Result<(), Box<dyn std::error::Error>> {
         let file = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
@@ -73,6 +73,10 @@ let show_type characteristics =
   else "MANY"                   (* print all *)
  */
 
+use core::fmt;
+
+use crate::pe::utils;
+
 pub const IMAGE_FILE_RELOCS_STRIPPED: u16 = 0x0001;
 pub const IMAGE_FILE_EXECUTABLE_IMAGE: u16 = 0x0002;
 pub const IMAGE_FILE_LINE_NUMS_STRIPPED: u16 = 0x0004;
@@ -97,3 +101,124 @@ pub fn is_dll(characteristics: u16) -> bool {
 pub fn is_exe(characteristics: u16) -> bool {
     characteristics & IMAGE_FILE_EXECUTABLE_IMAGE == IMAGE_FILE_EXECUTABLE_IMAGE
 }
+
+const CHARACTERISTICS: [(u32, &str); 16] = [
+    (
+        IMAGE_FILE_RELOCS_STRIPPED as u32,
+        "IMAGE_FILE_RELOCS_STRIPPED",
+    ),
+    (
+        IMAGE_FILE_EXECUTABLE_IMAGE as u32,
+        "IMAGE_FILE_EXECUTABLE_IMAGE",
+    ),
+    (
+        IMAGE_FILE_LINE_NUMS_STRIPPED as u32,
+        "IMAGE_FILE_LINE_NUMS_STRIPPED",
+    ),
+    (
+        IMAGE_FILE_LOCAL_SYMS_STRIPPED as u32,
+        "IMAGE_FILE_LOCAL_SYMS_STRIPPED",
+    ),
+    (
+        IMAGE_FILE_AGGRESSIVE_WS_TRIM as u32,
+        "IMAGE_FILE_AGGRESSIVE_WS_TRIM",
+    ),
+    (
+        IMAGE_FILE_LARGE_ADDRESS_AWARE as u32,
+        "IMAGE_FILE_LARGE_ADDRESS_AWARE",
+    ),
+    (RESERVED as u32, "RESERVED"),
+    (
+        IMAGE_FILE_BYTES_REVERSED_LO as u32,
+        "IMAGE_FILE_BYTES_REVERSED_LO",
+    ),
+    (IMAGE_FILE_32BIT_MACHINE as u32, "IMAGE_FILE_32BIT_MACHINE"),
+    (
+        IMAGE_FILE_DEBUG_STRIPPED as u32,
+        "IMAGE_FILE_DEBUG_STRIPPED",
+    ),
+    (
+        IMAGE_FILE_REMOVABLE_RUN_FROM_SWAP as u32,
+        "IMAGE_FILE_REMOVABLE_RUN_FROM_SWAP",
+    ),
+    (
+        IMAGE_FILE_NET_RUN_FROM_SWAP as u32,
+        "IMAGE_FILE_NET_RUN_FROM_SWAP",
+    ),
+    (IMAGE_FILE_SYSTEM as u32, "IMAGE_FILE_SYSTEM"),
+    (IMAGE_FILE_DLL as u32, "IMAGE_FILE_DLL"),
+    (
+        IMAGE_FILE_UP_SYSTEM_ONLY as u32,
+        "IMAGE_FILE_UP_SYSTEM_ONLY",
+    ),
+    (
+        IMAGE_FILE_BYTES_REVERSED_HI as u32,
+        "IMAGE_FILE_BYTES_REVERSED_HI",
+    ),
+];
+
+/// The `Characteristics` field of a COFF header, a set of `IMAGE_FILE_*` flags
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+pub struct Characteristics(pub u16);
+
+impl Characteristics {
+    /// The raw value of the flags
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// Whether every flag in `flags` is set
+    pub fn contains(self, flags: u16) -> bool {
+        self.0 & flags == flags
+    }
+
+    pub fn is_dll(self) -> bool {
+        is_dll(self.0)
+    }
+
+    pub fn is_exe(self) -> bool {
+        is_exe(self.0)
+    }
+
+    /// Iterates over the names of the known flags that are set
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        CHARACTERISTICS
+            .iter()
+            .filter(move |(flag, _)| u32::from(self.0) & flag == *flag)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl From<u16> for Characteristics {
+    fn from(bits: u16) -> Self {
+        Characteristics(bits)
+    }
+}
+
+impl From<Characteristics> for u16 {
+    fn from(characteristics: Characteristics) -> Self {
+        characteristics.0
+    }
+}
+
+impl fmt::Display for Characteristics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        utils::fmt_flags(f, u32::from(self.0), &CHARACTERISTICS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_characteristics() {
+        let characteristics = Characteristics(IMAGE_FILE_EXECUTABLE_IMAGE | IMAGE_FILE_DLL);
+        assert!(characteristics.is_dll());
+        assert_eq!(
+            characteristics.to_string(),
+            "IMAGE_FILE_EXECUTABLE_IMAGE | IMAGE_FILE_DLL"
+        );
+        assert_eq!(Characteristics(0).to_string(), "0x0");
+    }
+}
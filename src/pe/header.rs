@@ -2,6 +2,8 @@ use crate::error;
 use crate::pe::{optional_header, section_table, symbol};
 use crate::strtab;
 use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
 use log::debug;
 use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
 
@@ -141,6 +143,231 @@ pub const COFF_MACHINE_SH5: u16 = 0x1a8;
 pub const COFF_MACHINE_THUMB: u16 = 0x1c2;
 /// MIPS little-endian WCE v2
 pub const COFF_MACHINE_WCEMIPSV2: u16 = 0x169;
+/// LoongArch 32-bit address space
+pub const COFF_MACHINE_LOONGARCH32: u16 = 0x6232;
+/// LoongArch 64-bit address space
+pub const COFF_MACHINE_LOONGARCH64: u16 = 0x6264;
+
+/// The target machine of a PE, COFF or TE image, see the `COFF_MACHINE_*` constants
+#[repr(u16)]
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Machine {
+    Unknown = COFF_MACHINE_UNKNOWN,
+    Am33 = COFF_MACHINE_AM33,
+    X86_64 = COFF_MACHINE_X86_64,
+    Arm = COFF_MACHINE_ARM,
+    Arm64 = COFF_MACHINE_ARM64,
+    ArmNt = COFF_MACHINE_ARMNT,
+    Ebc = COFF_MACHINE_EBC,
+    X86 = COFF_MACHINE_X86,
+    Ia64 = COFF_MACHINE_IA64,
+    M32R = COFF_MACHINE_M32R,
+    Mips16 = COFF_MACHINE_MIPS16,
+    MipsFpu = COFF_MACHINE_MIPSFPU,
+    MipsFpu16 = COFF_MACHINE_MIPSFPU16,
+    PowerPc = COFF_MACHINE_POWERPC,
+    PowerPcFp = COFF_MACHINE_POWERPCFP,
+    R4000 = COFF_MACHINE_R4000,
+    RiscV32 = COFF_MACHINE_RISCV32,
+    RiscV64 = COFF_MACHINE_RISCV64,
+    RiscV128 = COFF_MACHINE_RISCV128,
+    Sh3 = COFF_MACHINE_SH3,
+    Sh3Dsp = COFF_MACHINE_SH3DSP,
+    Sh4 = COFF_MACHINE_SH4,
+    Sh5 = COFF_MACHINE_SH5,
+    Thumb = COFF_MACHINE_THUMB,
+    WceMipsV2 = COFF_MACHINE_WCEMIPSV2,
+    LoongArch32 = COFF_MACHINE_LOONGARCH32,
+    LoongArch64 = COFF_MACHINE_LOONGARCH64,
+}
+
+impl Machine {
+    /// The name of the machine type, as in the `COFF_MACHINE_*` constants
+    pub fn name(self) -> &'static str {
+        match self {
+            Machine::Unknown => "UNKNOWN",
+            Machine::Am33 => "AM33",
+            Machine::X86_64 => "X86_64",
+            Machine::Arm => "ARM",
+            Machine::Arm64 => "ARM64",
+            Machine::ArmNt => "ARMNT",
+            Machine::Ebc => "EBC",
+            Machine::X86 => "X86",
+            Machine::Ia64 => "IA64",
+            Machine::M32R => "M32R",
+            Machine::Mips16 => "MIPS16",
+            Machine::MipsFpu => "MIPSFPU",
+            Machine::MipsFpu16 => "MIPSFPU16",
+            Machine::PowerPc => "POWERPC",
+            Machine::PowerPcFp => "POWERPCFP",
+            Machine::R4000 => "R4000",
+            Machine::RiscV32 => "RISCV32",
+            Machine::RiscV64 => "RISCV64",
+            Machine::RiscV128 => "RISCV128",
+            Machine::Sh3 => "SH3",
+            Machine::Sh3Dsp => "SH3DSP",
+            Machine::Sh4 => "SH4",
+            Machine::Sh5 => "SH5",
+            Machine::Thumb => "THUMB",
+            Machine::WceMipsV2 => "WCEMIPSV2",
+            Machine::LoongArch32 => "LOONGARCH32",
+            Machine::LoongArch64 => "LOONGARCH64",
+        }
+    }
+}
+
+impl TryFrom<u16> for Machine {
+    type Error = error::Error;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Ok(match value {
+            COFF_MACHINE_UNKNOWN => Machine::Unknown,
+            COFF_MACHINE_AM33 => Machine::Am33,
+            COFF_MACHINE_X86_64 => Machine::X86_64,
+            COFF_MACHINE_ARM => Machine::Arm,
+            COFF_MACHINE_ARM64 => Machine::Arm64,
+            COFF_MACHINE_ARMNT => Machine::ArmNt,
+            COFF_MACHINE_EBC => Machine::Ebc,
+            COFF_MACHINE_X86 => Machine::X86,
+            COFF_MACHINE_IA64 => Machine::Ia64,
+            COFF_MACHINE_M32R => Machine::M32R,
+            COFF_MACHINE_MIPS16 => Machine::Mips16,
+            COFF_MACHINE_MIPSFPU => Machine::MipsFpu,
+            COFF_MACHINE_MIPSFPU16 => Machine::MipsFpu16,
+            COFF_MACHINE_POWERPC => Machine::PowerPc,
+            COFF_MACHINE_POWERPCFP => Machine::PowerPcFp,
+            COFF_MACHINE_R4000 => Machine::R4000,
+            COFF_MACHINE_RISCV32 => Machine::RiscV32,
+            COFF_MACHINE_RISCV64 => Machine::RiscV64,
+            COFF_MACHINE_RISCV128 => Machine::RiscV128,
+            COFF_MACHINE_SH3 => Machine::Sh3,
+            COFF_MACHINE_SH3DSP => Machine::Sh3Dsp,
+            COFF_MACHINE_SH4 => Machine::Sh4,
+            COFF_MACHINE_SH5 => Machine::Sh5,
+            COFF_MACHINE_THUMB => Machine::Thumb,
+            COFF_MACHINE_WCEMIPSV2 => Machine::WceMipsV2,
+            COFF_MACHINE_LOONGARCH32 => Machine::LoongArch32,
+            COFF_MACHINE_LOONGARCH64 => Machine::LoongArch64,
+            _ => {
+                return Err(error::Error::Malformed(format!(
+                    "Unknown machine type ({:#x})",
+                    value
+                )))
+            }
+        })
+    }
+}
+
+impl From<Machine> for u16 {
+    fn from(machine: Machine) -> Self {
+        machine as u16
+    }
+}
+
+impl fmt::Display for Machine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The subsystem required to run a PE or TE image, see the `PE_SUBSYSTEM_*` constants
+#[repr(u16)]
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Subsystem {
+    Unknown = PE_SUBSYSTEM_UNKNOWN,
+    Native = PE_SUBSYSTEM_NATIVE,
+    WindowsGui = PE_SUBSYSTEM_WINDOWS_GUI,
+    WindowsCui = PE_SUBSYSTEM_WINDOWS_CUI,
+    PosixCui = PE_SUBSYSTEM_POSIX_CUI,
+    WindowsCeGui = PE_SUBSYSTEM_WINDOWS_CE_GUI,
+    EfiApplication = PE_SUBSYSTEM_EFI_APPLICATION,
+    EfiBootServiceDriver = PE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER,
+    EfiRuntimeDriver = PE_SUBSYSTEM_EFI_RUNTIME_DRIVER,
+    EfiRom = PE_SUBSYSTEM_EFI_ROM,
+    Xbox = PE_SUBSYSTEM_XBOX,
+    WindowsBootApplication = PE_SUBSYSTEM_WINDOWS_BOOT_APPLICATION,
+}
+
+impl Subsystem {
+    /// The name of the subsystem, as in the `PE_SUBSYSTEM_*` constants
+    pub fn name(self) -> &'static str {
+        match self {
+            Subsystem::Unknown => "UNKNOWN",
+            Subsystem::Native => "NATIVE",
+            Subsystem::WindowsGui => "WINDOWS_GUI",
+            Subsystem::WindowsCui => "WINDOWS_CUI",
+            Subsystem::PosixCui => "POSIX_CUI",
+            Subsystem::WindowsCeGui => "WINDOWS_CE_GUI",
+            Subsystem::EfiApplication => "EFI_APPLICATION",
+            Subsystem::EfiBootServiceDriver => "EFI_BOOT_SERVICE_DRIVER",
+            Subsystem::EfiRuntimeDriver => "EFI_RUNTIME_DRIVER",
+            Subsystem::EfiRom => "EFI_ROM",
+            Subsystem::Xbox => "XBOX",
+            Subsystem::WindowsBootApplication => "WINDOWS_BOOT_APPLICATION",
+        }
+    }
+
+    /// Whether this is one of the UEFI subsystems
+    pub fn is_efi(self) -> bool {
+        matches!(
+            self,
+            Subsystem::EfiApplication
+                | Subsystem::EfiBootServiceDriver
+                | Subsystem::EfiRuntimeDriver
+                | Subsystem::EfiRom
+        )
+    }
+}
+
+impl TryFrom<u16> for Subsystem {
+    type Error = error::Error;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Ok(match value {
+            PE_SUBSYSTEM_UNKNOWN => Subsystem::Unknown,
+            PE_SUBSYSTEM_NATIVE => Subsystem::Native,
+            PE_SUBSYSTEM_WINDOWS_GUI => Subsystem::WindowsGui,
+            PE_SUBSYSTEM_WINDOWS_CUI => Subsystem::WindowsCui,
+            PE_SUBSYSTEM_POSIX_CUI => Subsystem::PosixCui,
+            PE_SUBSYSTEM_WINDOWS_CE_GUI => Subsystem::WindowsCeGui,
+            PE_SUBSYSTEM_EFI_APPLICATION => Subsystem::EfiApplication,
+            PE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER => Subsystem::EfiBootServiceDriver,
+            PE_SUBSYSTEM_EFI_RUNTIME_DRIVER => Subsystem::EfiRuntimeDriver,
+            PE_SUBSYSTEM_EFI_ROM => Subsystem::EfiRom,
+            PE_SUBSYSTEM_XBOX => Subsystem::Xbox,
+            PE_SUBSYSTEM_WINDOWS_BOOT_APPLICATION => Subsystem::WindowsBootApplication,
+            _ => {
+                return Err(error::Error::Malformed(format!(
+                    "Unknown subsystem ({:#x})",
+                    value
+                )))
+            }
+        })
+    }
+}
+
+/// TE images store the subsystem in a single byte
+impl TryFrom<u8> for Subsystem {
+    type Error = error::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Subsystem::try_from(u16::from(value))
+    }
+}
+
+impl From<Subsystem> for u16 {
+    fn from(subsystem: Subsystem) -> Self {
+        subsystem as u16
+    }
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
 
 impl CoffHeader {
     pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
@@ -219,7 +446,8 @@ impl Header {
 
 #[cfg(test)]
 mod tests {
-    use super::{Header, COFF_MACHINE_X86, DOS_MAGIC, PE_MAGIC};
+    use super::{Header, Machine, Subsystem, COFF_MACHINE_X86, DOS_MAGIC, PE_MAGIC};
+    use core::convert::TryFrom;
 
     const CRSS_HEADER: [u8; 688] = [
         0x4d, 0x5a, 0x90, 0x00, 0x03, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0xff, 0xff, 0x00,
@@ -278,4 +506,21 @@ mod tests {
         assert!(header.coff_header.machine == COFF_MACHINE_X86);
         println!("header: {:?}", &header);
     }
+
+    #[test]
+    fn typed_machine_and_subsystem() {
+        let header = Header::parse(&&CRSS_HEADER[..]).unwrap();
+        let machine = Machine::try_from(header.coff_header.machine).unwrap();
+        assert_eq!(machine, Machine::X86);
+        assert_eq!(machine.to_string(), "X86");
+        let subsystem = header.optional_header.unwrap().windows_fields.subsystem;
+        let subsystem = Subsystem::try_from(subsystem).unwrap();
+        assert_eq!(subsystem, Subsystem::Native);
+        assert_eq!(
+            Subsystem::try_from(10u8).unwrap(),
+            Subsystem::EfiApplication
+        );
+        assert!(Subsystem::EfiRom.is_efi());
+        assert!(Machine::try_from(0x1234).is_err());
+    }
 }
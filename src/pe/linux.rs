@@ -0,0 +1,414 @@
+//! Linux kernel images.
+//!
+//! x86 and arm64 kernels built with `CONFIG_EFI_STUB` are PE images that also carry the header
+//! their native boot protocol expects: the x86 real-mode setup header (`HdrS`) at offset 0x1f1,
+//! and the arm64 `Image` header at the start of the file.
+//!
+//! See the kernel's `Documentation/arch/x86/boot.rst` and `Documentation/arch/arm64/booting.rst`.
+
+use scroll::{Pread, Pwrite, SizeWith};
+
+use crate::error;
+
+use super::PE;
+
+/// The offset of the x86 setup header
+pub const SETUP_HEADER_OFFSET: usize = 0x1f1;
+/// `0xAA55`, the boot sector signature at offset 0x1fe
+pub const BOOT_FLAG: u16 = 0xaa55;
+/// `HdrS`, the setup header signature at offset 0x202
+pub const SETUP_HEADER_MAGIC: u32 = 0x5372_6448;
+/// The offset of the kernel version string is relative to the end of the boot sector
+pub const KERNEL_VERSION_BASE: usize = 0x200;
+/// The number of setup sectors assumed when the header says zero
+pub const DEFAULT_SETUP_SECTS: u8 = 4;
+pub const SECTOR_SIZE: usize = 512;
+
+/// `loadflags`: the protected-mode code is loaded at 0x100000
+pub const LOADED_HIGH: u8 = 0x01;
+/// `loadflags`: the loader may use the heap up to `heap_end_ptr`
+pub const CAN_USE_HEAP: u8 = 0x80;
+
+/// `xloadflags`: the kernel has a 64-bit entry point at 0x200
+pub const XLF_KERNEL_64: u16 = 0x01;
+/// `xloadflags`: the kernel, boot parameters and command line may be above 4 GiB
+pub const XLF_CAN_BE_LOADED_ABOVE_4G: u16 = 0x02;
+/// `xloadflags`: the kernel supports the 32-bit EFI handover entry point
+pub const XLF_EFI_HANDOVER_32: u16 = 0x04;
+/// `xloadflags`: the kernel supports the 64-bit EFI handover entry point
+pub const XLF_EFI_HANDOVER_64: u16 = 0x08;
+/// `xloadflags`: the kernel supports kexec from a 64-bit EFI environment
+pub const XLF_EFI_KEXEC: u16 = 0x10;
+
+/// The x86 real-mode kernel header, as of boot protocol 2.15
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, SizeWith)]
+pub struct SetupHeader {
+    pub setup_sects: u8,
+    pub root_flags: u16,
+    pub syssize: u32,
+    pub ram_size: u16,
+    pub vid_mode: u16,
+    pub root_dev: u16,
+    pub boot_flag: u16,
+    pub jump: u16,
+    pub header: u32,
+    pub version: u16,
+    pub realmode_swtch: u32,
+    pub start_sys_seg: u16,
+    pub kernel_version: u16,
+    pub type_of_loader: u8,
+    pub loadflags: u8,
+    pub setup_move_size: u16,
+    pub code32_start: u32,
+    pub ramdisk_image: u32,
+    pub ramdisk_size: u32,
+    pub bootsect_kludge: u32,
+    pub heap_end_ptr: u16,
+    pub ext_loader_ver: u8,
+    pub ext_loader_type: u8,
+    pub cmd_line_ptr: u32,
+    pub initrd_addr_max: u32,
+    pub kernel_alignment: u32,
+    pub relocatable_kernel: u8,
+    pub min_alignment: u8,
+    pub xloadflags: u16,
+    pub cmdline_size: u32,
+    pub hardware_subarch: u32,
+    pub hardware_subarch_data: u64,
+    pub payload_offset: u32,
+    pub payload_length: u32,
+    pub setup_data: u64,
+    pub pref_address: u64,
+    pub init_size: u32,
+    pub handover_offset: u32,
+    pub kernel_info_offset: u32,
+}
+
+pub const SIZEOF_SETUP_HEADER: usize = 0x7b;
+
+/// The format of the compressed kernel carried by a bzImage
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PayloadFormat {
+    Gzip,
+    Bzip2,
+    Lzma,
+    Xz,
+    Lzo,
+    Lz4,
+    Zstd,
+    /// An uncompressed ELF image
+    Elf,
+    Unknown,
+}
+
+impl PayloadFormat {
+    /// Recognizes the format of `payload` from its magic bytes
+    pub fn detect(payload: &[u8]) -> Self {
+        const MAGICS: &[(&[u8], PayloadFormat)] = &[
+            (b"\x1f\x8b", PayloadFormat::Gzip),
+            (b"\x1f\x9e", PayloadFormat::Gzip),
+            (b"BZh", PayloadFormat::Bzip2),
+            (b"\x5d\x00\x00", PayloadFormat::Lzma),
+            (b"\xfd7zXZ\x00", PayloadFormat::Xz),
+            (b"\x89LZO", PayloadFormat::Lzo),
+            (b"\x02\x21\x4c\x18", PayloadFormat::Lz4),
+            (b"\x28\xb5\x2f\xfd", PayloadFormat::Zstd),
+            (b"\x7fELF", PayloadFormat::Elf),
+        ];
+        MAGICS
+            .iter()
+            .find(|(magic, _)| payload.starts_with(magic))
+            .map_or(PayloadFormat::Unknown, |&(_, format)| format)
+    }
+}
+
+impl SetupHeader {
+    /// Parses the setup header of `bytes`, returning `None` if it has no `HdrS` signature
+    pub fn parse(bytes: &[u8]) -> Option<error::Result<Self>> {
+        match bytes.pread_with::<u32>(SETUP_HEADER_OFFSET + 0x11, scroll::LE) {
+            Ok(SETUP_HEADER_MAGIC) => {}
+            _ => return None,
+        }
+        // Older protocols have a shorter header, the rest of which belongs to the setup code
+        let mut header = [0u8; SIZEOF_SETUP_HEADER];
+        let available = bytes.len().saturating_sub(SETUP_HEADER_OFFSET);
+        let len = core::cmp::min(available, SIZEOF_SETUP_HEADER);
+        header[..len].copy_from_slice(&bytes[SETUP_HEADER_OFFSET..SETUP_HEADER_OFFSET + len]);
+        let mut header: SetupHeader = match header.pread_with(0, scroll::LE) {
+            Ok(header) => header,
+            Err(err) => return Some(Err(err.into())),
+        };
+        header.truncate_to_protocol();
+        Some(Ok(header))
+    }
+
+    /// Zeroes the fields not defined by the header's protocol version
+    fn truncate_to_protocol(&mut self) {
+        if self.version < 0x020f {
+            self.kernel_info_offset = 0;
+        }
+        if self.version < 0x020c {
+            self.xloadflags = 0;
+        }
+        if self.version < 0x020b {
+            self.handover_offset = 0;
+        }
+        if self.version < 0x020a {
+            self.min_alignment = 0;
+            self.pref_address = 0;
+            self.init_size = 0;
+        }
+        if self.version < 0x0209 {
+            self.setup_data = 0;
+        }
+        if self.version < 0x0208 {
+            self.payload_offset = 0;
+            self.payload_length = 0;
+        }
+        if self.version < 0x0207 {
+            self.hardware_subarch = 0;
+            self.hardware_subarch_data = 0;
+        }
+        if self.version < 0x0206 {
+            self.cmdline_size = 0;
+        }
+        if self.version < 0x0205 {
+            self.kernel_alignment = 0;
+            self.relocatable_kernel = 0;
+        }
+    }
+
+    /// The boot protocol version, as `(major, minor)`
+    pub fn protocol_version(&self) -> (u8, u8) {
+        ((self.version >> 8) as u8, self.version as u8)
+    }
+
+    /// The size of the real-mode setup code, including the boot sector
+    pub fn setup_size(&self) -> usize {
+        let setup_sects = match self.setup_sects {
+            0 => DEFAULT_SETUP_SECTS,
+            setup_sects => setup_sects,
+        };
+        (setup_sects as usize + 1) * SECTOR_SIZE
+    }
+
+    /// Whether the kernel has a 64-bit entry point
+    pub fn is_64(&self) -> bool {
+        self.xloadflags & XLF_KERNEL_64 != 0
+    }
+
+    /// Reads the kernel version string of `bytes`, e.g., `6.1.0-13-amd64 (debian-kernel@...) #1
+    /// SMP PREEMPT_DYNAMIC Debian 6.1.55-1 (2023-09-29)`
+    pub fn kernel_version<'a>(&self, bytes: &'a [u8]) -> Option<error::Result<&'a str>> {
+        if self.kernel_version == 0 {
+            return None;
+        }
+        let offset = KERNEL_VERSION_BASE + self.kernel_version as usize;
+        Some(bytes.pread::<&str>(offset).map_err(Into::into))
+    }
+
+    /// The protected-mode kernel of `bytes`, i.e., everything after the setup code
+    pub fn protected_mode_kernel<'a>(&self, bytes: &'a [u8]) -> error::Result<&'a [u8]> {
+        bytes.get(self.setup_size()..).ok_or_else(|| {
+            error::Error::Malformed(format!(
+                "Linux setup code size ({:#x}) exceeds the image size ({:#x})",
+                self.setup_size(),
+                bytes.len()
+            ))
+        })
+    }
+
+    /// The compressed kernel of `bytes`, if the boot protocol describes it (2.08 and later)
+    pub fn payload<'a>(&self, bytes: &'a [u8]) -> Option<error::Result<&'a [u8]>> {
+        if self.payload_length == 0 {
+            return None;
+        }
+        let payload = self.protected_mode_kernel(bytes).and_then(|kernel| {
+            let start = self.payload_offset as usize;
+            let end = start.checked_add(self.payload_length as usize);
+            end.and_then(|end| kernel.get(start..end)).ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "Linux payload ({:#x}, {:#x}) is out of bounds",
+                    self.payload_offset, self.payload_length
+                ))
+            })
+        });
+        Some(payload)
+    }
+
+    /// The format of the compressed kernel of `bytes`
+    pub fn payload_format(&self, bytes: &[u8]) -> Option<error::Result<PayloadFormat>> {
+        self.payload(bytes)
+            .map(|payload| payload.map(PayloadFormat::detect))
+    }
+}
+
+/// `ARM\x64`, the arm64 `Image` header signature
+pub const ARM64_IMAGE_MAGIC: u32 = 0x644d_5241;
+
+/// `flags`: the kernel is big-endian
+pub const ARM64_FLAG_BE: u64 = 0x1;
+/// `flags`: the mask of the kernel page size (1: 4K, 2: 16K, 3: 64K)
+pub const ARM64_FLAG_PAGE_SIZE_MASK: u64 = 0x6;
+
+/// The arm64 `Image` header
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, SizeWith)]
+pub struct Arm64ImageHeader {
+    /// Executable code, `MZ` followed by a branch for EFI stub kernels
+    pub code0: u32,
+    pub code1: u32,
+    /// The image load offset from the start of RAM
+    pub text_offset: u64,
+    /// The effective image size
+    pub image_size: u64,
+    pub flags: u64,
+    pub res2: u64,
+    pub res3: u64,
+    pub res4: u64,
+    pub magic: u32,
+    /// The offset of the PE header
+    pub res5: u32,
+}
+
+pub const SIZEOF_ARM64_IMAGE_HEADER: usize = 0x40;
+
+impl Arm64ImageHeader {
+    /// Parses the `Image` header of `bytes`, returning `None` if it has no `ARM\x64` signature
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        bytes
+            .pread_with::<Arm64ImageHeader>(0, scroll::LE)
+            .ok()
+            .filter(|header| header.magic == ARM64_IMAGE_MAGIC)
+    }
+
+    pub fn is_big_endian(&self) -> bool {
+        self.flags & ARM64_FLAG_BE != 0
+    }
+
+    /// The kernel page size, if specified
+    pub fn page_size(&self) -> Option<usize> {
+        match (self.flags & ARM64_FLAG_PAGE_SIZE_MASK) >> 1 {
+            1 => Some(0x1000),
+            2 => Some(0x4000),
+            3 => Some(0x10000),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> PE<'a> {
+    /// Parses the x86 setup header, if the image is a bzImage
+    pub fn linux_setup_header(&self) -> Option<error::Result<SetupHeader>> {
+        SetupHeader::parse(self.bytes)
+    }
+
+    /// Parses the arm64 `Image` header, if the image is an arm64 kernel
+    pub fn linux_arm64_header(&self) -> Option<Arm64ImageHeader> {
+        Arm64ImageHeader::parse(self.bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+
+    const VERSION: &[u8] = b"6.1.0-13-amd64 (debian-kernel@lists.debian.org) #1 SMP\0";
+    const PAYLOAD: &[u8] = b"\x28\xb5\x2f\xfdcompressed kernel";
+
+    /// A minimal bzImage: a boot sector and one setup sector holding the version string, then
+    /// the protected-mode kernel with its payload
+    fn bzimage(version: u16) -> Vec<u8> {
+        let header = SetupHeader {
+            setup_sects: 1,
+            boot_flag: BOOT_FLAG,
+            jump: 0x66eb,
+            header: SETUP_HEADER_MAGIC,
+            version,
+            kernel_version: 0x100,
+            loadflags: LOADED_HIGH | CAN_USE_HEAP,
+            relocatable_kernel: 1,
+            xloadflags: XLF_KERNEL_64 | XLF_EFI_HANDOVER_64,
+            cmdline_size: 0x7ff,
+            payload_offset: 0x10,
+            payload_length: PAYLOAD.len() as u32,
+            init_size: 0x100_0000,
+            handover_offset: 0x190,
+            ..Default::default()
+        };
+        let mut bytes = vec![0u8; 2 * SECTOR_SIZE + 0x10];
+        bytes[..2].copy_from_slice(b"MZ");
+        bytes
+            .pwrite_with(header, SETUP_HEADER_OFFSET, scroll::LE)
+            .unwrap();
+        bytes[0x300..0x300 + VERSION.len()].copy_from_slice(VERSION);
+        bytes.extend_from_slice(PAYLOAD);
+        bytes
+    }
+
+    #[test]
+    fn parse_setup_header() {
+        let bytes = bzimage(0x020f);
+        let header = SetupHeader::parse(&bytes).unwrap().unwrap();
+        assert_eq!(header.protocol_version(), (2, 15));
+        assert_eq!(header.boot_flag, BOOT_FLAG);
+        assert!(header.is_64());
+        assert_eq!(header.setup_size(), 0x400);
+        assert_eq!(
+            header.kernel_version(&bytes).unwrap().unwrap(),
+            "6.1.0-13-amd64 (debian-kernel@lists.debian.org) #1 SMP"
+        );
+        assert_eq!(header.payload(&bytes).unwrap().unwrap(), PAYLOAD);
+        assert_eq!(
+            header.payload_format(&bytes).unwrap().unwrap(),
+            PayloadFormat::Zstd
+        );
+        assert_eq!(header.handover_offset, 0x190);
+    }
+
+    #[test]
+    fn old_protocol() {
+        let bytes = bzimage(0x0206);
+        let header = SetupHeader::parse(&bytes).unwrap().unwrap();
+        assert_eq!(header.protocol_version(), (2, 6));
+        assert_eq!(header.cmdline_size, 0x7ff);
+        assert!(!header.is_64());
+        assert!(header.payload(&bytes).is_none());
+        assert_eq!(header.handover_offset, 0);
+
+        let mut bytes = bzimage(0x020f);
+        bytes.truncate(0x2f0);
+        let header = SetupHeader::parse(&bytes).unwrap().unwrap();
+        assert!(header.kernel_version(&bytes).unwrap().is_err());
+        assert!(header.payload(&bytes).unwrap().is_err());
+    }
+
+    #[test]
+    fn not_a_kernel() {
+        let pe = PE::parse(REALTEK_LAN).unwrap();
+        assert!(pe.linux_setup_header().is_none());
+        assert!(pe.linux_arm64_header().is_none());
+    }
+
+    #[test]
+    fn arm64_header() {
+        let header = Arm64ImageHeader {
+            code0: 0x9100_5a4d,
+            text_offset: 0,
+            image_size: 0x200_0000,
+            flags: 0xa,
+            magic: ARM64_IMAGE_MAGIC,
+            res5: 0x40,
+            ..Default::default()
+        };
+        let mut bytes = vec![0u8; SIZEOF_ARM64_IMAGE_HEADER];
+        bytes.pwrite_with(header, 0, scroll::LE).unwrap();
+        let header = Arm64ImageHeader::parse(&bytes).unwrap();
+        assert!(!header.is_big_endian());
+        assert_eq!(header.page_size(), Some(0x1000));
+        assert_eq!(header.res5, 0x40);
+        assert!(Arm64ImageHeader::parse(&bytes[..0x3c]).is_none());
+    }
+}
@@ -0,0 +1,81 @@
+//! TCG PE/COFF image measurement.
+//!
+//! Measured boot records `EV_EFI_BOOT_SERVICES_APPLICATION` and driver events with the digest of
+//! the PE/COFF image, as defined by the TCG EFI Platform Specification. The digest covers the
+//! same ranges as authenticode, but the image is not padded to a multiple of 8 bytes.
+
+use super::PE;
+
+/// A digest algorithm used to measure images, e.g., a wrapper around a SHA-256 implementation
+pub trait ImageHasher {
+    type Output;
+
+    /// Hashes `data`, following the data hashed so far
+    fn update(&mut self, data: &[u8]);
+
+    /// Returns the digest of the data hashed so far
+    fn finalize(self) -> Self::Output;
+}
+
+impl<'a> PE<'a> {
+    /// Computes the TCG PE/COFF image digest of the binary with `hasher`
+    pub fn measure<H: ImageHasher>(&self, mut hasher: H) -> H::Output {
+        for range in self.measured_ranges() {
+            hasher.update(range);
+        }
+        hasher.finalize()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+
+    /// Records the hashed data instead of digesting it
+    #[derive(Default)]
+    pub(crate) struct Recorder(pub(crate) Vec<u8>);
+
+    impl ImageHasher for Recorder {
+        type Output = Vec<u8>;
+
+        fn update(&mut self, data: &[u8]) {
+            self.0.extend_from_slice(data);
+        }
+
+        fn finalize(self) -> Vec<u8> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn measure_pe() {
+        let pe = PE::parse(REALTEK_LAN).unwrap();
+        let measured = pe.measure(Recorder::default());
+        let authenticode = pe
+            .authenticode_ranges()
+            .flatten()
+            .copied()
+            .collect::<Vec<u8>>();
+        assert!(authenticode.starts_with(&measured));
+        assert_eq!(
+            authenticode.len() - measured.len(),
+            (8 - REALTEK_LAN.len() % 8) % 8
+        );
+
+        // Everything but the checksum, the certificate table entry and the certificate table
+        let certificate_table = pe
+            .header
+            .optional_header
+            .unwrap()
+            .data_directories
+            .get_certificate_table()
+            .unwrap();
+        assert_eq!(
+            measured.len(),
+            REALTEK_LAN.len() - 4 - 8 - certificate_table.size as usize
+        );
+    }
+}
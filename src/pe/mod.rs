@@ -4,8 +4,10 @@
 // TODO: panics with unwrap on None for apisetschema.dll, fhuxgraphics.dll and some others
 
 use alloc::vec::Vec;
+use core::cmp;
 use scroll::Pread;
 
+pub mod anomalies;
 pub mod authenticode;
 pub mod certificate_table;
 pub mod characteristic;
@@ -14,16 +16,21 @@ pub mod debug;
 pub mod exception;
 pub mod export;
 pub mod header;
+pub mod linux;
+pub mod measure;
 pub mod import;
 pub mod optional_header;
 pub mod options;
 pub mod relocation;
+pub mod sbat;
 pub mod section_table;
 pub mod symbol;
+pub mod uki;
 pub mod utils;
 
 use crate::container;
 use crate::error;
+use crate::overlay::Overlay;
 use crate::strtab;
 
 use log::{debug, warn};
@@ -35,7 +42,7 @@ use self::relocation::BaseRelocations;
 /// An analyzed PE32/PE32+ binary
 pub struct PE<'a> {
     /// Underlying bytes
-    bytes: &'a [u8],
+    pub(crate) bytes: &'a [u8],
     authenticode_excluded_sections: Option<authenticode::ExcludedSections>,
     /// The PE header
     pub header: header::Header,
@@ -313,6 +320,52 @@ impl<'a> PE<'a> {
     pub fn subsystem(&self) -> Option<u16> {
         Some(self.header.optional_header?.windows_fields.subsystem)
     }
+
+    /// Returns the data appended after the end of the image, if there is any.
+    ///
+    /// The end of the image is the furthest section end as read by the loader (see
+    /// [`utils::section_end_offset`]), and never precedes the headers. The attribute certificate
+    /// table is never part of the overlay: if it directly follows the image, the overlay begins
+    /// after it, and if it terminates the file, the overlay stops right before it.
+    pub fn overlay(&self) -> Option<Overlay<'a>> {
+        let file_end = self.bytes.len();
+        let section_table_end = self.header.dos_header.pe_pointer as usize
+            + header::SIZEOF_PE_MAGIC
+            + header::SIZEOF_COFF_HEADER
+            + self.header.coff_header.size_of_optional_header as usize
+            + self.sections.len() * section_table::SIZEOF_SECTION_TABLE;
+        let (file_alignment, size_of_headers, certificate_table) = match self.header.optional_header
+        {
+            Some(optional_header) => (
+                optional_header.windows_fields.file_alignment,
+                optional_header.windows_fields.size_of_headers as usize,
+                *optional_header.data_directories.get_certificate_table(),
+            ),
+            None => (0, 0, None),
+        };
+
+        let mut image_end = self
+            .sections
+            .iter()
+            .filter(|section| section.size_of_raw_data != 0)
+            .map(|section| utils::section_end_offset(section, file_alignment))
+            .fold(cmp::max(section_table_end, size_of_headers), cmp::max);
+        let mut overlay_end = file_end;
+
+        if let Some(certificate_table) = certificate_table.filter(|table| table.size != 0) {
+            // the certificate table's "virtual address" is a file offset
+            let start = certificate_table.virtual_address as usize;
+            let end = start.saturating_add(certificate_table.size as usize);
+            // certificates are quadword aligned, so allow for padding after the last section
+            if start <= (image_end + 7) & !7 && end > image_end {
+                image_end = end;
+            } else if start > image_end && end >= file_end {
+                overlay_end = start;
+            }
+        }
+
+        Overlay::from_range(self.bytes, image_end, overlay_end)
+    }
 }
 
 /// An analyzed COFF object
@@ -441,4 +494,20 @@ mod tests {
             panic!("must not parse PE with invalid PE header");
         }
     }
+
+    #[test]
+    fn overlay_around_certificate_table() {
+        let file = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+        let pe = PE::parse(&file[..]).unwrap();
+        assert!(pe.overlay().is_none());
+
+        // signed, then appended to
+        let mut appended = file.to_vec();
+        appended.extend_from_slice(b"PAYLOAD");
+        let pe = PE::parse(&appended).unwrap();
+        let overlay = pe.overlay().expect("overlay after the certificate table");
+        assert_eq!(overlay.offset, file.len());
+        assert_eq!(overlay.size, 7);
+        assert_eq!(overlay.bytes, b"PAYLOAD");
+    }
 }
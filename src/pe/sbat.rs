@@ -0,0 +1,157 @@
+//! Secure Boot Advanced Targeting (SBAT) metadata.
+//!
+//! Shim, GRUB, systemd-boot and Unified Kernel Images carry a `.sbat` section: a CSV file whose
+//! records name a component and its generation number. Revocations (`SbatLevel`) use the same
+//! format, and revoke every component whose generation is lower than the listed one.
+//!
+//! See <https://github.com/rhboot/shim/blob/main/SBAT.md>.
+
+use alloc::vec::Vec;
+
+use crate::error;
+
+use super::PE;
+
+/// The name of the section holding the SBAT metadata
+pub const SBAT_SECTION_NAME: &str = ".sbat";
+
+/// A record of an SBAT CSV file
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct SbatEntry<'a> {
+    pub component_name: &'a str,
+    pub component_generation: u32,
+    /// The remaining fields are absent from revocation lists
+    pub vendor_name: Option<&'a str>,
+    pub vendor_package_name: Option<&'a str>,
+    pub vendor_version: Option<&'a str>,
+    pub vendor_url: Option<&'a str>,
+}
+
+impl<'a> SbatEntry<'a> {
+    /// Parses a single CSV record
+    pub fn parse(line: &'a str) -> error::Result<Self> {
+        let mut fields = line.split(',').map(str::trim);
+        let component_name = fields.next().filter(|name| !name.is_empty());
+        let component_generation = fields.next().and_then(|field| field.parse().ok());
+        match (component_name, component_generation) {
+            (Some(component_name), Some(component_generation)) => Ok(SbatEntry {
+                component_name,
+                component_generation,
+                vendor_name: fields.next(),
+                vendor_package_name: fields.next(),
+                vendor_version: fields.next(),
+                vendor_url: fields.next(),
+            }),
+            _ => Err(error::Error::Malformed(format!(
+                "SBAT record is malformed ({:?})",
+                line
+            ))),
+        }
+    }
+}
+
+/// The records of an SBAT CSV file, e.g., the `.sbat` section of an image or a revocation list
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Sbat<'a> {
+    pub entries: Vec<SbatEntry<'a>>,
+}
+
+impl<'a> Sbat<'a> {
+    /// Parses the CSV in `bytes`, up to the first NUL byte, skipping empty lines
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        let end = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(bytes.len());
+        let text = core::str::from_utf8(&bytes[..end])
+            .map_err(|_| error::Error::Malformed("SBAT data is not valid UTF-8".into()))?;
+        let entries = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(SbatEntry::parse)
+            .collect::<error::Result<_>>()?;
+        Ok(Sbat { entries })
+    }
+
+    /// The generation of the component named `component_name`, the highest one if it is
+    /// listed several times
+    pub fn generation(&self, component_name: &str) -> Option<u32> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.component_name == component_name)
+            .map(|entry| entry.component_generation)
+            .max()
+    }
+
+    /// Returns the entries revoked by the revocation list `revocations`, i.e., those whose
+    /// generation is lower than the one it lists for their component
+    pub fn revoked_by(&self, revocations: &Sbat) -> Vec<&SbatEntry<'a>> {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    revocations.generation(entry.component_name),
+                    Some(generation) if entry.component_generation < generation
+                )
+            })
+            .collect()
+    }
+}
+
+impl<'a> PE<'a> {
+    /// Parses the SBAT metadata of the image, if it has a `.sbat` section
+    pub fn sbat(&self) -> Option<error::Result<Sbat<'a>>> {
+        super::uki::section_contents(self, SBAT_SECTION_NAME).map(Sbat::parse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SBAT: &[u8] =
+        b"sbat,1,SBAT Version,sbat,1,https://github.com/rhboot/shim/blob/main/SBAT.md\n\
+shim,3,UEFI shim,shim,1,https://github.com/rhboot/shim\n\
+grub,3,Free Software Foundation,grub,2.06,https://www.gnu.org/software/grub/\n\
+grub.debian,4,Debian,grub2,2.06-13,https://tracker.debian.org/pkg/grub2\n\0\0\0";
+
+    #[test]
+    fn parse_sbat() {
+        let sbat = Sbat::parse(SBAT).unwrap();
+        assert_eq!(sbat.entries.len(), 4);
+        assert_eq!(
+            sbat.entries[2],
+            SbatEntry {
+                component_name: "grub",
+                component_generation: 3,
+                vendor_name: Some("Free Software Foundation"),
+                vendor_package_name: Some("grub"),
+                vendor_version: Some("2.06"),
+                vendor_url: Some("https://www.gnu.org/software/grub/"),
+            }
+        );
+        assert_eq!(sbat.generation("grub.debian"), Some(4));
+        assert_eq!(sbat.generation("systemd"), None);
+    }
+
+    #[test]
+    fn revocations() {
+        let sbat = Sbat::parse(SBAT).unwrap();
+        let level = Sbat::parse(b"sbat,1,2023012900\nshim,2\ngrub,3\ngrub.debian,4\n").unwrap();
+        assert_eq!(level.entries[1].vendor_name, None);
+        assert!(sbat.revoked_by(&level).is_empty());
+
+        let level = Sbat::parse(b"sbat,1,2024010900\nshim,4\ngrub,4\n").unwrap();
+        let revoked = sbat.revoked_by(&level);
+        assert_eq!(revoked.len(), 2);
+        assert_eq!(revoked[0].component_name, "shim");
+        assert_eq!(revoked[1].component_name, "grub");
+    }
+
+    #[test]
+    fn malformed_sbat() {
+        assert!(Sbat::parse(b"sbat,one\n").is_err());
+        assert!(Sbat::parse(b",1\n").is_err());
+        assert!(Sbat::parse(b"sbat,1,\xff\n").is_err());
+    }
+}
@@ -1,8 +1,9 @@
 use crate::error::{self, Error};
 use alloc::string::{String, ToString};
+use core::fmt;
 use scroll::{ctx, Pread, Pwrite};
 
-use crate::pe::utils::PESectionTable;
+use crate::pe::utils::{self, PESectionTable};
 
 #[repr(C)]
 #[derive(Debug, PartialEq, Clone, Default)]
@@ -285,6 +286,115 @@ pub const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
 /// The section can be written to.
 pub const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
 
+const SECTION_CHARACTERISTICS: [(u32, &str); 20] = [
+    (IMAGE_SCN_TYPE_NO_PAD, "IMAGE_SCN_TYPE_NO_PAD"),
+    (IMAGE_SCN_CNT_CODE, "IMAGE_SCN_CNT_CODE"),
+    (
+        IMAGE_SCN_CNT_INITIALIZED_DATA,
+        "IMAGE_SCN_CNT_INITIALIZED_DATA",
+    ),
+    (
+        IMAGE_SCN_CNT_UNINITIALIZED_DATA,
+        "IMAGE_SCN_CNT_UNINITIALIZED_DATA",
+    ),
+    (IMAGE_SCN_LNK_OTHER, "IMAGE_SCN_LNK_OTHER"),
+    (IMAGE_SCN_LNK_INFO, "IMAGE_SCN_LNK_INFO"),
+    (IMAGE_SCN_LNK_REMOVE, "IMAGE_SCN_LNK_REMOVE"),
+    (IMAGE_SCN_LNK_COMDAT, "IMAGE_SCN_LNK_COMDAT"),
+    (IMAGE_SCN_GPREL, "IMAGE_SCN_GPREL"),
+    (IMAGE_SCN_MEM_PURGEABLE, "IMAGE_SCN_MEM_PURGEABLE"),
+    (IMAGE_SCN_MEM_LOCKED, "IMAGE_SCN_MEM_LOCKED"),
+    (IMAGE_SCN_MEM_PRELOAD, "IMAGE_SCN_MEM_PRELOAD"),
+    (IMAGE_SCN_LNK_NRELOC_OVFL, "IMAGE_SCN_LNK_NRELOC_OVFL"),
+    (IMAGE_SCN_MEM_DISCARDABLE, "IMAGE_SCN_MEM_DISCARDABLE"),
+    (IMAGE_SCN_MEM_NOT_CACHED, "IMAGE_SCN_MEM_NOT_CACHED"),
+    (IMAGE_SCN_MEM_NOT_PAGED, "IMAGE_SCN_MEM_NOT_PAGED"),
+    (IMAGE_SCN_MEM_SHARED, "IMAGE_SCN_MEM_SHARED"),
+    (IMAGE_SCN_MEM_EXECUTE, "IMAGE_SCN_MEM_EXECUTE"),
+    (IMAGE_SCN_MEM_READ, "IMAGE_SCN_MEM_READ"),
+    (IMAGE_SCN_MEM_WRITE, "IMAGE_SCN_MEM_WRITE"),
+];
+
+/// The `Characteristics` field of a section header, a set of `IMAGE_SCN_*` flags with the
+/// section alignment encoded in the `IMAGE_SCN_ALIGN_MASK` nibble
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+pub struct SectionCharacteristics(pub u32);
+
+impl SectionCharacteristics {
+    /// The raw value of the flags
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether every flag in `flags` is set
+    pub fn contains(self, flags: u32) -> bool {
+        self.0 & flags == flags
+    }
+
+    pub fn is_code(self) -> bool {
+        self.contains(IMAGE_SCN_CNT_CODE)
+    }
+
+    pub fn is_readable(self) -> bool {
+        self.contains(IMAGE_SCN_MEM_READ)
+    }
+
+    pub fn is_writable(self) -> bool {
+        self.contains(IMAGE_SCN_MEM_WRITE)
+    }
+
+    pub fn is_executable(self) -> bool {
+        self.contains(IMAGE_SCN_MEM_EXECUTE)
+    }
+
+    /// Decodes the `IMAGE_SCN_ALIGN_*` nibble into an alignment in bytes.
+    ///
+    /// Returns `None` if no alignment is specified, or if the nibble holds one of the two
+    /// undefined values.
+    pub fn alignment(self) -> Option<u32> {
+        match (self.0 & IMAGE_SCN_ALIGN_MASK) >> 20 {
+            nibble @ 1..=14 => Some(1 << (nibble - 1)),
+            _ => None,
+        }
+    }
+
+    /// Iterates over the names of the known flags that are set, excluding the alignment
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        SECTION_CHARACTERISTICS
+            .iter()
+            .filter(move |(flag, _)| self.0 & flag == *flag)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl From<u32> for SectionCharacteristics {
+    fn from(bits: u32) -> Self {
+        SectionCharacteristics(bits)
+    }
+}
+
+impl From<SectionCharacteristics> for u32 {
+    fn from(characteristics: SectionCharacteristics) -> Self {
+        characteristics.0
+    }
+}
+
+impl fmt::Display for SectionCharacteristics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = self.0 & !IMAGE_SCN_ALIGN_MASK;
+        match self.alignment() {
+            Some(alignment) => {
+                if flags != 0 {
+                    utils::fmt_flags(f, flags, &SECTION_CHARACTERISTICS)?;
+                    f.write_str(" | ")?;
+                }
+                write!(f, "IMAGE_SCN_ALIGN_{}BYTES", alignment)
+            }
+            None => utils::fmt_flags(f, self.0, &SECTION_CHARACTERISTICS),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -309,4 +419,29 @@ mod tests {
         #[cfg(target_pointer_width = "64")]
         assert!(section.set_name_offset(0x1_000_000_000).is_err());
     }
+
+    #[test]
+    fn section_characteristics() {
+        let characteristics = SectionCharacteristics(
+            IMAGE_SCN_CNT_CODE
+                | IMAGE_SCN_MEM_EXECUTE
+                | IMAGE_SCN_MEM_READ
+                | IMAGE_SCN_ALIGN_16BYTES,
+        );
+        assert!(characteristics.is_executable());
+        assert!(!characteristics.is_writable());
+        assert_eq!(characteristics.alignment(), Some(16));
+        assert_eq!(
+            characteristics.to_string(),
+            "IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ | IMAGE_SCN_ALIGN_16BYTES"
+        );
+        assert_eq!(
+            SectionCharacteristics(IMAGE_SCN_ALIGN_8192BYTES).alignment(),
+            Some(8192)
+        );
+        assert_eq!(
+            SectionCharacteristics(IMAGE_SCN_ALIGN_MASK).alignment(),
+            None
+        );
+    }
 }
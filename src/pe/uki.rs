@@ -0,0 +1,239 @@
+//! Unified Kernel Images (UKI).
+//!
+//! A UKI is an EFI stub (usually systemd-stub) with the parts needed to boot Linux stored in
+//! dedicated sections: the kernel itself (`.linux`), an initrd, the command line, the
+//! `os-release` file, a splash image, a device tree and the TPM PCR signatures and public key.
+//!
+//! See the UAPI Group "Unified Kernel Image" specification.
+
+use alloc::vec::Vec;
+use core::cmp;
+
+use crate::error;
+
+use super::sbat::{Sbat, SBAT_SECTION_NAME};
+use super::utils::PESectionTable;
+use super::PE;
+
+pub const LINUX_SECTION_NAME: &str = ".linux";
+pub const INITRD_SECTION_NAME: &str = ".initrd";
+pub const UCODE_SECTION_NAME: &str = ".ucode";
+pub const CMDLINE_SECTION_NAME: &str = ".cmdline";
+pub const OSREL_SECTION_NAME: &str = ".osrel";
+pub const UNAME_SECTION_NAME: &str = ".uname";
+pub const SPLASH_SECTION_NAME: &str = ".splash";
+pub const DTB_SECTION_NAME: &str = ".dtb";
+pub const PCRSIG_SECTION_NAME: &str = ".pcrsig";
+pub const PCRPKEY_SECTION_NAME: &str = ".pcrpkey";
+
+/// Returns the contents of the first section named `name` as mapped by the loader, i.e.,
+/// `VirtualSize` bytes, capped to the raw data present in the file
+pub(super) fn section_contents<'a>(pe: &PE<'a>, name: &str) -> Option<&'a [u8]> {
+    let section = pe
+        .sections
+        .iter()
+        .find(|section| section.name().ok() == Some(name))?;
+    let mut size = section.size_of_raw_data as usize;
+    if section.virtual_size != 0 {
+        size = cmp::min(size, section.virtual_size as usize);
+    }
+    let start = section.pointer_to_raw_data as usize;
+    pe.bytes.get(start..start.checked_add(size)?)
+}
+
+/// Returns the text of a section, without its trailing NUL bytes
+fn text(data: &[u8]) -> error::Result<&str> {
+    let end = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(data.len());
+    core::str::from_utf8(&data[..end])
+        .map_err(|_| error::Error::Malformed("UKI section text is not valid UTF-8".into()))
+}
+
+/// The parts of a Unified Kernel Image, each borrowed from the section holding it
+#[derive(Debug, Clone, Default)]
+pub struct Uki<'a> {
+    /// The kernel, itself an EFI stub PE image
+    pub linux: &'a [u8],
+    pub initrd: Option<&'a [u8]>,
+    pub ucode: Option<&'a [u8]>,
+    pub cmdline: Option<&'a str>,
+    /// The `os-release` file of the image
+    pub osrel: Option<&'a str>,
+    /// The kernel release, as `uname -r` would report it
+    pub uname: Option<&'a str>,
+    pub splash: Option<&'a [u8]>,
+    pub dtb: Option<&'a [u8]>,
+    /// The JSON signatures of the expected TPM PCR 11 values
+    pub pcrsig: Option<&'a str>,
+    /// The PEM public key matching `pcrsig`
+    pub pcrpkey: Option<&'a [u8]>,
+    pub sbat: Option<Sbat<'a>>,
+}
+
+impl<'a> Uki<'a> {
+    /// Reads the UKI parts of `pe`, returning `None` if it has no `.linux` section
+    pub fn from_pe(pe: &PE<'a>) -> Option<error::Result<Self>> {
+        let linux = section_contents(pe, LINUX_SECTION_NAME)?;
+        Some(Self::parse_sections(pe, linux))
+    }
+
+    fn parse_sections(pe: &PE<'a>, linux: &'a [u8]) -> error::Result<Self> {
+        let section = |name| section_contents(pe, name);
+        let text_section = |name| section(name).map(text).transpose();
+        Ok(Uki {
+            linux,
+            initrd: section(INITRD_SECTION_NAME),
+            ucode: section(UCODE_SECTION_NAME),
+            cmdline: text_section(CMDLINE_SECTION_NAME)?,
+            osrel: text_section(OSREL_SECTION_NAME)?,
+            uname: text_section(UNAME_SECTION_NAME)?,
+            splash: section(SPLASH_SECTION_NAME),
+            dtb: section(DTB_SECTION_NAME),
+            pcrsig: text_section(PCRSIG_SECTION_NAME)?,
+            pcrpkey: section(PCRPKEY_SECTION_NAME),
+            sbat: section(SBAT_SECTION_NAME).map(Sbat::parse).transpose()?,
+        })
+    }
+
+    /// Parses the embedded kernel
+    pub fn kernel(&self) -> error::Result<PE<'a>> {
+        PE::parse(self.linux)
+    }
+
+    /// The key/value pairs of the `os-release` file, with the values unquoted.
+    ///
+    /// Comments and lines without an `=` are skipped; escape sequences within quoted values are
+    /// left as-is.
+    pub fn os_release(&self) -> Vec<(&'a str, &'a str)> {
+        let osrel = match self.osrel {
+            Some(osrel) => osrel,
+            None => return Vec::new(),
+        };
+        osrel
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                let value = value.trim();
+                let unquoted = ['"', '\''].iter().find_map(|&quote| {
+                    value
+                        .strip_prefix(quote)
+                        .and_then(|value| value.strip_suffix(quote))
+                });
+                Some((key.trim(), unquoted.unwrap_or(value)))
+            })
+            .collect()
+    }
+
+    /// The value of `key` in the `os-release` file
+    pub fn os_release_value(&self, key: &str) -> Option<&'a str> {
+        self.os_release()
+            .into_iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value)
+    }
+}
+
+impl<'a> PE<'a> {
+    /// Reads the parts of a Unified Kernel Image, if the image is one
+    pub fn uki(&self) -> Option<error::Result<Uki<'a>>> {
+        Uki::from_pe(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pe::header::{SIZEOF_COFF_HEADER, SIZEOF_PE_MAGIC};
+    use crate::pe::section_table::{SectionTable, SIZEOF_SECTION_TABLE};
+    use scroll::Pwrite;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+
+    /// Replaces the first sections of a copy of `base` by `sections`, whose contents are
+    /// appended to the file
+    fn replace_sections(base: &[u8], sections: &[(&str, &[u8])]) -> Vec<u8> {
+        let pe = PE::parse(base).unwrap();
+        assert!(sections.len() < pe.sections.len());
+        let mut offset = pe.header.dos_header.pe_pointer as usize
+            + SIZEOF_PE_MAGIC
+            + SIZEOF_COFF_HEADER
+            + pe.header.coff_header.size_of_optional_header as usize;
+
+        let mut bytes = base.to_vec();
+        let mut virtual_address = pe
+            .sections
+            .iter()
+            .map(|section| section.virtual_address + section.virtual_size)
+            .max()
+            .unwrap();
+        for (name, data) in sections {
+            virtual_address = (virtual_address + 0xfff) & !0xfff;
+            bytes.resize((bytes.len() + 0x1ff) & !0x1ff, 0);
+            let mut section = SectionTable {
+                virtual_size: data.len() as u32,
+                virtual_address,
+                size_of_raw_data: ((data.len() + 0x1ff) & !0x1ff) as u32,
+                pointer_to_raw_data: bytes.len() as u32,
+                ..Default::default()
+            };
+            section.name[..name.len()].copy_from_slice(name.as_bytes());
+            bytes.pwrite_with(section, offset, scroll::LE).unwrap();
+            bytes.extend_from_slice(data);
+            offset += SIZEOF_SECTION_TABLE;
+            virtual_address += data.len() as u32;
+        }
+        bytes.resize((bytes.len() + 0x1ff) & !0x1ff, 0);
+        bytes
+    }
+
+    #[test]
+    fn parse_uki() {
+        let osrel = b"NAME=\"Debian GNU/Linux\"\nID=debian\n# comment\nVERSION_ID='12'\n";
+        let sbat = b"sbat,1,SBAT Version,sbat,1,https://github.com/rhboot/shim/blob/main/SBAT.md\n\
+systemd-stub,1,The systemd Developers,systemd,254,https://systemd.io/\n";
+        let bytes = replace_sections(
+            REALTEK_LAN,
+            &[
+                (".osrel", osrel),
+                (".cmdline", b"root=/dev/sda1 quiet\0"),
+                (".sbat", sbat),
+                (".linux", REALTEK_LAN),
+            ],
+        );
+        let pe = PE::parse(&bytes).unwrap();
+        let uki = pe.uki().unwrap().unwrap();
+        assert_eq!(uki.cmdline, Some("root=/dev/sda1 quiet"));
+        assert_eq!(uki.uname, None);
+        assert_eq!(uki.initrd, None);
+        assert_eq!(
+            uki.os_release(),
+            [
+                ("NAME", "Debian GNU/Linux"),
+                ("ID", "debian"),
+                ("VERSION_ID", "12")
+            ]
+        );
+        assert_eq!(uki.os_release_value("ID"), Some("debian"));
+        assert_eq!(
+            uki.sbat.as_ref().unwrap().generation("systemd-stub"),
+            Some(1)
+        );
+        assert_eq!(pe.sbat().unwrap().unwrap().entries.len(), 2);
+
+        assert_eq!(uki.linux, REALTEK_LAN);
+        let kernel = uki.kernel().unwrap();
+        assert_eq!(kernel.sections.len(), 5);
+        assert!(kernel.uki().is_none());
+    }
+
+    #[test]
+    fn not_a_uki() {
+        let pe = PE::parse(REALTEK_LAN).unwrap();
+        assert!(pe.uki().is_none());
+        assert!(pe.sbat().is_none());
+    }
+}
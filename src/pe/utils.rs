@@ -87,6 +87,16 @@ fn section_read_size<T: PESectionTable>(section: &T, file_alignment: u32) -> usi
     }
 }
 
+/// Returns the file offset one past the last byte of `section` that the loader reads from disk.
+///
+/// This uses the same rounding rules as [`find_offset`], which makes it suitable for locating
+/// the end of the image (and therefore the start of any overlay).
+pub fn section_end_offset<T: PESectionTable>(section: &T, file_alignment: u32) -> usize {
+    let file_alignment = cmp::max(file_alignment, 1);
+    aligned_pointer_to_raw_data(section.pointer_to_raw_data() as usize)
+        .saturating_add(section_read_size(section, file_alignment))
+}
+
 fn rva2offset<T: PESectionTable>(rva: usize, section: &T) -> usize {
     (rva - section.virtual_address() as usize)
         + aligned_pointer_to_raw_data(section.pointer_to_raw_data() as usize)
@@ -228,6 +238,34 @@ where
     Ok(result)
 }
 
+/// Writes the names of the `flags` set in `bits`, separated by ` | `, followed by any remaining
+/// unknown bits in hexadecimal.
+pub(crate) fn fmt_flags(
+    f: &mut core::fmt::Formatter,
+    bits: u32,
+    flags: &[(u32, &str)],
+) -> core::fmt::Result {
+    let mut remaining = bits;
+    let mut first = true;
+    for &(flag, name) in flags {
+        if flag != 0 && bits & flag == flag {
+            if !first {
+                f.write_str(" | ")?;
+            }
+            f.write_str(name)?;
+            remaining &= !flag;
+            first = false;
+        }
+    }
+    if remaining != 0 || first {
+        if !first {
+            f.write_str(" | ")?;
+        }
+        write!(f, "{:#x}", remaining)?;
+    }
+    Ok(())
+}
+
 pub(crate) fn pad(length: usize, alignment: Option<usize>) -> Option<Vec<u8>> {
     match alignment {
         Some(alignment) => {
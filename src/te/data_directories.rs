@@ -13,6 +13,13 @@ pub struct DataDirectories {
 }
 
 impl DataDirectories {
+    pub fn new(base_relocation: Option<DataDirectory>, debug: Option<DataDirectory>) -> Self {
+        Self {
+            base_relocation,
+            debug,
+        }
+    }
+
     fn parse_single(
         bytes: &[u8],
         offset: &mut usize,
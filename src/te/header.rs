@@ -1,74 +1,82 @@
 use crate::error;
 use scroll::{ctx, Pread, Pwrite, SizeWith};
 
+use crate::pe::header as pe;
 use crate::te::data_directories::{DataDirectories, SIZEOF_DATA_DIRECTORIES};
 use crate::te::section_table;
 
+pub use crate::pe::header::{Machine, Subsystem};
+
 pub const SIZEOF_TE_HEADER: usize = 24 + SIZEOF_DATA_DIRECTORIES;
 pub const TE_MAGIC: u16 = 0x5a56;
 
-pub const TE_SUBSYSTEM_UNKNOWN: u8 = 0;
-pub const TE_SUBSYSTEM_NATIVE: u8 = 1;
-pub const TE_SUBSYSTEM_WINDOWS_GUI: u8 = 2;
-pub const TE_SUBSYSTEM_WINDOWS_CUI: u8 = 3;
-pub const TE_SUBSYSTEM_POSIX_CUI: u8 = 7;
-pub const TE_SUBSYSTEM_WINDOWS_CE_GUI: u8 = 9;
-pub const TE_SUBSYSTEM_EFI_APPLICATION: u8 = 10;
-pub const TE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER: u8 = 11;
-pub const TE_SUBSYSTEM_EFI_RUNTIME_DRIVER: u8 = 12;
-pub const TE_SUBSYSTEM_EFI_ROM: u8 = 13;
-pub const TE_SUBSYSTEM_XBOX: u8 = 14;
-pub const TE_SUBSYSTEM_WINDOWS_BOOT_APPLICATION: u8 = 16;
+pub const TE_SUBSYSTEM_UNKNOWN: u8 = pe::PE_SUBSYSTEM_UNKNOWN as u8;
+pub const TE_SUBSYSTEM_NATIVE: u8 = pe::PE_SUBSYSTEM_NATIVE as u8;
+pub const TE_SUBSYSTEM_WINDOWS_GUI: u8 = pe::PE_SUBSYSTEM_WINDOWS_GUI as u8;
+pub const TE_SUBSYSTEM_WINDOWS_CUI: u8 = pe::PE_SUBSYSTEM_WINDOWS_CUI as u8;
+pub const TE_SUBSYSTEM_POSIX_CUI: u8 = pe::PE_SUBSYSTEM_POSIX_CUI as u8;
+pub const TE_SUBSYSTEM_WINDOWS_CE_GUI: u8 = pe::PE_SUBSYSTEM_WINDOWS_CE_GUI as u8;
+pub const TE_SUBSYSTEM_EFI_APPLICATION: u8 = pe::PE_SUBSYSTEM_EFI_APPLICATION as u8;
+pub const TE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER: u8 = pe::PE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER as u8;
+pub const TE_SUBSYSTEM_EFI_RUNTIME_DRIVER: u8 = pe::PE_SUBSYSTEM_EFI_RUNTIME_DRIVER as u8;
+pub const TE_SUBSYSTEM_EFI_ROM: u8 = pe::PE_SUBSYSTEM_EFI_ROM as u8;
+pub const TE_SUBSYSTEM_XBOX: u8 = pe::PE_SUBSYSTEM_XBOX as u8;
+pub const TE_SUBSYSTEM_WINDOWS_BOOT_APPLICATION: u8 =
+    pe::PE_SUBSYSTEM_WINDOWS_BOOT_APPLICATION as u8;
 
-pub const TE_MACHINE_UNKNOWN: u16 = 0x0;
+pub const TE_MACHINE_UNKNOWN: u16 = pe::COFF_MACHINE_UNKNOWN;
 /// Matsushita AM33
-pub const TE_MACHINE_AM33: u16 = 0x1d3;
+pub const TE_MACHINE_AM33: u16 = pe::COFF_MACHINE_AM33;
 /// x64
-pub const TE_MACHINE_X86_64: u16 = 0x8664;
+pub const TE_MACHINE_X86_64: u16 = pe::COFF_MACHINE_X86_64;
 /// ARM little endian
-pub const TE_MACHINE_ARM: u16 = 0x1c0;
+pub const TE_MACHINE_ARM: u16 = pe::COFF_MACHINE_ARM;
 /// ARM64 little endian
-pub const TE_MACHINE_ARM64: u16 = 0xaa64;
+pub const TE_MACHINE_ARM64: u16 = pe::COFF_MACHINE_ARM64;
 /// ARM Thumb-2 little endian
-pub const TE_MACHINE_ARMNT: u16 = 0x1c4;
+pub const TE_MACHINE_ARMNT: u16 = pe::COFF_MACHINE_ARMNT;
 /// EFI byte code
-pub const TE_MACHINE_EBC: u16 = 0xebc;
+pub const TE_MACHINE_EBC: u16 = pe::COFF_MACHINE_EBC;
 /// Intel 386 or later processors and compatible processors
-pub const TE_MACHINE_X86: u16 = 0x14c;
+pub const TE_MACHINE_X86: u16 = pe::COFF_MACHINE_X86;
 /// Intel Itanium processor family
-pub const TE_MACHINE_IA64: u16 = 0x200;
+pub const TE_MACHINE_IA64: u16 = pe::COFF_MACHINE_IA64;
 /// Mitsubishi M32R little endian
-pub const TE_MACHINE_M32R: u16 = 0x9041;
+pub const TE_MACHINE_M32R: u16 = pe::COFF_MACHINE_M32R;
 /// MIPS16
-pub const TE_MACHINE_MIPS16: u16 = 0x266;
+pub const TE_MACHINE_MIPS16: u16 = pe::COFF_MACHINE_MIPS16;
 /// MIPS with FPU
-pub const TE_MACHINE_MIPSFPU: u16 = 0x366;
+pub const TE_MACHINE_MIPSFPU: u16 = pe::COFF_MACHINE_MIPSFPU;
 /// MIPS16 with FPU
-pub const TE_MACHINE_MIPSFPU16: u16 = 0x466;
+pub const TE_MACHINE_MIPSFPU16: u16 = pe::COFF_MACHINE_MIPSFPU16;
 /// Power PC little endian
-pub const TE_MACHINE_POWERPC: u16 = 0x1f0;
+pub const TE_MACHINE_POWERPC: u16 = pe::COFF_MACHINE_POWERPC;
 /// Power PC with floating point support
-pub const TE_MACHINE_POWERPCFP: u16 = 0x1f1;
+pub const TE_MACHINE_POWERPCFP: u16 = pe::COFF_MACHINE_POWERPCFP;
 /// MIPS little endian
-pub const TE_MACHINE_R4000: u16 = 0x166;
+pub const TE_MACHINE_R4000: u16 = pe::COFF_MACHINE_R4000;
 /// RISC-V 32-bit address space
-pub const TE_MACHINE_RISCV32: u16 = 0x5032;
+pub const TE_MACHINE_RISCV32: u16 = pe::COFF_MACHINE_RISCV32;
 /// RISC-V 64-bit address space
-pub const TE_MACHINE_RISCV64: u16 = 0x5064;
+pub const TE_MACHINE_RISCV64: u16 = pe::COFF_MACHINE_RISCV64;
 /// RISC-V 128-bit address space
-pub const TE_MACHINE_RISCV128: u16 = 0x5128;
+pub const TE_MACHINE_RISCV128: u16 = pe::COFF_MACHINE_RISCV128;
 /// Hitachi SH3
-pub const TE_MACHINE_SH3: u16 = 0x1a2;
+pub const TE_MACHINE_SH3: u16 = pe::COFF_MACHINE_SH3;
 /// Hitachi SH3 DSP
-pub const TE_MACHINE_SH3DSP: u16 = 0x1a3;
+pub const TE_MACHINE_SH3DSP: u16 = pe::COFF_MACHINE_SH3DSP;
 /// Hitachi SH4
-pub const TE_MACHINE_SH4: u16 = 0x1a6;
+pub const TE_MACHINE_SH4: u16 = pe::COFF_MACHINE_SH4;
 /// Hitachi SH5
-pub const TE_MACHINE_SH5: u16 = 0x1a8;
+pub const TE_MACHINE_SH5: u16 = pe::COFF_MACHINE_SH5;
 /// Thumb
-pub const TE_MACHINE_THUMB: u16 = 0x1c2;
+pub const TE_MACHINE_THUMB: u16 = pe::COFF_MACHINE_THUMB;
 /// MIPS little-endian WCE v2
-pub const TE_MACHINE_WCEMIPSV2: u16 = 0x169;
+pub const TE_MACHINE_WCEMIPSV2: u16 = pe::COFF_MACHINE_WCEMIPSV2;
+/// LoongArch 32-bit address space
+pub const TE_MACHINE_LOONGARCH32: u16 = pe::COFF_MACHINE_LOONGARCH32;
+/// LoongArch 64-bit address space
+pub const TE_MACHINE_LOONGARCH64: u16 = pe::COFF_MACHINE_LOONGARCH64;
 
 #[repr(C)]
 #[derive(Debug, PartialEq, Copy, Clone, Pread, SizeWith)]
@@ -0,0 +1,271 @@
+//! Loading TE images into memory.
+//!
+//! A TE image is loaded like the PE image it was stripped from, except that the first
+//! `stripped_size - SIZEOF_TE_HEADER` bytes of the original image are missing: the TE header
+//! lands at the address where that many bytes of PE headers would otherwise have ended. Section
+//! RVAs, the entry point and the image base all refer to the original PE layout and are
+//! corrected by that delta here.
+
+use alloc::vec::Vec;
+use core::cmp;
+use core::convert::TryFrom;
+use scroll::{Pread, Pwrite};
+
+use crate::error;
+use crate::pe::relocation;
+use crate::pe::utils::PESectionTable;
+use crate::te::header::SIZEOF_TE_HEADER;
+use crate::te::section_table::SIZEOF_SECTION_TABLE;
+use crate::te::TE;
+
+/// Offset of `image_base` within the TE header
+const OFFSET_IMAGE_BASE: usize = 16;
+
+/// A TE image mapped into memory by [`load`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedImage {
+    /// The mapped image; `image[0]` is the TE header, located at `base`
+    pub image: Vec<u8>,
+    /// The address the image was loaded at
+    pub base: u64,
+    /// The address of the entry point once loaded at `base`
+    pub entry_point: u64,
+}
+
+/// Maps the TE image in `bytes` so that its TE header lands at `new_base`.
+///
+/// Sections are copied to their RVAs, the base relocations are applied for the difference
+/// between `new_base` and the image's preferred load address, and the `image_base` field of the
+/// mapped TE header is updated accordingly, as the EDK2 PE/COFF loader does.
+///
+/// The image size comes from the section table, which can make it arbitrarily large; images
+/// larger than `max_size` bytes are an error.
+pub fn load(bytes: &[u8], new_base: u64, max_size: usize) -> error::Result<LoadedImage> {
+    let te = TE::parse(bytes)?;
+    let header = &te.header;
+    let stripped_offset = (header.stripped_size as u32).wrapping_sub(SIZEOF_TE_HEADER as u32);
+    let headers_size = SIZEOF_TE_HEADER + te.sections.len() * SIZEOF_SECTION_TABLE;
+
+    // RVAs are relative to the original PE image, which started `stripped_offset` bytes earlier
+    let image_offset = |rva: u32| -> error::Result<usize> {
+        rva.checked_sub(stripped_offset)
+            .map(|offset| offset as usize)
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "RVA {:#x} lies within the stripped TE headers ({:#x})",
+                    rva, stripped_offset
+                ))
+            })
+    };
+
+    let mut image_size = headers_size as u64;
+    for section in &te.sections {
+        let size = cmp::max(section.virtual_size, section.size_of_raw_data) as u64;
+        let end = image_offset(section.virtual_address)? as u64 + size;
+        image_size = cmp::max(image_size, end);
+    }
+    let image_size = usize::try_from(image_size)
+        .ok()
+        .filter(|&size| size <= max_size)
+        .ok_or_else(|| {
+            error::Error::Malformed(format!(
+                "Image size {:#x} exceeds the maximum size {:#x}",
+                image_size, max_size
+            ))
+        })?;
+
+    let mut image = Vec::new();
+    image
+        .try_reserve_exact(image_size)
+        .map_err(|_| error::Error::Malformed(format!("Image size {:#x} is too big", image_size)))?;
+    image.resize(image_size, 0);
+    let headers = bytes.get(..headers_size).ok_or_else(|| {
+        error::Error::Malformed(format!(
+            "TE section table ends past the end of the image ({:#x})",
+            headers_size
+        ))
+    })?;
+    image[..headers_size].copy_from_slice(headers);
+
+    for section in &te.sections {
+        let mut size = section.size_of_raw_data as usize;
+        if section.virtual_size != 0 {
+            size = cmp::min(size, section.virtual_size as usize);
+        }
+        if size == 0 {
+            continue;
+        }
+        let start = section.pointer_to_raw_data() as usize;
+        let data = bytes
+            .get(start..start.saturating_add(size))
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "Section {:?} data ({:#x}..{:#x}) is out of bounds",
+                    section.name().unwrap_or(""),
+                    start,
+                    start.saturating_add(size)
+                ))
+            })?;
+        let offset = image_offset(section.virtual_address)?;
+        image[offset..offset + size].copy_from_slice(data);
+    }
+
+    let preferred_base = header.image_base.wrapping_add(stripped_offset as u64);
+    let delta = new_base.wrapping_sub(preferred_base);
+    if delta != 0 {
+        let has_relocations = header
+            .data_directories
+            .get_base_relocation_table()
+            .is_some();
+        let relocations = te.base_relocations(bytes);
+        if has_relocations && relocations.is_none() {
+            return Err(error::Error::Malformed(
+                "TE base relocation directory cannot be parsed, the image cannot be rebased".into(),
+            ));
+        }
+        if let Some(relocations) = relocations {
+            for reloc in relocations {
+                let rva = reloc
+                    .header
+                    .virtual_address
+                    .wrapping_add(reloc.entry.offset() as u32);
+                apply_relocation(&mut image, image_offset(rva)?, reloc.entry.typ(), delta)?;
+            }
+        }
+        image.pwrite_with(
+            new_base.wrapping_sub(stripped_offset as u64),
+            OFFSET_IMAGE_BASE,
+            scroll::LE,
+        )?;
+    }
+
+    let entry_point = new_base.wrapping_add(image_offset(header.address_of_entry_point)? as u64);
+
+    Ok(LoadedImage {
+        image,
+        base: new_base,
+        entry_point,
+    })
+}
+
+/// Applies a single base relocation of type `typ` at `offset` within `image`
+fn apply_relocation(image: &mut [u8], offset: usize, typ: u16, delta: u64) -> error::Result<()> {
+    match typ {
+        relocation::IMAGE_REL_BASED_ABSOLUTE => {}
+        relocation::IMAGE_REL_BASED_HIGH => {
+            let value: u16 = image.pread_with(offset, scroll::LE)?;
+            let value = value.wrapping_add((delta as u32 >> 16) as u16);
+            image.pwrite_with(value, offset, scroll::LE)?;
+        }
+        relocation::IMAGE_REL_BASED_LOW => {
+            let value: u16 = image.pread_with(offset, scroll::LE)?;
+            image.pwrite_with(value.wrapping_add(delta as u16), offset, scroll::LE)?;
+        }
+        relocation::IMAGE_REL_BASED_HIGHLOW => {
+            let value: u32 = image.pread_with(offset, scroll::LE)?;
+            image.pwrite_with(value.wrapping_add(delta as u32), offset, scroll::LE)?;
+        }
+        relocation::IMAGE_REL_BASED_DIR64 => {
+            let value: u64 = image.pread_with(offset, scroll::LE)?;
+            image.pwrite_with(value.wrapping_add(delta), offset, scroll::LE)?;
+        }
+        _ => {
+            return Err(error::Error::Malformed(format!(
+                "Unsupported base relocation type {} at {:#x}",
+                typ, offset
+            )))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pe;
+    use crate::te::writer;
+
+    const MAX_SIZE: usize = 0x10_0000;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+
+    #[test]
+    fn load_at_preferred_base() {
+        let pe = pe::PE::parse(REALTEK_LAN).unwrap();
+        let bytes = writer::from_pe(&pe).unwrap();
+        let te = TE::parse(&bytes).unwrap();
+        let stripped_offset = te.header.stripped_size as u64 - SIZEOF_TE_HEADER as u64;
+        let base = te.header.image_base + stripped_offset;
+
+        let loaded = load(&bytes, base, MAX_SIZE).unwrap();
+        assert_eq!(loaded.base, base);
+        assert_eq!(loaded.entry_point, te.entry_point());
+        assert_eq!(
+            &loaded.image[..SIZEOF_TE_HEADER],
+            &bytes[..SIZEOF_TE_HEADER]
+        );
+        for section in &pe.sections {
+            let start = (section.virtual_address as u64 - stripped_offset) as usize;
+            let size = cmp::min(section.virtual_size, section.size_of_raw_data) as usize;
+            let raw = section.pointer_to_raw_data as usize;
+            assert_eq!(
+                &loaded.image[start..start + size],
+                &REALTEK_LAN[raw..raw + size]
+            );
+        }
+    }
+
+    #[test]
+    fn load_rebased() {
+        let pe = pe::PE::parse(REALTEK_LAN).unwrap();
+        let bytes = writer::from_pe(&pe).unwrap();
+        let te = TE::parse(&bytes).unwrap();
+        let stripped_offset = te.header.stripped_size as u64 - SIZEOF_TE_HEADER as u64;
+        let preferred = te.header.image_base + stripped_offset;
+        let new_base = 0x8000_0000;
+        let delta = new_base - preferred;
+
+        let preferred_image = load(&bytes, preferred, MAX_SIZE).unwrap().image;
+        let loaded = load(&bytes, new_base, MAX_SIZE).unwrap();
+        assert_eq!(
+            loaded.entry_point,
+            new_base + te.header.address_of_entry_point as u64 - stripped_offset
+        );
+
+        let relocs = te.base_relocations(&bytes).unwrap().collect::<Vec<_>>();
+        assert!(relocs
+            .iter()
+            .any(|reloc| reloc.entry.typ() == relocation::IMAGE_REL_BASED_DIR64));
+        for reloc in relocs {
+            if reloc.entry.typ() != relocation::IMAGE_REL_BASED_DIR64 {
+                continue;
+            }
+            let rva = reloc.header.virtual_address as u64 + reloc.entry.offset() as u64;
+            let offset = (rva - stripped_offset) as usize;
+            let before: u64 = preferred_image.pread_with(offset, scroll::LE).unwrap();
+            let after: u64 = loaded.image.pread_with(offset, scroll::LE).unwrap();
+            assert_eq!(after, before.wrapping_add(delta));
+        }
+
+        let rebased = TE::parse(&loaded.image).unwrap();
+        assert_eq!(rebased.header.image_base + stripped_offset, new_base);
+    }
+
+    #[test]
+    fn load_errors() {
+        let pe = pe::PE::parse(REALTEK_LAN).unwrap();
+        let mut bytes = writer::from_pe(&pe).unwrap();
+        let te = TE::parse(&bytes).unwrap();
+        let stripped_offset = te.header.stripped_size as u64 - SIZEOF_TE_HEADER as u64;
+        let preferred = te.header.image_base + stripped_offset;
+
+        let size = load(&bytes, preferred, MAX_SIZE).unwrap().image.len();
+        assert!(load(&bytes, preferred, size).is_ok());
+        assert!(load(&bytes, preferred, size - 1).is_err());
+
+        // a base relocation directory outside of the sections can't rebase the image
+        bytes.pwrite_with(0xffff_f000u32, 24, scroll::LE).unwrap();
+        assert!(load(&bytes, preferred, MAX_SIZE).is_ok());
+        assert!(load(&bytes, 0x8000_0000, MAX_SIZE).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! TCG PE/COFF image measurement of TE images.
+//!
+//! The TE header stands in for the stripped PE headers: it is hashed as the image header, and
+//! the section data is located with the section offsets corrected by the stripped size. TE
+//! images have neither a checksum nor a certificate table, so nothing else is excluded.
+
+use alloc::vec::Vec;
+
+use crate::error;
+use crate::pe::measure::ImageHasher;
+use crate::pe::utils::PESectionTable;
+
+use super::header::SIZEOF_TE_HEADER;
+use super::section_table::SIZEOF_SECTION_TABLE;
+use super::TE;
+
+impl<'a> TE<'a> {
+    /// Returns the ranges of `bytes`, the image `self` was parsed from, that are hashed by the
+    /// TCG PE/COFF image measurement
+    pub fn measured_ranges<'b>(&self, bytes: &'b [u8]) -> error::Result<Vec<&'b [u8]>> {
+        let range = |start: usize, size: usize| {
+            start
+                .checked_add(size)
+                .and_then(|end| bytes.get(start..end))
+                .ok_or_else(|| {
+                    error::Error::Malformed(format!(
+                        "TE measured range ({:#x}, {:#x}) is out of bounds",
+                        start, size
+                    ))
+                })
+        };
+
+        let mut sections = self
+            .sections
+            .iter()
+            .filter(|section| section.size_of_raw_data != 0)
+            .collect::<Vec<_>>();
+        sections.sort_by_key(|section| section.pointer_to_raw_data());
+
+        // The headers end where the first section starts, as `SizeOfHeaders` is gone
+        let section_table_end = SIZEOF_TE_HEADER + self.sections.len() * SIZEOF_SECTION_TABLE;
+        let end_image_header = sections.first().map_or(section_table_end, |section| {
+            section.pointer_to_raw_data() as usize
+        });
+        let mut ranges = vec![range(0, end_image_header)?];
+        let mut sum_of_bytes_hashed = end_image_header;
+
+        for section in sections {
+            let size = section.size_of_raw_data as usize;
+            ranges.push(range(section.pointer_to_raw_data() as usize, size)?);
+            sum_of_bytes_hashed += size;
+        }
+
+        if let Some(extra_data) = bytes.get(sum_of_bytes_hashed..) {
+            if !extra_data.is_empty() {
+                ranges.push(extra_data);
+            }
+        }
+        Ok(ranges)
+    }
+
+    /// Computes the TCG PE/COFF image digest of `bytes`, the image `self` was parsed from, with
+    /// `hasher`
+    pub fn measure<H: ImageHasher>(&self, bytes: &[u8], mut hasher: H) -> error::Result<H::Output> {
+        for range in self.measured_ranges(bytes)? {
+            hasher.update(range);
+        }
+        Ok(hasher.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pe;
+    use crate::pe::measure::tests::Recorder;
+    use crate::te::writer;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+
+    #[test]
+    fn measure_te() {
+        let pe = pe::PE::parse(REALTEK_LAN).unwrap();
+        let bytes = writer::from_pe(&pe).unwrap();
+        let te = TE::parse(&bytes).unwrap();
+        let measured = te.measure(&bytes, Recorder::default()).unwrap();
+
+        // The TE header, the section table and every section, sorted by file offset
+        let ranges = te.measured_ranges(&bytes).unwrap();
+        assert_eq!(ranges[0], &bytes[..ranges[0].len()]);
+        assert_eq!(&ranges[0][..2], b"VZ");
+        assert_eq!(measured.len(), ranges.iter().map(|range| range.len()).sum());
+
+        // The sections hash the same bytes as in the PE image
+        let text = &pe.sections[0];
+        let start = text.pointer_to_raw_data as usize;
+        assert_eq!(
+            ranges[1],
+            &REALTEK_LAN[start..start + text.size_of_raw_data as usize]
+        );
+    }
+
+    #[test]
+    fn truncated_te() {
+        let pe = pe::PE::parse(REALTEK_LAN).unwrap();
+        let bytes = writer::from_pe(&pe).unwrap();
+        let te = TE::parse(&bytes).unwrap();
+        let truncated = &bytes[..bytes.len() / 2];
+        assert!(te.measure(truncated, Recorder::default()).is_err());
+    }
+}
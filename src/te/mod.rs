@@ -1,6 +1,9 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use scroll::Pread;
 
 use crate::error;
+use crate::pe::options::{self, ParseMode};
 use crate::pe::relocation::BaseRelocations;
 use crate::pe::utils;
 
@@ -9,54 +12,133 @@ use self::header::SIZEOF_TE_HEADER;
 pub mod data_directories;
 pub mod debug;
 pub mod header;
+pub mod loader;
+pub mod measure;
 pub mod section_table;
+pub mod writer;
+
+/// A part of a TE image that was skipped by [`TE::parse_with_opts`] in
+/// [`ParseMode::Permissive`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Skipped {
+    /// The section headers from `index` onwards could not be read
+    Sections { index: usize, reason: String },
+    /// The debug directory could not be read
+    DebugDirectory { reason: String },
+    /// The CodeView record referenced by the debug directory could not be read
+    CodeviewRecord { reason: String },
+}
 
 #[derive(Debug, Clone)]
 pub struct TE<'a> {
     pub header: header::Header,
     pub sections: Vec<section_table::SectionTable>,
     pub debug_data: Option<debug::DebugData<'a>>,
+    skipped: Vec<Skipped>,
 }
 
 impl<'a> TE<'a> {
+    /// Reads a TE image from `bytes`, failing on malformed section headers or debug information;
+    /// see [`TE::parse_with_opts`] to skip those instead.
     pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        let opts = options::ParseOptions {
+            parse_mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        Self::parse_with_opts(bytes, &opts)
+    }
+
+    /// Reads a TE image from `bytes`. In [`ParseMode::Permissive`], malformed section headers and
+    /// debug information are recorded in [`TE::skipped`] instead of failing the whole parse.
+    pub fn parse_with_opts(bytes: &'a [u8], opts: &options::ParseOptions) -> error::Result<Self> {
         let header = header::Header::parse(bytes)?;
+        let mut skipped = Vec::new();
 
         log::debug!("{:#?}", header);
 
         let mut offset = header::SIZEOF_TE_HEADER;
-        let sections = header.sections(bytes, &mut offset)?;
-        let mut debug_data = None;
+        let nsections = header.number_of_sections as usize;
+        let mut sections = Vec::with_capacity(nsections);
+        for index in 0..nsections {
+            match section_table::SectionTable::parse(
+                bytes,
+                &mut offset,
+                header.stripped_size as u32,
+            ) {
+                Ok(section) => {
+                    log::debug!("({}) {:#?}", index, section);
+                    sections.push(section);
+                }
+                Err(err) => {
+                    skip(opts, &mut skipped, err, |reason| Skipped::Sections {
+                        index,
+                        reason,
+                    })?;
+                    break;
+                }
+            }
+        }
 
+        let mut debug_data = None;
         if let Some(debug_table) = *header.data_directories.get_debug_table() {
-            let image_debug_directory =
-                debug::ImageDebugDirectory::parse(bytes, debug_table, &sections, 0x10)?;
-
-            // NOTE: we need to adjust the pointer to raw data
-            let codeview_pdb70_debug_info = debug::CodeviewPDB70DebugInfo::parse(
+            match debug::ImageDebugDirectory::parse_with_opts(
                 bytes,
-                &debug::ImageDebugDirectory {
-                    pointer_to_raw_data: image_debug_directory
-                        .pointer_to_raw_data
-                        .wrapping_sub(header.stripped_size as u32)
-                        .wrapping_add(SIZEOF_TE_HEADER as u32),
-                    ..image_debug_directory
-                },
-            )?;
-
-            debug_data = Some(debug::DebugData {
-                image_debug_directory,
-                codeview_pdb70_debug_info,
-            });
+                debug_table,
+                &sections,
+                0x10,
+                opts,
+            ) {
+                Ok(image_debug_directory) => {
+                    // NOTE: we need to adjust the pointer to raw data
+                    let codeview_pdb70_debug_info =
+                        match debug::CodeviewPDB70DebugInfo::parse_with_opts(
+                            bytes,
+                            &debug::ImageDebugDirectory {
+                                pointer_to_raw_data: image_debug_directory
+                                    .pointer_to_raw_data
+                                    .wrapping_sub(header.stripped_size as u32)
+                                    .wrapping_add(SIZEOF_TE_HEADER as u32),
+                                ..image_debug_directory
+                            },
+                            opts,
+                        ) {
+                            Ok(info) => info,
+                            Err(err) => {
+                                skip(opts, &mut skipped, err, |reason| Skipped::CodeviewRecord {
+                                    reason,
+                                })?;
+                                None
+                            }
+                        };
+
+                    debug_data = Some(debug::DebugData {
+                        image_debug_directory,
+                        codeview_pdb70_debug_info,
+                    });
+                }
+                Err(err) => {
+                    skip(opts, &mut skipped, err, |reason| Skipped::DebugDirectory {
+                        reason,
+                    })?;
+                }
+            }
         }
 
         Ok(Self {
             header,
             sections,
             debug_data,
+            skipped,
         })
     }
 
+    /// Parts of the image that could not be parsed and were skipped; always empty in
+    /// [`ParseMode::Strict`]
+    pub fn skipped(&self) -> &[Skipped] {
+        &self.skipped
+    }
+
     pub fn base_relocations(&self, bytes: &'a [u8]) -> Option<BaseRelocations<'a>> {
         let dds = self.header.data_directories;
         let relocs = dds.get_base_relocation_table().as_ref()?;
@@ -87,3 +169,20 @@ impl<'a> TE<'a> {
         self.header.subsystem
     }
 }
+
+/// Fails with `err` in [`ParseMode::Strict`], otherwise records it in `skipped`
+fn skip(
+    opts: &options::ParseOptions,
+    skipped: &mut Vec<Skipped>,
+    err: error::Error,
+    kind: impl FnOnce(String) -> Skipped,
+) -> error::Result<()> {
+    match opts.parse_mode {
+        ParseMode::Strict => Err(err),
+        ParseMode::Permissive => {
+            log::warn!("Skipping malformed TE data: {}", err);
+            skipped.push(kind(err.to_string()));
+            Ok(())
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error;
+use crate::pe::section_table as pe;
 use crate::pe::utils::PESectionTable;
 use scroll::{ctx, Pread, Pwrite};
 
@@ -91,6 +92,24 @@ impl SectionTable {
 
         Ok(table)
     }
+
+    /// Builds the TE section header equivalent to the PE `section`, for an image whose first
+    /// `stripped_size` bytes were replaced by the TE header
+    pub fn from_pe(section: &pe::SectionTable, stripped_size: u32) -> Self {
+        SectionTable {
+            name: section.name,
+            virtual_size: section.virtual_size,
+            virtual_address: section.virtual_address,
+            size_of_raw_data: section.size_of_raw_data,
+            pointer_to_raw_data: section.pointer_to_raw_data,
+            pointer_to_relocations: section.pointer_to_relocations,
+            pointer_to_linenumbers: section.pointer_to_linenumbers,
+            number_of_relocations: section.number_of_relocations,
+            number_of_linenumbers: section.number_of_linenumbers,
+            characteristics: section.characteristics,
+            stripped_size,
+        }
+    }
 }
 
 impl ctx::SizeWith<scroll::Endian> for SectionTable {
@@ -123,59 +142,17 @@ impl ctx::IntoCtx<scroll::Endian> for SectionTable {
     }
 }
 
-/// The section should not be padded to the next boundary. This flag is obsolete and is replaced
-/// by `IMAGE_SCN_ALIGN_1BYTES`. This is valid only for object files.
-pub const IMAGE_SCN_TYPE_NO_PAD: u32 = 0x0000_0008;
-/// The section contains executable code.
-pub const IMAGE_SCN_CNT_CODE: u32 = 0x0000_0020;
-/// The section contains initialized data.
-pub const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x0000_0040;
-///  The section contains uninitialized data.
-pub const IMAGE_SCN_CNT_UNINITIALIZED_DATA: u32 = 0x0000_0080;
-pub const IMAGE_SCN_LNK_OTHER: u32 = 0x0000_0100;
-/// The section contains comments or other information. The .drectve section has this type.
-/// This is valid for object files only.
-pub const IMAGE_SCN_LNK_INFO: u32 = 0x0000_0200;
-/// The section will not become part of the image. This is valid only for object files.
-pub const IMAGE_SCN_LNK_REMOVE: u32 = 0x0000_0800;
-/// The section contains COMDAT data. This is valid only for object files.
-pub const IMAGE_SCN_LNK_COMDAT: u32 = 0x0000_1000;
-/// The section contains data referenced through the global pointer (GP).
-pub const IMAGE_SCN_GPREL: u32 = 0x0000_8000;
-pub const IMAGE_SCN_MEM_PURGEABLE: u32 = 0x0002_0000;
-pub const IMAGE_SCN_MEM_16BIT: u32 = 0x0002_0000;
-pub const IMAGE_SCN_MEM_LOCKED: u32 = 0x0004_0000;
-pub const IMAGE_SCN_MEM_PRELOAD: u32 = 0x0008_0000;
-
-pub const IMAGE_SCN_ALIGN_1BYTES: u32 = 0x0010_0000;
-pub const IMAGE_SCN_ALIGN_2BYTES: u32 = 0x0020_0000;
-pub const IMAGE_SCN_ALIGN_4BYTES: u32 = 0x0030_0000;
-pub const IMAGE_SCN_ALIGN_8BYTES: u32 = 0x0040_0000;
-pub const IMAGE_SCN_ALIGN_16BYTES: u32 = 0x0050_0000;
-pub const IMAGE_SCN_ALIGN_32BYTES: u32 = 0x0060_0000;
-pub const IMAGE_SCN_ALIGN_64BYTES: u32 = 0x0070_0000;
-pub const IMAGE_SCN_ALIGN_128BYTES: u32 = 0x0080_0000;
-pub const IMAGE_SCN_ALIGN_256BYTES: u32 = 0x0090_0000;
-pub const IMAGE_SCN_ALIGN_512BYTES: u32 = 0x00A0_0000;
-pub const IMAGE_SCN_ALIGN_1024BYTES: u32 = 0x00B0_0000;
-pub const IMAGE_SCN_ALIGN_2048BYTES: u32 = 0x00C0_0000;
-pub const IMAGE_SCN_ALIGN_4096BYTES: u32 = 0x00D0_0000;
-pub const IMAGE_SCN_ALIGN_8192BYTES: u32 = 0x00E0_0000;
-pub const IMAGE_SCN_ALIGN_MASK: u32 = 0x00F0_0000;
-
-/// The section contains extended relocations.
-pub const IMAGE_SCN_LNK_NRELOC_OVFL: u32 = 0x0100_0000;
-/// The section can be discarded as needed.
-pub const IMAGE_SCN_MEM_DISCARDABLE: u32 = 0x0200_0000;
-/// The section cannot be cached.
-pub const IMAGE_SCN_MEM_NOT_CACHED: u32 = 0x0400_0000;
-/// The section is not pageable.
-pub const IMAGE_SCN_MEM_NOT_PAGED: u32 = 0x0800_0000;
-/// The section can be shared in memory.
-pub const IMAGE_SCN_MEM_SHARED: u32 = 0x1000_0000;
-/// The section can be executed as code.
-pub const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
-/// The section can be read.
-pub const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
-/// The section can be written to.
-pub const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
+pub use crate::pe::section_table::{
+    SectionCharacteristics, IMAGE_SCN_ALIGN_1024BYTES, IMAGE_SCN_ALIGN_128BYTES,
+    IMAGE_SCN_ALIGN_16BYTES, IMAGE_SCN_ALIGN_1BYTES, IMAGE_SCN_ALIGN_2048BYTES,
+    IMAGE_SCN_ALIGN_256BYTES, IMAGE_SCN_ALIGN_2BYTES, IMAGE_SCN_ALIGN_32BYTES,
+    IMAGE_SCN_ALIGN_4096BYTES, IMAGE_SCN_ALIGN_4BYTES, IMAGE_SCN_ALIGN_512BYTES,
+    IMAGE_SCN_ALIGN_64BYTES, IMAGE_SCN_ALIGN_8192BYTES, IMAGE_SCN_ALIGN_8BYTES,
+    IMAGE_SCN_ALIGN_MASK, IMAGE_SCN_CNT_CODE, IMAGE_SCN_CNT_INITIALIZED_DATA,
+    IMAGE_SCN_CNT_UNINITIALIZED_DATA, IMAGE_SCN_GPREL, IMAGE_SCN_LNK_COMDAT, IMAGE_SCN_LNK_INFO,
+    IMAGE_SCN_LNK_NRELOC_OVFL, IMAGE_SCN_LNK_OTHER, IMAGE_SCN_LNK_REMOVE, IMAGE_SCN_MEM_16BIT,
+    IMAGE_SCN_MEM_DISCARDABLE, IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_LOCKED,
+    IMAGE_SCN_MEM_NOT_CACHED, IMAGE_SCN_MEM_NOT_PAGED, IMAGE_SCN_MEM_PRELOAD,
+    IMAGE_SCN_MEM_PURGEABLE, IMAGE_SCN_MEM_READ, IMAGE_SCN_MEM_SHARED, IMAGE_SCN_MEM_WRITE,
+    IMAGE_SCN_TYPE_NO_PAD,
+};
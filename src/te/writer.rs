@@ -0,0 +1,199 @@
+//! Serialization of TE images, and conversion of PE images into TE.
+//!
+//! A TE image is a PE image whose DOS header, PE signature, COFF header and optional header have
+//! been replaced by the much smaller [`header::Header`]. Everything from the section table
+//! onwards is kept verbatim, so section headers and debug directories keep their original file
+//! offsets; readers correct them by `stripped_size - SIZEOF_TE_HEADER`.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cmp;
+use core::convert::TryFrom;
+use scroll::Pwrite;
+
+use crate::error;
+use crate::pe;
+use crate::pe::utils;
+use crate::te::data_directories::DataDirectories;
+use crate::te::header::{self, Header, SIZEOF_TE_HEADER, TE_MAGIC};
+use crate::te::section_table::{SectionTable, SIZEOF_SECTION_TABLE};
+use crate::te::TE;
+
+/// Serializes a TE image made of `header`, the section headers in `sections`, and `body`, which is
+/// everything that follows the section table (i.e., the section contents)
+pub fn write(header: &Header, sections: &[SectionTable], body: &[u8]) -> error::Result<Vec<u8>> {
+    if header.number_of_sections as usize != sections.len() {
+        return Err(error::Error::Malformed(format!(
+            "TE header declares {} sections but {} were given",
+            header.number_of_sections,
+            sections.len()
+        )));
+    }
+
+    let headers_size = SIZEOF_TE_HEADER + sections.len() * SIZEOF_SECTION_TABLE;
+    let mut bytes = vec![0u8; headers_size + body.len()];
+    let offset = &mut 0;
+
+    bytes.gwrite_with(*header, offset, scroll::LE)?;
+    for section in sections {
+        bytes.gwrite_with(section.clone(), offset, scroll::LE)?;
+    }
+    bytes[headers_size..].copy_from_slice(body);
+
+    Ok(bytes)
+}
+
+/// Converts `pe` into the equivalent TE image, the way EDK2's GenFw does.
+///
+/// The stripped size covers everything before the PE section table, the base relocation and debug
+/// data directories are carried over, and the remaining data directories are dropped. Any data
+/// past the last section (such as an Authenticode signature) is not part of the TE image.
+pub fn from_pe(pe: &pe::PE) -> error::Result<Vec<u8>> {
+    let bytes = pe.bytes;
+    let coff_header = &pe.header.coff_header;
+    let optional_header = pe.header.optional_header.ok_or_else(|| {
+        error::Error::Malformed("Cannot convert a PE without optional header to TE".to_string())
+    })?;
+
+    let section_table_offset = pe.header.dos_header.pe_pointer as usize
+        + pe::header::SIZEOF_PE_MAGIC
+        + pe::header::SIZEOF_COFF_HEADER
+        + coff_header.size_of_optional_header as usize;
+    let stripped_size = u16::try_from(section_table_offset).map_err(|_| {
+        error::Error::Malformed(format!(
+            "PE section table offset ({:#x}) cannot be expressed as a TE stripped size",
+            section_table_offset
+        ))
+    })?;
+    let number_of_sections = u8::try_from(pe.sections.len()).map_err(|_| {
+        error::Error::Malformed(format!(
+            "Too many sections for a TE image ({})",
+            pe.sections.len()
+        ))
+    })?;
+    let subsystem = u8::try_from(optional_header.windows_fields.subsystem).map_err(|_| {
+        error::Error::Malformed(format!(
+            "Subsystem cannot be represented in a TE image ({:#x})",
+            optional_header.windows_fields.subsystem
+        ))
+    })?;
+
+    let data_directories = &optional_header.data_directories;
+    let header = Header {
+        signature: TE_MAGIC,
+        machine: coff_header.machine,
+        number_of_sections,
+        subsystem,
+        stripped_size,
+        // TE headers only have room for 32-bit RVAs
+        address_of_entry_point: optional_header.standard_fields.address_of_entry_point as u32,
+        base_of_code: optional_header.standard_fields.base_of_code as u32,
+        image_base: optional_header.windows_fields.image_base,
+        data_directories: DataDirectories::new(
+            *data_directories.get_base_relocation_table(),
+            *data_directories.get_debug_table(),
+        ),
+    };
+
+    let sections = pe
+        .sections
+        .iter()
+        .map(|section| SectionTable::from_pe(section, stripped_size as u32))
+        .collect::<Vec<_>>();
+
+    let file_alignment = optional_header.windows_fields.file_alignment;
+    let body_start =
+        section_table_offset + pe.sections.len() * pe::section_table::SIZEOF_SECTION_TABLE;
+    let body_end = pe
+        .sections
+        .iter()
+        .filter(|section| section.size_of_raw_data != 0)
+        .map(|section| utils::section_end_offset(section, file_alignment))
+        .fold(body_start, cmp::max);
+    let body = bytes.get(body_start..body_end).ok_or_else(|| {
+        error::Error::Malformed(format!(
+            "PE section data ({:#x}..{:#x}) is out of bounds",
+            body_start, body_end
+        ))
+    })?;
+
+    write(&header, &sections, body)
+}
+
+impl<'a> TE<'a> {
+    /// Serializes this TE image, taking the section contents from `bytes`, the image it was
+    /// parsed from
+    pub fn write(&self, bytes: &[u8]) -> error::Result<Vec<u8>> {
+        let body_start = header::SIZEOF_TE_HEADER + self.sections.len() * SIZEOF_SECTION_TABLE;
+        let body = bytes.get(body_start..).ok_or_else(|| {
+            error::Error::Malformed(format!(
+                "TE section table ends past the end of the image ({:#x})",
+                body_start
+            ))
+        })?;
+        write(&self.header, &self.sections, body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pe::utils::PESectionTable;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+
+    #[test]
+    fn pe_to_te() {
+        let pe = pe::PE::parse(REALTEK_LAN).unwrap();
+        let bytes = from_pe(&pe).unwrap();
+        let te = TE::parse(&bytes).unwrap();
+
+        let optional_header = pe.header.optional_header.unwrap();
+        let section_table_offset = pe.header.dos_header.pe_pointer as usize
+            + pe::header::SIZEOF_PE_MAGIC
+            + pe::header::SIZEOF_COFF_HEADER
+            + pe.header.coff_header.size_of_optional_header as usize;
+        assert_eq!(te.header.stripped_size as usize, section_table_offset);
+        assert_eq!(te.header.machine, pe.header.coff_header.machine);
+        assert_eq!(
+            te.subsystem() as u16,
+            optional_header.windows_fields.subsystem
+        );
+        assert_eq!(te.entry_point(), pe.image_base as u64 + pe.entry as u64);
+        assert_eq!(te.sections.len(), pe.sections.len());
+
+        for (te_section, pe_section) in te.sections.iter().zip(&pe.sections) {
+            let te_offset = te_section.pointer_to_raw_data() as usize;
+            let pe_offset = pe_section.pointer_to_raw_data as usize;
+            let size = pe_section.size_of_raw_data as usize;
+            assert_eq!(
+                &bytes[te_offset..te_offset + size],
+                &REALTEK_LAN[pe_offset..pe_offset + size]
+            );
+        }
+
+        let te_relocs = te.base_relocations(&bytes).unwrap().count();
+        let pe_relocs = pe.base_relocations(REALTEK_LAN).unwrap().count();
+        assert_eq!(te_relocs, pe_relocs);
+        assert_eq!(
+            te.debug_data.map(|data| data.image_debug_directory),
+            pe.debug_data.map(|data| data.image_debug_directory)
+        );
+    }
+
+    #[test]
+    fn write_roundtrip() {
+        let pe = pe::PE::parse(REALTEK_LAN).unwrap();
+        let bytes = from_pe(&pe).unwrap();
+        let te = TE::parse(&bytes).unwrap();
+        assert_eq!(te.write(&bytes).unwrap(), bytes);
+    }
+
+    #[test]
+    fn section_count_mismatch() {
+        let pe = pe::PE::parse(REALTEK_LAN).unwrap();
+        let bytes = from_pe(&pe).unwrap();
+        let te = TE::parse(&bytes).unwrap();
+        assert!(write(&te.header, &te.sections[1..], &[]).is_err());
+    }
+}
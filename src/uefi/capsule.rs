@@ -0,0 +1,549 @@
+//! UEFI capsules, the containers firmware updates are delivered in.
+//!
+//! A capsule is an `EFI_CAPSULE_HEADER` followed by a body whose format is given by the capsule
+//! GUID. Firmware Management Protocol (FMP) capsules carry a list of embedded drivers and of
+//! update images, each with an image type GUID and, usually, an authentication header
+//! (`EFI_FIRMWARE_IMAGE_AUTHENTICATION`) made of a monotonic count and a
+//! `WIN_CERTIFICATE_UEFI_GUID`. Other capsules usually carry firmware volumes directly.
+//!
+//! See the UEFI Specification, "Firmware Update and Reporting".
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use scroll::{Pread, Pwrite, SizeWith};
+
+use crate::error;
+use crate::guid::Guid;
+use crate::pe;
+use crate::pe::certificate_table::{
+    AttributeCertificate, AttributeCertificateHeader, AttributeCertificateRevision,
+    AttributeCertificateType, UefiGuidCertificate,
+};
+use crate::uefi::fv::{Executable, FirmwareVolume};
+
+pub const SIZEOF_CAPSULE_HEADER: usize = 28;
+pub const SIZEOF_FMP_CAPSULE_HEADER: usize = 8;
+pub const SIZEOF_FMP_PAYLOAD_HEADER: usize = 16;
+/// Size of the monotonic count preceding the `WIN_CERTIFICATE_UEFI_GUID` of an update image
+pub const SIZEOF_MONOTONIC_COUNT: usize = 8;
+
+pub const CAPSULE_FLAGS_PERSIST_ACROSS_RESET: u32 = 0x0001_0000;
+pub const CAPSULE_FLAGS_POPULATE_SYSTEM_TABLE: u32 = 0x0002_0000;
+pub const CAPSULE_FLAGS_INITIATE_RESET: u32 = 0x0004_0000;
+
+/// The image is authenticated, see `EFI_FIRMWARE_MANAGEMENT_CAPSULE_IMAGE_HEADER.ImageCapsuleSupport`
+pub const CAPSULE_SUPPORT_AUTHENTICATION: u64 = 0x0000_0001;
+/// The image carries a dependency expression
+pub const CAPSULE_SUPPORT_DEPENDENCY: u64 = 0x0000_0002;
+
+/// `EFI_FIRMWARE_MANAGEMENT_CAPSULE_HEADER_INIT_VERSION`
+pub const FMP_CAPSULE_HEADER_VERSION: u32 = 1;
+/// "MSS1", the signature of the EDK2 `FMP_PAYLOAD_HEADER`
+pub const FMP_PAYLOAD_HEADER_SIGNATURE: u32 = 0x3153_534d;
+
+/// EFI_FIRMWARE_MANAGEMENT_CAPSULE_ID_GUID
+pub const FIRMWARE_MANAGEMENT_CAPSULE_ID_GUID: Guid = Guid::new(
+    0x6dcb_d5ed,
+    0xe82d,
+    0x4c44,
+    [0xbd, 0xa1, 0x71, 0x94, 0x19, 0x9a, 0xd9, 0x2a],
+);
+/// EFI_CAPSULE_GUID, for capsules carrying firmware volumes
+pub const CAPSULE_GUID: Guid = Guid::new(
+    0x3b66_86bd,
+    0x0d76,
+    0x4030,
+    [0xb7, 0x0e, 0xb5, 0x51, 0x9e, 0x2f, 0xc5, 0xa0],
+);
+
+/// `EFI_CAPSULE_HEADER`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, SizeWith)]
+pub struct CapsuleHeader {
+    pub capsule_guid: Guid,
+    /// The size of the header, the body starts at this offset
+    pub header_size: u32,
+    pub flags: u32,
+    /// The size of the whole capsule, header included
+    pub capsule_image_size: u32,
+}
+
+/// The fixed part of `EFI_FIRMWARE_MANAGEMENT_CAPSULE_HEADER`, which is followed by the offsets
+/// of the embedded drivers and then of the payload items
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, SizeWith)]
+pub struct FmpCapsuleHeader {
+    pub version: u32,
+    pub embedded_driver_count: u16,
+    pub payload_item_count: u16,
+}
+
+/// `EFI_FIRMWARE_MANAGEMENT_CAPSULE_IMAGE_HEADER`; fields missing from older versions are zero
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct FmpImageHeader {
+    pub version: u32,
+    pub update_image_type_id: Guid,
+    pub update_image_index: u8,
+    pub reserved: [u8; 3],
+    pub update_image_size: u32,
+    pub update_vendor_code_size: u32,
+    /// Since version 2
+    pub update_hardware_instance: u64,
+    /// Since version 3
+    pub image_capsule_support: u64,
+}
+
+impl FmpImageHeader {
+    /// Parses the image header at `offset`, returning it along with its size
+    pub fn parse(bytes: &[u8], offset: usize) -> error::Result<(Self, usize)> {
+        let mut cursor = offset;
+        let mut header = FmpImageHeader {
+            version: bytes.gread_with(&mut cursor, scroll::LE)?,
+            update_image_type_id: bytes.gread_with(&mut cursor, scroll::LE)?,
+            update_image_index: bytes.gread_with(&mut cursor, scroll::LE)?,
+            ..Default::default()
+        };
+        header
+            .reserved
+            .copy_from_slice(bytes.gread_with(&mut cursor, 3)?);
+        header.update_image_size = bytes.gread_with(&mut cursor, scroll::LE)?;
+        header.update_vendor_code_size = bytes.gread_with(&mut cursor, scroll::LE)?;
+        match header.version {
+            1 => {}
+            2 => {
+                header.update_hardware_instance = bytes.gread_with(&mut cursor, scroll::LE)?;
+            }
+            3 => {
+                header.update_hardware_instance = bytes.gread_with(&mut cursor, scroll::LE)?;
+                header.image_capsule_support = bytes.gread_with(&mut cursor, scroll::LE)?;
+            }
+            version => {
+                return Err(error::Error::Malformed(format!(
+                    "FMP capsule image header at {:#x} has an unknown version ({})",
+                    offset, version
+                )))
+            }
+        }
+        Ok((header, cursor - offset))
+    }
+}
+
+/// The EDK2 `FMP_PAYLOAD_HEADER`, prepended to the image by `GenerateCapsule`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, SizeWith)]
+pub struct FmpPayloadHeader {
+    pub signature: u32,
+    pub header_size: u32,
+    pub fw_version: u32,
+    pub lowest_supported_version: u32,
+}
+
+/// `EFI_FIRMWARE_IMAGE_AUTHENTICATION`, which precedes the data of authenticated images
+#[derive(Debug, Clone)]
+pub struct ImageAuthentication<'a> {
+    pub monotonic_count: u64,
+    /// The `WIN_CERTIFICATE_UEFI_GUID`
+    pub certificate: AttributeCertificate<'a>,
+}
+
+impl<'a> ImageAuthentication<'a> {
+    /// Parses the authentication header at the start of `bytes`, returning it along with its
+    /// size
+    pub fn parse(bytes: &'a [u8]) -> error::Result<(Self, usize)> {
+        let monotonic_count = bytes.pread_with(0, scroll::LE)?;
+        let mut offset = SIZEOF_MONOTONIC_COUNT;
+        let certificate = AttributeCertificate::parse(bytes, &mut offset)?;
+        if certificate.certificate_type != AttributeCertificateType::EfiGuid {
+            return Err(error::Error::Malformed(format!(
+                "FMP image authentication has an unexpected certificate type ({:?})",
+                certificate.certificate_type
+            )));
+        }
+        // unlike in the PE certificate table, the image data directly follows the certificate
+        let size = SIZEOF_MONOTONIC_COUNT + certificate.length as usize;
+        Ok((
+            ImageAuthentication {
+                monotonic_count,
+                certificate,
+            },
+            size,
+        ))
+    }
+
+    /// Decodes the `WIN_CERTIFICATE_UEFI_GUID`, whose type is usually
+    /// [`EFI_CERT_TYPE_PKCS7_GUID`](crate::pe::certificate_table::EFI_CERT_TYPE_PKCS7_GUID)
+    pub fn uefi_guid_certificate(&self) -> error::Result<UefiGuidCertificate<'a>> {
+        UefiGuidCertificate::parse(self.certificate.certificate)
+    }
+
+    /// Whether `bytes` look like they start with an authentication header, for images whose
+    /// header predates `ImageCapsuleSupport`
+    fn is_present(bytes: &[u8]) -> bool {
+        bytes
+            .pread_with::<AttributeCertificateHeader>(SIZEOF_MONOTONIC_COUNT, scroll::LE)
+            .map(|header| {
+                header.revision == AttributeCertificateRevision::Revision2_0 as u16
+                    && header.certificate_type == AttributeCertificateType::EfiGuid as u16
+                    && (header.length as usize) <= bytes.len() - SIZEOF_MONOTONIC_COUNT
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// An update image of an FMP capsule
+#[derive(Debug, Clone)]
+pub struct FmpImage<'a> {
+    /// Offset of the image header within the FMP capsule
+    pub offset: usize,
+    pub header: FmpImageHeader,
+    pub authentication: Option<ImageAuthentication<'a>>,
+    pub payload_header: Option<FmpPayloadHeader>,
+    /// The firmware image, without the authentication and payload headers
+    pub image: &'a [u8],
+    pub vendor_code: &'a [u8],
+}
+
+impl<'a> FmpImage<'a> {
+    /// Parses the update image at `offset` within the FMP capsule `bytes`
+    pub fn parse(bytes: &'a [u8], offset: usize) -> error::Result<Self> {
+        let (header, header_size) = FmpImageHeader::parse(bytes, offset)?;
+        let start = offset + header_size;
+        let image_end = start.saturating_add(header.update_image_size as usize);
+        let vendor_code_end = image_end.saturating_add(header.update_vendor_code_size as usize);
+        if vendor_code_end > bytes.len() {
+            return Err(error::Error::Malformed(format!(
+                "FMP capsule image at {:#x} ends past the end of the capsule ({:#x})",
+                offset, vendor_code_end
+            )));
+        }
+        let mut image = &bytes[start..image_end];
+        let vendor_code = &bytes[image_end..vendor_code_end];
+
+        let mut authentication = None;
+        if header.image_capsule_support & CAPSULE_SUPPORT_AUTHENTICATION != 0
+            || (header.version < 3 && ImageAuthentication::is_present(image))
+        {
+            let (auth, size) = ImageAuthentication::parse(image)?;
+            image = image.get(size..).ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "FMP capsule image at {:#x} is smaller than its authentication ({:#x})",
+                    offset, size
+                ))
+            })?;
+            authentication = Some(auth);
+        }
+
+        let mut payload_header = None;
+        if let Ok(payload) = image.pread_with::<FmpPayloadHeader>(0, scroll::LE) {
+            let size = payload.header_size as usize;
+            if payload.signature == FMP_PAYLOAD_HEADER_SIGNATURE
+                && size >= SIZEOF_FMP_PAYLOAD_HEADER
+                && size <= image.len()
+            {
+                image = &image[size..];
+                payload_header = Some(payload);
+            }
+        }
+
+        Ok(FmpImage {
+            offset,
+            header,
+            authentication,
+            payload_header,
+            image,
+            vendor_code,
+        })
+    }
+
+    /// The firmware version of the image, from its payload header
+    pub fn fw_version(&self) -> Option<u32> {
+        self.payload_header.map(|header| header.fw_version)
+    }
+
+    /// Finds and parses the firmware volumes in the image
+    pub fn firmware_volumes(&self) -> Vec<FirmwareVolume<'a>> {
+        FirmwareVolume::find_all(self.image)
+    }
+}
+
+/// The body of an FMP capsule
+#[derive(Debug, Clone)]
+pub struct FmpCapsule<'a> {
+    pub header: FmpCapsuleHeader,
+    /// The offsets of the embedded drivers and of the payload items, relative to `data`
+    pub item_offsets: Vec<u64>,
+    /// The embedded drivers, PE images to be loaded before the images are processed
+    pub embedded_drivers: Vec<&'a [u8]>,
+    pub images: Vec<FmpImage<'a>>,
+    /// The whole FMP capsule, header included
+    pub data: &'a [u8],
+}
+
+impl<'a> FmpCapsule<'a> {
+    /// Parses the FMP capsule at the start of `bytes`, the body of a capsule
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        let header: FmpCapsuleHeader = bytes.pread_with(0, scroll::LE)?;
+        if header.version != FMP_CAPSULE_HEADER_VERSION {
+            return Err(error::Error::Malformed(format!(
+                "FMP capsule header has an unknown version ({})",
+                header.version
+            )));
+        }
+        let count = header.embedded_driver_count as usize + header.payload_item_count as usize;
+        let mut offset = SIZEOF_FMP_CAPSULE_HEADER;
+        let mut item_offsets = Vec::with_capacity(count);
+        for _ in 0..count {
+            item_offsets.push(bytes.gread_with::<u64>(&mut offset, scroll::LE)?);
+        }
+        let item_offset = |index: usize| -> error::Result<usize> {
+            let item_offset = item_offsets[index];
+            usize::try_from(item_offset)
+                .ok()
+                .filter(|&item_offset| item_offset >= offset && item_offset <= bytes.len())
+                .ok_or_else(|| {
+                    error::Error::Malformed(format!(
+                        "FMP capsule item {} has an invalid offset ({:#x})",
+                        index, item_offset
+                    ))
+                })
+        };
+
+        // embedded drivers have no size of their own, they extend up to the next item
+        let mut embedded_drivers = Vec::with_capacity(header.embedded_driver_count as usize);
+        for index in 0..header.embedded_driver_count as usize {
+            let start = item_offset(index)?;
+            let end = if index + 1 < count {
+                item_offset(index + 1)?
+            } else {
+                bytes.len()
+            };
+            let driver = bytes.get(start..end).ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "FMP capsule embedded driver {} overlaps the next item",
+                    index
+                ))
+            })?;
+            embedded_drivers.push(driver);
+        }
+
+        let mut images = Vec::with_capacity(header.payload_item_count as usize);
+        for index in header.embedded_driver_count as usize..count {
+            images.push(FmpImage::parse(bytes, item_offset(index)?)?);
+        }
+
+        Ok(FmpCapsule {
+            header,
+            item_offsets,
+            embedded_drivers,
+            images,
+            data: bytes,
+        })
+    }
+
+    /// Parses the embedded drivers
+    pub fn drivers(&self) -> Vec<error::Result<pe::PE<'a>>> {
+        self.embedded_drivers
+            .iter()
+            .map(|driver| pe::PE::parse(driver))
+            .collect()
+    }
+}
+
+/// A UEFI capsule
+#[derive(Debug, Clone)]
+pub struct Capsule<'a> {
+    pub header: CapsuleHeader,
+    /// The body of the capsule, if it is an FMP capsule
+    pub fmp: Option<FmpCapsule<'a>>,
+    /// The capsule contents, without the header
+    pub body: &'a [u8],
+}
+
+impl<'a> Capsule<'a> {
+    /// Parses the capsule at the start of `bytes`
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        let header: CapsuleHeader = bytes.pread_with(0, scroll::LE)?;
+        let header_size = header.header_size as usize;
+        let size = header.capsule_image_size as usize;
+        if header_size < SIZEOF_CAPSULE_HEADER || header_size > size || size > bytes.len() {
+            return Err(error::Error::Malformed(format!(
+                "Capsule sizes are invalid (header: {:#x}, image: {:#x}, available: {:#x})",
+                header_size,
+                size,
+                bytes.len()
+            )));
+        }
+        let body = &bytes[header_size..size];
+        let fmp = if header.capsule_guid == FIRMWARE_MANAGEMENT_CAPSULE_ID_GUID {
+            Some(FmpCapsule::parse(body)?)
+        } else {
+            None
+        };
+        Ok(Capsule { header, fmp, body })
+    }
+
+    /// Finds and parses the firmware volumes carried by the capsule: those of the update images
+    /// of an FMP capsule, or those of the body of any other capsule
+    pub fn firmware_volumes(&self) -> Vec<FirmwareVolume<'a>> {
+        match &self.fmp {
+            Some(fmp) => fmp
+                .images
+                .iter()
+                .flat_map(|image| image.firmware_volumes())
+                .collect(),
+            None => FirmwareVolume::find_all(self.body),
+        }
+    }
+
+    /// Calls `visitor` on every PE32 and TE image found in the firmware volumes of the capsule,
+    /// along with the name of its file; see [`FirmwareVolume::executables`]
+    pub fn executables(&self, mut visitor: impl FnMut(Guid, error::Result<Executable>)) {
+        for volume in self.firmware_volumes() {
+            volume.executables(&mut visitor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pe::certificate_table::EFI_CERT_TYPE_PKCS7_GUID;
+    use crate::uefi::fv::tests::sample_volume;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+
+    const IMAGE_TYPE_ID: Guid = Guid::new(0x1234_5678, 0x9abc, 0xdef0, [0x11; 8]);
+
+    fn fmp_image(volume: &[u8], signature: &[u8]) -> Vec<u8> {
+        let mut data = 7u64.to_le_bytes().to_vec();
+        let length = (8 + 16 + signature.len()) as u32;
+        data.extend_from_slice(&length.to_le_bytes());
+        data.extend_from_slice(&0x0200u16.to_le_bytes());
+        data.extend_from_slice(&0x0ef1u16.to_le_bytes());
+        data.extend_from_slice(&EFI_CERT_TYPE_PKCS7_GUID.to_bytes());
+        data.extend_from_slice(signature);
+        let payload = FmpPayloadHeader {
+            signature: FMP_PAYLOAD_HEADER_SIGNATURE,
+            header_size: SIZEOF_FMP_PAYLOAD_HEADER as u32,
+            fw_version: 0x0102_0304,
+            lowest_supported_version: 1,
+        };
+        let mut payload_bytes = [0u8; SIZEOF_FMP_PAYLOAD_HEADER];
+        payload_bytes.pwrite_with(payload, 0, scroll::LE).unwrap();
+        data.extend_from_slice(&payload_bytes);
+        data.extend_from_slice(volume);
+
+        let mut bytes = 3u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&IMAGE_TYPE_ID.to_bytes());
+        bytes.extend_from_slice(&[1, 0, 0, 0]);
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&CAPSULE_SUPPORT_AUTHENTICATION.to_le_bytes());
+        bytes.extend(data);
+        bytes.extend_from_slice(b"vend");
+        bytes
+    }
+
+    fn fmp_capsule(volume: &[u8]) -> Vec<u8> {
+        let signature = &pe::PE::parse(REALTEK_LAN).unwrap().certificates[0].certificate[..64];
+        let image = fmp_image(volume, signature);
+
+        let mut fmp = vec![0u8; SIZEOF_FMP_CAPSULE_HEADER + 2 * 8];
+        fmp.pwrite_with(
+            FmpCapsuleHeader {
+                version: FMP_CAPSULE_HEADER_VERSION,
+                embedded_driver_count: 1,
+                payload_item_count: 1,
+            },
+            0,
+            scroll::LE,
+        )
+        .unwrap();
+        let driver_offset = fmp.len();
+        let image_offset = driver_offset + REALTEK_LAN.len();
+        fmp.pwrite_with(driver_offset as u64, 8, scroll::LE)
+            .unwrap();
+        fmp.pwrite_with(image_offset as u64, 16, scroll::LE)
+            .unwrap();
+        fmp.extend_from_slice(REALTEK_LAN);
+        fmp.extend(image);
+
+        let header = CapsuleHeader {
+            capsule_guid: FIRMWARE_MANAGEMENT_CAPSULE_ID_GUID,
+            header_size: 32,
+            flags: CAPSULE_FLAGS_PERSIST_ACROSS_RESET,
+            capsule_image_size: (32 + fmp.len()) as u32,
+        };
+        let mut bytes = vec![0u8; 32];
+        bytes.pwrite_with(header, 0, scroll::LE).unwrap();
+        bytes.extend(fmp);
+        bytes
+    }
+
+    #[test]
+    fn parse_fmp_capsule() {
+        let volume = sample_volume();
+        let bytes = fmp_capsule(&volume);
+        let capsule = Capsule::parse(&bytes).unwrap();
+        assert_eq!(capsule.header.flags, CAPSULE_FLAGS_PERSIST_ACROSS_RESET);
+        let fmp = capsule.fmp.as_ref().unwrap();
+        assert_eq!(fmp.embedded_drivers, [REALTEK_LAN]);
+        let signature = &pe::PE::parse(REALTEK_LAN).unwrap().certificates[0].certificate[..64];
+        assert!(fmp.drivers()[0].is_ok());
+
+        assert_eq!(fmp.images.len(), 1);
+        let image = &fmp.images[0];
+        assert_eq!(image.header.version, 3);
+        assert_eq!(image.header.update_image_type_id, IMAGE_TYPE_ID);
+        assert_eq!(image.header.update_image_index, 1);
+        assert_eq!(image.fw_version(), Some(0x0102_0304));
+        assert_eq!(image.vendor_code, b"vend");
+        assert_eq!(image.image, &volume[..]);
+
+        let auth = image.authentication.as_ref().unwrap();
+        assert_eq!(auth.monotonic_count, 7);
+        assert_eq!(
+            auth.certificate.certificate_type,
+            AttributeCertificateType::EfiGuid
+        );
+        let certificate = auth.uefi_guid_certificate().unwrap();
+        assert_eq!(certificate.cert_type, EFI_CERT_TYPE_PKCS7_GUID);
+        assert_eq!(certificate.data, signature);
+
+        assert_eq!(capsule.firmware_volumes().len(), 1);
+        let mut executables = 0;
+        capsule.executables(|_, _| executables += 1);
+        assert_eq!(executables, 2);
+    }
+
+    #[test]
+    fn parse_firmware_volume_capsule() {
+        let volume = sample_volume();
+        let header = CapsuleHeader {
+            capsule_guid: CAPSULE_GUID,
+            header_size: SIZEOF_CAPSULE_HEADER as u32,
+            flags: 0,
+            capsule_image_size: (SIZEOF_CAPSULE_HEADER + volume.len()) as u32,
+        };
+        let mut bytes = vec![0u8; SIZEOF_CAPSULE_HEADER];
+        bytes.pwrite_with(header, 0, scroll::LE).unwrap();
+        bytes.extend_from_slice(&volume);
+
+        let capsule = Capsule::parse(&bytes).unwrap();
+        assert!(capsule.fmp.is_none());
+        assert_eq!(capsule.firmware_volumes().len(), 1);
+        let mut executables = 0;
+        capsule.executables(|_, _| executables += 1);
+        assert_eq!(executables, 2);
+    }
+
+    #[test]
+    fn truncated_image() {
+        let volume = sample_volume();
+        let mut bytes = fmp_capsule(&volume);
+        let length = bytes.len() as u32 - 8;
+        bytes.pwrite_with(length, 24, scroll::LE).unwrap();
+        assert!(Capsule::parse(&bytes[..length as usize]).is_err());
+    }
+}
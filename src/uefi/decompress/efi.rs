@@ -0,0 +1,476 @@
+//! The EFI and Tiano decompressors.
+//!
+//! Both formats are an LZ77 variant with static Huffman coding (derived from LZH), introduced by
+//! the EFI specification for `EFI_COMPRESSION_SECTION`. Tiano compression is the same format
+//! with a larger window, which needs one more bit to encode the match position table size.
+//!
+//! This is a port of EDK2's `BaseUefiDecompressLib`.
+
+use alloc::vec::Vec;
+use scroll::Pread;
+
+use crate::error;
+
+const BITBUFSIZ: u32 = 32;
+const MAXMATCH: usize = 256;
+const THRESHOLD: usize = 3;
+const CODE_BIT: usize = 16;
+/// Number of literal and match length symbols
+const NC: usize = 0xff + MAXMATCH + 2 - THRESHOLD;
+const CBIT: u32 = 9;
+const MAXPBIT: usize = 5;
+const TBIT: u32 = 5;
+const MAXNP: usize = (1 << MAXPBIT) - 1;
+const NT: usize = CODE_BIT + 3;
+const NPT: usize = if NT > MAXNP { NT } else { MAXNP };
+const NODES: usize = 2 * NC - 1;
+
+/// The size of the compressed and original size fields preceding the bit stream
+pub const SIZEOF_HEADER: usize = 8;
+
+/// Which of the two closely related formats to decode
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Version {
+    /// EFI 1.1 compression, 4-bit position table size
+    Efi,
+    /// Tiano compression, 5-bit position table size
+    Tiano,
+}
+
+/// A child slot of a Huffman table entry, which either lives in the lookup table or in the
+/// left/right trees for codes longer than the table width
+#[derive(Copy, Clone)]
+enum Slot {
+    Table(usize),
+    Left(usize),
+    Right(usize),
+}
+
+struct Decoder<'a> {
+    src: &'a [u8],
+    in_pos: usize,
+    comp_size: u32,
+    /// Number of bits shifted out of the bit buffer, including the initial fill
+    bit_pos: u64,
+    bit_count: u32,
+    bit_buf: u32,
+    sub_bit_buf: u32,
+    block_size: u16,
+    pbit: u32,
+    left: [u16; NODES],
+    right: [u16; NODES],
+    c_len: [u8; NC],
+    pt_len: [u8; NPT],
+    c_table: [u16; 4096],
+    pt_table: [u16; 256],
+}
+
+fn bad_table() -> error::Error {
+    error::Error::Malformed("EFI compressed data has an invalid Huffman table".into())
+}
+
+impl<'a> Decoder<'a> {
+    /// Shifts `n` bits out of the bit buffer, refilling it from the source
+    fn fill_buf(&mut self, n: u32) {
+        let mut n = n;
+        self.bit_pos += n as u64;
+        self.bit_buf = ((self.bit_buf as u64) << n) as u32;
+        while n > self.bit_count {
+            n -= self.bit_count;
+            self.bit_buf |= ((self.sub_bit_buf as u64) << n) as u32;
+            if self.comp_size > 0 {
+                self.comp_size -= 1;
+                self.sub_bit_buf = self.src.get(self.in_pos).copied().unwrap_or(0) as u32;
+                self.in_pos += 1;
+            } else {
+                self.sub_bit_buf = 0;
+            }
+            self.bit_count = 8;
+        }
+        self.bit_count -= n;
+        self.bit_buf |= self.sub_bit_buf >> self.bit_count;
+    }
+
+    /// Fails if the decoded symbols used more bits than the compressed data holds, the bit buffer
+    /// being padded with zeroes past its end
+    fn check_exhausted(&self) -> error::Result<()> {
+        if self.bit_pos > BITBUFSIZ as u64 + self.src.len() as u64 * 8 {
+            return Err(error::Error::Malformed(
+                "EFI compressed data ends before the original size is reached".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn get_bits(&mut self, n: u32) -> u32 {
+        let bits = ((self.bit_buf as u64) >> (BITBUFSIZ - n)) as u32;
+        self.fill_buf(n);
+        bits
+    }
+
+    fn set_slot(&mut self, table: &mut [u16], slot: Slot, value: u16) {
+        match slot {
+            Slot::Table(index) => table[index] = value,
+            Slot::Left(index) => self.left[index] = value,
+            Slot::Right(index) => self.right[index] = value,
+        }
+    }
+
+    fn get_slot(&self, table: &[u16], slot: Slot) -> u16 {
+        match slot {
+            Slot::Table(index) => table[index],
+            Slot::Left(index) => self.left[index],
+            Slot::Right(index) => self.right[index],
+        }
+    }
+
+    /// Builds the lookup table for the code lengths in `bit_len`
+    fn make_table(
+        &mut self,
+        num_of_char: usize,
+        bit_len: &[u8],
+        table_bits: u32,
+        table: &mut [u16],
+    ) -> error::Result<()> {
+        let mut count = [0u16; 17];
+        let mut weight = [0u16; 17];
+        let mut start = [0u16; 18];
+
+        for &len in &bit_len[..num_of_char] {
+            if len > 16 {
+                return Err(bad_table());
+            }
+            count[len as usize] += 1;
+        }
+
+        for index in 1..=16 {
+            let word = (start[index] as u32).wrapping_add((count[index] as u32) << (16 - index));
+            start[index + 1] = word as u16;
+        }
+        if start[17] != 0 {
+            return Err(bad_table());
+        }
+
+        let ju_bits = 16 - table_bits;
+        for index in 1..=table_bits as usize {
+            start[index] >>= ju_bits;
+            weight[index] = 1 << (table_bits as usize - index);
+        }
+        for (index, weight) in weight.iter_mut().enumerate().skip(table_bits as usize + 1) {
+            *weight = 1 << (16 - index);
+        }
+
+        let index = (start[table_bits as usize + 1] >> ju_bits) as usize;
+        let table_size = 1usize << table_bits;
+        if index != 0 && index < table_size {
+            for entry in &mut table[index..table_size] {
+                *entry = 0;
+            }
+        }
+
+        let mut avail = num_of_char;
+        let mask = 1u16 << (15 - table_bits);
+        for (char, &len) in bit_len[..num_of_char].iter().enumerate() {
+            let len = len as usize;
+            if len == 0 {
+                continue;
+            }
+            let next_code = start[len].wrapping_add(weight[len]);
+            if len <= table_bits as usize {
+                if start[len] >= next_code || next_code as usize > table_size {
+                    return Err(bad_table());
+                }
+                for entry in &mut table[start[len] as usize..next_code as usize] {
+                    *entry = char as u16;
+                }
+            } else {
+                let mut code = start[len];
+                let mut slot = Slot::Table((code >> ju_bits) as usize);
+                for _ in 0..len - table_bits as usize {
+                    if self.get_slot(table, slot) == 0 && avail < NODES {
+                        self.right[avail] = 0;
+                        self.left[avail] = 0;
+                        self.set_slot(table, slot, avail as u16);
+                        avail += 1;
+                    }
+                    let node = self.get_slot(table, slot) as usize;
+                    if node < NODES {
+                        slot = if code & mask != 0 {
+                            Slot::Right(node)
+                        } else {
+                            Slot::Left(node)
+                        };
+                    }
+                    code <<= 1;
+                }
+                self.set_slot(table, slot, char as u16);
+            }
+            start[len] = next_code;
+        }
+        Ok(())
+    }
+
+    /// Walks the left/right trees from `value` for codes longer than `table_bits`
+    fn walk_tree(&self, mut value: u16, limit: usize, table_bits: u32) -> error::Result<u16> {
+        let mut mask = 1u32 << (BITBUFSIZ - 1 - table_bits);
+        while value as usize >= limit {
+            if mask == 0 || value as usize >= NODES {
+                return Err(bad_table());
+            }
+            value = if self.bit_buf & mask != 0 {
+                self.right[value as usize]
+            } else {
+                self.left[value as usize]
+            };
+            mask >>= 1;
+        }
+        Ok(value)
+    }
+
+    /// Decodes a match position
+    fn decode_p(&mut self) -> error::Result<u32> {
+        let value = self.pt_table[(self.bit_buf >> (BITBUFSIZ - 8)) as usize];
+        let value = self.walk_tree(value, MAXNP, 8)?;
+        self.fill_buf(self.pt_len[value as usize] as u32);
+        let value = value as u32;
+        let value = if value > 1 {
+            (1 << (value - 1)) + self.get_bits(value - 1)
+        } else {
+            value
+        };
+        self.check_exhausted()?;
+        Ok(value)
+    }
+
+    /// Reads the code lengths of the code length tree (`special` is `Some(3)`) or of the
+    /// position tree
+    fn read_pt_len(&mut self, nn: usize, nbit: u32, special: Option<usize>) -> error::Result<()> {
+        let number = self.get_bits(nbit) as usize;
+        if number == 0 {
+            let char = self.get_bits(nbit) as u16;
+            self.pt_table = [char; 256];
+            for len in &mut self.pt_len[..nn] {
+                *len = 0;
+            }
+            return Ok(());
+        }
+
+        let mut index = 0;
+        while index < number && index < NPT {
+            let mut char = self.bit_buf >> (BITBUFSIZ - 3);
+            if char == 7 {
+                let mut mask = 1u32 << (BITBUFSIZ - 1 - 3);
+                while mask & self.bit_buf != 0 {
+                    mask >>= 1;
+                    char += 1;
+                }
+            }
+            self.fill_buf(if char < 7 { 3 } else { char - 3 });
+            self.pt_len[index] = char as u8;
+            index += 1;
+            if Some(index) == special {
+                let zeros = self.get_bits(2) as usize;
+                for _ in 0..zeros {
+                    if index >= NPT {
+                        break;
+                    }
+                    self.pt_len[index] = 0;
+                    index += 1;
+                }
+            }
+        }
+        while index < nn && index < NPT {
+            self.pt_len[index] = 0;
+            index += 1;
+        }
+
+        let pt_len = self.pt_len;
+        let mut pt_table = self.pt_table;
+        self.make_table(nn, &pt_len, 8, &mut pt_table)?;
+        self.pt_table = pt_table;
+        Ok(())
+    }
+
+    /// Reads the code lengths of the literal and match length tree
+    fn read_c_len(&mut self) -> error::Result<()> {
+        let number = self.get_bits(CBIT) as usize;
+        if number == 0 {
+            let char = self.get_bits(CBIT) as u16;
+            self.c_len = [0; NC];
+            self.c_table = [char; 4096];
+            return Ok(());
+        }
+
+        let mut index = 0;
+        while index < number && index < NC {
+            let char = self.pt_table[(self.bit_buf >> (BITBUFSIZ - 8)) as usize];
+            let char = self.walk_tree(char, NT, 8)?;
+            self.fill_buf(self.pt_len[char as usize] as u32);
+            if char <= 2 {
+                let zeros = match char {
+                    0 => 1,
+                    1 => self.get_bits(4) as usize + 3,
+                    _ => self.get_bits(CBIT) as usize + 20,
+                };
+                for _ in 0..zeros {
+                    if index >= NC {
+                        break;
+                    }
+                    self.c_len[index] = 0;
+                    index += 1;
+                }
+            } else {
+                self.c_len[index] = (char - 2) as u8;
+                index += 1;
+            }
+        }
+        for len in &mut self.c_len[index..] {
+            *len = 0;
+        }
+
+        let c_len = self.c_len;
+        let mut c_table = self.c_table;
+        self.make_table(NC, &c_len, 12, &mut c_table)?;
+        self.c_table = c_table;
+        Ok(())
+    }
+
+    /// Decodes a literal or match length symbol, reading a new block header if needed
+    fn decode_c(&mut self) -> error::Result<u16> {
+        if self.block_size == 0 {
+            self.block_size = self.get_bits(16) as u16;
+            self.read_pt_len(NT, TBIT, Some(3))?;
+            self.read_c_len()?;
+            self.read_pt_len(MAXNP, self.pbit, None)?;
+        }
+        self.block_size = self.block_size.wrapping_sub(1);
+
+        let value = self.c_table[(self.bit_buf >> (BITBUFSIZ - 12)) as usize];
+        let value = self.walk_tree(value, NC, 12)?;
+        self.fill_buf(self.c_len[value as usize] as u32);
+        self.check_exhausted()?;
+        Ok(value)
+    }
+}
+
+/// Decompresses `bytes`, made of the compressed and original sizes followed by the bit stream,
+/// failing if the original size is larger than `max_size`
+pub fn decompress(bytes: &[u8], version: Version, max_size: usize) -> error::Result<Vec<u8>> {
+    let comp_size: u32 = bytes.pread_with(0, scroll::LE)?;
+    let orig_size: u32 = bytes.pread_with(4, scroll::LE)?;
+    if (comp_size as usize).saturating_add(SIZEOF_HEADER) > bytes.len() {
+        return Err(error::Error::Malformed(format!(
+            "EFI compressed size ({:#x}) exceeds the available data ({:#x})",
+            comp_size,
+            bytes.len()
+        )));
+    }
+
+    let mut decoder = Decoder {
+        src: &bytes[SIZEOF_HEADER..SIZEOF_HEADER + comp_size as usize],
+        in_pos: 0,
+        comp_size,
+        bit_pos: 0,
+        bit_count: 0,
+        bit_buf: 0,
+        sub_bit_buf: 0,
+        block_size: 0,
+        pbit: match version {
+            Version::Efi => 4,
+            Version::Tiano => 5,
+        },
+        left: [0; NODES],
+        right: [0; NODES],
+        c_len: [0; NC],
+        pt_len: [0; NPT],
+        c_table: [0; 4096],
+        pt_table: [0; 256],
+    };
+    decoder.fill_buf(BITBUFSIZ);
+
+    let orig_size = orig_size as usize;
+    if orig_size > max_size {
+        return Err(super::too_large(max_size));
+    }
+    // the original size is untrusted, don't reserve more than the data could plausibly expand to
+    let mut out = Vec::with_capacity(orig_size.min(bytes.len() * 16));
+    while out.len() < orig_size {
+        let char = decoder.decode_c()? as usize;
+        if char < 256 {
+            out.push(char as u8);
+            continue;
+        }
+
+        let length = char - (256 - THRESHOLD);
+        let distance = decoder.decode_p()? as usize + 1;
+        let start = out.len().checked_sub(distance).ok_or_else(|| {
+            error::Error::Malformed(format!(
+                "EFI compressed data refers {:#x} bytes back at offset {:#x}",
+                distance,
+                out.len()
+            ))
+        })?;
+        for index in start..start + length {
+            if out.len() >= orig_size {
+                break;
+            }
+            out.push(out[index]);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_SIZE: usize = 0x10_0000;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../../tests/bins/efi/RealtekLan.efi");
+    static EFI_COMPRESSED: &[u8] = include_bytes!("../../../tests/bins/uefi/RealtekLan.efi.efi");
+    static TIANO_COMPRESSED: &[u8] =
+        include_bytes!("../../../tests/bins/uefi/RealtekLan.efi.tiano");
+
+    #[test]
+    fn efi() {
+        assert_eq!(
+            decompress(EFI_COMPRESSED, Version::Efi, MAX_SIZE).unwrap(),
+            REALTEK_LAN
+        );
+    }
+
+    #[test]
+    fn tiano() {
+        assert_eq!(
+            decompress(TIANO_COMPRESSED, Version::Tiano, MAX_SIZE).unwrap(),
+            REALTEK_LAN
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(decompress(&EFI_COMPRESSED[..100], Version::Efi, MAX_SIZE).is_err());
+    }
+
+    #[test]
+    fn exhausted() {
+        // a shorter compressed size cuts the bit stream while output is still expected
+        let mut bytes = EFI_COMPRESSED.to_vec();
+        bytes[..4].copy_from_slice(&100u32.to_le_bytes());
+        assert!(decompress(&bytes, Version::Efi, MAX_SIZE).is_err());
+
+        // an empty bit stream can't produce 4GiB of output
+        let mut bytes = [0u8; SIZEOF_HEADER];
+        bytes[4..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(decompress(&bytes, Version::Efi, usize::MAX).is_err());
+        assert!(decompress(&bytes, Version::Tiano, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn max_size() {
+        let size = REALTEK_LAN.len();
+        assert!(decompress(EFI_COMPRESSED, Version::Efi, size).is_ok());
+        assert!(decompress(EFI_COMPRESSED, Version::Efi, size - 1).is_err());
+        assert!(decompress(TIANO_COMPRESSED, Version::Tiano, size - 1).is_err());
+    }
+}
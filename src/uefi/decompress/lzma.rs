@@ -0,0 +1,443 @@
+//! The LZMA decompressor, for the `.lzma` ("LZMA alone") streams produced by EDK2's
+//! `LzmaCompress`, and the x86 branch converter used by `LzmaF86Compress`.
+//!
+//! A stream is a 13-byte header (properties byte, dictionary size, uncompressed size) followed
+//! by the range coded data. This follows the reference decoder of the LZMA SDK (`LzmaSpec.cpp`).
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use scroll::Pread;
+
+use crate::error;
+
+/// The size of the properties, dictionary size and uncompressed size preceding the stream
+pub const SIZEOF_HEADER: usize = 13;
+
+const NUM_BIT_MODEL_TOTAL_BITS: u32 = 11;
+const BIT_MODEL_TOTAL: u32 = 1 << NUM_BIT_MODEL_TOTAL_BITS;
+const NUM_MOVE_BITS: u32 = 5;
+const PROB_INIT: u16 = (BIT_MODEL_TOTAL / 2) as u16;
+const TOP_VALUE: u32 = 1 << 24;
+
+const NUM_STATES: usize = 12;
+const NUM_POS_BITS_MAX: usize = 4;
+const NUM_LEN_TO_POS_STATES: usize = 4;
+const NUM_ALIGN_BITS: usize = 4;
+const START_POS_MODEL_INDEX: u32 = 4;
+const END_POS_MODEL_INDEX: u32 = 14;
+const NUM_FULL_DISTANCES: usize = 1 << (END_POS_MODEL_INDEX >> 1);
+const MATCH_MIN_LEN: usize = 2;
+
+fn corrupted() -> error::Error {
+    error::Error::Malformed("LZMA stream is corrupted".into())
+}
+
+struct RangeDecoder<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    range: u32,
+    code: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    fn new(bytes: &'a [u8]) -> error::Result<Self> {
+        let mut decoder = RangeDecoder {
+            bytes,
+            offset: 0,
+            range: 0xffff_ffff,
+            code: 0,
+        };
+        if decoder.next_byte()? != 0 {
+            return Err(corrupted());
+        }
+        for _ in 0..4 {
+            decoder.code = (decoder.code << 8) | decoder.next_byte()? as u32;
+        }
+        if decoder.code == decoder.range {
+            return Err(corrupted());
+        }
+        Ok(decoder)
+    }
+
+    fn next_byte(&mut self) -> error::Result<u8> {
+        let byte = self.bytes.pread::<u8>(self.offset)?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn normalize(&mut self) -> error::Result<()> {
+        if self.range < TOP_VALUE {
+            self.range <<= 8;
+            self.code = (self.code << 8) | self.next_byte()? as u32;
+        }
+        Ok(())
+    }
+
+    fn decode_direct_bits(&mut self, num_bits: u32) -> error::Result<u32> {
+        let mut res = 0u32;
+        for _ in 0..num_bits {
+            self.range >>= 1;
+            self.code = self.code.wrapping_sub(self.range);
+            let t = 0u32.wrapping_sub(self.code >> 31);
+            self.code = self.code.wrapping_add(self.range & t);
+            if self.code == self.range {
+                return Err(corrupted());
+            }
+            self.normalize()?;
+            res = (res << 1).wrapping_add(t.wrapping_add(1));
+        }
+        Ok(res)
+    }
+
+    fn decode_bit(&mut self, prob: &mut u16) -> error::Result<u32> {
+        let bound = (self.range >> NUM_BIT_MODEL_TOTAL_BITS) * *prob as u32;
+        let bit = if self.code < bound {
+            *prob += ((BIT_MODEL_TOTAL - *prob as u32) >> NUM_MOVE_BITS) as u16;
+            self.range = bound;
+            0
+        } else {
+            *prob -= *prob >> NUM_MOVE_BITS;
+            self.code -= bound;
+            self.range -= bound;
+            1
+        };
+        self.normalize()?;
+        Ok(bit)
+    }
+
+    fn bit_tree_decode(&mut self, probs: &mut [u16], num_bits: u32) -> error::Result<u32> {
+        let mut m = 1usize;
+        for _ in 0..num_bits {
+            m = (m << 1) + self.decode_bit(&mut probs[m])? as usize;
+        }
+        Ok(m as u32 - (1 << num_bits))
+    }
+
+    fn bit_tree_reverse_decode(&mut self, probs: &mut [u16], num_bits: u32) -> error::Result<u32> {
+        let mut m = 1usize;
+        let mut symbol = 0u32;
+        for i in 0..num_bits {
+            let bit = self.decode_bit(&mut probs[m])?;
+            m = (m << 1) + bit as usize;
+            symbol |= bit << i;
+        }
+        Ok(symbol)
+    }
+}
+
+struct LenDecoder {
+    choice: u16,
+    choice2: u16,
+    low: [[u16; 1 << 3]; 1 << NUM_POS_BITS_MAX],
+    mid: [[u16; 1 << 3]; 1 << NUM_POS_BITS_MAX],
+    high: [u16; 1 << 8],
+}
+
+impl LenDecoder {
+    fn new() -> Self {
+        LenDecoder {
+            choice: PROB_INIT,
+            choice2: PROB_INIT,
+            low: [[PROB_INIT; 1 << 3]; 1 << NUM_POS_BITS_MAX],
+            mid: [[PROB_INIT; 1 << 3]; 1 << NUM_POS_BITS_MAX],
+            high: [PROB_INIT; 1 << 8],
+        }
+    }
+
+    fn decode(&mut self, rc: &mut RangeDecoder, pos_state: usize) -> error::Result<usize> {
+        if rc.decode_bit(&mut self.choice)? == 0 {
+            return Ok(rc.bit_tree_decode(&mut self.low[pos_state], 3)? as usize);
+        }
+        if rc.decode_bit(&mut self.choice2)? == 0 {
+            return Ok(8 + rc.bit_tree_decode(&mut self.mid[pos_state], 3)? as usize);
+        }
+        Ok(16 + rc.bit_tree_decode(&mut self.high, 8)? as usize)
+    }
+}
+
+/// Decompresses an LZMA stream with its 13-byte header, failing if it is larger than `max_size`
+/// bytes
+pub fn decompress(bytes: &[u8], max_size: usize) -> error::Result<Vec<u8>> {
+    let mut properties: u8 = bytes.pread(0)?;
+    let dict_size: u32 = bytes.pread_with(1, scroll::LE)?;
+    let unpack_size: u64 = bytes.pread_with(5, scroll::LE)?;
+    if properties >= 9 * 5 * 5 {
+        return Err(error::Error::Malformed(format!(
+            "LZMA properties are invalid ({:#x})",
+            properties
+        )));
+    }
+    let lc = (properties % 9) as u32;
+    properties /= 9;
+    let lp = (properties % 5) as u32;
+    let pb = (properties / 5) as u32;
+    // the uncompressed size is all ones when the stream is terminated by an end marker instead
+    let unpack_size = if unpack_size == u64::MAX {
+        None
+    } else {
+        Some(usize::try_from(unpack_size).map_err(|_| corrupted())?)
+    };
+    if matches!(unpack_size, Some(size) if size > max_size) {
+        return Err(super::too_large(max_size));
+    }
+    let dict_size = dict_size.max(1 << 12) as usize;
+
+    let mut rc = RangeDecoder::new(&bytes[SIZEOF_HEADER..])?;
+    let mut literal_probs = vec![PROB_INIT; 0x300 << (lc + lp)];
+    let mut pos_slot = [[PROB_INIT; 1 << 6]; NUM_LEN_TO_POS_STATES];
+    let mut pos_decoders = [PROB_INIT; 1 + NUM_FULL_DISTANCES - END_POS_MODEL_INDEX as usize];
+    let mut align = [PROB_INIT; 1 << NUM_ALIGN_BITS];
+    let mut is_match = [PROB_INIT; NUM_STATES << NUM_POS_BITS_MAX];
+    let mut is_rep = [PROB_INIT; NUM_STATES];
+    let mut is_rep_g0 = [PROB_INIT; NUM_STATES];
+    let mut is_rep_g1 = [PROB_INIT; NUM_STATES];
+    let mut is_rep_g2 = [PROB_INIT; NUM_STATES];
+    let mut is_rep0_long = [PROB_INIT; NUM_STATES << NUM_POS_BITS_MAX];
+    let mut len_decoder = LenDecoder::new();
+    let mut rep_len_decoder = LenDecoder::new();
+
+    // cap the initial allocation, the size comes from untrusted input
+    let mut out = Vec::with_capacity(unpack_size.unwrap_or(0).min(bytes.len() * 16));
+    let (mut rep0, mut rep1, mut rep2, mut rep3) = (0usize, 0usize, 0usize, 0usize);
+    let mut state = 0usize;
+
+    loop {
+        if unpack_size == Some(out.len()) {
+            break;
+        }
+        let pos_state = out.len() & ((1 << pb) - 1);
+
+        if rc.decode_bit(&mut is_match[(state << NUM_POS_BITS_MAX) + pos_state])? == 0 {
+            let prev_byte = out.last().copied().unwrap_or(0) as usize;
+            let lit_state =
+                ((out.len() & ((1 << lp) - 1)) << lc) + (prev_byte >> (8 - lc as usize));
+            let probs = &mut literal_probs[0x300 * lit_state..0x300 * (lit_state + 1)];
+            let mut symbol = 1usize;
+            if state >= 7 {
+                let mut match_byte = out[out.len() - rep0 - 1] as usize;
+                while symbol < 0x100 {
+                    let match_bit = (match_byte >> 7) & 1;
+                    match_byte <<= 1;
+                    let bit = rc.decode_bit(&mut probs[((1 + match_bit) << 8) + symbol])? as usize;
+                    symbol = (symbol << 1) | bit;
+                    if match_bit != bit {
+                        break;
+                    }
+                }
+            }
+            while symbol < 0x100 {
+                symbol = (symbol << 1) | rc.decode_bit(&mut probs[symbol])? as usize;
+            }
+            if out.len() == max_size {
+                return Err(super::too_large(max_size));
+            }
+            out.push((symbol - 0x100) as u8);
+            state = if state < 4 {
+                0
+            } else if state < 10 {
+                state - 3
+            } else {
+                state - 6
+            };
+            continue;
+        }
+
+        let mut len;
+        if rc.decode_bit(&mut is_rep[state])? != 0 {
+            if out.is_empty() {
+                return Err(corrupted());
+            }
+            if rc.decode_bit(&mut is_rep_g0[state])? == 0 {
+                if rc.decode_bit(&mut is_rep0_long[(state << NUM_POS_BITS_MAX) + pos_state])? == 0 {
+                    // short rep: a single byte at distance rep0
+                    state = if state < 7 { 9 } else { 11 };
+                    out.push(out[out.len() - rep0 - 1]);
+                    continue;
+                }
+            } else {
+                let dist;
+                if rc.decode_bit(&mut is_rep_g1[state])? == 0 {
+                    dist = rep1;
+                } else {
+                    if rc.decode_bit(&mut is_rep_g2[state])? == 0 {
+                        dist = rep2;
+                    } else {
+                        dist = rep3;
+                        rep3 = rep2;
+                    }
+                    rep2 = rep1;
+                }
+                rep1 = rep0;
+                rep0 = dist;
+            }
+            len = rep_len_decoder.decode(&mut rc, pos_state)?;
+            state = if state < 7 { 8 } else { 11 };
+        } else {
+            rep3 = rep2;
+            rep2 = rep1;
+            rep1 = rep0;
+            len = len_decoder.decode(&mut rc, pos_state)?;
+            state = if state < 7 { 7 } else { 10 };
+
+            let len_state = len.min(NUM_LEN_TO_POS_STATES - 1);
+            let slot = rc.bit_tree_decode(&mut pos_slot[len_state], 6)?;
+            rep0 = if slot < START_POS_MODEL_INDEX {
+                slot as usize
+            } else {
+                let num_direct_bits = (slot >> 1) - 1;
+                let mut dist = (2 | (slot & 1)) << num_direct_bits;
+                if slot < END_POS_MODEL_INDEX {
+                    let base = (dist - slot) as usize;
+                    dist +=
+                        rc.bit_tree_reverse_decode(&mut pos_decoders[base..], num_direct_bits)?;
+                } else {
+                    dist += rc.decode_direct_bits(num_direct_bits - NUM_ALIGN_BITS as u32)?
+                        << NUM_ALIGN_BITS;
+                    dist += rc.bit_tree_reverse_decode(&mut align, NUM_ALIGN_BITS as u32)?;
+                }
+                dist as usize
+            };
+
+            if rep0 == 0xffff_ffff {
+                // end marker
+                if unpack_size.is_some() && unpack_size != Some(out.len()) {
+                    return Err(corrupted());
+                }
+                break;
+            }
+            if rep0 >= dict_size || rep0 >= out.len() {
+                return Err(corrupted());
+            }
+        }
+
+        len += MATCH_MIN_LEN;
+        match unpack_size {
+            Some(unpack_size) if out.len() + len > unpack_size => return Err(corrupted()),
+            None if out.len() + len > max_size => return Err(super::too_large(max_size)),
+            _ => (),
+        }
+        let start = out.len() - rep0 - 1;
+        for index in start..start + len {
+            out.push(out[index]);
+        }
+    }
+
+    Ok(out)
+}
+
+/// Reverts the x86 branch conversion (BCJ) applied before compression by `LzmaF86Compress`,
+/// turning the absolute `call`/`jmp` targets back into relative ones
+pub fn x86_convert_decode(data: &mut [u8]) {
+    const MASK_TO_ALLOWED_STATUS: [bool; 8] = [true, true, true, false, true, false, false, false];
+    const MASK_TO_BIT_NUMBER: [u32; 8] = [0, 1, 2, 2, 3, 3, 3, 3];
+    fn test_86_ms_byte(byte: u8) -> bool {
+        byte == 0 || byte == 0xff
+    }
+
+    if data.len() < 5 {
+        return;
+    }
+    let ip: u32 = 5;
+    let mut prev_mask: u32 = 0;
+    let mut prev_pos = usize::MAX;
+    let mut pos = 0usize;
+    let limit = data.len() - 4;
+
+    loop {
+        while pos < limit && data[pos] & 0xfe != 0xe8 {
+            pos += 1;
+        }
+        if pos >= limit {
+            break;
+        }
+
+        let distance = pos.wrapping_sub(prev_pos);
+        if distance > 3 {
+            prev_mask = 0;
+        } else {
+            prev_mask = (prev_mask << (distance - 1)) & 0x7;
+            if prev_mask != 0 {
+                let byte = data[pos + 4 - MASK_TO_BIT_NUMBER[prev_mask as usize] as usize];
+                if !MASK_TO_ALLOWED_STATUS[prev_mask as usize] || test_86_ms_byte(byte) {
+                    prev_pos = pos;
+                    prev_mask = ((prev_mask << 1) & 0x7) | 1;
+                    pos += 1;
+                    continue;
+                }
+            }
+        }
+        prev_pos = pos;
+
+        if test_86_ms_byte(data[pos + 4]) {
+            let mut src =
+                u32::from_le_bytes([data[pos + 1], data[pos + 2], data[pos + 3], data[pos + 4]]);
+            let mut dest;
+            loop {
+                dest = src.wrapping_sub(ip.wrapping_add(pos as u32));
+                if prev_mask == 0 {
+                    break;
+                }
+                let index = MASK_TO_BIT_NUMBER[prev_mask as usize] * 8;
+                let byte = (dest >> (24 - index)) as u8;
+                if !test_86_ms_byte(byte) {
+                    break;
+                }
+                src = dest ^ ((1u32 << (32 - index)) - 1);
+            }
+            data[pos + 4] = !(((dest >> 24) & 1).wrapping_sub(1)) as u8;
+            data[pos + 3] = (dest >> 16) as u8;
+            data[pos + 2] = (dest >> 8) as u8;
+            data[pos + 1] = dest as u8;
+            pos += 5;
+        } else {
+            prev_mask = ((prev_mask << 1) & 0x7) | 1;
+            pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_SIZE: usize = 0x10_0000;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../../tests/bins/efi/RealtekLan.efi");
+    static LZMA_COMPRESSED: &[u8] = include_bytes!("../../../tests/bins/uefi/RealtekLan.efi.lzma");
+    static LZMAF86_COMPRESSED: &[u8] =
+        include_bytes!("../../../tests/bins/uefi/RealtekLan.efi.lzmaf86");
+
+    #[test]
+    fn lzma() {
+        assert_eq!(decompress(LZMA_COMPRESSED, MAX_SIZE).unwrap(), REALTEK_LAN);
+    }
+
+    #[test]
+    fn lzma_f86() {
+        let mut data = decompress(LZMAF86_COMPRESSED, MAX_SIZE).unwrap();
+        assert_ne!(data, REALTEK_LAN);
+        x86_convert_decode(&mut data);
+        assert_eq!(data, REALTEK_LAN);
+    }
+
+    #[test]
+    fn end_marker() {
+        // same stream, with an unknown size: decoding stops at the end marker
+        let mut bytes = LZMA_COMPRESSED.to_vec();
+        bytes[5..13].copy_from_slice(&[0xff; 8]);
+        assert_eq!(decompress(&bytes, MAX_SIZE).unwrap(), REALTEK_LAN);
+        assert!(decompress(&bytes, REALTEK_LAN.len() - 1).is_err());
+    }
+
+    #[test]
+    fn max_size() {
+        let size = REALTEK_LAN.len();
+        assert_eq!(decompress(LZMA_COMPRESSED, size).unwrap(), REALTEK_LAN);
+        assert!(decompress(LZMA_COMPRESSED, size - 1).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(decompress(&LZMA_COMPRESSED[..LZMA_COMPRESSED.len() / 2], MAX_SIZE).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+//! Decompressors for the compressed encapsulation sections found in firmware volumes.
+//!
+//! Requires the `uefi_decompress` feature.
+
+use alloc::vec::Vec;
+
+use crate::error;
+use crate::guid::Guid;
+
+pub mod efi;
+pub mod lzma;
+
+/// `EFI_STANDARD_COMPRESSION` data in a GUID defined section, as produced by EDK2's
+/// `TianoCompress`
+pub const TIANO_CUSTOM_DECOMPRESS_GUID: Guid = Guid::new(
+    0xa312_80ad,
+    0x481e,
+    0x41b6,
+    [0x95, 0xe8, 0x12, 0x7f, 0x4c, 0x98, 0x47, 0x79],
+);
+/// LZMA compressed data in a GUID defined section
+pub const LZMA_CUSTOM_DECOMPRESS_GUID: Guid = Guid::new(
+    0xee4e_5898,
+    0x3914,
+    0x4259,
+    [0x9d, 0x6e, 0xdc, 0x7b, 0xd7, 0x94, 0x03, 0xcf],
+);
+/// LZMA compressed data in a GUID defined section, x86 branch converted before compression
+pub const LZMAF86_CUSTOM_DECOMPRESS_GUID: Guid = Guid::new(
+    0xd42a_e6bd,
+    0x1352,
+    0x4bfb,
+    [0x90, 0x9a, 0xca, 0x72, 0xa6, 0xea, 0xe8, 0x89],
+);
+
+/// The maximum size of a decompressed section when walking a firmware volume, see
+/// [`FirmwareVolume::walk`](crate::uefi::fv::FirmwareVolume::walk)
+pub const DEFAULT_MAX_SIZE: usize = 64 << 20;
+
+fn too_large(max_size: usize) -> error::Error {
+    error::Error::Malformed(format!(
+        "Decompressed data exceeds the maximum size {:#x}",
+        max_size
+    ))
+}
+
+/// Decompresses the data of an `EFI_COMPRESSION_SECTION` using `EFI_STANDARD_COMPRESSION`,
+/// failing if it is larger than `max_size` bytes.
+///
+/// Firmware in the wild uses both the EFI and the Tiano variant for these sections, and nothing
+/// in the section tells them apart: the EFI variant is tried first, then the Tiano one.
+pub fn decompress_standard(bytes: &[u8], max_size: usize) -> error::Result<Vec<u8>> {
+    efi::decompress(bytes, efi::Version::Efi, max_size)
+        .or_else(|_| efi::decompress(bytes, efi::Version::Tiano, max_size))
+}
+
+/// Decompresses the data of a GUID defined section processed by the decompressor named `guid`,
+/// failing if it is larger than `max_size` bytes, or returning `None` if it is not a known one
+pub fn decompress_guided(
+    guid: &Guid,
+    bytes: &[u8],
+    max_size: usize,
+) -> Option<error::Result<Vec<u8>>> {
+    match *guid {
+        TIANO_CUSTOM_DECOMPRESS_GUID => Some(efi::decompress(bytes, efi::Version::Tiano, max_size)),
+        LZMA_CUSTOM_DECOMPRESS_GUID => Some(lzma::decompress(bytes, max_size)),
+        LZMAF86_CUSTOM_DECOMPRESS_GUID => {
+            Some(lzma::decompress(bytes, max_size).map(|mut data| {
+                lzma::x86_convert_decode(&mut data);
+                data
+            }))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,1226 @@
+//! UEFI Platform Initialization firmware volumes, as found in SPI flash images.
+//!
+//! A firmware volume (`EFI_FIRMWARE_VOLUME_HEADER`) holds a sequence of FFS files. Apart from raw
+//! and pad files, each file is a sequence of sections: leaf sections carry PE32 or TE
+//! executables, dependency expressions, names and versions, while encapsulation sections
+//! (compression, GUID-defined) and firmware volume image sections nest further sections or
+//! volumes.
+//!
+//! See the UEFI Platform Initialization Specification, Volume 3.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use scroll::Pread;
+
+use crate::error;
+use crate::guid::Guid;
+use crate::pe;
+use crate::te;
+#[cfg(feature = "uefi_decompress")]
+use crate::uefi::decompress;
+#[cfg(feature = "uefi_decompress")]
+use log::warn;
+
+/// "_FVH"
+pub const FV_SIGNATURE: u32 = 0x4856_465f;
+/// Offset of the signature within the firmware volume header
+pub const OFFSET_FV_SIGNATURE: usize = 40;
+pub const SIZEOF_FV_HEADER: usize = 56;
+pub const SIZEOF_BLOCK_MAP_ENTRY: usize = 8;
+pub const SIZEOF_FV_EXT_HEADER: usize = 20;
+pub const SIZEOF_FV_EXT_ENTRY_HEADER: usize = 4;
+pub const SIZEOF_FFS_FILE_HEADER: usize = 24;
+pub const SIZEOF_FFS_FILE_HEADER2: usize = 32;
+pub const SIZEOF_COMMON_SECTION_HEADER: usize = 4;
+pub const SIZEOF_COMMON_SECTION_HEADER2: usize = 8;
+/// The size of the fixed part of `EFI_GUID_DEFINED_SECTION`, after the common section header
+pub const SIZEOF_GUID_DEFINED_SECTION: usize = 20;
+
+/// The deepest nesting of encapsulation sections and firmware volume images that is parsed
+const MAX_NESTING_DEPTH: usize = 32;
+
+/// EFI_FIRMWARE_FILE_SYSTEM2_GUID
+pub const FIRMWARE_FILE_SYSTEM2_GUID: Guid = Guid::new(
+    0x8c8c_e578,
+    0x8a3d,
+    0x4f1c,
+    [0x99, 0x35, 0x89, 0x61, 0x85, 0xc3, 0x2d, 0xd3],
+);
+/// EFI_FIRMWARE_FILE_SYSTEM3_GUID
+pub const FIRMWARE_FILE_SYSTEM3_GUID: Guid = Guid::new(
+    0x5473_c07a,
+    0x3dcb,
+    0x4dca,
+    [0xbd, 0x6f, 0x1e, 0x96, 0x89, 0xe7, 0x34, 0x9a],
+);
+
+/// Erased flash reads back as ones rather than zeroes
+pub const EFI_FVB2_ERASE_POLARITY: u32 = 0x0000_0800;
+
+pub const EFI_FV_FILETYPE_RAW: u8 = 0x01;
+pub const EFI_FV_FILETYPE_FREEFORM: u8 = 0x02;
+pub const EFI_FV_FILETYPE_SECURITY_CORE: u8 = 0x03;
+pub const EFI_FV_FILETYPE_PEI_CORE: u8 = 0x04;
+pub const EFI_FV_FILETYPE_DXE_CORE: u8 = 0x05;
+pub const EFI_FV_FILETYPE_PEIM: u8 = 0x06;
+pub const EFI_FV_FILETYPE_DRIVER: u8 = 0x07;
+pub const EFI_FV_FILETYPE_COMBINED_PEIM_DRIVER: u8 = 0x08;
+pub const EFI_FV_FILETYPE_APPLICATION: u8 = 0x09;
+pub const EFI_FV_FILETYPE_MM: u8 = 0x0a;
+pub const EFI_FV_FILETYPE_FIRMWARE_VOLUME_IMAGE: u8 = 0x0b;
+pub const EFI_FV_FILETYPE_COMBINED_MM_DXE: u8 = 0x0c;
+pub const EFI_FV_FILETYPE_MM_CORE: u8 = 0x0d;
+pub const EFI_FV_FILETYPE_MM_STANDALONE: u8 = 0x0e;
+pub const EFI_FV_FILETYPE_MM_CORE_STANDALONE: u8 = 0x0f;
+pub const EFI_FV_FILETYPE_OEM_MIN: u8 = 0xc0;
+pub const EFI_FV_FILETYPE_OEM_MAX: u8 = 0xdf;
+pub const EFI_FV_FILETYPE_DEBUG_MIN: u8 = 0xe0;
+pub const EFI_FV_FILETYPE_DEBUG_MAX: u8 = 0xef;
+pub const EFI_FV_FILETYPE_FFS_PAD: u8 = 0xf0;
+
+/// Returns the name of the FFS file type `typ`
+pub fn file_type_to_str(typ: u8) -> &'static str {
+    match typ {
+        EFI_FV_FILETYPE_RAW => "RAW",
+        EFI_FV_FILETYPE_FREEFORM => "FREEFORM",
+        EFI_FV_FILETYPE_SECURITY_CORE => "SECURITY_CORE",
+        EFI_FV_FILETYPE_PEI_CORE => "PEI_CORE",
+        EFI_FV_FILETYPE_DXE_CORE => "DXE_CORE",
+        EFI_FV_FILETYPE_PEIM => "PEIM",
+        EFI_FV_FILETYPE_DRIVER => "DRIVER",
+        EFI_FV_FILETYPE_COMBINED_PEIM_DRIVER => "COMBINED_PEIM_DRIVER",
+        EFI_FV_FILETYPE_APPLICATION => "APPLICATION",
+        EFI_FV_FILETYPE_MM => "MM",
+        EFI_FV_FILETYPE_FIRMWARE_VOLUME_IMAGE => "FIRMWARE_VOLUME_IMAGE",
+        EFI_FV_FILETYPE_COMBINED_MM_DXE => "COMBINED_MM_DXE",
+        EFI_FV_FILETYPE_MM_CORE => "MM_CORE",
+        EFI_FV_FILETYPE_MM_STANDALONE => "MM_STANDALONE",
+        EFI_FV_FILETYPE_MM_CORE_STANDALONE => "MM_CORE_STANDALONE",
+        EFI_FV_FILETYPE_OEM_MIN..=EFI_FV_FILETYPE_OEM_MAX => "OEM",
+        EFI_FV_FILETYPE_DEBUG_MIN..=EFI_FV_FILETYPE_DEBUG_MAX => "DEBUG",
+        EFI_FV_FILETYPE_FFS_PAD => "FFS_PAD",
+        _ => "UNKNOWN",
+    }
+}
+
+/// The file uses an `EFI_FFS_FILE_HEADER2` with a 64-bit size
+pub const FFS_ATTRIB_LARGE_FILE: u8 = 0x01;
+pub const FFS_ATTRIB_DATA_ALIGNMENT_2: u8 = 0x02;
+pub const FFS_ATTRIB_FIXED: u8 = 0x04;
+pub const FFS_ATTRIB_DATA_ALIGNMENT: u8 = 0x38;
+pub const FFS_ATTRIB_CHECKSUM: u8 = 0x40;
+
+pub const EFI_FILE_HEADER_CONSTRUCTION: u8 = 0x01;
+pub const EFI_FILE_HEADER_VALID: u8 = 0x02;
+pub const EFI_FILE_DATA_VALID: u8 = 0x04;
+pub const EFI_FILE_MARKED_FOR_UPDATE: u8 = 0x08;
+pub const EFI_FILE_DELETED: u8 = 0x10;
+pub const EFI_FILE_HEADER_INVALID: u8 = 0x20;
+
+pub const EFI_SECTION_COMPRESSION: u8 = 0x01;
+pub const EFI_SECTION_GUID_DEFINED: u8 = 0x02;
+pub const EFI_SECTION_DISPOSABLE: u8 = 0x03;
+pub const EFI_SECTION_PE32: u8 = 0x10;
+pub const EFI_SECTION_PIC: u8 = 0x11;
+pub const EFI_SECTION_TE: u8 = 0x12;
+pub const EFI_SECTION_DXE_DEPEX: u8 = 0x13;
+pub const EFI_SECTION_VERSION: u8 = 0x14;
+pub const EFI_SECTION_USER_INTERFACE: u8 = 0x15;
+pub const EFI_SECTION_COMPATIBILITY16: u8 = 0x16;
+pub const EFI_SECTION_FIRMWARE_VOLUME_IMAGE: u8 = 0x17;
+pub const EFI_SECTION_FREEFORM_SUBTYPE_GUID: u8 = 0x18;
+pub const EFI_SECTION_RAW: u8 = 0x19;
+pub const EFI_SECTION_PEI_DEPEX: u8 = 0x1b;
+pub const EFI_SECTION_MM_DEPEX: u8 = 0x1c;
+
+/// Returns the name of the section type `typ`
+pub fn section_type_to_str(typ: u8) -> &'static str {
+    match typ {
+        EFI_SECTION_COMPRESSION => "COMPRESSION",
+        EFI_SECTION_GUID_DEFINED => "GUID_DEFINED",
+        EFI_SECTION_DISPOSABLE => "DISPOSABLE",
+        EFI_SECTION_PE32 => "PE32",
+        EFI_SECTION_PIC => "PIC",
+        EFI_SECTION_TE => "TE",
+        EFI_SECTION_DXE_DEPEX => "DXE_DEPEX",
+        EFI_SECTION_VERSION => "VERSION",
+        EFI_SECTION_USER_INTERFACE => "USER_INTERFACE",
+        EFI_SECTION_COMPATIBILITY16 => "COMPATIBILITY16",
+        EFI_SECTION_FIRMWARE_VOLUME_IMAGE => "FIRMWARE_VOLUME_IMAGE",
+        EFI_SECTION_FREEFORM_SUBTYPE_GUID => "FREEFORM_SUBTYPE_GUID",
+        EFI_SECTION_RAW => "RAW",
+        EFI_SECTION_PEI_DEPEX => "PEI_DEPEX",
+        EFI_SECTION_MM_DEPEX => "MM_DEPEX",
+        _ => "UNKNOWN",
+    }
+}
+
+/// The section data is not compressed
+pub const EFI_NOT_COMPRESSED: u8 = 0x00;
+/// The section data is compressed with the EFI (Tiano) compression algorithm
+pub const EFI_STANDARD_COMPRESSION: u8 = 0x01;
+
+/// The section data must be processed (e.g., decoded) before the encapsulated sections can be read
+pub const EFI_GUIDED_SECTION_PROCESSING_REQUIRED: u16 = 0x01;
+pub const EFI_GUIDED_SECTION_AUTH_STATUS_VALID: u16 = 0x02;
+
+fn align_up(offset: usize, alignment: usize) -> usize {
+    (offset + alignment - 1) & !(alignment - 1)
+}
+
+fn read_u24(bytes: &[u8], offset: usize) -> error::Result<u32> {
+    let size: &[u8] = bytes.pread_with(offset, 3)?;
+    Ok(u32::from(size[0]) | u32::from(size[1]) << 8 | u32::from(size[2]) << 16)
+}
+
+/// Decodes a NUL-terminated UCS-2 string
+fn ucs2_to_string(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect::<Vec<_>>();
+    String::from_utf16_lossy(&units)
+}
+
+/// Returns whether `bytes` starts with a firmware volume header
+pub fn is_firmware_volume(bytes: &[u8]) -> bool {
+    bytes
+        .pread_with::<u32>(OFFSET_FV_SIGNATURE, scroll::LE)
+        .ok()
+        == Some(FV_SIGNATURE)
+}
+
+/// The fixed part of `EFI_FIRMWARE_VOLUME_HEADER`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct FirmwareVolumeHeader {
+    pub zero_vector: [u8; 16],
+    pub file_system_guid: Guid,
+    pub fv_length: u64,
+    pub signature: u32,
+    pub attributes: u32,
+    pub header_length: u16,
+    pub checksum: u16,
+    pub ext_header_offset: u16,
+    pub reserved: u8,
+    pub revision: u8,
+}
+
+impl FirmwareVolumeHeader {
+    pub fn parse(bytes: &[u8]) -> error::Result<Self> {
+        let offset = &mut 0;
+        let mut header = FirmwareVolumeHeader::default();
+        header
+            .zero_vector
+            .copy_from_slice(bytes.gread_with(offset, 16)?);
+        header.file_system_guid = bytes.gread_with(offset, scroll::LE)?;
+        header.fv_length = bytes.gread_with(offset, scroll::LE)?;
+        header.signature = bytes.gread_with(offset, scroll::LE)?;
+        header.attributes = bytes.gread_with(offset, scroll::LE)?;
+        header.header_length = bytes.gread_with(offset, scroll::LE)?;
+        header.checksum = bytes.gread_with(offset, scroll::LE)?;
+        header.ext_header_offset = bytes.gread_with(offset, scroll::LE)?;
+        header.reserved = bytes.gread_with(offset, scroll::LE)?;
+        header.revision = bytes.gread_with(offset, scroll::LE)?;
+
+        if header.signature != FV_SIGNATURE {
+            return Err(error::Error::Malformed(format!(
+                "Firmware volume header is malformed (signature: {:#x})",
+                header.signature
+            )));
+        }
+        Ok(header)
+    }
+
+    /// Whether erased flash reads back as `0xff` in this volume
+    pub fn erase_polarity(&self) -> bool {
+        self.attributes & EFI_FVB2_ERASE_POLARITY != 0
+    }
+
+    /// Whether the volume uses one of the FFS file systems this module understands
+    pub fn is_ffs(&self) -> bool {
+        self.file_system_guid == FIRMWARE_FILE_SYSTEM2_GUID
+            || self.file_system_guid == FIRMWARE_FILE_SYSTEM3_GUID
+    }
+}
+
+/// An `EFI_FV_BLOCK_MAP_ENTRY`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread)]
+pub struct BlockMapEntry {
+    pub num_blocks: u32,
+    pub length: u32,
+}
+
+/// An entry of the firmware volume extended header (`EFI_FIRMWARE_VOLUME_EXT_ENTRY`)
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ExtendedHeaderEntry<'a> {
+    pub typ: u16,
+    /// The entry contents, without the entry header
+    pub data: &'a [u8],
+}
+
+/// An `EFI_FIRMWARE_VOLUME_EXT_HEADER` and its entries
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExtendedHeader<'a> {
+    pub fv_name: Guid,
+    pub ext_header_size: u32,
+    pub entries: Vec<ExtendedHeaderEntry<'a>>,
+}
+
+impl<'a> ExtendedHeader<'a> {
+    pub fn parse(bytes: &'a [u8], offset: usize) -> error::Result<Self> {
+        let mut cursor = offset;
+        let fv_name = bytes.gread_with(&mut cursor, scroll::LE)?;
+        let ext_header_size: u32 = bytes.gread_with(&mut cursor, scroll::LE)?;
+        let end = offset.saturating_add(ext_header_size as usize);
+        if (ext_header_size as usize) < SIZEOF_FV_EXT_HEADER || end > bytes.len() {
+            return Err(error::Error::Malformed(format!(
+                "Firmware volume extended header size is invalid ({:#x})",
+                ext_header_size
+            )));
+        }
+
+        let mut entries = Vec::new();
+        while cursor + SIZEOF_FV_EXT_ENTRY_HEADER <= end {
+            let size: u16 = bytes.pread_with(cursor, scroll::LE)?;
+            let typ: u16 = bytes.pread_with(cursor + 2, scroll::LE)?;
+            let size = size as usize;
+            if size < SIZEOF_FV_EXT_ENTRY_HEADER || cursor + size > end {
+                return Err(error::Error::Malformed(format!(
+                    "Firmware volume extended header entry at {:#x} has an invalid size ({:#x})",
+                    cursor, size
+                )));
+            }
+            entries.push(ExtendedHeaderEntry {
+                typ,
+                data: &bytes[cursor + SIZEOF_FV_EXT_ENTRY_HEADER..cursor + size],
+            });
+            cursor += size;
+        }
+
+        Ok(ExtendedHeader {
+            fv_name,
+            ext_header_size,
+            entries,
+        })
+    }
+}
+
+/// A firmware volume and the FFS files it contains
+#[derive(Debug, Clone)]
+pub struct FirmwareVolume<'a> {
+    /// Offset of the volume within the bytes it was found in
+    pub offset: usize,
+    pub header: FirmwareVolumeHeader,
+    pub block_map: Vec<BlockMapEntry>,
+    pub ext_header: Option<ExtendedHeader<'a>>,
+    /// The files of the volume; empty unless the volume uses an FFS file system
+    pub files: Vec<File<'a>>,
+    /// The whole volume, header included
+    pub data: &'a [u8],
+}
+
+impl<'a> FirmwareVolume<'a> {
+    /// Parses the firmware volume at the start of `bytes`
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        Self::parse_nested(bytes, 0)
+    }
+
+    /// Parses a firmware volume nested `depth` encapsulation levels deep
+    fn parse_nested(bytes: &'a [u8], depth: usize) -> error::Result<Self> {
+        check_depth(depth)?;
+        let header = FirmwareVolumeHeader::parse(bytes)?;
+        let length = usize::try_from(header.fv_length)
+            .ok()
+            .filter(|&length| length <= bytes.len())
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "Firmware volume length ({:#x}) exceeds the available data ({:#x})",
+                    header.fv_length,
+                    bytes.len()
+                ))
+            })?;
+        let header_length = header.header_length as usize;
+        if header_length < SIZEOF_FV_HEADER + SIZEOF_BLOCK_MAP_ENTRY || header_length > length {
+            return Err(error::Error::Malformed(format!(
+                "Firmware volume header length is invalid ({:#x})",
+                header_length
+            )));
+        }
+        let data = &bytes[..length];
+
+        let mut block_map = Vec::new();
+        let mut offset = SIZEOF_FV_HEADER;
+        while offset + SIZEOF_BLOCK_MAP_ENTRY <= header_length {
+            let entry: BlockMapEntry = data.gread_with(&mut offset, scroll::LE)?;
+            if entry.num_blocks == 0 && entry.length == 0 {
+                break;
+            }
+            block_map.push(entry);
+        }
+
+        let mut files_offset = header_length;
+        let mut ext_header = None;
+        if header.ext_header_offset != 0 {
+            let ext_offset = header.ext_header_offset as usize;
+            let ext = ExtendedHeader::parse(data, ext_offset)?;
+            files_offset = files_offset.max(ext_offset + ext.ext_header_size as usize);
+            ext_header = Some(ext);
+        }
+
+        let mut files = Vec::new();
+        if header.is_ffs() {
+            let erased = if header.erase_polarity() { 0xff } else { 0x00 };
+            let mut offset = align_up(files_offset, 8);
+            while offset + SIZEOF_FFS_FILE_HEADER <= length {
+                // the remainder of the volume is free space
+                if data[offset..offset + SIZEOF_FFS_FILE_HEADER]
+                    .iter()
+                    .all(|&byte| byte == erased)
+                {
+                    break;
+                }
+                let file = File::parse_nested(data, offset, header.erase_polarity(), depth)?;
+                offset = align_up(offset + file.size, 8);
+                files.push(file);
+            }
+        }
+
+        Ok(FirmwareVolume {
+            offset: 0,
+            header,
+            block_map,
+            ext_header,
+            files,
+            data,
+        })
+    }
+
+    /// Finds and parses every firmware volume in `bytes`, e.g., a SPI flash image.
+    ///
+    /// Volumes are looked for at every 16-byte boundary; data that fails to parse as a volume is
+    /// skipped.
+    pub fn find_all(bytes: &'a [u8]) -> Vec<Self> {
+        let mut volumes = Vec::new();
+        let mut offset = 0;
+        while offset + SIZEOF_FV_HEADER <= bytes.len() {
+            if is_firmware_volume(&bytes[offset..]) {
+                if let Ok(mut volume) = Self::parse(&bytes[offset..]) {
+                    volume.offset = offset;
+                    offset += align_up(volume.data.len().max(1), 16);
+                    volumes.push(volume);
+                    continue;
+                }
+            }
+            offset += 16;
+        }
+        volumes
+    }
+
+    /// The name of the volume, from its extended header
+    pub fn name(&self) -> Option<Guid> {
+        self.ext_header.as_ref().map(|ext| ext.fv_name)
+    }
+
+    /// Whether the 16-bit sum of the volume header is zero, as required
+    pub fn is_header_checksum_valid(&self) -> bool {
+        self.data[..self.header.header_length as usize]
+            .chunks_exact(2)
+            .fold(0u16, |sum, word| {
+                sum.wrapping_add(u16::from_le_bytes([word[0], word[1]]))
+            })
+            == 0
+    }
+
+    /// Calls `visitor` on every PE32 and TE image in the volume, along with the name of its
+    /// file, in the order of [`FirmwareVolume::walk`].
+    ///
+    /// Images nested in encapsulation sections and firmware volume images are included. With the
+    /// `uefi_decompress` feature, so are those in compressed sections, which is why the images
+    /// are handed to a visitor: they may borrow data decompressed for the duration of the call.
+    pub fn executables(&self, mut visitor: impl FnMut(Guid, error::Result<Executable>)) {
+        self.walk(|file, section| {
+            if let Some(executable) = section.executable() {
+                visitor(file.name, executable);
+            }
+        })
+    }
+
+    /// Calls `visitor` on every section of every file in the volume, depth first, along with
+    /// the file containing it.
+    ///
+    /// Encapsulated sections and the files of nested firmware volume images are visited as
+    /// well. With the `uefi_decompress` feature, compressed sections are decompressed and their
+    /// contents visited too, up to `decompress::DEFAULT_MAX_SIZE` bytes per section (see
+    /// `FirmwareVolume::walk_with_max_size`); sections failing to decompress are skipped with a
+    /// warning.
+    pub fn walk(&self, mut visitor: impl FnMut(&File, &Section)) {
+        Walker {
+            visitor: &mut visitor,
+            #[cfg(feature = "uefi_decompress")]
+            max_size: decompress::DEFAULT_MAX_SIZE,
+        }
+        .volume(self, 0)
+    }
+
+    /// Like [`FirmwareVolume::walk`], skipping the compressed sections whose contents are larger
+    /// than `max_size` bytes
+    #[cfg(feature = "uefi_decompress")]
+    pub fn walk_with_max_size(&self, max_size: usize, mut visitor: impl FnMut(&File, &Section)) {
+        Walker {
+            visitor: &mut visitor,
+            max_size,
+        }
+        .volume(self, 0)
+    }
+}
+
+/// The state of [`FirmwareVolume::walk`]
+struct Walker<'v> {
+    visitor: &'v mut dyn FnMut(&File, &Section),
+    /// The maximum size of a decompressed section
+    #[cfg(feature = "uefi_decompress")]
+    max_size: usize,
+}
+
+impl<'v> Walker<'v> {
+    /// Walks the files of `volume`, whose sections are nested `depth` levels deep
+    fn volume(&mut self, volume: &FirmwareVolume, depth: usize) {
+        for file in &volume.files {
+            self.sections(file, &file.sections, depth);
+        }
+    }
+
+    fn sections(&mut self, file: &File, sections: &[Section], depth: usize) {
+        for section in sections {
+            (self.visitor)(file, section);
+            if let SectionData::FirmwareVolume(volume) = &section.data {
+                self.volume(volume, depth + 1);
+            }
+            self.sections(file, section.sections(), depth + 1);
+            #[cfg(feature = "uefi_decompress")]
+            self.decompressed(file, section, depth);
+        }
+    }
+
+    #[cfg(feature = "uefi_decompress")]
+    fn decompressed(&mut self, file: &File, section: &Section, depth: usize) {
+        // decompressed sections count towards the nesting limit, a compressed section may hold
+        // another one indefinitely
+        if let Err(err) = check_depth(depth + 1) {
+            warn!("Cannot decompress sections of file {}: {}", file.name, err);
+            return;
+        }
+        let data = match section.decompress(self.max_size) {
+            Some(Ok(data)) => data,
+            Some(Err(err)) => {
+                warn!(
+                    "Cannot decompress {} section of file {}: {}",
+                    section_type_to_str(section.typ),
+                    file.name,
+                    err
+                );
+                return;
+            }
+            None => return,
+        };
+        match Section::parse_all_nested(&data, depth + 1) {
+            Ok(sections) => self.sections(file, &sections, depth + 1),
+            Err(err) => warn!(
+                "Cannot parse decompressed sections of file {}: {}",
+                file.name, err
+            ),
+        }
+    }
+}
+
+fn check_depth(depth: usize) -> error::Result<()> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err(error::Error::Malformed(format!(
+            "Sections are nested more than {} levels deep",
+            MAX_NESTING_DEPTH
+        )));
+    }
+    Ok(())
+}
+
+/// An FFS file (`EFI_FFS_FILE_HEADER` or `EFI_FFS_FILE_HEADER2`) and its sections
+#[derive(Debug, Clone)]
+pub struct File<'a> {
+    /// Offset of the file header within the volume
+    pub offset: usize,
+    pub name: Guid,
+    pub integrity_check: u16,
+    pub typ: u8,
+    pub attributes: u8,
+    /// The size of the file, header included
+    pub size: usize,
+    pub header_size: usize,
+    /// The file state bits, already corrected for the volume erase polarity
+    pub state: u8,
+    /// The file contents, without the header
+    pub data: &'a [u8],
+    /// The sections of the file; empty for raw and pad files
+    pub sections: Vec<Section<'a>>,
+}
+
+impl<'a> File<'a> {
+    /// Parses the FFS file at `offset` within the volume `bytes`
+    pub fn parse(bytes: &'a [u8], offset: usize, erase_polarity: bool) -> error::Result<Self> {
+        Self::parse_nested(bytes, offset, erase_polarity, 0)
+    }
+
+    fn parse_nested(
+        bytes: &'a [u8],
+        offset: usize,
+        erase_polarity: bool,
+        depth: usize,
+    ) -> error::Result<Self> {
+        let mut cursor = offset;
+        let name = bytes.gread_with(&mut cursor, scroll::LE)?;
+        let integrity_check = bytes.gread_with(&mut cursor, scroll::LE)?;
+        let typ: u8 = bytes.gread_with(&mut cursor, scroll::LE)?;
+        let attributes: u8 = bytes.gread_with(&mut cursor, scroll::LE)?;
+        let size = read_u24(bytes, cursor)? as u64;
+        let state: u8 = bytes.pread_with(cursor + 3, scroll::LE)?;
+
+        let (size, header_size) = if attributes & FFS_ATTRIB_LARGE_FILE != 0 {
+            let extended_size: u64 =
+                bytes.pread_with(offset + SIZEOF_FFS_FILE_HEADER, scroll::LE)?;
+            (extended_size, SIZEOF_FFS_FILE_HEADER2)
+        } else {
+            (size, SIZEOF_FFS_FILE_HEADER)
+        };
+        let end = usize::try_from(size)
+            .ok()
+            .filter(|&size| size >= header_size)
+            .and_then(|size| offset.checked_add(size))
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "FFS file {} at {:#x} has an invalid size ({:#x})",
+                    name, offset, size
+                ))
+            })?;
+        let data = &bytes[offset + header_size..end];
+
+        let sections = match typ {
+            EFI_FV_FILETYPE_RAW | EFI_FV_FILETYPE_FFS_PAD => Vec::new(),
+            EFI_FV_FILETYPE_FREEFORM..=EFI_FV_FILETYPE_MM_CORE_STANDALONE => {
+                Section::parse_all_nested(data, depth)?
+            }
+            // OEM, debug and unknown files have no defined layout
+            _ => Vec::new(),
+        };
+
+        Ok(File {
+            offset,
+            name,
+            integrity_check,
+            typ,
+            attributes,
+            size: end - offset,
+            header_size,
+            state: if erase_polarity { !state } else { state },
+            data,
+            sections,
+        })
+    }
+
+    /// Whether the file is valid, i.e., its most significant state bit is `EFI_FILE_DATA_VALID`
+    pub fn is_valid(&self) -> bool {
+        let state = self.state & 0x3f;
+        state & !(EFI_FILE_DATA_VALID | EFI_FILE_HEADER_VALID | EFI_FILE_HEADER_CONSTRUCTION) == 0
+            && state & EFI_FILE_DATA_VALID != 0
+    }
+
+    /// Whether the file was marked as deleted
+    pub fn is_deleted(&self) -> bool {
+        self.state & (EFI_FILE_DELETED | EFI_FILE_HEADER_INVALID) != 0
+    }
+
+    /// The name of the file, from its user interface section
+    pub fn user_interface(&self) -> Option<&str> {
+        fn find<'s>(sections: &'s [Section]) -> Option<&'s str> {
+            sections.iter().find_map(|section| match &section.data {
+                SectionData::UserInterface(name) => Some(name.as_str()),
+                _ => find(section.sections()),
+            })
+        }
+        find(&self.sections)
+    }
+}
+
+/// An executable image carried by a PE32 or TE section
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Executable<'a> {
+    PE(pe::PE<'a>),
+    TE(te::TE<'a>),
+}
+
+/// The decoded contents of a section
+#[derive(Debug, Clone)]
+pub enum SectionData<'a> {
+    /// `EFI_COMPRESSION_SECTION`; `sections` is only populated when the data is not compressed,
+    /// see [`Section::decompress`]
+    Compression {
+        uncompressed_length: u32,
+        compression_type: u8,
+        data: &'a [u8],
+        sections: Vec<Section<'a>>,
+    },
+    /// `EFI_GUID_DEFINED_SECTION`; `sections` is only populated when no processing is required,
+    /// see [`Section::decompress`]
+    GuidDefined {
+        guid: Guid,
+        data_offset: u16,
+        attributes: u16,
+        data: &'a [u8],
+        sections: Vec<Section<'a>>,
+    },
+    Disposable(&'a [u8]),
+    Pe32(&'a [u8]),
+    Pic(&'a [u8]),
+    Te(&'a [u8]),
+    /// A DXE, PEI or MM dependency expression
+    Depex(&'a [u8]),
+    Version {
+        build_number: u16,
+        version: String,
+    },
+    UserInterface(String),
+    Compatibility16(&'a [u8]),
+    FirmwareVolume(Box<FirmwareVolume<'a>>),
+    FreeformSubtypeGuid {
+        guid: Guid,
+        data: &'a [u8],
+    },
+    Raw(&'a [u8]),
+    Unknown(&'a [u8]),
+}
+
+/// A file section (`EFI_COMMON_SECTION_HEADER` or `EFI_COMMON_SECTION_HEADER2`)
+#[derive(Debug, Clone)]
+pub struct Section<'a> {
+    pub typ: u8,
+    /// The size of the section, header included
+    pub size: usize,
+    pub header_size: usize,
+    pub data: SectionData<'a>,
+}
+
+impl<'a> Section<'a> {
+    /// Parses the sequence of 4-byte aligned sections making up `bytes`, e.g., the contents of
+    /// a file or of an encapsulation section
+    pub fn parse_all(bytes: &'a [u8]) -> error::Result<Vec<Self>> {
+        Self::parse_all_nested(bytes, 0)
+    }
+
+    fn parse_all_nested(bytes: &'a [u8], depth: usize) -> error::Result<Vec<Self>> {
+        let mut sections = Vec::new();
+        let mut offset = 0;
+        while offset + SIZEOF_COMMON_SECTION_HEADER <= bytes.len() {
+            let section = Self::parse_nested(bytes, offset, depth)?;
+            offset = align_up(offset + section.size, 4);
+            sections.push(section);
+        }
+        Ok(sections)
+    }
+
+    /// Parses the section at `offset` within `bytes`
+    pub fn parse(bytes: &'a [u8], offset: usize) -> error::Result<Self> {
+        Self::parse_nested(bytes, offset, 0)
+    }
+
+    /// Parses a section nested `depth` encapsulation levels deep
+    fn parse_nested(bytes: &'a [u8], offset: usize, depth: usize) -> error::Result<Self> {
+        check_depth(depth)?;
+        let size = read_u24(bytes, offset)?;
+        let typ: u8 = bytes.pread_with(offset + 3, scroll::LE)?;
+        let (size, header_size) = if size == 0x00ff_ffff {
+            let extended_size: u32 = bytes.pread_with(offset + 4, scroll::LE)?;
+            (extended_size as usize, SIZEOF_COMMON_SECTION_HEADER2)
+        } else {
+            (size as usize, SIZEOF_COMMON_SECTION_HEADER)
+        };
+        let section = bytes
+            .get(offset..offset.saturating_add(size))
+            .filter(|_| size >= header_size)
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "{} section at {:#x} has an invalid size ({:#x})",
+                    section_type_to_str(typ),
+                    offset,
+                    size
+                ))
+            })?;
+        let body = &section[header_size..];
+
+        let data = match typ {
+            EFI_SECTION_COMPRESSION => {
+                let uncompressed_length = body.pread_with(0, scroll::LE)?;
+                let compression_type = body.pread_with(4, scroll::LE)?;
+                let data = &body[5..];
+                let sections = if compression_type == EFI_NOT_COMPRESSED {
+                    Self::parse_all_nested(data, depth + 1)?
+                } else {
+                    Vec::new()
+                };
+                SectionData::Compression {
+                    uncompressed_length,
+                    compression_type,
+                    data,
+                    sections,
+                }
+            }
+            EFI_SECTION_GUID_DEFINED => {
+                let guid = body.pread_with(0, scroll::LE)?;
+                let data_offset: u16 = body.pread_with(16, scroll::LE)?;
+                let attributes: u16 = body.pread_with(18, scroll::LE)?;
+                // the data can't overlap the headers
+                let data = section
+                    .get(data_offset as usize..)
+                    .filter(|_| data_offset as usize >= header_size + SIZEOF_GUID_DEFINED_SECTION)
+                    .ok_or_else(|| {
+                        error::Error::Malformed(format!(
+                            "GUID defined section at {:#x} has an invalid data offset ({:#x})",
+                            offset, data_offset
+                        ))
+                    })?;
+                let sections = if attributes & EFI_GUIDED_SECTION_PROCESSING_REQUIRED == 0 {
+                    Self::parse_all_nested(data, depth + 1)?
+                } else {
+                    Vec::new()
+                };
+                SectionData::GuidDefined {
+                    guid,
+                    data_offset,
+                    attributes,
+                    data,
+                    sections,
+                }
+            }
+            EFI_SECTION_DISPOSABLE => SectionData::Disposable(body),
+            EFI_SECTION_PE32 => SectionData::Pe32(body),
+            EFI_SECTION_PIC => SectionData::Pic(body),
+            EFI_SECTION_TE => SectionData::Te(body),
+            EFI_SECTION_DXE_DEPEX | EFI_SECTION_PEI_DEPEX | EFI_SECTION_MM_DEPEX => {
+                SectionData::Depex(body)
+            }
+            EFI_SECTION_VERSION => SectionData::Version {
+                build_number: body.pread_with(0, scroll::LE)?,
+                version: ucs2_to_string(&body[2..]),
+            },
+            EFI_SECTION_USER_INTERFACE => SectionData::UserInterface(ucs2_to_string(body)),
+            EFI_SECTION_COMPATIBILITY16 => SectionData::Compatibility16(body),
+            EFI_SECTION_FIRMWARE_VOLUME_IMAGE => SectionData::FirmwareVolume(Box::new(
+                FirmwareVolume::parse_nested(body, depth + 1)?,
+            )),
+            EFI_SECTION_FREEFORM_SUBTYPE_GUID => SectionData::FreeformSubtypeGuid {
+                guid: body.pread_with(0, scroll::LE)?,
+                data: &body[16..],
+            },
+            EFI_SECTION_RAW => SectionData::Raw(body),
+            _ => SectionData::Unknown(body),
+        };
+
+        Ok(Section {
+            typ,
+            size,
+            header_size,
+            data,
+        })
+    }
+
+    /// The sections encapsulated by this section, if it is an encapsulation section whose
+    /// contents could be read as-is
+    pub fn sections(&self) -> &[Section<'a>] {
+        match &self.data {
+            SectionData::Compression { sections, .. }
+            | SectionData::GuidDefined { sections, .. } => sections,
+            _ => &[],
+        }
+    }
+
+    /// Decompresses the contents of a compression section, or of a GUID defined section
+    /// processed by one of the known decompressors (Tiano, LZMA, LZMA with x86 branch
+    /// conversion), returning `None` for any other section.
+    ///
+    /// The result is a sequence of sections, which can be parsed with [`Section::parse_all`]; it
+    /// is an error for it to be larger than `max_size` bytes.
+    #[cfg(feature = "uefi_decompress")]
+    pub fn decompress(&self, max_size: usize) -> Option<error::Result<Vec<u8>>> {
+        match &self.data {
+            SectionData::Compression {
+                compression_type: EFI_STANDARD_COMPRESSION,
+                data,
+                ..
+            } => Some(decompress::decompress_standard(data, max_size)),
+            SectionData::GuidDefined {
+                guid,
+                attributes,
+                data,
+                ..
+            } if attributes & EFI_GUIDED_SECTION_PROCESSING_REQUIRED != 0 => {
+                decompress::decompress_guided(guid, data, max_size)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses the image carried by a PE32 or TE section
+    pub fn executable(&self) -> Option<error::Result<Executable<'a>>> {
+        match self.data {
+            SectionData::Pe32(bytes) => Some(pe::PE::parse(bytes).map(Executable::PE)),
+            SectionData::Te(bytes) => Some(te::TE::parse(bytes).map(Executable::TE)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use scroll::Pwrite;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+
+    const DRIVER_GUID: Guid = Guid::new(0x1111_1111, 0x2222, 0x3333, [0x44; 8]);
+    const PEIM_GUID: Guid = Guid::new(0x5555_5555, 0x6666, 0x7777, [0x88; 8]);
+    const RAW_GUID: Guid = Guid::new(0x9999_9999, 0xaaaa, 0xbbbb, [0xcc; 8]);
+    const FV_NAME: Guid = Guid::new(0xdddd_dddd, 0xeeee, 0xffff, [0x00; 8]);
+
+    fn ucs2(string: &str) -> Vec<u8> {
+        string
+            .encode_utf16()
+            .chain(Some(0))
+            .flat_map(|unit| unit.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    fn section(typ: u8, body: &[u8]) -> Vec<u8> {
+        let size = (SIZEOF_COMMON_SECTION_HEADER + body.len()) as u32;
+        let mut bytes = size.to_le_bytes()[..3].to_vec();
+        bytes.push(typ);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    fn sections(sections: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for section in sections {
+            bytes.resize(align_up(bytes.len(), 4), 0);
+            bytes.extend_from_slice(section);
+        }
+        bytes
+    }
+
+    fn file(name: Guid, typ: u8, body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0u8; SIZEOF_FFS_FILE_HEADER];
+        bytes.pwrite_with(name, 0, scroll::LE).unwrap();
+        bytes[18] = typ;
+        let size = (SIZEOF_FFS_FILE_HEADER + body.len()) as u32;
+        bytes[20..23].copy_from_slice(&size.to_le_bytes()[..3]);
+        // header and data valid, with an erase polarity of 1
+        bytes[23] = !(EFI_FILE_HEADER_CONSTRUCTION | EFI_FILE_HEADER_VALID | EFI_FILE_DATA_VALID);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    fn volume(files: &[Vec<u8>]) -> Vec<u8> {
+        let header_length = SIZEOF_FV_HEADER + 2 * SIZEOF_BLOCK_MAP_ENTRY;
+        let mut bytes = vec![0u8; header_length];
+        bytes
+            .pwrite_with(FIRMWARE_FILE_SYSTEM2_GUID, 16, scroll::LE)
+            .unwrap();
+        bytes
+            .pwrite_with(FV_SIGNATURE, OFFSET_FV_SIGNATURE, scroll::LE)
+            .unwrap();
+        bytes
+            .pwrite_with(EFI_FVB2_ERASE_POLARITY, 44, scroll::LE)
+            .unwrap();
+        bytes
+            .pwrite_with(header_length as u16, 48, scroll::LE)
+            .unwrap();
+        bytes
+            .pwrite_with(header_length as u16, 52, scroll::LE)
+            .unwrap();
+        bytes[55] = 2;
+
+        // extended header, directly after the block map
+        bytes.extend_from_slice(&FV_NAME.to_bytes());
+        bytes.extend_from_slice(&(SIZEOF_FV_EXT_HEADER as u32).to_le_bytes());
+        for file in files {
+            bytes.resize(align_up(bytes.len(), 8), 0xff);
+            bytes.extend_from_slice(file);
+        }
+        // trailing free space
+        bytes.resize(align_up(bytes.len() + 0x100, 0x1000), 0xff);
+
+        let length = bytes.len();
+        bytes.pwrite_with(length as u64, 32, scroll::LE).unwrap();
+        bytes
+            .pwrite_with(1u32, SIZEOF_FV_HEADER, scroll::LE)
+            .unwrap();
+        bytes
+            .pwrite_with(length as u32, SIZEOF_FV_HEADER + 4, scroll::LE)
+            .unwrap();
+        let sum = bytes[..header_length]
+            .chunks_exact(2)
+            .fold(0u16, |sum, word| {
+                sum.wrapping_add(u16::from_le_bytes([word[0], word[1]]))
+            });
+        bytes
+            .pwrite_with(0u16.wrapping_sub(sum), 50, scroll::LE)
+            .unwrap();
+        bytes
+    }
+
+    pub(crate) fn sample_volume() -> Vec<u8> {
+        let pe = pe::PE::parse(REALTEK_LAN).unwrap();
+        let te = te::writer::from_pe(&pe).unwrap();
+
+        let mut version = 7u16.to_le_bytes().to_vec();
+        version.extend(ucs2("1.0"));
+        let driver = sections(&[
+            section(EFI_SECTION_PE32, REALTEK_LAN),
+            section(EFI_SECTION_USER_INTERFACE, &ucs2("RealtekLan")),
+            section(EFI_SECTION_VERSION, &version),
+        ]);
+        let peim = sections(&[
+            section(EFI_SECTION_PEI_DEPEX, &[0x06, 0x08]),
+            section(EFI_SECTION_TE, &te),
+        ]);
+        volume(&[
+            file(DRIVER_GUID, EFI_FV_FILETYPE_DRIVER, &driver),
+            file(PEIM_GUID, EFI_FV_FILETYPE_PEIM, &peim),
+            file(RAW_GUID, EFI_FV_FILETYPE_RAW, b"raw data"),
+        ])
+    }
+
+    #[test]
+    fn parse_volume() {
+        let bytes = sample_volume();
+        let volume = FirmwareVolume::parse(&bytes).unwrap();
+        assert!(volume.header.is_ffs());
+        assert!(volume.is_header_checksum_valid());
+        assert_eq!(volume.name(), Some(FV_NAME));
+        assert_eq!(
+            volume.block_map,
+            [BlockMapEntry {
+                num_blocks: 1,
+                length: bytes.len() as u32
+            }]
+        );
+        assert_eq!(volume.files.len(), 3);
+
+        let driver = &volume.files[0];
+        assert_eq!(driver.name, DRIVER_GUID);
+        assert_eq!(file_type_to_str(driver.typ), "DRIVER");
+        assert!(driver.is_valid());
+        assert!(!driver.is_deleted());
+        assert_eq!(driver.user_interface(), Some("RealtekLan"));
+        assert_eq!(driver.sections.len(), 3);
+        match &driver.sections[2].data {
+            SectionData::Version {
+                build_number,
+                version,
+            } => {
+                assert_eq!(*build_number, 7);
+                assert_eq!(version, "1.0");
+            }
+            data => panic!("unexpected section data: {:?}", data),
+        }
+
+        let peim = &volume.files[1];
+        assert_eq!(peim.sections.len(), 2);
+        assert!(matches!(
+            peim.sections[0].data,
+            SectionData::Depex(&[0x06, 0x08])
+        ));
+
+        let raw = &volume.files[2];
+        assert!(raw.sections.is_empty());
+        assert_eq!(raw.data, b"raw data");
+
+        let mut executables = Vec::new();
+        volume.executables(|name, executable| {
+            executables.push((name, matches!(executable, Ok(Executable::PE(_)))))
+        });
+        assert_eq!(executables, [(DRIVER_GUID, true), (PEIM_GUID, false)]);
+    }
+
+    #[test]
+    fn nested_volume() {
+        let inner = sample_volume();
+        // a GUID-defined section that needs no processing, wrapping a firmware volume image
+        let mut guided = vec![0u8; 20];
+        guided.pwrite_with(FV_NAME, 0, scroll::LE).unwrap();
+        guided
+            .pwrite_with((SIZEOF_COMMON_SECTION_HEADER + 20) as u16, 16, scroll::LE)
+            .unwrap();
+        guided.extend(section(EFI_SECTION_FIRMWARE_VOLUME_IMAGE, &inner));
+        let outer = volume(&[file(
+            RAW_GUID,
+            EFI_FV_FILETYPE_FIRMWARE_VOLUME_IMAGE,
+            &section(EFI_SECTION_GUID_DEFINED, &guided),
+        )]);
+
+        let volume = FirmwareVolume::parse(&outer).unwrap();
+        let sections = volume.files[0].sections[0].sections();
+        assert_eq!(sections.len(), 1);
+        assert!(matches!(
+            sections[0].data,
+            SectionData::FirmwareVolume(ref inner) if inner.files.len() == 3
+        ));
+        let mut executables = 0;
+        volume.executables(|_, _| executables += 1);
+        assert_eq!(executables, 2);
+    }
+
+    #[test]
+    fn walk_sections() {
+        let inner = sample_volume();
+        let outer = volume(&[
+            file(
+                RAW_GUID,
+                EFI_FV_FILETYPE_FIRMWARE_VOLUME_IMAGE,
+                &section(EFI_SECTION_FIRMWARE_VOLUME_IMAGE, &inner),
+            ),
+            file(
+                PEIM_GUID,
+                EFI_FV_FILETYPE_FREEFORM,
+                &section(EFI_SECTION_RAW, b"raw"),
+            ),
+        ]);
+
+        let volume = FirmwareVolume::parse(&outer).unwrap();
+        let mut visited = Vec::new();
+        volume.walk(|file, section| visited.push((file.name, section.typ)));
+        assert_eq!(
+            visited,
+            [
+                (RAW_GUID, EFI_SECTION_FIRMWARE_VOLUME_IMAGE),
+                (DRIVER_GUID, EFI_SECTION_PE32),
+                (DRIVER_GUID, EFI_SECTION_USER_INTERFACE),
+                (DRIVER_GUID, EFI_SECTION_VERSION),
+                (PEIM_GUID, EFI_SECTION_PEI_DEPEX),
+                (PEIM_GUID, EFI_SECTION_TE),
+                (PEIM_GUID, EFI_SECTION_RAW),
+            ]
+        );
+    }
+
+    #[cfg(feature = "uefi_decompress")]
+    #[test]
+    fn walk_compressed_sections() {
+        static LZMA_PE32: &[u8] = include_bytes!("../../tests/bins/uefi/RealtekLan.pe32.lzma");
+        let mut guided = vec![0u8; 20];
+        guided
+            .pwrite_with(decompress::LZMA_CUSTOM_DECOMPRESS_GUID, 0, scroll::LE)
+            .unwrap();
+        guided
+            .pwrite_with((SIZEOF_COMMON_SECTION_HEADER + 20) as u16, 16, scroll::LE)
+            .unwrap();
+        guided
+            .pwrite_with(EFI_GUIDED_SECTION_PROCESSING_REQUIRED, 18, scroll::LE)
+            .unwrap();
+        guided.extend_from_slice(LZMA_PE32);
+        let bytes = volume(&[file(
+            DRIVER_GUID,
+            EFI_FV_FILETYPE_DRIVER,
+            &section(EFI_SECTION_GUID_DEFINED, &guided),
+        )]);
+
+        let volume = FirmwareVolume::parse(&bytes).unwrap();
+        let decompressed = volume.files[0].sections[0]
+            .decompress(decompress::DEFAULT_MAX_SIZE)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&decompressed[SIZEOF_COMMON_SECTION_HEADER..], REALTEK_LAN);
+
+        let mut executables = 0;
+        volume.executables(|name, executable| {
+            assert_eq!(name, DRIVER_GUID);
+            assert!(matches!(executable, Ok(Executable::PE(_))));
+            executables += 1;
+        });
+        assert_eq!(executables, 1);
+
+        // the decompressed sections are larger than the limit
+        let mut sections = 0;
+        volume.walk_with_max_size(REALTEK_LAN.len(), |_, _| sections += 1);
+        assert_eq!(sections, 1);
+        volume.walk_with_max_size(decompressed.len(), |_, _| sections += 1);
+        assert_eq!(sections, 3);
+    }
+
+    #[test]
+    fn invalid_guid_defined_data_offset() {
+        // a data offset pointing back at the section header
+        let mut guided = vec![0u8; 20];
+        guided.pwrite_with(FV_NAME, 0, scroll::LE).unwrap();
+        let bytes = volume(&[file(
+            RAW_GUID,
+            EFI_FV_FILETYPE_FREEFORM,
+            &section(EFI_SECTION_GUID_DEFINED, &guided),
+        )]);
+        assert!(FirmwareVolume::parse(&bytes).is_err());
+        let mut section = section(EFI_SECTION_GUID_DEFINED, &guided);
+        section.pwrite_with(4u16, 20, scroll::LE).unwrap();
+        assert!(Section::parse_all(&section).is_err());
+    }
+
+    #[test]
+    fn deeply_nested_sections() {
+        let mut guided = section(EFI_SECTION_RAW, b"raw");
+        for _ in 0..MAX_NESTING_DEPTH + 1 {
+            let mut body = vec![0u8; 20];
+            body.pwrite_with((SIZEOF_COMMON_SECTION_HEADER + 20) as u16, 16, scroll::LE)
+                .unwrap();
+            body.extend(guided);
+            guided = section(EFI_SECTION_GUID_DEFINED, &body);
+        }
+        assert!(Section::parse_all(&guided).is_err());
+        // one level less parses
+        let body = &guided[SIZEOF_COMMON_SECTION_HEADER + 20..];
+        assert!(Section::parse_all(body).is_ok());
+    }
+
+    #[test]
+    fn find_volumes_in_flash_image() {
+        let volume = sample_volume();
+        let mut flash = vec![0xffu8; 0x1000];
+        flash.extend_from_slice(&volume);
+        flash.extend_from_slice(&[0xff; 0x1000]);
+        flash.extend_from_slice(&volume);
+
+        let volumes = FirmwareVolume::find_all(&flash);
+        assert_eq!(volumes.len(), 2);
+        assert_eq!(volumes[0].offset, 0x1000);
+        assert_eq!(volumes[1].offset, 0x2000 + volume.len());
+    }
+
+    #[test]
+    fn object_detection() {
+        let bytes = sample_volume();
+        match crate::Object::parse(&bytes).unwrap() {
+            crate::Object::FirmwareVolume(volume) => assert_eq!(volume.files.len(), 3),
+            object => panic!("unexpected object: {:?}", object),
+        }
+    }
+
+    #[test]
+    fn truncated_file() {
+        let mut bytes = sample_volume();
+        // grow the first file past the end of the volume
+        let first_file = align_up(SIZEOF_FV_HEADER + 2 * SIZEOF_BLOCK_MAP_ENTRY + 20, 8);
+        bytes[first_file + 20..first_file + 23].copy_from_slice(&[0xff, 0xff, 0xfe]);
+        assert!(FirmwareVolume::parse(&bytes).is_err());
+    }
+}
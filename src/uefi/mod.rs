@@ -0,0 +1,8 @@
+//! UEFI firmware containers: the structures PE and TE images are shipped in on platform flash.
+
+pub mod capsule;
+#[cfg(feature = "uefi_decompress")]
+pub mod decompress;
+pub mod fv;
+pub mod option_rom;
+pub mod sigdb;
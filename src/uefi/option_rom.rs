@@ -0,0 +1,345 @@
+//! PCI expansion ROMs (option ROMs), as found on GPUs, NICs and storage controllers.
+//!
+//! An option ROM is a chain of images, each starting with a `0xAA55` expansion ROM header that
+//! points to a PCI data structure (`PCIR`) giving the image length, its code type and whether it
+//! is the last image. EFI images (code type 3) use an `EFI_PCI_EXPANSION_ROM_HEADER`, which
+//! locates an optionally compressed PE32+ image with an `EFI_ROM` subsystem.
+//!
+//! See the PCI Firmware Specification and the UEFI Specification, "EFI PCI Option ROMs".
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use scroll::{Pread, Pwrite, SizeWith};
+
+use crate::error;
+#[cfg(feature = "uefi_decompress")]
+use crate::uefi::decompress;
+
+/// The signature of an expansion ROM header
+pub const PCI_EXPANSION_ROM_HEADER_SIGNATURE: u16 = 0xaa55;
+/// "PCIR"
+pub const PCI_DATA_STRUCTURE_SIGNATURE: u32 = 0x5249_4350;
+/// The `EfiSignature` of an EFI expansion ROM header
+pub const EFI_PCI_EXPANSION_ROM_HEADER_EFISIGNATURE: u32 = 0x0ef1;
+/// Offset of the pointer to the PCI data structure within the expansion ROM header
+pub const OFFSET_PCIR_OFFSET: usize = 0x18;
+pub const SIZEOF_EFI_PCI_EXPANSION_ROM_HEADER: usize = 0x1c;
+/// Image lengths are given in units of 512 bytes
+pub const ROM_BLOCK_SIZE: usize = 512;
+
+pub const PCI_CODE_TYPE_PCAT_IMAGE: u8 = 0x00;
+pub const PCI_CODE_TYPE_OPEN_FIRMWARE: u8 = 0x01;
+pub const PCI_CODE_TYPE_HP_PA_RISC: u8 = 0x02;
+pub const PCI_CODE_TYPE_EFI_IMAGE: u8 = 0x03;
+
+/// Set in `indicator` for the last image of the ROM
+pub const PCI_INDICATOR_LAST_IMAGE: u8 = 0x80;
+
+pub const EFI_PCI_EXPANSION_ROM_HEADER_COMPRESSED: u16 = 0x0001;
+
+/// Returns the name of a PCI data structure code type
+pub fn code_type_to_str(code_type: u8) -> &'static str {
+    match code_type {
+        PCI_CODE_TYPE_PCAT_IMAGE => "PCAT_IMAGE",
+        PCI_CODE_TYPE_OPEN_FIRMWARE => "OPEN_FIRMWARE",
+        PCI_CODE_TYPE_HP_PA_RISC => "HP_PA_RISC",
+        PCI_CODE_TYPE_EFI_IMAGE => "EFI_IMAGE",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Returns whether `bytes` starts with an expansion ROM header
+pub fn is_option_rom(bytes: &[u8]) -> bool {
+    bytes.pread_with::<u16>(0, scroll::LE).ok() == Some(PCI_EXPANSION_ROM_HEADER_SIGNATURE)
+}
+
+/// `PCI_DATA_STRUCTURE`, with the PCI 3.0 fields
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, SizeWith)]
+pub struct PciDataStructure {
+    pub signature: u32,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    /// The offset of the device list, since PCI 3.0
+    pub device_list_offset: u16,
+    pub length: u16,
+    pub revision: u8,
+    pub class_code: [u8; 3],
+    /// The length of the image, in units of 512 bytes
+    pub image_length: u16,
+    pub code_revision: u16,
+    pub code_type: u8,
+    pub indicator: u8,
+    pub max_runtime_image_length: u16,
+    pub config_utility_code_header_offset: u16,
+    pub dmtf_clp_entry_point_offset: u16,
+}
+
+/// `EFI_PCI_EXPANSION_ROM_HEADER`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, SizeWith)]
+pub struct EfiRomHeader {
+    pub signature: u16,
+    /// The size of the image, in units of 512 bytes
+    pub initialization_size: u16,
+    pub efi_signature: u32,
+    pub efi_subsystem: u16,
+    pub efi_machine_type: u16,
+    pub compression_type: u16,
+    pub reserved: [u8; 8],
+    /// The offset of the PE32+ image within the ROM image
+    pub efi_image_header_offset: u16,
+    pub pcir_offset: u16,
+}
+
+impl EfiRomHeader {
+    /// Whether the PE32+ image is compressed with the EFI compression algorithm
+    pub fn is_compressed(&self) -> bool {
+        self.compression_type == EFI_PCI_EXPANSION_ROM_HEADER_COMPRESSED
+    }
+}
+
+/// An image of an option ROM
+#[derive(Debug, Clone)]
+pub struct RomImage<'a> {
+    /// Offset of the image within the ROM
+    pub offset: usize,
+    pub pcir: PciDataStructure,
+    /// The EFI expansion ROM header, for EFI images
+    pub efi_header: Option<EfiRomHeader>,
+    /// The whole image, expansion ROM header included
+    pub data: &'a [u8],
+}
+
+impl<'a> RomImage<'a> {
+    /// Parses the image at `offset` within the ROM `bytes`
+    pub fn parse(bytes: &'a [u8], offset: usize) -> error::Result<Self> {
+        let signature: u16 = bytes.pread_with(offset, scroll::LE)?;
+        if signature != PCI_EXPANSION_ROM_HEADER_SIGNATURE {
+            return Err(error::Error::Malformed(format!(
+                "Option ROM image at {:#x} has an invalid signature ({:#x})",
+                offset, signature
+            )));
+        }
+        let pcir_offset: u16 = bytes.pread_with(offset + OFFSET_PCIR_OFFSET, scroll::LE)?;
+        let pcir: PciDataStructure = bytes.pread_with(offset + pcir_offset as usize, scroll::LE)?;
+        if pcir.signature != PCI_DATA_STRUCTURE_SIGNATURE {
+            return Err(error::Error::Malformed(format!(
+                "Option ROM image at {:#x} has an invalid PCI data structure signature ({:#x})",
+                offset, pcir.signature
+            )));
+        }
+        let length = pcir.image_length as usize * ROM_BLOCK_SIZE;
+        let data = bytes
+            .get(offset..offset + length)
+            .filter(|_| length != 0)
+            .ok_or_else(|| {
+                error::Error::Malformed(format!(
+                    "Option ROM image at {:#x} has an invalid length ({:#x})",
+                    offset, length
+                ))
+            })?;
+
+        let efi_header = if pcir.code_type == PCI_CODE_TYPE_EFI_IMAGE {
+            let header: EfiRomHeader = data.pread_with(0, scroll::LE)?;
+            if header.efi_signature != EFI_PCI_EXPANSION_ROM_HEADER_EFISIGNATURE {
+                return Err(error::Error::Malformed(format!(
+                    "EFI option ROM image at {:#x} has an invalid EFI signature ({:#x})",
+                    offset, header.efi_signature
+                )));
+            }
+            Some(header)
+        } else {
+            None
+        };
+
+        Ok(RomImage {
+            offset,
+            pcir,
+            efi_header,
+            data,
+        })
+    }
+
+    /// Whether this is the last image of the ROM
+    pub fn is_last(&self) -> bool {
+        self.pcir.indicator & PCI_INDICATOR_LAST_IMAGE != 0
+    }
+
+    /// Returns the PE32+ image of an EFI image, decompressing it if needed, to be parsed with
+    /// [`PE::parse`](crate::pe::PE::parse).
+    ///
+    /// Returns `None` for legacy images. Compressed images require the `uefi_decompress`
+    /// feature and are an error otherwise, as are those decompressing to more than
+    /// `decompress::DEFAULT_MAX_SIZE` bytes.
+    pub fn efi_image(&self) -> Option<error::Result<Cow<'a, [u8]>>> {
+        let header = self.efi_header?;
+        Some(self.efi_image_data(&header).and_then(|data| {
+            if header.is_compressed() {
+                decompress_image(data).map(Cow::Owned)
+            } else {
+                Ok(Cow::Borrowed(data))
+            }
+        }))
+    }
+
+    fn efi_image_data(&self, header: &EfiRomHeader) -> error::Result<&'a [u8]> {
+        let start = header.efi_image_header_offset as usize;
+        // the initialization size covers the image; trailing data up to the image length is not
+        // part of it
+        let end = (header.initialization_size as usize * ROM_BLOCK_SIZE).min(self.data.len());
+        self.data.get(start..end).ok_or_else(|| {
+            error::Error::Malformed(format!(
+                "EFI option ROM image at {:#x} has an invalid image offset ({:#x})",
+                self.offset, start
+            ))
+        })
+    }
+}
+
+#[cfg(feature = "uefi_decompress")]
+fn decompress_image(data: &[u8]) -> error::Result<Vec<u8>> {
+    decompress::decompress_standard(data, decompress::DEFAULT_MAX_SIZE)
+}
+
+#[cfg(not(feature = "uefi_decompress"))]
+fn decompress_image(_data: &[u8]) -> error::Result<Vec<u8>> {
+    Err(error::Error::Malformed(
+        "EFI option ROM image is compressed, which requires the `uefi_decompress` feature".into(),
+    ))
+}
+
+/// A PCI option ROM and its chain of images
+#[derive(Debug, Clone)]
+pub struct OptionRom<'a> {
+    pub images: Vec<RomImage<'a>>,
+}
+
+impl<'a> OptionRom<'a> {
+    /// Parses the chain of images starting at the beginning of `bytes`, up to the image marked
+    /// as last or the end of `bytes`
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        let mut images = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let image = RomImage::parse(bytes, offset)?;
+            offset += image.data.len();
+            let last = image.is_last();
+            images.push(image);
+            if last || !is_option_rom(&bytes[offset..]) {
+                break;
+            }
+        }
+        Ok(OptionRom { images })
+    }
+
+    /// The EFI images of the ROM
+    pub fn efi_images(&self) -> impl Iterator<Item = &RomImage<'a>> {
+        self.images
+            .iter()
+            .filter(|image| image.efi_header.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pe;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+
+    fn pcir(length: usize, code_type: u8, last: bool) -> [u8; 0x1c] {
+        let mut bytes = [0u8; 0x1c];
+        let pcir = PciDataStructure {
+            signature: PCI_DATA_STRUCTURE_SIGNATURE,
+            vendor_id: 0x10ec,
+            device_id: 0x8168,
+            length: 0x1c,
+            revision: 3,
+            class_code: [0x00, 0x00, 0x02],
+            image_length: ((length + ROM_BLOCK_SIZE - 1) / ROM_BLOCK_SIZE) as u16,
+            code_type,
+            indicator: if last { PCI_INDICATOR_LAST_IMAGE } else { 0 },
+            ..Default::default()
+        };
+        bytes.pwrite_with(pcir, 0, scroll::LE).unwrap();
+        bytes
+    }
+
+    fn legacy_image() -> Vec<u8> {
+        let mut bytes = vec![0u8; ROM_BLOCK_SIZE];
+        bytes
+            .pwrite_with(PCI_EXPANSION_ROM_HEADER_SIGNATURE, 0, scroll::LE)
+            .unwrap();
+        bytes[2] = 1;
+        bytes
+            .pwrite_with(0x40u16, OFFSET_PCIR_OFFSET, scroll::LE)
+            .unwrap();
+        bytes[0x40..0x5c].copy_from_slice(&pcir(ROM_BLOCK_SIZE, PCI_CODE_TYPE_PCAT_IMAGE, false));
+        bytes
+    }
+
+    fn efi_image(image: &[u8], compression_type: u16) -> Vec<u8> {
+        let image_offset = 0x60;
+        let length = image_offset + image.len();
+        let blocks = (length + ROM_BLOCK_SIZE - 1) / ROM_BLOCK_SIZE;
+        let header = EfiRomHeader {
+            signature: PCI_EXPANSION_ROM_HEADER_SIGNATURE,
+            initialization_size: blocks as u16,
+            efi_signature: EFI_PCI_EXPANSION_ROM_HEADER_EFISIGNATURE,
+            efi_subsystem: pe::header::PE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER,
+            efi_machine_type: pe::header::COFF_MACHINE_X86_64,
+            compression_type,
+            reserved: [0; 8],
+            efi_image_header_offset: image_offset as u16,
+            pcir_offset: 0x1c,
+        };
+        let mut bytes = vec![0u8; blocks * ROM_BLOCK_SIZE];
+        bytes.pwrite_with(header, 0, scroll::LE).unwrap();
+        bytes[0x1c..0x38].copy_from_slice(&pcir(length, PCI_CODE_TYPE_EFI_IMAGE, true));
+        bytes[image_offset..length].copy_from_slice(image);
+        bytes
+    }
+
+    #[test]
+    fn parse_option_rom() {
+        let mut bytes = legacy_image();
+        bytes.extend(efi_image(REALTEK_LAN, 0));
+        // trailing padding is not part of the ROM
+        bytes.extend_from_slice(&[0xff; 0x100]);
+
+        let rom = OptionRom::parse(&bytes).unwrap();
+        assert_eq!(rom.images.len(), 2);
+        let legacy = &rom.images[0];
+        assert_eq!(code_type_to_str(legacy.pcir.code_type), "PCAT_IMAGE");
+        assert_eq!(legacy.pcir.vendor_id, 0x10ec);
+        assert!(!legacy.is_last());
+        assert!(legacy.efi_image().is_none());
+
+        let efi = &rom.images[1];
+        assert_eq!(efi.offset, ROM_BLOCK_SIZE);
+        assert!(efi.is_last());
+        assert_eq!(rom.efi_images().count(), 1);
+        let image = efi.efi_image().unwrap().unwrap();
+        assert!(matches!(image, Cow::Borrowed(_)));
+        assert_eq!(&image[..REALTEK_LAN.len()], REALTEK_LAN);
+        assert!(pe::PE::parse(&image).is_ok());
+    }
+
+    #[cfg(feature = "uefi_decompress")]
+    #[test]
+    fn compressed_efi_image() {
+        static COMPRESSED: &[u8] = include_bytes!("../../tests/bins/uefi/RealtekLan.efi.efi");
+        let bytes = efi_image(COMPRESSED, EFI_PCI_EXPANSION_ROM_HEADER_COMPRESSED);
+        let rom = OptionRom::parse(&bytes).unwrap();
+        let image = rom.images[0].efi_image().unwrap().unwrap();
+        assert_eq!(&image[..], REALTEK_LAN);
+    }
+
+    #[test]
+    fn invalid_pcir() {
+        let mut bytes = legacy_image();
+        bytes[0x40] = b'X';
+        assert!(OptionRom::parse(&bytes).is_err());
+    }
+}
@@ -0,0 +1,502 @@
+//! Secure Boot signature databases (`db`, `dbx`, `KEK`, `PK`) and authenticated variables.
+//!
+//! A signature database is a sequence of `EFI_SIGNATURE_LIST`s, each holding signatures of a
+//! single type: image digests, X.509 certificates or digests of the to-be-signed part of
+//! revoked certificates. Updates to these variables, such as the `dbxupdate.bin` files, are
+//! prefixed with an `EFI_VARIABLE_AUTHENTICATION_2` header.
+//!
+//! See the UEFI Specification, "Secure Boot and Driver Signing".
+
+use alloc::vec::Vec;
+use scroll::{Pread, Pwrite, SizeWith};
+
+use crate::error;
+use crate::guid::{Guid, SIZEOF_GUID};
+use crate::pe;
+use crate::pe::authenticode::ExcludedSectionsIter;
+use crate::pe::certificate_table::{
+    AttributeCertificate, AttributeCertificateType, UefiGuidCertificate,
+};
+
+pub const SIZEOF_SIGNATURE_LIST_HEADER: usize = 28;
+pub const SIZEOF_EFI_TIME: usize = 16;
+
+/// EFI_CERT_SHA1_GUID
+pub const EFI_CERT_SHA1_GUID: Guid = Guid::new(
+    0x826c_a512,
+    0xcf10,
+    0x4ac9,
+    [0xb1, 0x87, 0xbe, 0x01, 0x49, 0x66, 0x31, 0xbd],
+);
+/// EFI_CERT_SHA224_GUID
+pub const EFI_CERT_SHA224_GUID: Guid = Guid::new(
+    0x0b6e_5233,
+    0xa65c,
+    0x44c9,
+    [0x94, 0x07, 0xd9, 0xab, 0x83, 0xbf, 0xc8, 0xbd],
+);
+/// EFI_CERT_SHA256_GUID
+pub const EFI_CERT_SHA256_GUID: Guid = Guid::new(
+    0xc1c4_1626,
+    0x504c,
+    0x4092,
+    [0xac, 0xa9, 0x41, 0xf9, 0x36, 0x93, 0x43, 0x28],
+);
+/// EFI_CERT_SHA384_GUID
+pub const EFI_CERT_SHA384_GUID: Guid = Guid::new(
+    0xff3e_5307,
+    0x9fd0,
+    0x48c9,
+    [0x85, 0xf1, 0x8a, 0xd5, 0x6c, 0x70, 0x1e, 0x01],
+);
+/// EFI_CERT_SHA512_GUID
+pub const EFI_CERT_SHA512_GUID: Guid = Guid::new(
+    0x093e_0fae,
+    0xa6c4,
+    0x4f50,
+    [0x9f, 0x1b, 0xd4, 0x1e, 0x2b, 0x89, 0xc1, 0x9a],
+);
+/// EFI_CERT_RSA2048_GUID
+pub const EFI_CERT_RSA2048_GUID: Guid = Guid::new(
+    0x3c57_66e8,
+    0x269c,
+    0x4e34,
+    [0xaa, 0x14, 0xed, 0x77, 0x6e, 0x85, 0xb3, 0xb6],
+);
+/// EFI_CERT_X509_GUID
+pub const EFI_CERT_X509_GUID: Guid = Guid::new(
+    0xa5c0_59a1,
+    0x94e4,
+    0x4aa7,
+    [0x87, 0xb5, 0xab, 0x15, 0x5c, 0x2b, 0xf0, 0x72],
+);
+/// EFI_CERT_X509_SHA256_GUID
+pub const EFI_CERT_X509_SHA256_GUID: Guid = Guid::new(
+    0x3bd2_a492,
+    0x96c0,
+    0x4079,
+    [0xb4, 0x20, 0xfc, 0xf9, 0x8e, 0xf1, 0x03, 0xed],
+);
+/// EFI_CERT_X509_SHA384_GUID
+pub const EFI_CERT_X509_SHA384_GUID: Guid = Guid::new(
+    0x7076_876e,
+    0x80c2,
+    0x4ee6,
+    [0xaa, 0xd2, 0x28, 0xb3, 0x49, 0xa6, 0x86, 0x5b],
+);
+/// EFI_CERT_X509_SHA512_GUID
+pub const EFI_CERT_X509_SHA512_GUID: Guid = Guid::new(
+    0x446d_bf63,
+    0x2502,
+    0x4cda,
+    [0xbc, 0xfa, 0x24, 0x65, 0xd2, 0xb0, 0xfe, 0x9d],
+);
+
+/// EFI_IMAGE_SECURITY_DATABASE_GUID, the vendor GUID of the `db` and `dbx` variables
+pub const EFI_IMAGE_SECURITY_DATABASE_GUID: Guid = Guid::new(
+    0xd719_b2cb,
+    0x3d3a,
+    0x4596,
+    [0xa3, 0xbc, 0xda, 0xd0, 0x0e, 0x67, 0x65, 0x6f],
+);
+/// EFI_GLOBAL_VARIABLE, the vendor GUID of the `PK` and `KEK` variables
+pub const EFI_GLOBAL_VARIABLE_GUID: Guid = Guid::new(
+    0x8be4_df61,
+    0x93ca,
+    0x11d2,
+    [0xaa, 0x0d, 0x00, 0xe0, 0x98, 0x03, 0x2b, 0x8c],
+);
+
+/// Returns the name of a signature type GUID
+pub fn signature_type_to_str(guid: &Guid) -> &'static str {
+    match *guid {
+        EFI_CERT_SHA1_GUID => "SHA1",
+        EFI_CERT_SHA224_GUID => "SHA224",
+        EFI_CERT_SHA256_GUID => "SHA256",
+        EFI_CERT_SHA384_GUID => "SHA384",
+        EFI_CERT_SHA512_GUID => "SHA512",
+        EFI_CERT_RSA2048_GUID => "RSA2048",
+        EFI_CERT_X509_GUID => "X509",
+        EFI_CERT_X509_SHA256_GUID => "X509_SHA256",
+        EFI_CERT_X509_SHA384_GUID => "X509_SHA384",
+        EFI_CERT_X509_SHA512_GUID => "X509_SHA512",
+        _ => "UNKNOWN",
+    }
+}
+
+/// The size of the signature data of a signature type, for types with a fixed size
+fn signature_data_size(guid: &Guid) -> Option<usize> {
+    Some(match *guid {
+        EFI_CERT_SHA1_GUID => 20,
+        EFI_CERT_SHA224_GUID => 28,
+        EFI_CERT_SHA256_GUID => 32,
+        EFI_CERT_SHA384_GUID => 48,
+        EFI_CERT_SHA512_GUID => 64,
+        EFI_CERT_RSA2048_GUID => 256,
+        EFI_CERT_X509_SHA256_GUID => 32 + SIZEOF_EFI_TIME,
+        EFI_CERT_X509_SHA384_GUID => 48 + SIZEOF_EFI_TIME,
+        EFI_CERT_X509_SHA512_GUID => 64 + SIZEOF_EFI_TIME,
+        _ => return None,
+    })
+}
+
+/// `EFI_TIME`
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Pread, Pwrite, SizeWith)]
+pub struct EfiTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub pad1: u8,
+    pub nanosecond: u32,
+    pub time_zone: i16,
+    pub daylight: u8,
+    pub pad2: u8,
+}
+
+/// The decoded data of an `EFI_SIGNATURE_DATA`, according to the type of its list
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SignatureData<'a> {
+    /// `EFI_CERT_SHA*`: the digest of an image
+    Hash(&'a [u8]),
+    /// `EFI_CERT_RSA2048`: an RSA 2048 public key modulus
+    Rsa2048(&'a [u8]),
+    /// `EFI_CERT_X509`: a DER-encoded X.509 certificate
+    X509(&'a [u8]),
+    /// `EFI_CERT_X509_SHA*`: the digest of the to-be-signed part of a certificate, along with
+    /// the time it was revoked at
+    X509Hash {
+        to_be_signed_hash: &'a [u8],
+        time_of_revocation: EfiTime,
+    },
+    Unknown(&'a [u8]),
+}
+
+/// An `EFI_SIGNATURE_DATA`
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Signature<'a> {
+    /// The agent which added the signature to the list
+    pub owner: Guid,
+    pub data: SignatureData<'a>,
+}
+
+/// An `EFI_SIGNATURE_LIST`
+#[derive(Debug, PartialEq, Clone)]
+pub struct SignatureList<'a> {
+    pub signature_type: Guid,
+    /// The size of the list, header included
+    pub signature_list_size: u32,
+    pub signature_header_size: u32,
+    /// The size of each signature, owner included
+    pub signature_size: u32,
+    pub signature_header: &'a [u8],
+    pub signatures: Vec<Signature<'a>>,
+}
+
+impl<'a> SignatureList<'a> {
+    /// Parses the signature list at `offset` within `bytes`
+    pub fn parse(bytes: &'a [u8], offset: usize) -> error::Result<Self> {
+        let mut cursor = offset;
+        let signature_type: Guid = bytes.gread_with(&mut cursor, scroll::LE)?;
+        let signature_list_size: u32 = bytes.gread_with(&mut cursor, scroll::LE)?;
+        let signature_header_size: u32 = bytes.gread_with(&mut cursor, scroll::LE)?;
+        let signature_size: u32 = bytes.gread_with(&mut cursor, scroll::LE)?;
+
+        let end = offset.saturating_add(signature_list_size as usize);
+        let header_end = cursor.saturating_add(signature_header_size as usize);
+        let size = signature_size as usize;
+        if end > bytes.len() || header_end > end {
+            return Err(error::Error::Malformed(format!(
+                "Signature list at {:#x} has an invalid size ({:#x})",
+                offset, signature_list_size
+            )));
+        }
+        if size <= SIZEOF_GUID
+            || !bytes[header_end..end]
+                .chunks_exact(size)
+                .remainder()
+                .is_empty()
+            || matches!(signature_data_size(&signature_type), Some(data_size) if data_size + SIZEOF_GUID != size)
+        {
+            return Err(error::Error::Malformed(format!(
+                "Signature list at {:#x} has an invalid {} signature size ({:#x})",
+                offset,
+                signature_type_to_str(&signature_type),
+                signature_size
+            )));
+        }
+        let signature_header = &bytes[cursor..header_end];
+
+        let signatures = bytes[header_end..end]
+            .chunks_exact(size)
+            .map(|signature| {
+                // chunks are larger than a GUID, as checked above
+                let owner = signature.pread_with(0, scroll::LE).unwrap();
+                let data = &signature[SIZEOF_GUID..];
+                let data = match signature_type {
+                    EFI_CERT_SHA1_GUID | EFI_CERT_SHA224_GUID | EFI_CERT_SHA256_GUID
+                    | EFI_CERT_SHA384_GUID | EFI_CERT_SHA512_GUID => SignatureData::Hash(data),
+                    EFI_CERT_RSA2048_GUID => SignatureData::Rsa2048(data),
+                    EFI_CERT_X509_GUID => SignatureData::X509(data),
+                    EFI_CERT_X509_SHA256_GUID
+                    | EFI_CERT_X509_SHA384_GUID
+                    | EFI_CERT_X509_SHA512_GUID => {
+                        let (hash, time) = data.split_at(data.len() - SIZEOF_EFI_TIME);
+                        SignatureData::X509Hash {
+                            to_be_signed_hash: hash,
+                            // the size was checked against the signature type above
+                            time_of_revocation: time.pread_with(0, scroll::LE).unwrap(),
+                        }
+                    }
+                    _ => SignatureData::Unknown(data),
+                };
+                Signature { owner, data }
+            })
+            .collect();
+
+        Ok(SignatureList {
+            signature_type,
+            signature_list_size,
+            signature_header_size,
+            signature_size,
+            signature_header,
+            signatures,
+        })
+    }
+}
+
+/// A signature database, the contents of the `db`, `dbx`, `KEK` or `PK` variables
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SignatureDatabase<'a> {
+    pub lists: Vec<SignatureList<'a>>,
+}
+
+impl<'a> SignatureDatabase<'a> {
+    /// Parses the sequence of signature lists making up `bytes`
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        let mut lists = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            // lists are at least as large as their header, or fail to parse
+            let list = SignatureList::parse(bytes, offset)?;
+            offset += list.signature_list_size as usize;
+            lists.push(list);
+        }
+        Ok(SignatureDatabase { lists })
+    }
+
+    /// Iterates over the signatures of every list, along with the type of their list
+    pub fn signatures(&self) -> impl Iterator<Item = (&Guid, &Signature<'a>)> {
+        self.lists.iter().flat_map(|list| {
+            list.signatures
+                .iter()
+                .map(move |signature| (&list.signature_type, signature))
+        })
+    }
+
+    /// Whether the database holds the image digest `digest` in a list of type `signature_type`,
+    /// e.g., [`EFI_CERT_SHA256_GUID`]
+    pub fn contains_hash(&self, signature_type: &Guid, digest: &[u8]) -> bool {
+        self.signatures().any(|(typ, signature)| {
+            typ == signature_type && signature.data == SignatureData::Hash(digest)
+        })
+    }
+
+    /// Whether the SHA-256 authenticode digest of `pe` is in the database, e.g., whether the
+    /// image is revoked by a `dbx`.
+    ///
+    /// `sha256` must return the SHA-256 digest of the concatenation of the ranges it is given,
+    /// which are those returned by [`pe::PE::authenticode_ranges`].
+    pub fn contains_pe_digest<F>(&self, pe: &pe::PE, sha256: F) -> bool
+    where
+        F: FnOnce(ExcludedSectionsIter) -> [u8; 32],
+    {
+        self.contains_hash(&EFI_CERT_SHA256_GUID, &sha256(pe.authenticode_ranges()))
+    }
+}
+
+/// `EFI_VARIABLE_AUTHENTICATION_2`, the header of time-based authenticated variable writes
+#[derive(Debug, Clone)]
+pub struct VariableAuthentication2<'a> {
+    pub time_stamp: EfiTime,
+    /// The `WIN_CERTIFICATE_UEFI_GUID`, whose type must be `EFI_CERT_TYPE_PKCS7_GUID`
+    pub certificate: AttributeCertificate<'a>,
+}
+
+impl<'a> VariableAuthentication2<'a> {
+    /// Parses the authentication header at the start of `bytes`, returning it along with its
+    /// size
+    pub fn parse(bytes: &'a [u8]) -> error::Result<(Self, usize)> {
+        let time_stamp = bytes.pread_with(0, scroll::LE)?;
+        let mut offset = SIZEOF_EFI_TIME;
+        let certificate = AttributeCertificate::parse(bytes, &mut offset)?;
+        if certificate.certificate_type != AttributeCertificateType::EfiGuid {
+            return Err(error::Error::Malformed(format!(
+                "Variable authentication has an unexpected certificate type ({:?})",
+                certificate.certificate_type
+            )));
+        }
+        let size = SIZEOF_EFI_TIME + certificate.length as usize;
+        Ok((
+            VariableAuthentication2 {
+                time_stamp,
+                certificate,
+            },
+            size,
+        ))
+    }
+
+    /// Decodes the `WIN_CERTIFICATE_UEFI_GUID`, whose data is a PKCS#7 `SignedData`
+    pub fn uefi_guid_certificate(&self) -> error::Result<UefiGuidCertificate<'a>> {
+        UefiGuidCertificate::parse(self.certificate.certificate)
+    }
+}
+
+/// An authenticated variable write, e.g., a `dbxupdate.bin` file
+#[derive(Debug, Clone)]
+pub struct AuthenticatedVariable<'a> {
+    pub authentication: VariableAuthentication2<'a>,
+    /// The new variable contents
+    pub data: &'a [u8],
+}
+
+impl<'a> AuthenticatedVariable<'a> {
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        let (authentication, size) = VariableAuthentication2::parse(bytes)?;
+        let data = bytes.get(size..).ok_or_else(|| {
+            error::Error::Malformed(format!(
+                "Variable authentication size ({:#x}) exceeds the available data ({:#x})",
+                size,
+                bytes.len()
+            ))
+        })?;
+        Ok(AuthenticatedVariable {
+            authentication,
+            data,
+        })
+    }
+
+    /// Parses the variable contents as a signature database
+    pub fn signature_database(&self) -> error::Result<SignatureDatabase<'a>> {
+        SignatureDatabase::parse(self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pe::certificate_table::EFI_CERT_TYPE_PKCS7_GUID;
+
+    static REALTEK_LAN: &[u8] = include_bytes!("../../tests/bins/efi/RealtekLan.efi");
+    static MULTI_CERTS: &[u8] = include_bytes!("../../tests/bins/efi/MultiCerts.efi");
+
+    const OWNER: Guid = Guid::new(0x77fa_9abd, 0x0359, 0x4d32, [0xbd; 8]);
+
+    fn signature_list(signature_type: Guid, signatures: &[&[u8]]) -> Vec<u8> {
+        let size = 16 + signatures[0].len();
+        let mut bytes = signature_type.to_bytes().to_vec();
+        let list_size = SIZEOF_SIGNATURE_LIST_HEADER + size * signatures.len();
+        bytes.extend_from_slice(&(list_size as u32).to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(size as u32).to_le_bytes());
+        for signature in signatures {
+            bytes.extend_from_slice(&OWNER.to_bytes());
+            bytes.extend_from_slice(signature);
+        }
+        bytes
+    }
+
+    /// A stand-in for SHA-256, folding the hashed ranges into 32 bytes
+    fn fold(ranges: ExcludedSectionsIter) -> [u8; 32] {
+        let mut digest = [0u8; 32];
+        for (index, byte) in ranges.flatten().enumerate() {
+            digest[index % 32] = digest[index % 32].wrapping_mul(31).wrapping_add(*byte);
+        }
+        digest
+    }
+
+    #[test]
+    fn parse_signature_database() {
+        let time = EfiTime {
+            year: 2023,
+            month: 5,
+            day: 9,
+            ..Default::default()
+        };
+        let mut tbs = vec![0xaa; 32];
+        let mut time_bytes = [0u8; SIZEOF_EFI_TIME];
+        time_bytes.pwrite_with(time, 0, scroll::LE).unwrap();
+        tbs.extend_from_slice(&time_bytes);
+
+        let mut bytes = signature_list(EFI_CERT_SHA256_GUID, &[&[0x11; 32], &[0x22; 32]]);
+        bytes.extend(signature_list(EFI_CERT_X509_GUID, &[b"certificate"]));
+        bytes.extend(signature_list(EFI_CERT_X509_SHA256_GUID, &[&tbs]));
+
+        let db = SignatureDatabase::parse(&bytes).unwrap();
+        assert_eq!(db.lists.len(), 3);
+        assert_eq!(db.signatures().count(), 4);
+        assert_eq!(signature_type_to_str(&db.lists[0].signature_type), "SHA256");
+        assert_eq!(db.lists[0].signatures[1].owner, OWNER);
+        assert!(db.contains_hash(&EFI_CERT_SHA256_GUID, &[0x22; 32]));
+        assert!(!db.contains_hash(&EFI_CERT_SHA256_GUID, &[0x33; 32]));
+        assert_eq!(
+            db.lists[1].signatures[0].data,
+            SignatureData::X509(b"certificate")
+        );
+        assert_eq!(
+            db.lists[2].signatures[0].data,
+            SignatureData::X509Hash {
+                to_be_signed_hash: &[0xaa; 32],
+                time_of_revocation: time,
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_signature_size() {
+        let bytes = signature_list(EFI_CERT_SHA256_GUID, &[&[0x11; 20]]);
+        assert!(SignatureDatabase::parse(&bytes).is_err());
+        let bytes = signature_list(EFI_CERT_SHA1_GUID, &[&[0x11; 20]]);
+        assert!(SignatureDatabase::parse(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn dbx_check() {
+        let revoked = pe::PE::parse(REALTEK_LAN).unwrap();
+        let allowed = pe::PE::parse(MULTI_CERTS).unwrap();
+        let digest = fold(revoked.authenticode_ranges());
+        let bytes = signature_list(EFI_CERT_SHA256_GUID, &[&[0x11; 32], &digest]);
+        let dbx = SignatureDatabase::parse(&bytes).unwrap();
+        assert!(dbx.contains_pe_digest(&revoked, fold));
+        assert!(!dbx.contains_pe_digest(&allowed, fold));
+    }
+
+    #[test]
+    fn parse_authenticated_variable() {
+        let db = signature_list(EFI_CERT_SHA256_GUID, &[&[0x11; 32]]);
+        let pkcs7 = b"signed data";
+        let mut bytes = [0u8; SIZEOF_EFI_TIME].to_vec();
+        bytes[0..2].copy_from_slice(&2010u16.to_le_bytes());
+        let length = (8 + 16 + pkcs7.len()) as u32;
+        bytes.extend_from_slice(&length.to_le_bytes());
+        bytes.extend_from_slice(&0x0200u16.to_le_bytes());
+        bytes.extend_from_slice(&(AttributeCertificateType::EfiGuid as u16).to_le_bytes());
+        bytes.extend_from_slice(&EFI_CERT_TYPE_PKCS7_GUID.to_bytes());
+        bytes.extend_from_slice(pkcs7);
+        bytes.extend_from_slice(&db);
+
+        let variable = AuthenticatedVariable::parse(&bytes).unwrap();
+        assert_eq!(variable.authentication.time_stamp.year, 2010);
+        let certificate = variable.authentication.uefi_guid_certificate().unwrap();
+        assert_eq!(certificate.cert_type, EFI_CERT_TYPE_PKCS7_GUID);
+        assert_eq!(certificate.data, pkcs7);
+        assert_eq!(variable.data, &db[..]);
+        assert!(variable
+            .signature_database()
+            .unwrap()
+            .contains_hash(&EFI_CERT_SHA256_GUID, &[0x11; 32]));
+    }
+}
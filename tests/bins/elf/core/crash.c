@@ -0,0 +1,31 @@
+#include <elf.h>
+#include <pthread.h>
+#include <sys/auxv.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+static void *idle(void *arg) {
+    pause();
+    return arg;
+}
+
+/* the vDSO embeds the paths of the kernel build, keep it out of the dump */
+static void unmap_vdso(void) {
+    const Elf64_Ehdr *ehdr = (const Elf64_Ehdr *)getauxval(AT_SYSINFO_EHDR);
+    const Elf64_Phdr *phdr = (const Elf64_Phdr *)((const char *)ehdr + ehdr->e_phoff);
+    for (int i = 0; i < ehdr->e_phnum; i++) {
+        if (phdr[i].p_type == PT_LOAD) {
+            munmap((void *)ehdr, (phdr[i].p_memsz + 0xfff) & ~0xfffUL);
+            return;
+        }
+    }
+}
+
+int main(void) {
+    pthread_t thread;
+    pthread_create(&thread, NULL, idle, NULL);
+    usleep(10000);
+    unmap_vdso();
+    *(volatile int *)0x10 = 42;
+    return 0;
+}
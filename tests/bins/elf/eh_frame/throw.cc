@@ -0,0 +1,19 @@
+#include <cstdio>
+#include <stdexcept>
+
+// Throwing and catching gives main a personality routine and a language-specific data area.
+__attribute__((noinline)) void fail(int n) {
+    if (n > 1) {
+        throw std::runtime_error("fail");
+    }
+}
+
+int main(int argc, char **argv) {
+    try {
+        fail(argc);
+    } catch (const std::exception &e) {
+        puts(e.what());
+        return 1;
+    }
+    return 0;
+}
@@ -0,0 +1,12 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+// Taking the address of puts gives it a GOT slot with GLOB_DAT, and its calls go through .plt.got.
+int (*const put)(const char *) = puts;
+
+int main(int argc, char **argv) {
+    printf("%d\n", argc);
+    put(argv[0]);
+    puts("hello");
+    exit(0);
+}
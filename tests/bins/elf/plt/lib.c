@@ -0,0 +1,7 @@
+// The imports are left undefined, to link i386 objects without 32-bit C libraries.
+extern int foo(int);
+extern int bar(int);
+
+int baz(int n) {
+    return foo(n) + bar(n);
+}
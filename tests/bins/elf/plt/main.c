@@ -0,0 +1,8 @@
+// Calls baz of lib.c from a non-PIC i386 executable, through absolute PLT stubs.
+extern int baz(int);
+
+void _start(void) {
+    baz(1);
+    for (;;) {
+    }
+}
@@ -0,0 +1,18 @@
+extern int ext_value;
+extern int ext_func(int);
+extern int weak_func(int) __attribute__((weak));
+
+int counter = 3;
+int *counter_ptr = &counter;
+int (*ext_ptr)(int) = ext_func;
+static const char message[] = "relocated";
+const char *message_ptr = message + 2;
+
+static int add(int a, int b) { return a + b; }
+
+int entry(int x) {
+    counter += ext_value;
+    if (weak_func)
+        x = weak_func(x);
+    return add(ext_func(x), counter) + message[x & 7];
+}
@@ -0,0 +1,9 @@
+#include <stdio.h>
+
+static const char *names[] = {"zero", "one", "two", "three", "four", "five"};
+static const char **name_ptrs[] = {&names[0], &names[2], &names[4]};
+
+int main(int argc, char **argv) {
+    puts(*name_ptrs[argc % 3]);
+    return 0;
+}
@@ -0,0 +1,7 @@
+/* 256 MiB of .bss, which rewriting the binary must not store in the file */
+static char big[256 << 20];
+
+int main(int argc, char **argv) {
+    big[argc] = 1;
+    return big[1];
+}
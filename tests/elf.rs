@@ -503,3 +503,705 @@ fn test_symver() -> Result<(), goblin::error::Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_overlay() {
+    let bytes = include_bytes!("bins/elf/gnu_hash/hello.so");
+    let elf = Elf::parse(bytes).unwrap();
+    assert!(elf.overlay(bytes).is_none());
+
+    let mut appended = bytes.to_vec();
+    appended.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+    let elf = Elf::parse(&appended).unwrap();
+    let overlay = elf.overlay(&appended).expect("overlay");
+    assert_eq!(overlay.offset, bytes.len());
+    assert_eq!(overlay.size, 4);
+    assert_eq!(overlay.bytes, &[0xde, 0xad, 0xbe, 0xef]);
+}
+
+fn section_index(elf: &Elf, name: &str) -> usize {
+    elf.section_headers
+        .iter()
+        .position(|shdr| elf.shdr_strtab.get_at(shdr.sh_name) == Some(name))
+        .unwrap()
+}
+
+#[cfg(feature = "elf_decompress")]
+#[test]
+fn test_section_data_decompression() {
+    let bytes = include_bytes!("bins/elf/compressed/hello.o");
+    let elf = Elf::parse(bytes).unwrap();
+    let index = section_index(&elf, ".debug_info");
+    let debug_info = elf.section_data(bytes, index).unwrap();
+    assert!(matches!(debug_info, std::borrow::Cow::Borrowed(_)));
+
+    for (bytes, name) in [
+        (&include_bytes!("bins/elf/compressed/hello-zlib.o")[..], ".debug_info"),
+        (&include_bytes!("bins/elf/compressed/hello-zstd.o")[..], ".debug_info"),
+        (&include_bytes!("bins/elf/compressed/hello-zlib-gnu.o")[..], ".zdebug_info"),
+    ] {
+        let elf = Elf::parse(bytes).unwrap();
+        let index = section_index(&elf, name);
+        assert_eq!(elf.section_data(bytes, index).unwrap(), debug_info);
+    }
+}
+
+#[cfg(not(feature = "elf_decompress"))]
+#[test]
+fn test_section_data_without_decompression() {
+    let bytes = include_bytes!("bins/elf/compressed/hello-zstd.o");
+    let elf = Elf::parse(bytes).unwrap();
+    assert!(elf.section_data(bytes, section_index(&elf, ".debug_info")).is_err());
+    let text = elf.section_data(bytes, section_index(&elf, ".text")).unwrap();
+    let shdr = &elf.section_headers[section_index(&elf, ".text")];
+    assert_eq!(text.len() as u64, shdr.sh_size);
+    assert!(elf.section_data(bytes, elf.section_headers.len()).is_err());
+}
+
+#[test]
+fn test_relr() {
+    let bytes = include_bytes!("bins/elf/relr/relr");
+    let elf = Elf::parse(bytes).unwrap();
+    let offsets = elf.relr.iter().map(|reloc| reloc.r_offset).collect::<Vec<_>>();
+    assert_eq!(
+        offsets,
+        [
+            0x3d80, 0x3d88, 0x3d90, 0x3d98, 0x3da0, 0x4028, 0x4040, 0x4048, 0x4050, 0x4058,
+            0x4060, 0x4068
+        ]
+    );
+    assert!(elf
+        .relr
+        .iter()
+        .all(|reloc| reloc.r_type == goblin::elf::reloc::R_X86_64_RELATIVE));
+    assert!(elf
+        .section_headers
+        .iter()
+        .any(|shdr| shdr.sh_type == goblin::elf::section_header::SHT_RELR));
+    assert!(elf.android_relocs.is_empty());
+    {
+        use goblin::elf::section_header::{sht_to_str, SHT_NUM, SHT_RELR};
+        assert_eq!(sht_to_str(SHT_RELR), "SHT_RELR");
+        assert_eq!(sht_to_str(SHT_NUM), "SHT_NUM");
+    }
+
+    let bytes = include_bytes!("bins/elf/gnu_hash/hello.so");
+    let elf = Elf::parse(bytes).unwrap();
+    assert!(elf.relr.is_empty());
+}
+
+#[test]
+fn test_notes() {
+    let bytes: &[u8] = include_bytes!("bins/elf/notes/hello");
+    let elf = Elf::parse(bytes).expect("parse notes binary");
+    assert_eq!(
+        elf.build_id(bytes).unwrap().unwrap(),
+        [
+            0x37, 0xa8, 0x3d, 0xb5, 0x52, 0x12, 0x84, 0xee, 0x32, 0x1a, 0xc4, 0x88, 0xd9, 0x63,
+            0xb4, 0xd9, 0x1a, 0x8f, 0x4b, 0xda
+        ]
+    );
+
+    let abi_tag = elf.abi_tag(bytes).unwrap().unwrap();
+    assert_eq!(abi_tag.os_to_str(), "Linux");
+    assert_eq!((abi_tag.major, abi_tag.minor, abi_tag.subminor), (3, 2, 0));
+
+    let properties = elf.gnu_properties(bytes).unwrap().unwrap();
+    assert!(properties.x86_ibt());
+    assert!(properties.x86_shstk());
+    assert_eq!(
+        properties.x86_isa_1_needed(),
+        goblin::elf::note::GNU_PROPERTY_X86_ISA_1_BASELINE
+    );
+
+    let metadata = elf.package_metadata(bytes).unwrap().unwrap();
+    assert_eq!(metadata.get("name"), Some("hello"));
+    assert_eq!(metadata.get("version"), Some("1.0-1"));
+    assert_eq!(metadata.get("architecture"), Some("amd64"));
+
+    // a malformed note segment doesn't hide the notes of the following ones
+    use goblin::elf::program_header::PT_NOTE;
+    use goblin::elf::section_header::SHT_NOTE;
+    let truncated = bytes.len() as u64 - 4;
+    let mut broken = elf.clone();
+    broken
+        .program_headers
+        .iter_mut()
+        .find(|phdr| phdr.p_type == PT_NOTE)
+        .unwrap()
+        .p_offset = truncated;
+    assert!(broken.build_id(bytes).unwrap().is_ok());
+    assert!(broken.gnu_properties(bytes).unwrap().unwrap().x86_ibt());
+    for phdr in broken.program_headers.iter_mut().filter(|phdr| phdr.p_type == PT_NOTE) {
+        phdr.p_offset = truncated;
+    }
+    for shdr in broken.section_headers.iter_mut().filter(|shdr| shdr.sh_type == SHT_NOTE) {
+        shdr.sh_offset = truncated;
+    }
+    assert!(broken.build_id(bytes).unwrap().is_err());
+
+    // notes are also found through the sections
+    let object: &[u8] = include_bytes!("bins/elf/notes/hello.o");
+    let elf = Elf::parse(object).expect("parse object");
+    assert!(elf.program_headers.is_empty());
+    assert!(elf.gnu_properties(object).unwrap().unwrap().x86_ibt());
+    assert!(elf.build_id(object).is_none());
+    assert!(elf.package_metadata(object).is_none());
+
+    let bytes: &[u8] = include_bytes!("bins/elf/relr/relr");
+    let elf = Elf::parse(bytes).expect("parse relr binary");
+    let properties = elf.gnu_properties(bytes).unwrap().unwrap();
+    assert!(!properties.x86_ibt());
+    assert!(!properties.x86_shstk());
+}
+
+#[test]
+fn test_core() {
+    use goblin::elf::core::{Core, Registers, AT_PAGESZ, AT_PHDR};
+
+    let bytes: &[u8] = include_bytes!("bins/elf/core/core");
+    let core = Core::parse(bytes).expect("parse core dump");
+
+    // the main thread crashed writing to 0x10, while the other one waited in libc
+    assert_eq!(core.threads.len(), 2);
+    let crashed = &core.threads[0];
+    assert_eq!(crashed.prstatus.pr_cursig, 11);
+    assert_eq!(crashed.prstatus.pr_pid, 7459);
+    assert_eq!(core.threads[1].prstatus.pr_pid, 7460);
+    assert!(matches!(crashed.registers, Registers::X86_64(_)));
+    assert_eq!(crashed.registers.pc(), Some(0x55ca_a3ec_20fa));
+    assert_eq!(crashed.registers.sp(), Some(0x7fff_e881_da50));
+    assert_eq!(crashed.notes.len(), 2);
+    assert_eq!(crashed.notes[0].n_type, goblin::elf::note::NT_FPREGSET);
+
+    let siginfo = core.siginfo.unwrap();
+    assert_eq!(siginfo.si_signo, 11);
+    assert_eq!(siginfo.si_addr, Some(0x10));
+    assert_eq!(siginfo.si_pid, None);
+
+    let prpsinfo = core.prpsinfo.unwrap();
+    assert_eq!(prpsinfo.pr_pid, 7459);
+    assert_eq!(prpsinfo.pr_fname, b"crash");
+    assert_eq!(prpsinfo.pr_psargs, b"./crash ");
+
+    assert_eq!(core.mapped_files.len(), 15);
+    let text = core.mapped_file(0x55ca_a3ec_20fa).unwrap();
+    assert!(text.path.ends_with("/crash"));
+    assert_eq!(
+        (text.start, text.end, text.offset),
+        (0x55ca_a3ec_2000, 0x55ca_a3ec_3000, 0x1000)
+    );
+    assert!(core.mapped_file(0x10).is_none());
+
+    assert_eq!(core.auxv_value(AT_PAGESZ), Some(0x1000));
+    // only the first page of mapped ELF files was dumped
+    let phdr = core.auxv_value(AT_PHDR).unwrap();
+    assert_eq!(core.read_memory(phdr - 0x40, 4), Some(&b"\x7fELF"[..]));
+    assert_eq!(core.read_word(phdr), Some(0x4_0000_0006));
+    assert_eq!(core.read_memory(text.start, 4), None);
+    assert_eq!(core.read_memory(phdr, 0x1000), None);
+
+    let bytes: &[u8] = include_bytes!("bins/elf/relr/relr");
+    assert!(Core::parse(bytes).is_err());
+}
+
+#[test]
+fn test_writer() {
+    use goblin::elf::program_header::{PT_DYNAMIC, PT_LOAD, PT_PHDR};
+    use goblin::elf::writer::Writer;
+
+    fn section<'a>(elf: &'a Elf, name: &str) -> &'a goblin::elf::SectionHeader {
+        &elf.section_headers[section_index(elf, name)]
+    }
+
+    let bytes: &[u8] = include_bytes!("bins/elf/relr/relr");
+    let interpreter = "/lib64/../lib64/ld-linux-x86-64.so.2";
+    let libraries = [
+        "libm.so.6",
+        "libpthread.so.0",
+        "libdl.so.2",
+        "librt.so.1",
+        "libresolv.so.2",
+        "libutil.so.1",
+        "libanl.so.1",
+        "libmvec.so.1",
+    ];
+    let mut writer = Writer::new(bytes).unwrap();
+    writer
+        .set_interpreter(interpreter)
+        .set_runpath("$ORIGIN/../lib")
+        .add_needed("libc.so.6");
+    for library in &libraries {
+        writer.add_needed(library);
+    }
+    let out = writer.write().unwrap();
+    let elf = Elf::parse(&out).unwrap();
+    assert_eq!(elf.interpreter.as_deref(), Some(interpreter));
+    assert_eq!(elf.libraries[0], "libc.so.6");
+    assert_eq!(elf.libraries[1..], libraries);
+    assert_eq!(elf.runpaths, ["$ORIGIN/../lib"]);
+    assert_eq!(elf.dynsyms.len(), Elf::parse(bytes).unwrap().dynsyms.len());
+
+    // the moved parts are in a new segment, after the other ones
+    let loads: Vec<_> = elf
+        .program_headers
+        .iter()
+        .filter(|phdr| phdr.p_type == PT_LOAD)
+        .collect();
+    assert_eq!(loads.len(), 5);
+    let segment = loads[4];
+    assert!(segment.is_write());
+    assert!(loads[3].p_vaddr + loads[3].p_memsz <= segment.p_vaddr);
+    assert_eq!(segment.p_offset + segment.p_filesz, out.len() as u64);
+    let phdr = &elf.program_headers[0];
+    assert_eq!(phdr.p_type, PT_PHDR);
+    assert_eq!(phdr.p_offset, elf.header.e_phoff);
+    assert_eq!(
+        (phdr.p_offset, phdr.p_vaddr),
+        (segment.p_offset, segment.p_vaddr)
+    );
+    let dynamic = elf
+        .program_headers
+        .iter()
+        .find(|phdr| phdr.p_type == PT_DYNAMIC)
+        .unwrap();
+    assert!(segment.vm_range().contains(&(dynamic.p_vaddr as usize)));
+    assert_eq!(section(&elf, ".dynamic").sh_offset, dynamic.p_offset);
+    let dynamic = elf.dynamic.as_ref().unwrap();
+    let strtab = dynamic
+        .dyns
+        .iter()
+        .find(|dyn_| dyn_.d_tag == goblin::elf::dynamic::DT_STRTAB)
+        .unwrap();
+    assert_eq!(section(&elf, ".dynstr").sh_addr, strtab.d_val);
+    // `info.strtab` is the file offset
+    assert_eq!(
+        section(&elf, ".dynstr").sh_offset as usize,
+        dynamic.info.strtab
+    );
+    assert!(segment.vm_range().contains(&(strtab.d_val as usize)));
+    assert_eq!(
+        section(&elf, ".interp").sh_offset,
+        elf.program_headers[1].p_offset
+    );
+
+    // shorter interpreters and removals are made in place
+    let out = Writer::new(bytes)
+        .unwrap()
+        .set_interpreter("/lib/ld.so")
+        .remove_needed("libc.so.6")
+        .write()
+        .unwrap();
+    assert_eq!(out.len(), bytes.len());
+    let elf = Elf::parse(&out).unwrap();
+    assert_eq!(elf.interpreter.as_deref(), Some("/lib/ld.so"));
+    assert!(elf.libraries.is_empty());
+    assert_eq!(section(&elf, ".interp").sh_size, 11);
+
+    let bytes: &[u8] = include_bytes!("bins/elf/gnu_hash/hello.so");
+    let out = Writer::new(bytes)
+        .unwrap()
+        .set_soname("libhello.so.1")
+        .write()
+        .unwrap();
+    let elf = Elf::parse(&out).unwrap();
+    assert_eq!(elf.soname, Some("libhello.so.1"));
+    assert!(Writer::new(bytes)
+        .unwrap()
+        .set_interpreter("/lib/ld.so")
+        .write()
+        .is_err());
+}
+
+// the kernel finds the program headers of the new segment, which needs Linux 5.18 or later
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[test]
+fn test_writer_execute() {
+    use goblin::elf::writer::Writer;
+    use std::os::unix::fs::PermissionsExt;
+
+    let bytes: &[u8] = include_bytes!("bins/elf/writer/bss");
+    let out = Writer::new(bytes)
+        .unwrap()
+        .set_interpreter("/lib64/../lib64/ld-linux-x86-64.so.2")
+        .set_runpath("$ORIGIN/../lib")
+        .write()
+        .unwrap();
+    let path = std::env::temp_dir().join(format!("goblin-writer-{}", std::process::id()));
+    std::fs::write(&path, &out).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    // `bss` exits with 0 when given an argument
+    let status = std::process::Command::new(&path).arg("x").status();
+    std::fs::remove_file(&path).unwrap();
+    assert!(status.unwrap().success());
+}
+
+fn symbol_value(elf: &Elf, name: &str) -> u64 {
+    elf.syms
+        .iter()
+        .find(|sym| elf.strtab.get_at(sym.st_name) == Some(name))
+        .unwrap()
+        .st_value
+}
+
+#[test]
+fn test_find_dynsym() {
+    let libs: [&[u8]; 3] = [
+        include_bytes!("bins/elf/symver/lib64.so"),
+        include_bytes!("bins/elf/symver/lib32.so"),
+        include_bytes!("bins/elf/sysv_hash/lib.so"),
+    ];
+    for (i, bytes) in libs.iter().enumerate() {
+        let mut elf = Elf::parse(bytes).unwrap();
+        assert_eq!(elf.gnu_hash.is_some(), i < 2);
+        assert_eq!(elf.sysv_hash.is_some(), i == 2);
+        // the same lookups through the hash table, then through a linear scan
+        for _ in 0..2 {
+            let v0 = symbol_value(&elf, "some_func_v0");
+            let v1 = symbol_value(&elf, "some_func_v1");
+            let v2 = symbol_value(&elf, "some_func_v2");
+            let find = |name| elf.find_dynsym(name).map(|sym| sym.st_value);
+            assert_eq!(find("some_func"), Some(v0));
+            assert_eq!(find("some_func@v1"), Some(v1));
+            assert_eq!(find("some_func@v2"), Some(v2));
+            assert_eq!(find("some_func@@v2"), Some(v2));
+            // v1 is hidden, not the default version
+            assert_eq!(find("some_func@@v1"), None);
+            assert_eq!(find("some_func@v3"), None);
+            // imports are not definitions
+            assert_eq!(find("puts"), None);
+            assert_eq!(find("some_func_v0"), None);
+            elf.gnu_hash = None;
+            elf.sysv_hash = None;
+        }
+    }
+
+    let bytes: &[u8] = include_bytes!("bins/elf/gnu_hash/hello.so");
+    let elf = Elf::parse(bytes).unwrap();
+    let sym = elf.find_dynsym("helloWorld").unwrap();
+    assert_eq!(elf.dynstrtab.get_at(sym.st_name), Some("helloWorld"));
+    assert!(sym.is_function());
+}
+
+#[test]
+fn test_versioned_dynsyms() {
+    let bytes: &[u8] = include_bytes!("bins/elf/symver/lib64.so");
+    let elf = Elf::parse(bytes).unwrap();
+    let syms: Vec<_> = elf.versioned_dynsyms().collect();
+    assert_eq!(syms.len(), elf.dynsyms.len());
+    let names: Vec<_> = syms
+        .iter()
+        .filter(|sym| sym.name != Some(""))
+        .map(|sym| sym.to_string())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "_ITM_deregisterTMCloneTable",
+            "puts@GLIBC_2.2.5",
+            "__gmon_start__",
+            "_ITM_registerTMCloneTable",
+            "__cxa_finalize@GLIBC_2.2.5",
+            "some_func@@v2",
+            "some_func",
+            "some_func@v1",
+            "v1@@v1",
+            "v2@@v2",
+        ]
+    );
+    let puts = syms[2].version.unwrap();
+    assert_eq!(puts.name, "GLIBC_2.2.5");
+    assert_eq!(puts.library, Some("libc.so.6"));
+    assert!(!puts.hidden && !puts.is_default());
+    let v1 = syms[8].version.unwrap();
+    assert_eq!((v1.index, v1.name, v1.library), (2, "v1", None));
+    assert!(v1.hidden && !v1.is_default());
+
+    // the newest glibc version a binary requires
+    let bytes: &[u8] = include_bytes!("bins/elf/symver/prog32");
+    let elf = Elf::parse(bytes).unwrap();
+    let newest = elf
+        .versioned_dynsyms()
+        .filter_map(|sym| sym.version)
+        .filter(|version| version.library == Some("libc.so.6"))
+        .filter_map(|version| version.name.strip_prefix("GLIBC_"))
+        .map(|version| {
+            version
+                .split('.')
+                .map(|n| n.parse::<u32>().unwrap())
+                .collect::<Vec<_>>()
+        })
+        .max();
+    assert_eq!(newest, Some(vec![2, 1, 3]));
+}
+
+#[test]
+fn test_eh_frame() {
+    use goblin::elf::eh_frame::{CfiEntry, Pointer};
+
+    let bytes: &[u8] = include_bytes!("bins/elf/eh_frame/throw");
+    let mut elf = Elf::parse(bytes).unwrap();
+    let eh_frame = elf.eh_frame(bytes).unwrap().unwrap();
+    assert_eq!(eh_frame.address(), 0x2050);
+    let entries = eh_frame.entries().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(entries.len(), 10);
+    let cies = entries
+        .iter()
+        .filter(|entry| matches!(entry, CfiEntry::Cie(_)))
+        .count();
+    assert_eq!(cies, 3);
+    let hdr = elf.eh_frame_hdr(bytes).unwrap().unwrap();
+    assert_eq!(hdr.eh_frame_ptr(), 0x2050);
+    assert_eq!(hdr.len(), 7);
+
+    // main, which catches an exception
+    let fde = elf.find_fde(bytes, 0x10d0).unwrap().unwrap();
+    assert_eq!(fde.offset, 0xc0);
+    assert_eq!((fde.pc_begin, fde.pc_end()), (0x10c0, 0x1108));
+    assert_eq!(fde.cie.offset, 0x88);
+    assert_eq!(fde.cie.augmentation, "zPLR");
+    assert_eq!(fde.cie.code_alignment_factor, 1);
+    assert_eq!(fde.cie.data_alignment_factor, -8);
+    assert_eq!(fde.cie.return_address_register, 16);
+    assert_eq!(fde.cie.personality, Some(Pointer::Indirect(0x4058)));
+    assert_eq!(fde.lsda, Some(Pointer::Direct(0x2178)));
+    assert_eq!(fde.instructions.len(), 15);
+    assert_eq!(fde.instructions[0], 0x41);
+    assert_eq!(fde, eh_frame.find_fde(0x10d0).unwrap().unwrap());
+
+    assert_eq!(elf.find_fde(bytes, 0x1000).unwrap(), None);
+    assert_eq!(elf.find_fde(bytes, 0x1148).unwrap(), None);
+    assert_eq!(elf.find_fde(bytes, 0x2000).unwrap(), None);
+    for fde in eh_frame.fdes() {
+        let fde = fde.unwrap();
+        assert_eq!(elf.find_fde(bytes, fde.pc_begin).unwrap(), Some(fde));
+        assert_eq!(elf.find_fde(bytes, fde.pc_end() - 1).unwrap(), Some(fde));
+        assert_eq!(eh_frame.find_fde(fde.pc_begin).unwrap(), Some(fde));
+    }
+
+    // without section headers, .eh_frame is found through PT_GNU_EH_FRAME
+    elf.section_headers.clear();
+    let eh_frame = elf.eh_frame(bytes).unwrap().unwrap();
+    assert_eq!(eh_frame.address(), 0x2050);
+    assert_eq!(eh_frame.fdes().count(), 7);
+    assert_eq!(elf.find_fde(bytes, 0x10d0).unwrap(), Some(fde));
+
+    let bytes: &[u8] = include_bytes!("bins/elf/compressed/hello.o");
+    let elf = Elf::parse(bytes).unwrap();
+    assert!(elf.eh_frame_hdr(bytes).is_none());
+    let eh_frame = elf.eh_frame(bytes).unwrap().unwrap();
+    assert_eq!(eh_frame.fdes().count(), 2);
+}
+
+fn plt_names(bytes: &[u8]) -> Vec<(u64, String)> {
+    let elf = Elf::parse(bytes).unwrap();
+    elf.plt_entries(bytes)
+        .iter()
+        .map(|entry| (entry.address, entry.name.to_string()))
+        .collect()
+}
+
+fn named(entries: &[(u64, &str)]) -> Vec<(u64, String)> {
+    entries
+        .iter()
+        .map(|&(address, name)| (address, name.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_plt_entries() {
+    let lazy = named(&[
+        (0x1030, "puts"),
+        (0x1040, "printf"),
+        (0x1050, "exit"),
+        (0x1060, "__cxa_finalize"),
+    ]);
+    assert_eq!(plt_names(include_bytes!("bins/elf/plt/lazy")), lazy);
+    assert_eq!(plt_names(include_bytes!("bins/elf/plt/now")), lazy);
+
+    // with IBT, calls go to .plt.sec rather than .plt
+    let bytes: &[u8] = include_bytes!("bins/elf/plt/ibt");
+    assert_eq!(
+        plt_names(bytes),
+        named(&[
+            (0x1060, "__cxa_finalize"),
+            (0x1070, "puts"),
+            (0x1080, "printf"),
+            (0x1090, "exit"),
+        ])
+    );
+    let elf = Elf::parse(bytes).unwrap();
+    let puts = elf.plt_entries(bytes)[1];
+    assert_eq!(puts.got_address, 0x4000);
+    let sym = elf.dynsyms.get(puts.sym).unwrap();
+    assert_eq!(elf.dynstrtab.get_at(sym.st_name), Some("puts"));
+
+    assert_eq!(
+        plt_names(include_bytes!("bins/elf/plt/lazy32.so")),
+        named(&[(0x1010, "foo"), (0x1020, "bar")])
+    );
+    assert_eq!(
+        plt_names(include_bytes!("bins/elf/plt/ibt32.so")),
+        named(&[(0x1030, "foo"), (0x1040, "bar")])
+    );
+    assert_eq!(
+        plt_names(include_bytes!("bins/elf/plt/exe32")),
+        named(&[(0x0804_9010, "baz")])
+    );
+}
+
+/// Relocates the sections of `object` at the addresses of the same sections in `linked`, which
+/// the linker created from it, and checks they match
+fn assert_relocated_like_linker(object: &[u8], linked: &[u8]) {
+    let elf = Elf::parse(object).unwrap();
+    let linked_elf = Elf::parse(linked).unwrap();
+    let linked_section = |name: &str| {
+        linked_elf
+            .section_headers
+            .iter()
+            .find(|shdr| linked_elf.shdr_strtab.get_at(shdr.sh_name) == Some(name))
+    };
+    let section_addresses = elf
+        .section_headers
+        .iter()
+        .map(|shdr| {
+            let name = elf.shdr_strtab.get_at(shdr.sh_name).unwrap();
+            linked_section(name).map_or(0, |shdr| shdr.sh_addr)
+        })
+        .collect::<Vec<_>>();
+    let resolve = |name: &str| match name {
+        "ext_value" => Some(0x12340),
+        "ext_func" => Some(0x23450),
+        _ => None,
+    };
+    for name in &[".text", ".data", ".rodata"] {
+        let idx = elf
+            .section_headers
+            .iter()
+            .position(|shdr| elf.shdr_strtab.get_at(shdr.sh_name) == Some(name))
+            .unwrap();
+        let relocated = elf
+            .relocate_section(object, idx, &section_addresses, resolve)
+            .unwrap();
+        let range = linked_section(name).unwrap().file_range().unwrap();
+        assert_eq!(relocated, &linked[range], "{}", name);
+    }
+    // the undefined weak weak_func is at 0 when unresolved, but ext_value and ext_func must be
+    let text = elf
+        .section_headers
+        .iter()
+        .position(|shdr| elf.shdr_strtab.get_at(shdr.sh_name) == Some(".text"))
+        .unwrap();
+    assert!(elf
+        .relocate_section(object, text, &section_addresses, |_| None)
+        .is_err());
+}
+
+#[test]
+fn test_relocate_section() {
+    assert_relocated_like_linker(
+        include_bytes!("bins/elf/relocate/reloc.o"),
+        include_bytes!("bins/elf/relocate/reloc.linked"),
+    );
+    // i386 uses REL relocations, whose addends are in the section
+    assert_relocated_like_linker(
+        include_bytes!("bins/elf/relocate/reloc32.o"),
+        include_bytes!("bins/elf/relocate/reloc32.linked"),
+    );
+    let bytes: &[u8] = include_bytes!("bins/elf/relocate/reloc.linked");
+    let elf = Elf::parse(bytes).unwrap();
+    assert!(elf.relocate_section(bytes, 1, &[], |_| None).is_err());
+}
+
+#[test]
+fn test_relocate_image() {
+    use goblin::container::{Container, Ctx};
+    use goblin::elf::header::EM_X86_64;
+    use goblin::elf::reloc::{R_X86_64_64, R_X86_64_COPY, R_X86_64_NONE};
+    use goblin::elf::relocate::{apply, RelocValues};
+    use scroll::{Pread, LE};
+
+    const BASE: u64 = 0x7f00_0000_0000;
+    const MAX_SIZE: usize = 0x10_0000;
+    let resolve = |name: &str| match name {
+        "puts" => Some(0x7f12_3456_7000),
+        _ => Some(0),
+    };
+
+    let bytes: &[u8] = include_bytes!("bins/elf/plt/lazy");
+    let elf = Elf::parse(bytes).unwrap();
+    let (address, mut image) = elf.load_image(bytes, MAX_SIZE).unwrap();
+    assert_eq!(address, 0);
+    assert!(elf.relocate_image(&mut image, address, BASE, resolve).unwrap().is_empty());
+    let slot = |image: &[u8], address: u64| image.pread_with::<u64>(address as usize, LE).unwrap();
+    assert_eq!(slot(&image, 0x3dc8), BASE + 0x1180);
+    assert_eq!(slot(&image, 0x4020), BASE + 0x4020);
+    assert_eq!(slot(&image, 0x3dd8), 0x7f12_3456_7000);
+    assert_eq!(slot(&image, 0x4000), 0x7f12_3456_7000);
+    assert_eq!(slot(&image, 0x3fe0), 0);
+    assert!(elf.relocate_image(&mut image, address, BASE, |_| None).is_err());
+    // the size of the image comes from the program headers
+    let mut huge = elf.clone();
+    let load = huge
+        .program_headers
+        .iter_mut()
+        .rfind(|phdr| phdr.p_type == goblin::elf::program_header::PT_LOAD)
+        .unwrap();
+    load.p_memsz = 0x1_0000_0000;
+    assert!(huge.load_image(bytes, MAX_SIZE).is_err());
+
+    // RELR relocations add the base to the address at their place
+    let bytes: &[u8] = include_bytes!("bins/elf/relr/relr");
+    let elf = Elf::parse(bytes).unwrap();
+    let (address, mut image) = elf.load_image(bytes, MAX_SIZE).unwrap();
+    let original = image.clone();
+    assert!(elf.relocate_image(&mut image, address, BASE, resolve).unwrap().is_empty());
+    for reloc in elf.relr.iter() {
+        assert_eq!(
+            slot(&image, reloc.r_offset),
+            BASE + slot(&original, reloc.r_offset)
+        );
+    }
+    assert_eq!(slot(&image, 0x4000), 0x7f12_3456_7000);
+
+    // unsupported relocations are returned once the others are applied
+    let bytes: &[u8] = include_bytes!("bins/elf/eh_frame/throw");
+    let elf = Elf::parse(bytes).unwrap();
+    let (address, mut image) = elf.load_image(bytes, MAX_SIZE).unwrap();
+    let unsupported = elf.relocate_image(&mut image, address, BASE, resolve).unwrap();
+    assert_eq!(unsupported.len(), 1);
+    assert_eq!(unsupported[0].r_type, R_X86_64_COPY);
+    assert_eq!(unsupported[0].r_offset, 0x3da0);
+    assert_eq!(slot(&image, 0x4020), 0x7f12_3456_7000);
+    assert_eq!(slot(&image, 0x4048), BASE + 0x4048);
+
+    // i386 JUMP_SLOT relocations ignore the lazy binding address at their place
+    let bytes: &[u8] = include_bytes!("bins/elf/plt/lazy32.so");
+    let elf = Elf::parse(bytes).unwrap();
+    let (address, mut image) = elf.load_image(bytes, MAX_SIZE).unwrap();
+    let unsupported = elf
+        .relocate_image(&mut image, address, 0xf000_0000, |name| match name {
+            "foo" => Some(0xf700_1000),
+            "bar" => Some(0xf700_2000),
+            _ => None,
+        })
+        .unwrap();
+    assert!(unsupported.is_empty());
+    let slot = |address: usize| image.pread_with::<u32>(address, LE).unwrap();
+    assert_eq!(slot(0x4000), 0xf700_1000);
+    assert_eq!(slot(0x4004), 0xf700_2000);
+
+    let ctx = Ctx::new(Container::Big, scroll::Endian::Little);
+    let mut data = [0; 8];
+    let values = RelocValues {
+        symbol: 0x1000,
+        addend: Some(8),
+        ..Default::default()
+    };
+    apply(EM_X86_64, R_X86_64_64, &mut data, 0, &values, ctx).unwrap();
+    assert_eq!(data, 0x1008u64.to_le_bytes());
+    apply(EM_X86_64, R_X86_64_NONE, &mut data, 8, &values, ctx).unwrap();
+    assert!(apply(EM_X86_64, R_X86_64_COPY, &mut data, 0, &values, ctx).is_err());
+    // out of bounds
+    assert!(apply(EM_X86_64, R_X86_64_64, &mut data, 1, &values, ctx).is_err());
+}
@@ -577,3 +577,23 @@ fn relocations() {
     assert_eq!(reloc.is_pic(), true);
     assert_eq!(reloc.is_extern(), true);
 }
+
+#[test]
+fn overlay() {
+    let bytes = &DEADBEEF_MACH_64;
+    match Mach::parse(&bytes[..]).unwrap() {
+        Mach::Binary(binary) => assert!(binary.overlay().is_none()),
+        _ => panic!("got mach fat from regular binary"),
+    }
+
+    let mut appended = bytes.to_vec();
+    appended.extend_from_slice(b"trailer");
+    match Mach::parse(&appended).unwrap() {
+        Mach::Binary(binary) => {
+            let overlay = binary.overlay().expect("overlay");
+            assert_eq!(overlay.offset, bytes.len());
+            assert_eq!(overlay.bytes, b"trailer");
+        }
+        _ => panic!("got mach fat from regular binary"),
+    }
+}
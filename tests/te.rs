@@ -1,3 +1,4 @@
+use goblin::pe::options::{ParseMode, ParseOptions};
 use goblin::te::*;
 
 const TE_EFI: [u8; 15744] = [
@@ -1319,3 +1320,44 @@ const TE_EFI: [u8; 15744] = [
 fn parse_te() {
     let _te = TE::parse(&TE_EFI).unwrap();
 }
+
+#[test]
+fn write_te() {
+    let te = TE::parse(&TE_EFI).unwrap();
+    assert_eq!(te.write(&TE_EFI).unwrap(), &TE_EFI[..]);
+}
+
+#[test]
+fn parse_truncated_section_table() {
+    let bytes = &TE_EFI[..100];
+    let mut strict = ParseOptions::default();
+    strict.parse_mode = ParseMode::Strict;
+    assert!(TE::parse_with_opts(bytes, &strict).is_err());
+
+    let mut permissive = ParseOptions::default();
+    permissive.parse_mode = ParseMode::Permissive;
+    let te = TE::parse_with_opts(bytes, &permissive).unwrap();
+    assert_eq!(te.sections.len(), 1);
+    assert!(matches!(te.skipped(), [Skipped::Sections { index: 1, .. }]));
+}
+
+#[test]
+fn parse_bad_debug_directory() {
+    let mut bytes = TE_EFI.to_vec();
+    // point the debug directory at an RVA outside of every section
+    bytes[32..36].copy_from_slice(&0xdead_0000u32.to_le_bytes());
+    bytes[36..40].copy_from_slice(&0x1cu32.to_le_bytes());
+    let mut strict = ParseOptions::default();
+    strict.parse_mode = ParseMode::Strict;
+    assert!(TE::parse_with_opts(&bytes, &strict).is_err());
+
+    let mut permissive = ParseOptions::default();
+    permissive.parse_mode = ParseMode::Permissive;
+    let te = TE::parse_with_opts(&bytes, &permissive).unwrap();
+    assert_eq!(te.sections.len(), 5);
+    assert!(te.debug_data.is_none());
+    assert!(matches!(te.skipped(), [Skipped::DebugDirectory { .. }]));
+    // `TE::parse` is strict
+    assert!(TE::parse(&bytes).is_err());
+    assert!(TE::parse(&TE_EFI).unwrap().skipped().is_empty());
+}